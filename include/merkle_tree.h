@@ -0,0 +1,119 @@
+#ifndef MERKLE_TREE_H
+#define MERKLE_TREE_H
+
+/* Generated by cbindgen from src/ffi.rs; do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Result codes returned by every FFI function
+typedef enum MerkleStatus {
+  // The call succeeded
+  MerkleStatus_Ok = 0,
+  // A required pointer argument was null
+  MerkleStatus_NullPointer = 1,
+  // An argument was invalid, e.g. a non UTF-8 hasher name or an empty leaf list
+  MerkleStatus_InvalidArgument = 2,
+  // The hasher name is not a built-in hasher
+  MerkleStatus_UnknownHasher = 3,
+  // The leaf index is out of bounds
+  MerkleStatus_IndexOutOfBounds = 4,
+  // The output buffer is too small; `out_len` holds the required size
+  MerkleStatus_BufferTooSmall = 5,
+  // The serialized proof could not be parsed
+  MerkleStatus_InvalidProof = 6,
+  // The library panicked; this is a bug
+  MerkleStatus_Panic = 7,
+} MerkleStatus;
+
+// An opaque handle to a Merkle proof
+typedef struct MerkleProofHandle MerkleProofHandle;
+
+// An opaque handle to a Merkle tree
+typedef struct MerkleTreeHandle MerkleTreeHandle;
+
+// Builds a tree from `leaf_count` raw leaves
+//
+// On success `*out_tree` receives a new tree handle, owned by the caller and
+// released with [`merkle_tree_free`].
+//
+// # Safety
+//
+// `leaves` and `leaf_lens` must point to `leaf_count` entries each, where
+// `leaves[i]` points to `leaf_lens[i]` readable bytes. `hasher_name` must be
+// a NUL-terminated string and `out_tree` a valid pointer.
+enum MerkleStatus merkle_tree_new(const uint8_t *const *leaves,
+                                  const uintptr_t *leaf_lens,
+                                  uintptr_t leaf_count,
+                                  const char *hasher_name,
+                                  struct MerkleTreeHandle **out_tree);
+
+// Releases a tree handle; a null handle is ignored
+//
+// # Safety
+//
+// `tree` must be null or a handle from [`merkle_tree_new`] that hasn't been freed.
+void merkle_tree_free(struct MerkleTreeHandle *tree);
+
+// Copies the tree's root into `out`
+//
+// # Safety
+//
+// `tree` must be a live tree handle, `out` must point to `out_capacity`
+// writable bytes (or be null with zero capacity) and `out_len` must be valid.
+enum MerkleStatus merkle_tree_root(const struct MerkleTreeHandle *tree,
+                                   uint8_t *out,
+                                   uintptr_t out_capacity,
+                                   uintptr_t *out_len);
+
+// Generates the proof for the leaf at `index`
+//
+// On success `*out_proof` receives a new proof handle, owned by the caller
+// and released with [`merkle_proof_free`].
+//
+// # Safety
+//
+// `tree` must be a live tree handle and `out_proof` a valid pointer.
+enum MerkleStatus merkle_tree_generate_proof(const struct MerkleTreeHandle *tree,
+                                             uintptr_t index,
+                                             struct MerkleProofHandle **out_proof);
+
+// Releases a proof handle; a null handle is ignored
+//
+// # Safety
+//
+// `proof` must be null or a handle from [`merkle_tree_generate_proof`] that
+// hasn't been freed.
+void merkle_proof_free(struct MerkleProofHandle *proof);
+
+// Serializes a proof (see [`MerkleProof::to_bytes`]) into `out`
+//
+// # Safety
+//
+// `proof` must be a live proof handle, `out` must point to `out_capacity`
+// writable bytes (or be null with zero capacity) and `out_len` must be valid.
+enum MerkleStatus merkle_proof_serialize(const struct MerkleProofHandle *proof,
+                                         uint8_t *out,
+                                         uintptr_t out_capacity,
+                                         uintptr_t *out_len);
+
+// Verifies a serialized proof against a root, writing the result to `out_valid`
+//
+// A proof that parses but doesn't lead to `root` is not an error: the call
+// returns `Ok` with `*out_valid` set to false.
+//
+// # Safety
+//
+// `proof` and `root` must point to `proof_len` and `root_len` readable
+// bytes, `hasher_name` must be a NUL-terminated string and `out_valid` a
+// valid pointer.
+enum MerkleStatus merkle_proof_verify(const uint8_t *proof,
+                                      uintptr_t proof_len,
+                                      const uint8_t *root,
+                                      uintptr_t root_len,
+                                      const char *hasher_name,
+                                      bool *out_valid);
+
+#endif  /* MERKLE_TREE_H */
@@ -0,0 +1,35 @@
+//! Compile test for `merkle_tree` without `std`
+//!
+//! Feature unification turns `std` back on inside a workspace build, so build
+//! this crate on its own for a target without `std` to check the core:
+//!
+//! ```text
+//! cargo build -p merkle_tree_no_std_check --target thumbv7em-none-eabi
+//! ```
+#![no_std]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use merkle_tree::hasher::{Blake2bHasher, Hasher, Sha256Hasher};
+use merkle_tree::proof::{MerkleProof, ProofItem};
+use merkle_tree::tree::MerkleTree;
+
+/// Verifies a proof given as `(sibling, is_left)` pairs, as a verifier would
+pub fn verify_sha256(root: &[u8], leaf: &[u8], siblings: &[(&[u8], bool)]) -> bool {
+    let proof_items = siblings.iter()
+        .map(|&(hash, is_left)| ProofItem { hash: hash.into(), is_left })
+        .collect();
+    MerkleProof::new(leaf.to_vec(), proof_items, Sha256Hasher::new()).verify(root)
+}
+
+/// Builds a tree and checks every leaf's proof against its root
+pub fn build_and_verify(data: &[&[u8]]) -> bool {
+    let hasher = Blake2bHasher::new(32);
+    let leaves: Vec<Vec<u8>> = data.iter().map(|leaf| hasher.hash_leaf(leaf)).collect();
+    let tree = MerkleTree::new(leaves, hasher);
+    let root = tree.root();
+    (0..tree.unpadded_leaf_count()).all(|index| {
+        tree.generate_proof(index).is_ok_and(|proof| proof.verify(root) && tree.verify_proof(&proof))
+    })
+}
@@ -0,0 +1,155 @@
+//! Async tree construction for tokio-based pipelines
+//!
+//! Hashing is done off the async executor so large inputs don't starve other
+//! tasks. Trees built here are identical to the ones built by the matching
+//! synchronous helpers in [`crate::utils`].
+use std::io::Error;
+use futures::{Stream, StreamExt};
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::task;
+use crate::error::MerkleTreeError;
+use crate::hasher::Hasher;
+use crate::tree::MerkleTree;
+use crate::utils::RootFrontier;
+
+/// Number of stream items hashed between yields to the runtime
+const YIELD_INTERVAL: usize = 1024;
+
+/// Creates a Merkle tree over fixed-size chunks of an async reader
+///
+/// The async counterpart of [`crate::utils::create_tree_from_file`]: chunks
+/// keep their order, the final chunk is hashed unpadded, and the result is
+/// the tree plus the number of chunks. Each chunk is hashed on the blocking
+/// thread pool.
+pub async fn create_tree_from_async_reader<R, H>(
+    mut reader: R,
+    chunk_size: usize,
+    hasher: H
+) -> Result<(MerkleTree<H>, usize), MerkleTreeError>
+where
+    R: AsyncRead + Unpin,
+    H: Hasher + Send + 'static,
+{
+    if chunk_size == 0 {
+        return Err(MerkleTreeError::InvalidChunkSize);
+    }
+
+    let mut leaves = Vec::new();
+    let mut chunk = Vec::with_capacity(chunk_size);
+
+    loop {
+        chunk.clear();
+        if (&mut reader).take(chunk_size as u64).read_to_end(&mut chunk).await? == 0 {
+            break;
+        }
+
+        let chunk_hasher = hasher.clone();
+        let (leaf, buffer) = task::spawn_blocking(move || (chunk_hasher.hash_leaf(&chunk), chunk))
+            .await
+            .map_err(|err| MerkleTreeError::Io(Error::other(err)))?;
+        leaves.push(leaf);
+        chunk = buffer;
+    }
+
+    if leaves.is_empty() {
+        return Err(MerkleTreeError::EmptyLeaves);
+    }
+
+    let chunk_count = leaves.len();
+    let tree = task::spawn_blocking(move || MerkleTree::new_ordered(leaves, hasher))
+        .await
+        .map_err(|err| MerkleTreeError::Io(Error::other(err)))?;
+    Ok((tree, chunk_count))
+}
+
+/// Creates a Merkle tree from a stream of raw leaf data
+///
+/// Each item is hashed with `hash_leaf` and the tree is built like
+/// [`MerkleTree::new`], leaves sorted. The task yields to the runtime
+/// periodically while hashing, and the tree itself is built on the blocking
+/// thread pool.
+pub async fn build_from_stream<S, H>(stream: S, hasher: H) -> Result<MerkleTree<H>, MerkleTreeError>
+where
+    S: Stream<Item = Vec<u8>>,
+    H: Hasher + Send + 'static,
+{
+    futures::pin_mut!(stream);
+    let mut leaves = Vec::new();
+
+    while let Some(data) = stream.next().await {
+        leaves.push(hasher.hash_leaf(&data));
+        if leaves.len() % YIELD_INTERVAL == 0 {
+            task::yield_now().await;
+        }
+    }
+
+    if leaves.is_empty() {
+        return Err(MerkleTreeError::EmptyLeaves);
+    }
+
+    task::spawn_blocking(move || MerkleTree::new(leaves, hasher))
+        .await
+        .map_err(|err| MerkleTreeError::Io(Error::other(err)))
+}
+
+/// Builds a tree from leaves as they arrive, keeping a running root
+///
+/// Leaves are leaf hashes, kept in the order fed, so the finished tree is
+/// the one [`MerkleTree::new_ordered`] builds over the same leaves. Alongside
+/// them the builder keeps the O(log n) frontier of
+/// [`crate::utils::compute_root_streaming`], so [`AsyncTreeBuilder::current_root`]
+/// is the root of everything fed so far without rebuilding anything.
+pub struct AsyncTreeBuilder<H: Hasher> {
+    hasher: H,
+    leaves: Vec<Vec<u8>>,
+    frontier: RootFrontier,
+}
+
+impl<H: Hasher + Send + 'static> AsyncTreeBuilder<H> {
+    /// Creates a builder with no leaves
+    pub fn new(hasher: H) -> Self {
+        AsyncTreeBuilder { hasher, leaves: Vec::new(), frontier: RootFrontier::default() }
+    }
+
+    /// Adds the next leaf, yielding to the runtime periodically
+    pub async fn feed(&mut self, leaf: Vec<u8>) {
+        self.frontier.push_leaf(leaf.clone(), &self.hasher);
+        self.leaves.push(leaf);
+        if self.leaves.len().is_multiple_of(YIELD_INTERVAL) {
+            task::yield_now().await;
+        }
+    }
+
+    /// Feeds every leaf of a stream, in order
+    pub async fn feed_stream<S: Stream<Item = Vec<u8>>>(&mut self, stream: S) {
+        futures::pin_mut!(stream);
+        while let Some(leaf) = stream.next().await {
+            self.feed(leaf).await;
+        }
+    }
+
+    /// Gets the number of leaves fed so far
+    pub fn leaf_count(&self) -> usize {
+        self.leaves.len()
+    }
+
+    /// Gets the root of the tree over the leaves fed so far, `None` before the first
+    ///
+    /// This takes O(log n) hashes, padding as the finished tree would.
+    pub fn current_root(&self) -> Option<Vec<u8>> {
+        let last_leaf = self.leaves.last()?.clone();
+        self.frontier.clone().into_root(last_leaf, &self.hasher)
+    }
+
+    /// Builds the full tree for serving proofs, on the blocking thread pool
+    ///
+    /// Fails if no leaves were fed, or if a leaf isn't the hasher's output size.
+    pub async fn finish(self) -> Result<MerkleTree<H>, MerkleTreeError> {
+        let AsyncTreeBuilder { hasher, leaves, .. } = self;
+        task::spawn_blocking(move || {
+            MerkleTree::builder(hasher).sort_leaves(false).build(leaves)
+        })
+        .await
+        .map_err(|err| MerkleTreeError::Io(Error::other(err)))?
+    }
+}
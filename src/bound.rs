@@ -0,0 +1,89 @@
+//! Bound roots: commitments to a root together with how its tree was built
+//!
+//! The same leaves give the same root whether they were sorted or not, and
+//! padding makes five leaves hash like eight, so a bare root doesn't say
+//! which construction a verifier should expect. A bound root is
+//! `hash_leaf(root || leaf_count as u64 LE || params_id)`, where the leaf
+//! count excludes padding and [`TreeParams::params_id`] hashes the hasher
+//! name and construction options. Publishing it instead of the root makes a
+//! proof from one configuration fail against a tree built another way.
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use crate::hasher::Hasher;
+use crate::proof::MerkleProof;
+
+/// Prefixes the parameter encoding, so `params_id` can't equal a leaf hash
+const PARAMS_DOMAIN: &[u8] = b"merkle_tree.params.v1\0";
+
+/// The construction options a bound root commits to
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TreeParams {
+    /// The name from [`Hasher::name`]
+    pub hasher: String,
+    /// Whether the leaves were sorted on construction
+    pub sorted: bool,
+    /// Whether each leaf was hashed with its index
+    pub index_bound: bool,
+}
+
+impl TreeParams {
+    /// Describes a tree built with `hasher`, sorted or in the given order,
+    /// from leaves hashed without their indices
+    pub fn new<H: Hasher>(hasher: &H, sorted: bool) -> Self {
+        TreeParams { hasher: hasher.name(), sorted, index_bound: false }
+    }
+
+    /// Hashes the parameters into the identifier a bound root includes
+    ///
+    /// Padding is always by duplicating the last leaf; it is part of the
+    /// encoding so a different strategy would get a different identifier.
+    pub fn params_id<H: Hasher>(&self, hasher: &H) -> Vec<u8> {
+        let order = if self.sorted { "sorted" } else { "ordered" };
+        let leaves = if self.index_bound { ";leaves=index-bound" } else { "" };
+        let encoding = format!("hasher={};padding=duplicate-last;order={}{}", self.hasher, order, leaves);
+        let mut data = Vec::with_capacity(PARAMS_DOMAIN.len() + encoding.len());
+        data.extend_from_slice(PARAMS_DOMAIN);
+        data.extend_from_slice(encoding.as_bytes());
+        hasher.hash_leaf(&data)
+    }
+}
+
+/// Binds `root` to the number of leaves given, without padding, and the tree's parameters
+pub fn bound_root<H: Hasher>(hasher: &H, root: &[u8], leaf_count: u64, params: &TreeParams) -> Vec<u8> {
+    let params_id = params.params_id(hasher);
+    let mut data = Vec::with_capacity(root.len() + 8 + params_id.len());
+    data.extend_from_slice(root);
+    data.extend_from_slice(&leaf_count.to_le_bytes());
+    data.extend_from_slice(&params_id);
+    hasher.hash_leaf(&data)
+}
+
+impl<H: Hasher> MerkleProof<H> {
+    /// Verifies the proof against a bound root from [`bound_root`]
+    ///
+    /// The leaf count and parameters are the ones the publisher claims; the
+    /// proof only verifies if they are the ones the tree was built with. The
+    /// proof's path must also have the depth of a tree of `leaf_count` leaves
+    /// and lead to one of them rather than to padding.
+    pub fn verify_bound(&self, bound: &[u8], leaf_count: u64, params: &TreeParams) -> bool {
+        within_leaf_count(self, leaf_count)
+            && bound_root(&self.hasher, &self.calculate_root(), leaf_count, params) == bound
+    }
+}
+
+/// Checks that the proof's path fits a tree of `leaf_count` leaves and ends at one of them
+fn within_leaf_count<H: Hasher>(proof: &MerkleProof<H>, leaf_count: u64) -> bool {
+    crate::index::depth(leaf_count).is_some_and(|depth| proof.proof_items.len() == depth as usize)
+        && proof.path_index().is_some_and(|index| index < leaf_count)
+}
+
+/// Verifies each proof against a bound root, returning one result per proof
+pub fn verify_proofs_bound<H: Hasher>(
+    bound: &[u8],
+    leaf_count: u64,
+    params: &TreeParams,
+    proofs: &[MerkleProof<H>],
+) -> Vec<bool> {
+    proofs.iter().map(|proof| proof.verify_bound(bound, leaf_count, params)).collect()
+}
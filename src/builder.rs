@@ -0,0 +1,331 @@
+//! A builder for trees that need non-default options
+use alloc::vec;
+use alloc::vec::Vec;
+use crate::error::{LimitExceeded, MerkleTreeError};
+use crate::hasher::Hasher;
+use crate::store::MemoryNodeStore;
+use crate::tree::{BuildObserver, BuildStats, MerkleTree};
+
+/// Caps on the size of the input a tree is built from, for untrusted input
+///
+/// Each `None` is unlimited, which is the default. Item sizes are those of
+/// the data before hashing, such as a line or CSV value, or of the leaf
+/// itself for [`MerkleTreeBuilder::build`]. The builder and the line and CSV
+/// constructors in [`crate::utils`] check each item as they read it and stop
+/// at the first one over a limit, failing with [`LimitExceeded`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BuildLimits {
+    /// The most leaves the tree may have, before padding
+    pub max_leaves: Option<usize>,
+    /// The largest an item may be, in bytes
+    pub max_leaf_bytes: Option<usize>,
+    /// The most bytes all items may add up to
+    pub max_total_bytes: Option<usize>,
+}
+
+impl BuildLimits {
+    /// Starts counting input against the limits
+    pub(crate) fn counter(self) -> LimitCounter {
+        LimitCounter { limits: self, leaves: 0, bytes: 0 }
+    }
+}
+
+/// Input counted so far against [`BuildLimits`]
+pub(crate) struct LimitCounter {
+    limits: BuildLimits,
+    leaves: usize,
+    bytes: usize,
+}
+
+impl LimitCounter {
+    /// Counts one more leaf made from `len` bytes of input
+    pub(crate) fn add(&mut self, len: usize) -> Result<(), LimitExceeded> {
+        let item = self.leaves;
+        self.add_leaf()?;
+        self.add_bytes(item, len)
+    }
+
+    /// Counts one more leaf
+    pub(crate) fn add_leaf(&mut self) -> Result<(), LimitExceeded> {
+        self.leaves += 1;
+        match self.limits.max_leaves {
+            Some(max) if self.leaves > max => Err(LimitExceeded::Leaves { max }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Counts `len` bytes of input for the item at `item`, which may share a leaf with earlier ones
+    pub(crate) fn add_bytes(&mut self, item: usize, len: usize) -> Result<(), LimitExceeded> {
+        if let Some(max) = self.limits.max_leaf_bytes.filter(|&max| len > max) {
+            return Err(LimitExceeded::LeafBytes { item, len, max });
+        }
+        self.bytes = self.bytes.saturating_add(len);
+        match self.limits.max_total_bytes {
+            Some(max) if self.bytes > max => Err(LimitExceeded::TotalBytes { max }),
+            _ => Ok(()),
+        }
+    }
+
+    /// The longest item still accepted, so readers needn't buffer more than that
+    #[cfg(feature = "std")]
+    pub(crate) fn max_item_len(&self) -> usize {
+        let remaining = self.limits.max_total_bytes.map_or(usize::MAX, |max| max.saturating_sub(self.bytes));
+        self.limits.max_leaf_bytes.unwrap_or(usize::MAX).min(remaining)
+    }
+}
+
+/// Builds a [`MerkleTree`] with options that `MerkleTree::new` doesn't take
+///
+/// ```
+/// use merkle_tree::hasher::{Hasher, Sha256Hasher};
+/// use merkle_tree::tree::MerkleTree;
+///
+/// let hasher = Sha256Hasher::new();
+/// let leaves = vec![hasher.hash_leaf(b"a"), hasher.hash_leaf(b"b")];
+/// let tree = MerkleTree::builder(hasher).index_leaves(true).build(leaves).unwrap();
+/// assert!(tree.contains(&Sha256Hasher::new().hash_leaf(b"a")));
+/// ```
+///
+/// `P` is the progress callback's type; without one it is a function pointer
+/// that is never called.
+pub struct MerkleTreeBuilder<H: Hasher, P = fn(usize, usize, usize)> {
+    hasher: H,
+    index_leaves: bool,
+    sort_leaves: bool,
+    collect_stats: bool,
+    lazy: bool,
+    bind_indices: bool,
+    check_leaf_sizes: bool,
+    limits: BuildLimits,
+    progress: Option<P>,
+}
+
+impl<H: Hasher> MerkleTreeBuilder<H> {
+    /// Creates a builder with the default options
+    pub fn new(hasher: H) -> Self {
+        MerkleTreeBuilder {
+            hasher,
+            index_leaves: false,
+            sort_leaves: true,
+            collect_stats: false,
+            lazy: false,
+            bind_indices: false,
+            check_leaf_sizes: true,
+            limits: BuildLimits::default(),
+            progress: None,
+        }
+    }
+}
+
+impl<H: Hasher, P: FnMut(usize, usize, usize)> MerkleTreeBuilder<H, P> {
+    /// Keeps a map from leaf value to index, making value lookups O(1)
+    ///
+    /// Speeds up `find_leaf_index`, `contains` and `generate_proof_by_value`
+    /// at the cost of a second copy of every distinct leaf. Off by default.
+    pub fn index_leaves(mut self, index_leaves: bool) -> Self {
+        self.index_leaves = index_leaves;
+        self
+    }
+
+    /// Sorts the leaves before building, as `MerkleTree::new` does; on by default
+    ///
+    /// With sorting off the leaves keep the given order, as in
+    /// `MerkleTree::new_ordered`, and value lookups fall back to a linear scan
+    /// unless the leaves are indexed.
+    pub fn sort_leaves(mut self, sort_leaves: bool) -> Self {
+        self.sort_leaves = sort_leaves;
+        self
+    }
+
+    /// Records hash counts per level and build time, read back with `MerkleTree::build_stats`
+    pub fn collect_stats(mut self, collect_stats: bool) -> Self {
+        self.collect_stats = collect_stats;
+        self
+    }
+
+    /// Stores only the leaves and root, computing interior nodes when a proof needs them
+    ///
+    /// Construction hashes the leaves into the root without keeping any level
+    /// above them, so it is faster and holds less memory. The first proofs are
+    /// slower: each computes the nodes it needs from the leaves and caches
+    /// them (with `std`) for later proofs. Proofs are the same as an eager
+    /// tree's, but `generate_proof_ref` isn't available, and the progress
+    /// callback and per-level stats aren't reported. Off by default.
+    pub fn lazy(mut self, lazy: bool) -> Self {
+        self.lazy = lazy;
+        self
+    }
+
+    /// Hashes each item passed to [`MerkleTreeBuilder::build_from_data`] with
+    /// its index, so its leaf is only valid at that position
+    ///
+    /// Leaves are `hash_leaf(index as u64 LE || data)`, see
+    /// [`crate::utils::hash_indexed_leaf`]; leaves passed to
+    /// [`MerkleTreeBuilder::build`] should already be hashed that way. Sorting
+    /// would move leaves away from their indices, so this turns it off, and
+    /// building fails if it is turned back on. Off by default.
+    pub fn bind_indices(mut self, bind_indices: bool) -> Self {
+        self.bind_indices = bind_indices;
+        if bind_indices {
+            self.sort_leaves = false;
+        }
+        self
+    }
+
+    /// Rejects leaves that aren't the length of the hasher's output; on by default
+    ///
+    /// Leaves passed to [`MerkleTreeBuilder::build`] are meant to be hashes
+    /// already, so a raw value among them is almost always a mistake. Turn
+    /// this off for trees over leaves of another size.
+    pub fn check_leaf_sizes(mut self, check_leaf_sizes: bool) -> Self {
+        self.check_leaf_sizes = check_leaf_sizes;
+        self
+    }
+
+    /// Caps the number and size of the items the tree is built from; unlimited by default
+    ///
+    /// [`MerkleTreeBuilder::build_from_data`] checks each item before hashing
+    /// it, and [`MerkleTreeBuilder::build_from_iter`] each leaf as it pulls
+    /// it, so an oversized input fails without all its leaves being made.
+    pub fn limits(mut self, limits: BuildLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Calls `progress(level, done, total)` as each level above the leaves is hashed
+    ///
+    /// `done` is the number of pair hashes so far and `total` the number the
+    /// whole build takes, so the last call has `done == total`.
+    pub fn on_progress<F: FnMut(usize, usize, usize)>(self, progress: F) -> MerkleTreeBuilder<H, F> {
+        MerkleTreeBuilder {
+            hasher: self.hasher,
+            index_leaves: self.index_leaves,
+            sort_leaves: self.sort_leaves,
+            collect_stats: self.collect_stats,
+            lazy: self.lazy,
+            bind_indices: self.bind_indices,
+            check_leaf_sizes: self.check_leaf_sizes,
+            limits: self.limits,
+            progress: Some(progress),
+        }
+    }
+
+    /// Hashes each item into a leaf and builds the tree
+    ///
+    /// Items are hashed with `hash_leaf`, prefixed with their index if
+    /// [`MerkleTreeBuilder::bind_indices`] is on.
+    pub fn build_from_data<T: AsRef<[u8]>>(mut self, data: &[T]) -> Result<MerkleTree<H>, MerkleTreeError> {
+        let mut counter = self.limits.counter();
+        let leaves = data.iter().zip(0..).map(|(item, index)| {
+            counter.add(item.as_ref().len())?;
+            Ok(if self.bind_indices {
+                crate::utils::hash_indexed_leaf(&self.hasher, index, item.as_ref())
+            } else {
+                self.hasher.hash_leaf(item.as_ref())
+            })
+        }).collect::<Result<_, MerkleTreeError>>()?;
+        // The items were counted, and each leaf is the hasher's output
+        self.limits = BuildLimits::default();
+        self.build(leaves)
+    }
+
+    /// Builds the tree from an iterator of leaves, such as a database cursor
+    ///
+    /// As [`MerkleTreeBuilder::build`], after buffering the leaves. Each leaf
+    /// is checked against the [`MerkleTreeBuilder::limits`] as it is pulled,
+    /// so an oversized stream stops at the first leaf over them. The
+    /// iterator's size hint, capped at the leaf limit, is used to reserve room
+    /// for the padded leaf count up front, so padding doesn't reallocate.
+    pub fn build_from_iter<I: IntoIterator<Item = Vec<u8>>>(mut self, leaves: I) -> Result<MerkleTree<H>, MerkleTreeError> {
+        let leaves = leaves.into_iter();
+        let hint = leaves.size_hint().0.min(self.limits.max_leaves.unwrap_or(usize::MAX));
+        let mut buffered = Vec::with_capacity(hint.checked_next_power_of_two().unwrap_or(0));
+        let mut counter = self.limits.counter();
+        for leaf in leaves {
+            counter.add(leaf.len())?;
+            buffered.push(leaf);
+        }
+        // The leaves were counted
+        self.limits = BuildLimits::default();
+        self.build(buffered)
+    }
+
+    /// Builds the tree, failing if there are no leaves or more than
+    /// [`crate::index::max_leaf_count`], or, unless turned off with
+    /// [`MerkleTreeBuilder::check_leaf_sizes`], if a leaf has the wrong length,
+    /// or if the leaves go over the [`MerkleTreeBuilder::limits`]
+    pub fn build(mut self, leaves: Vec<Vec<u8>>) -> Result<MerkleTree<H>, MerkleTreeError> {
+        if self.limits != BuildLimits::default() {
+            let mut counter = self.limits.counter();
+            for leaf in &leaves {
+                counter.add(leaf.len())?;
+            }
+        }
+        crate::tree::check_leaf_count(leaves.len())?;
+        if self.bind_indices && self.sort_leaves {
+            return Err(MerkleTreeError::SortedIndexBinding);
+        }
+        if self.check_leaf_sizes {
+            crate::tree::check_leaf_sizes(&leaves, &self.hasher)?;
+        }
+
+        #[cfg(feature = "std")]
+        let started = self.collect_stats.then(std::time::Instant::now);
+        let leaf_count = leaves.len().next_power_of_two();
+        let mut observer = Observer {
+            progress: self.progress.as_mut(),
+            pair_hashes: if self.collect_stats { vec![0; leaf_count.trailing_zeros() as usize + 1] } else { Vec::new() },
+            done: 0,
+            total: leaf_count - 1,
+        };
+
+        let built = MerkleTree::with_store_observed(
+            leaves,
+            self.hasher,
+            MemoryNodeStore::new(),
+            self.sort_leaves,
+            self.lazy,
+            &mut observer,
+        );
+        let mut tree = match built {
+            Ok(tree) => tree,
+            Err(never) => match never {},
+        };
+
+        if self.collect_stats {
+            #[cfg(feature = "std")]
+            let elapsed = started.map(|started| started.elapsed());
+            #[cfg(not(feature = "std"))]
+            let elapsed = None;
+            tree.set_build_stats(BuildStats { pair_hashes: observer.pair_hashes, elapsed });
+        }
+        if self.index_leaves {
+            tree.index_leaves();
+        }
+        if self.bind_indices {
+            let Ok(()) = tree.set_index_bound();
+        }
+        Ok(tree)
+    }
+}
+
+/// Forwards level notifications to the progress callback and the stats
+struct Observer<'a, P> {
+    progress: Option<&'a mut P>,
+    /// Hashes per level; empty unless stats are collected
+    pair_hashes: Vec<usize>,
+    done: usize,
+    total: usize,
+}
+
+impl<P: FnMut(usize, usize, usize)> BuildObserver for Observer<'_, P> {
+    fn level_hashed(&mut self, level: usize, hashes: usize) {
+        self.done += hashes;
+        if let Some(count) = self.pair_hashes.get_mut(level) {
+            *count = hashes;
+        }
+        if let Some(progress) = self.progress.as_mut() {
+            progress(level, self.done, self.total);
+        }
+    }
+}
@@ -0,0 +1,65 @@
+//! Proofs packaged with the root they prove against
+//!
+//! A [`ProofBundle`] is a single object to hand a client: the proof, the
+//! root, and with the `signing` feature optionally a signed head for that
+//! root, so the client needs nothing else to check membership.
+use alloc::vec::Vec;
+use crate::error::MerkleTreeError;
+use crate::hasher::Hasher;
+use crate::index::LeafIndex;
+use crate::proof::MerkleProof;
+use crate::store::NodeStore;
+use crate::tree::MerkleTree;
+#[cfg(feature = "signing")]
+use crate::signing::{SignedRoot, VerifyingKey};
+
+/// A proof with the root it proves against
+pub struct ProofBundle<H: Hasher> {
+    pub proof: MerkleProof<H>,
+    pub root: Vec<u8>,
+    /// A signed head for `root`, if the publisher provided one
+    #[cfg(feature = "signing")]
+    pub signed_root: Option<SignedRoot>,
+}
+
+impl<H: Hasher> ProofBundle<H> {
+    /// Bundles a proof with its root
+    pub fn new(proof: MerkleProof<H>, root: Vec<u8>) -> Self {
+        ProofBundle {
+            proof,
+            root,
+            #[cfg(feature = "signing")]
+            signed_root: None,
+        }
+    }
+
+    /// Bundles the proof of the leaf at `index` with the tree's root
+    pub fn from_tree<S: NodeStore>(tree: &MerkleTree<H, S>, index: LeafIndex) -> Result<Self, MerkleTreeError> {
+        Ok(Self::new(tree.generate_proof(index)?, tree.root().to_vec()))
+    }
+
+    /// Adds a signed head, which must be for the bundle's root to verify
+    #[cfg(feature = "signing")]
+    pub fn with_signed_root(mut self, signed_root: SignedRoot) -> Self {
+        self.signed_root = Some(signed_root);
+        self
+    }
+
+    /// Checks the proof against the bundled root, and that any signed head is for that root
+    ///
+    /// The signature itself isn't checked; use [`ProofBundle::verify_signed`]
+    /// with the publisher's key for that.
+    pub fn verify(&self) -> bool {
+        #[cfg(feature = "signing")]
+        if self.signed_root.as_ref().is_some_and(|signed| signed.root != self.root) {
+            return false;
+        }
+        self.proof.verify(&self.root)
+    }
+
+    /// Checks the proof and that the bundle carries a signed head for its root valid under `key`
+    #[cfg(feature = "signing")]
+    pub fn verify_signed(&self, key: &VerifyingKey) -> bool {
+        self.signed_root.as_ref().is_some_and(|signed| signed.verify(key)) && self.verify()
+    }
+}
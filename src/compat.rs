@@ -0,0 +1,2 @@
+//! Conversions to and from the proof formats of other Merkle tree crates
+pub mod rs_merkle;
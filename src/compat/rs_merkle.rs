@@ -0,0 +1,227 @@
+//! Proofs for the `rs_merkle` crate
+//!
+//! An `rs_merkle` proof is a flat list of sibling hashes without directions;
+//! the verifier places them from the proven leaf indices and the total leaf
+//! count. The hashes go layer by layer from the leaves up, in index order
+//! within a layer, leaving out any node the verifier can hash itself, so one
+//! proof can cover several leaves.
+//!
+//! [`to_rs_merkle_proof`] gives an `rs_merkle::MerkleProof`, and
+//! [`from_rs_merkle_proof`] turns one back into a proof per leaf. Hashes
+//! cross over when the two hashers agree on pairs, as
+//! [`crate::hasher::Sha256Hasher`] does with `rs_merkle::algorithms::Sha256`.
+//!
+//! The crates differ on odd layers: `rs_merkle` carries the last node of an
+//! odd layer up unhashed, where this crate pads the leaves with copies of
+//! the last one. A tree over a power of two leaves has the same shape in
+//! both. For other counts, build the `rs_merkle` tree from
+//! [`rs_merkle_leaves`], which includes the padding, and verify with the
+//! padded count from [`leaf_position`]; the root is then this tree's. Proofs
+//! from `rs_merkle` trees of any count convert, skipping the layers where
+//! the proven node was carried up, and verify against that tree's root.
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::fmt;
+use crate::hasher::Hasher;
+use crate::index::{self, LeafIndex};
+use crate::proof::{MerkleProof, ProofItem};
+use crate::store::NodeStore;
+use crate::tree::MerkleTree;
+
+/// The length of every hash in an `rs_merkle` proof
+pub const HASH_LEN: usize = 32;
+
+/// A node hash in an `rs_merkle` proof
+pub type Hash = [u8; HASH_LEN];
+
+/// Reasons a proof can't be converted between the formats
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompatError {
+    /// A leaf, proof or hasher output isn't [`HASH_LEN`] bytes
+    InvalidHashLength { expected: usize, actual: usize },
+    /// The proof covers no leaves
+    NoLeaves,
+    /// The number of leaf indices and leaf hashes differ
+    LeafCountMismatch { indices: usize, hashes: usize },
+    /// A leaf index is past the end of the tree
+    IndexOutOfRange { index: usize, total: usize },
+    /// A leaf index was given twice
+    DuplicateIndex(usize),
+    /// The proof has the wrong number of hashes for its leaves
+    WrongProofLength { expected: usize, actual: usize },
+    /// The proof is too deep for its leaf count to fit a `usize`
+    TooDeep(usize),
+}
+
+impl fmt::Display for CompatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompatError::InvalidHashLength { expected, actual } => write!(f, "Hash is {} bytes, expected {}", actual, expected),
+            CompatError::NoLeaves => write!(f, "Proof covers no leaves"),
+            CompatError::LeafCountMismatch { indices, hashes } => write!(f, "{} leaf indices but {} leaf hashes", indices, hashes),
+            CompatError::IndexOutOfRange { index, total } => write!(f, "Leaf index {} out of range for {} leaves", index, total),
+            CompatError::DuplicateIndex(index) => write!(f, "Leaf index {} given twice", index),
+            CompatError::WrongProofLength { expected, actual } => write!(f, "Proof has {} hashes, expected {}", actual, expected),
+            CompatError::TooDeep(depth) => write!(f, "Proof of depth {} is too deep", depth),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for CompatError {}
+
+/// Places a proof's hashes beside the leaves and hashes every node they determine, layer by layer
+///
+/// As in `rs_merkle`, the last node of an odd layer has no sibling and is
+/// carried up as it is.
+fn partial_tree<H: Hasher>(
+    proof_hashes: &[Hash],
+    leaf_indices: &[usize],
+    leaf_hashes: &[Hash],
+    total_leaves: usize,
+    hasher: &H,
+) -> Result<Vec<BTreeMap<usize, Hash>>, CompatError> {
+    if hasher.output_size() != HASH_LEN {
+        return Err(CompatError::InvalidHashLength { expected: HASH_LEN, actual: hasher.output_size() });
+    }
+    if leaf_indices.len() != leaf_hashes.len() {
+        return Err(CompatError::LeafCountMismatch { indices: leaf_indices.len(), hashes: leaf_hashes.len() });
+    }
+    if leaf_indices.is_empty() {
+        return Err(CompatError::NoLeaves);
+    }
+    let mut layer = BTreeMap::new();
+    for (&index, hash) in leaf_indices.iter().zip(leaf_hashes) {
+        if index >= total_leaves {
+            return Err(CompatError::IndexOutOfRange { index, total: total_leaves });
+        }
+        if layer.insert(index, *hash).is_some() {
+            return Err(CompatError::DuplicateIndex(index));
+        }
+    }
+
+    // The width of each layer below the root
+    let mut widths = Vec::new();
+    let mut width = total_leaves;
+    while width > 1 {
+        widths.push(width);
+        width = width.div_ceil(2);
+    }
+
+    // The siblings the proof supplies on each layer, found from the indices alone
+    let mut known: Vec<usize> = layer.keys().copied().collect();
+    let mut proof_indices = Vec::with_capacity(widths.len());
+    for &width in &widths {
+        // `known` is sorted, and so are its siblings
+        let missing: Vec<usize> = known.iter()
+            .map(|index| index ^ 1)
+            .filter(|&sibling| sibling < width && known.binary_search(&sibling).is_err())
+            .collect();
+        known.dedup_by_key(|index| *index / 2);
+        known.iter_mut().for_each(|index| *index /= 2);
+        proof_indices.push(missing);
+    }
+    let expected = proof_indices.iter().map(Vec::len).sum();
+    if proof_hashes.len() != expected {
+        return Err(CompatError::WrongProofLength { expected, actual: proof_hashes.len() });
+    }
+
+    let mut proof_hashes = proof_hashes.iter();
+    let mut layers = Vec::with_capacity(widths.len() + 1);
+    for missing in proof_indices {
+        layer.extend(missing.into_iter().zip(proof_hashes.by_ref().copied()));
+        let parents = layer.iter()
+            .filter(|(index, _)| index.is_multiple_of(2))
+            .map(|(&index, left)| {
+                let parent = match layer.get(&(index + 1)) {
+                    Some(right) => hasher.hash_pair(left, right).try_into().expect("the hasher output is HASH_LEN bytes"),
+                    None => *left,
+                };
+                (index / 2, parent)
+            })
+            .collect();
+        layers.push(core::mem::replace(&mut layer, parents));
+    }
+    layers.push(layer);
+    Ok(layers)
+}
+
+/// Gets the leaves to build the matching `rs_merkle` tree from, padding included
+///
+/// `rs_merkle::MerkleTree::from_leaves` over these has the same root as
+/// `tree` when the hashers agree, whatever its leaf count.
+pub fn rs_merkle_leaves<H: Hasher, S: NodeStore>(tree: &MerkleTree<H, S>) -> Result<Vec<Hash>, CompatError> {
+    (0..tree.leaf_count)
+        .map(|index| match tree.get_leaf(index as LeafIndex) {
+            Some(leaf) => check_hash_len(leaf.as_ref()),
+            None => Err(CompatError::IndexOutOfRange { index, total: tree.leaf_count }),
+        })
+        .collect()
+}
+
+/// Gets the leaf index and leaf count to verify a converted proof with
+///
+/// A proof's directions spell out its leaf's index, and its length the
+/// padded leaf count.
+pub fn leaf_position<H: Hasher>(proof: &MerkleProof<H>) -> Result<(usize, usize), CompatError> {
+    let depth = proof.proof_items.len();
+    let total = u32::try_from(depth).ok().and_then(|depth| 1usize.checked_shl(depth)).ok_or(CompatError::TooDeep(depth))?;
+    let index = proof.path_index().and_then(index::to_usize).ok_or(CompatError::TooDeep(depth))?;
+    Ok((index, total))
+}
+
+/// Converts a proof of one leaf into an `rs_merkle` proof
+///
+/// The proven leaf and its position, from [`leaf_position`], are passed to
+/// `rs_merkle::MerkleProof::verify` alongside it; `T` is the `rs_merkle`
+/// hasher matching the proof's, such as `rs_merkle::algorithms::Sha256`.
+pub fn to_rs_merkle_proof<T, H>(proof: &MerkleProof<H>) -> Result<::rs_merkle::MerkleProof<T>, CompatError>
+where
+    T: ::rs_merkle::Hasher<Hash = Hash>,
+    H: Hasher,
+{
+    leaf_position(proof)?;
+    check_hash_len(&proof.leaf)?;
+    let proof_hashes = proof.proof_items.iter()
+        .map(|item| check_hash_len(&item.hash))
+        .collect::<Result<_, _>>()?;
+    Ok(::rs_merkle::MerkleProof::new(proof_hashes))
+}
+
+/// Converts an `rs_merkle` proof of one or more leaves into a proof per leaf
+///
+/// The leaf hashes are needed as well as their indices: a proof of several
+/// leaves leaves out the nodes they determine between them. Proofs come
+/// back in the order of `leaf_indices`, without items for the layers where
+/// `rs_merkle` carried the leaf's node up; `hasher` must hash pairs as `T`
+/// does.
+pub fn from_rs_merkle_proof<T, H>(
+    proof: &::rs_merkle::MerkleProof<T>,
+    leaf_indices: &[usize],
+    leaf_hashes: &[Hash],
+    total_leaves: usize,
+    hasher: H,
+) -> Result<Vec<MerkleProof<H>>, CompatError>
+where
+    T: ::rs_merkle::Hasher<Hash = Hash>,
+    H: Hasher,
+{
+    let layers = partial_tree(proof.proof_hashes(), leaf_indices, leaf_hashes, total_leaves, &hasher)?;
+    let proofs = leaf_indices.iter().zip(leaf_hashes)
+        .map(|(&leaf_index, leaf)| {
+            let proof_items = layers[..layers.len() - 1].iter().enumerate()
+                .filter_map(|(level, layer)| {
+                    let index = leaf_index >> level;
+                    let sibling = layer.get(&(index ^ 1))?;
+                    Some(ProofItem { hash: sibling.to_vec().into(), is_left: index % 2 == 1 })
+                })
+                .collect();
+            MerkleProof::new(leaf.to_vec(), proof_items, hasher.clone())
+        })
+        .collect();
+    Ok(proofs)
+}
+
+fn check_hash_len(hash: &[u8]) -> Result<Hash, CompatError> {
+    hash.try_into().map_err(|_| CompatError::InvalidHashLength { expected: HASH_LEN, actual: hash.len() })
+}
@@ -0,0 +1,238 @@
+//! Dumps of every node hash, for auditors recomputing a tree independently
+//!
+//! [`MerkleTree::export_nodes`] writes each level in turn, leaves (with
+//! padding) first and the root last, in one of two formats:
+//!
+//! - [`DumpFormat::Text`]: a `level,index,hex` line per node.
+//! - [`DumpFormat::Binary`]: the magic `MRKD` and a version byte, then per
+//!   node its level as a `u32`, index as a `u64` and hash length as a `u32`,
+//!   all little-endian, followed by the hash.
+//!
+//! [`verify_node_dump`] reads either format back, hashing each parent from
+//! the children before it as soon as the parent is read, so it stops at the
+//! first inconsistent record.
+use std::fmt;
+use std::io::{self, BufRead, BufWriter, Read, Write};
+use crate::error::MerkleTreeError;
+use crate::hasher::Hasher;
+use crate::index::LeafIndex;
+use crate::store::NodeStore;
+use crate::tree::MerkleTree;
+
+const MAGIC: &[u8; 4] = b"MRKD";
+const VERSION: u8 = 1;
+
+/// The format of a node dump
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DumpFormat {
+    /// A `level,index,hex` line per node
+    Text,
+    /// Length-prefixed binary records
+    Binary,
+}
+
+/// Reasons a node dump doesn't describe a consistent tree
+#[derive(Debug)]
+pub enum DumpError {
+    /// Reading the dump failed
+    Io(io::Error),
+    /// A record couldn't be parsed; records count from 1, so in text this is
+    /// the line number, and 0 is the binary header
+    Malformed { record: usize },
+    /// The binary dump was written by an unsupported format version
+    UnsupportedVersion(u8),
+    /// A node is not the next one in level order
+    OutOfOrder { level: usize, index: usize },
+    /// A node differs from the hash of its two children
+    Mismatch { level: usize, index: usize },
+    /// The dump ends partway through this level, or before reaching a single root
+    Incomplete { level: usize },
+}
+
+impl fmt::Display for DumpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DumpError::Io(err) => write!(f, "I/O error: {}", err),
+            DumpError::Malformed { record } => write!(f, "Malformed record {}", record),
+            DumpError::UnsupportedVersion(version) => write!(f, "Unsupported dump version {}", version),
+            DumpError::OutOfOrder { level, index } => write!(f, "Node ({}, {}) is out of order", level, index),
+            DumpError::Mismatch { level, index } => write!(f, "Node ({}, {}) does not match its children", level, index),
+            DumpError::Incomplete { level } => write!(f, "Dump ends before level {} is complete", level),
+        }
+    }
+}
+
+impl std::error::Error for DumpError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DumpError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for DumpError {
+    fn from(err: io::Error) -> Self {
+        DumpError::Io(err)
+    }
+}
+
+impl<H: Hasher, S: NodeStore> MerkleTree<H, S> {
+    /// Writes every node hash to `writer`, level by level from the leaves up
+    ///
+    /// Nodes are written one at a time through a buffer; lazy and pruned
+    /// trees compute their interior nodes as they go. Zeroized trees have no
+    /// nodes to write and fail.
+    pub fn export_nodes<W: Write>(&self, writer: W, format: DumpFormat) -> io::Result<()> {
+        let mut writer = BufWriter::new(writer);
+        if format == DumpFormat::Binary {
+            writer.write_all(MAGIC)?;
+            writer.write_all(&[VERSION])?;
+        }
+        let levels = self.leaf_count().trailing_zeros() as usize + 1;
+        for level in 0..levels {
+            for index in 0..self.leaf_count() >> level {
+                let node = self.get_node(level, index).ok_or_else(|| io::Error::other(self.missing(level, index)))?;
+                match format {
+                    DumpFormat::Text => writeln!(writer, "{},{},{}", level, index, hex::encode(&node))?,
+                    DumpFormat::Binary => {
+                        writer.write_all(&(level as u32).to_le_bytes())?;
+                        writer.write_all(&index.to_le_bytes())?;
+                        writer.write_all(&(node.len() as u32).to_le_bytes())?;
+                        writer.write_all(&node)?;
+                    }
+                }
+            }
+        }
+        writer.flush()
+    }
+
+    fn missing(&self, level: usize, index: LeafIndex) -> MerkleTreeError {
+        #[cfg(feature = "zeroize")]
+        if self.is_zeroized() {
+            return MerkleTreeError::LeavesZeroized;
+        }
+        MerkleTreeError::MissingNode { level, index }
+    }
+}
+
+/// Checks a dump from [`MerkleTree::export_nodes`], in either format, returning its root
+///
+/// Only the previous level is held in memory. A wrong leaf can't be told
+/// apart from a wrong parent, so either is reported as a
+/// [`DumpError::Mismatch`] at the parent; a wrong interior node or root is
+/// reported where it is.
+pub fn verify_node_dump<R: BufRead, H: Hasher>(mut reader: R, hasher: &H) -> Result<Vec<u8>, DumpError> {
+    let binary = reader.fill_buf()?.first() == Some(&MAGIC[0]);
+    if binary {
+        let mut header = [0u8; 5];
+        reader.read_exact(&mut header).map_err(|_| DumpError::Malformed { record: 0 })?;
+        if &header[..4] != MAGIC {
+            return Err(DumpError::Malformed { record: 0 });
+        }
+        if header[4] != VERSION {
+            return Err(DumpError::UnsupportedVersion(header[4]));
+        }
+    }
+
+    let mut checker = DumpChecker::default();
+    let mut line = String::new();
+    loop {
+        let record = checker.records + 1;
+        let node = if binary {
+            read_binary_record(&mut reader, record)?
+        } else {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 {
+                None
+            } else {
+                Some(parse_text_record(&line).ok_or(DumpError::Malformed { record })?)
+            }
+        };
+        match node {
+            Some((level, index, hash)) => checker.push(level, index, hash, hasher)?,
+            None => return checker.finish(),
+        }
+    }
+}
+
+fn parse_text_record(line: &str) -> Option<(usize, usize, Vec<u8>)> {
+    let mut fields = line.trim_end().split(',');
+    let level = fields.next()?.parse().ok()?;
+    let index = fields.next()?.parse().ok()?;
+    let hash = hex::decode(fields.next()?).ok()?;
+    fields.next().is_none().then_some((level, index, hash))
+}
+
+fn read_binary_record<R: Read>(reader: &mut R, record: usize) -> Result<Option<(usize, usize, Vec<u8>)>, DumpError> {
+    let mut header = [0u8; 16];
+    // A clean end is only allowed between records
+    let read = reader.read(&mut header[..1])?;
+    if read == 0 {
+        return Ok(None);
+    }
+    reader.read_exact(&mut header[1..]).map_err(|_| DumpError::Malformed { record })?;
+    let level = u32::from_le_bytes(header[..4].try_into().expect("4 bytes")) as usize;
+    let index = usize::try_from(u64::from_le_bytes(header[4..12].try_into().expect("8 bytes")))
+        .map_err(|_| DumpError::Malformed { record })?;
+    let len = u32::from_le_bytes(header[12..].try_into().expect("4 bytes")) as usize;
+    let mut hash = Vec::new();
+    reader.take(len as u64).read_to_end(&mut hash)?;
+    if hash.len() != len {
+        return Err(DumpError::Malformed { record });
+    }
+    Ok(Some((level, index, hash)))
+}
+
+/// Checks nodes as they arrive in level order
+#[derive(Default)]
+struct DumpChecker {
+    /// The complete level below the one being read
+    previous: Vec<Vec<u8>>,
+    /// The level being read so far
+    current: Vec<Vec<u8>>,
+    level: usize,
+    records: usize,
+}
+
+impl DumpChecker {
+    fn push<H: Hasher>(&mut self, level: usize, index: usize, hash: Vec<u8>, hasher: &H) -> Result<(), DumpError> {
+        if level == self.level + 1 && index == 0 && self.records > 0 {
+            if !self.level_complete() || self.current.len() == 1 {
+                return Err(DumpError::OutOfOrder { level, index });
+            }
+            self.previous = core::mem::take(&mut self.current);
+            self.level = level;
+        }
+        if level != self.level || index != self.current.len() {
+            return Err(DumpError::OutOfOrder { level, index });
+        }
+        if level > 0 {
+            let (Some(left), Some(right)) = (self.previous.get(2 * index), self.previous.get(2 * index + 1)) else {
+                return Err(DumpError::OutOfOrder { level, index });
+            };
+            if hasher.hash_pair(left, right) != hash {
+                return Err(DumpError::Mismatch { level, index });
+            }
+        }
+        self.current.push(hash);
+        self.records += 1;
+        Ok(())
+    }
+
+    /// Whether the level being read has all its nodes: a power of two of leaves, or half the level below
+    fn level_complete(&self) -> bool {
+        if self.level == 0 {
+            self.current.len().is_power_of_two()
+        } else {
+            self.current.len() == self.previous.len() / 2
+        }
+    }
+
+    fn finish(mut self) -> Result<Vec<u8>, DumpError> {
+        if self.current.len() != 1 || !self.level_complete() {
+            return Err(DumpError::Incomplete { level: self.level });
+        }
+        Ok(self.current.pop().expect("one node"))
+    }
+}
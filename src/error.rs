@@ -0,0 +1,223 @@
+//! The error type shared by the crate's fallible APIs
+//!
+//! Reading and writing outside formats fails with the format's own error
+//! type rather than a `MerkleTreeError` variant:
+//! [`crate::store::NodeStore::Error`] for storage backends,
+//! `persist::PersistError` and `persist::FormatError` for the file format,
+//! `utils::CsvTreeError` for CSV input, `msgpack::MsgpackError` for
+//! MessagePack, `compat::rs_merkle::CompatError` for `rs_merkle` proofs, and
+//! `dump::DumpError` and `vectors::VectorError` for node dumps and fixtures.
+//! Callers handle those failures apart from tree errors, and most are behind
+//! features, which would otherwise add variants to every build. Store and
+//! limit failures inside tree operations are wrapped, as
+//! [`MerkleTreeError::StoreRead`] and [`MerkleTreeError::LimitExceeded`].
+use alloc::string::String;
+use crate::index::LeafIndex;
+
+/// Errors from building trees, generating proofs and parsing proofs
+///
+/// Each variant's message comes from [`MerkleTreeError::message`], which
+/// `Display` follows with the variant's details.
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum MerkleTreeError {
+    /// A tree needs at least one leaf
+    #[error("{}", self.message())]
+    EmptyLeaves,
+    /// The leaf index is past the end of the tree
+    #[error("{}: index {index}, {len} leaves", self.message())]
+    LeafIndexOutOfBounds { index: LeafIndex, len: u64 },
+    /// The subtree index is past the end of the forest
+    #[error("{}: index {index}, {len} trees", self.message())]
+    TreeIndexOutOfBounds { index: usize, len: usize },
+    /// The leaf was added to pad the tree and has no proof of its own
+    #[error("{}: index {index}", self.message())]
+    PaddingLeaf { index: LeafIndex },
+    /// The position holds an interior node rather than a leaf
+    #[error("{}: position {position}", self.message())]
+    NotALeaf { position: u64 },
+    /// No leaf has the requested value
+    #[error("{}", self.message())]
+    LeafNotFound,
+    /// A serialized proof could not be parsed
+    #[error("{0}")]
+    ProofParse(#[from] ProofParseError),
+    /// The hasher doesn't produce the hashes the tree or proof needs
+    #[error("{}", self.message())]
+    HasherMismatch,
+    /// A proof has the wrong number of items for the tree
+    #[error("{}: {actual} items, expected {expected}", self.message())]
+    InvalidProofLength { expected: usize, actual: usize },
+    /// A proof's directions lead to a different leaf than the one given
+    #[error("{}: expected leaf {index}", self.message())]
+    ProofIndexMismatch { index: LeafIndex },
+    /// A leaf given to build a tree isn't the length of the hasher's output
+    #[error("{}: leaf {index} is {actual} bytes, expected {expected}", self.message())]
+    LeafSizeMismatch { index: usize, expected: usize, actual: usize },
+    /// A leaf or proof hash has the wrong length
+    #[error("{}: {actual} bytes, expected {expected}", self.message())]
+    InvalidHashLength { expected: usize, actual: usize },
+    /// The store lacks a node the tree needs
+    #[error("{}: level {level}, index {index}", self.message())]
+    MissingNode { level: usize, index: LeafIndex },
+    /// The store failed to read a node, see [`crate::store::StoreReadError`]
+    #[error("{0}")]
+    StoreRead(#[from] crate::store::StoreReadError),
+    /// The store doesn't hold a single complete tree and its record
+    #[error("{}", self.message())]
+    IncompleteStore,
+    /// The tree computes its interior nodes on demand, so it can't lend them out
+    #[error("{}", self.message())]
+    NodesNotStored,
+    /// Leaves bound to their indices can't also be sorted
+    #[error("{}", self.message())]
+    SortedIndexBinding,
+    /// More leaves than a tree can have, see [`crate::index::max_leaf_count`]
+    #[error("{}: {count}, the maximum is {max}", self.message())]
+    TooManyLeaves { count: u64, max: u64 },
+    /// The tree is too large for the requested rendering
+    #[error("{}: {nodes}, the limit is {max}", self.message())]
+    TooManyNodes { nodes: usize, max: usize },
+    /// The leaves were scrubbed with `zeroize_leaves`, so only the root is left
+    #[error("{}", self.message())]
+    LeavesZeroized,
+    /// The query needs a tree whose leaves were sorted on construction
+    #[error("{}", self.message())]
+    LeavesNotSorted,
+    /// A chunk size of zero was given
+    #[error("{}", self.message())]
+    InvalidChunkSize,
+    /// An input line is longer than the configured maximum
+    #[error("{}: line {line} exceeds {max} bytes", self.message())]
+    LineTooLong { line: usize, max: usize },
+    /// The input is larger than the [`crate::builder::BuildLimits`] allow
+    #[error("{message}: {0}", message = self.message())]
+    LimitExceeded(#[from] LimitExceeded),
+    /// Reading the input failed
+    #[cfg(feature = "std")]
+    #[error("{message}: {0}", message = self.message())]
+    Io(#[from] std::io::Error),
+}
+
+impl MerkleTreeError {
+    /// The fixed message for the variant, without its details
+    pub fn message(&self) -> &'static str {
+        match self {
+            MerkleTreeError::EmptyLeaves => "Cannot create a Merkle tree with no leaves",
+            MerkleTreeError::LeafIndexOutOfBounds { .. } => "Leaf index out of bounds",
+            MerkleTreeError::TreeIndexOutOfBounds { .. } => "Tree index out of bounds",
+            MerkleTreeError::PaddingLeaf { .. } => "Leaf index refers to padding",
+            MerkleTreeError::NotALeaf { .. } => "Position is not a leaf",
+            MerkleTreeError::LeafNotFound => "Leaf value not found in the tree",
+            MerkleTreeError::ProofParse(err) => err.message(),
+            MerkleTreeError::HasherMismatch => "Hasher output does not match the expected hash length",
+            MerkleTreeError::InvalidProofLength { .. } => "Invalid proof length",
+            MerkleTreeError::ProofIndexMismatch { .. } => "Proof is for a different leaf",
+            MerkleTreeError::LeafSizeMismatch { .. } => "Leaf is not the hasher's output size",
+            MerkleTreeError::InvalidHashLength { .. } => "Invalid hash length",
+            MerkleTreeError::MissingNode { .. } => "Missing node in the tree",
+            MerkleTreeError::StoreRead(_) => "Failed to read from the store",
+            MerkleTreeError::IncompleteStore => "Store does not contain a complete tree",
+            MerkleTreeError::NodesNotStored => "Interior nodes are not stored; use generate_proof",
+            MerkleTreeError::SortedIndexBinding => "Index-bound leaves cannot be sorted",
+            MerkleTreeError::TooManyLeaves { .. } => "Too many leaves",
+            MerkleTreeError::TooManyNodes { .. } => "Tree has too many nodes",
+            MerkleTreeError::LeavesZeroized => "Leaves have been zeroized",
+            MerkleTreeError::LeavesNotSorted => "Leaves are not sorted",
+            MerkleTreeError::InvalidChunkSize => "Chunk size must be non-zero",
+            MerkleTreeError::LineTooLong { .. } => "Line too long",
+            MerkleTreeError::LimitExceeded(_) => "Build limit exceeded",
+            #[cfg(feature = "std")]
+            MerkleTreeError::Io(_) => "I/O error",
+        }
+    }
+
+    /// The message the `&'static str` errors used to carry, see [`MerkleTreeError::message`]
+    #[deprecated(note = "match on the `MerkleTreeError` variant instead; this will be removed in the next release")]
+    pub fn as_str(&self) -> &'static str {
+        self.message()
+    }
+}
+
+/// Lets `?` keep working in functions that still return `&'static str`
+///
+/// Deprecated along with [`MerkleTreeError::as_str`] and removed with it in
+/// the next release.
+impl From<MerkleTreeError> for &'static str {
+    fn from(err: MerkleTreeError) -> Self {
+        #[allow(deprecated)]
+        err.as_str()
+    }
+}
+
+/// The limit in [`crate::builder::BuildLimits`] that an input went over
+///
+/// Items count from 0 in the order they were read, counting only those that
+/// became leaves.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
+pub enum LimitExceeded {
+    /// There are more than `max` leaves
+    #[error("More than {max} leaves")]
+    Leaves { max: usize },
+    /// An item is more than `max` bytes; `len` is as much of it as was read
+    #[error("Item {item} is over {max} bytes")]
+    LeafBytes { item: usize, len: usize, max: usize },
+    /// The items add up to more than `max` bytes
+    #[error("Input exceeds {max} bytes")]
+    TotalBytes { max: usize },
+}
+
+/// Reasons a serialized proof is malformed
+///
+/// Errors in a proof item give the item's index and, for the debug format,
+/// the field that was bad. As with [`MerkleTreeError`], `Display` is
+/// [`ProofParseError::message`] followed by the details.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
+pub enum ProofParseError {
+    /// The data ends in the middle of a field
+    #[error("{}", self.message())]
+    Truncated,
+    /// A direction byte is neither 0 (right) nor 1 (left)
+    #[error("{message}: byte {0}", message = self.message())]
+    InvalidDirection(u8),
+    /// There is data after the last proof item
+    #[error("{}", self.message())]
+    TrailingBytes,
+    /// A proof item lacks a field
+    #[error("{}: item {item} is missing '{field}'", self.message())]
+    MissingField { item: usize, field: &'static str },
+    /// A proof item's field is not valid hex
+    #[error("{}: item {item}, field '{field}'", self.message())]
+    InvalidHex { item: usize, field: &'static str },
+    /// A proof item's direction is neither "left" nor "right"
+    #[error("{}: item {item} has '{direction}'", self.message())]
+    UnknownDirection { item: usize, direction: String },
+    /// JSON that isn't an object with a `leaf` and either `proof` or `siblings`
+    #[error("{}", self.message())]
+    UnrecognizedShape,
+    /// A JSON proof lacks a top-level key
+    #[error("{message}: '{0}'", message = self.message())]
+    MissingKey(&'static str),
+    /// A value in a JSON proof has the wrong type, such as a number for a hash
+    #[error("{}: '{field}' must be {expected}", self.message())]
+    WrongType { field: String, expected: &'static str },
+}
+
+impl ProofParseError {
+    /// The fixed message for the variant, without its details
+    pub fn message(&self) -> &'static str {
+        match self {
+            ProofParseError::Truncated => "Truncated proof",
+            ProofParseError::InvalidDirection(_) => "Invalid proof direction",
+            ProofParseError::TrailingBytes => "Trailing bytes after proof",
+            ProofParseError::MissingField { .. } => "Missing proof field",
+            ProofParseError::InvalidHex { .. } => "Invalid hex in proof",
+            ProofParseError::UnknownDirection { .. } => "Invalid proof direction",
+            ProofParseError::UnrecognizedShape => "Unrecognized proof shape",
+            ProofParseError::MissingKey(_) => "Missing proof field",
+            ProofParseError::WrongType { .. } => "Proof field has the wrong type",
+        }
+    }
+}
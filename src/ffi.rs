@@ -0,0 +1,279 @@
+//! C ABI for building trees, generating proofs and verifying them
+//!
+//! Every function returns a [`MerkleStatus`] instead of unwinding; a panic
+//! inside the library is caught and reported as [`MerkleStatus::Panic`].
+//! Hashers are chosen by name (see [`BuiltinHasher::from_name`]) and leaves
+//! are raw data, hashed with the hasher's leaf hash.
+//!
+//! Ownership: handles returned through `out_*` pointers are owned by the
+//! caller and must be released with the matching `*_free` function exactly
+//! once. Input buffers are only borrowed for the duration of the call.
+//! Output buffers are caller-allocated: pass the buffer and its capacity,
+//! and the required length is always written to `out_len`, so a call with a
+//! too small (or null, zero-capacity) buffer reports the size to allocate.
+//!
+//! The C header is generated with `cbindgen` into `include/merkle_tree.h`
+//! (see `cbindgen.toml`). Build a linkable library with
+//! `cargo rustc --release --features ffi --crate-type cdylib` (or `staticlib`).
+use std::ffi::{c_char, CStr};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use crate::hasher::{BuiltinHasher, Hasher};
+use crate::index::LeafIndex;
+use crate::proof::MerkleProof;
+use crate::tree::MerkleTree;
+
+/// Result codes returned by every FFI function
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MerkleStatus {
+    /// The call succeeded
+    Ok = 0,
+    /// A required pointer argument was null
+    NullPointer = 1,
+    /// An argument was invalid, e.g. a non UTF-8 hasher name or an empty leaf list
+    InvalidArgument = 2,
+    /// The hasher name is not a built-in hasher
+    UnknownHasher = 3,
+    /// The leaf index is out of bounds
+    IndexOutOfBounds = 4,
+    /// The output buffer is too small; `out_len` holds the required size
+    BufferTooSmall = 5,
+    /// The serialized proof could not be parsed
+    InvalidProof = 6,
+    /// The library panicked; this is a bug
+    Panic = 7,
+}
+
+/// An opaque handle to a Merkle tree
+pub struct MerkleTreeHandle(MerkleTree<BuiltinHasher>);
+
+/// An opaque handle to a Merkle proof
+pub struct MerkleProofHandle(MerkleProof<BuiltinHasher>);
+
+/// Runs an FFI body, turning a panic into a status code
+fn guard<F: FnOnce() -> MerkleStatus>(body: F) -> MerkleStatus {
+    catch_unwind(AssertUnwindSafe(body)).unwrap_or(MerkleStatus::Panic)
+}
+
+/// Looks up a hasher from a NUL-terminated name
+unsafe fn hasher_from_name(name: *const c_char) -> Result<BuiltinHasher, MerkleStatus> {
+    if name.is_null() {
+        return Err(MerkleStatus::NullPointer);
+    }
+    let name = CStr::from_ptr(name).to_str().map_err(|_| MerkleStatus::InvalidArgument)?;
+    BuiltinHasher::from_name(name).ok_or(MerkleStatus::UnknownHasher)
+}
+
+/// Borrows a byte buffer, allowing a null pointer for an empty buffer
+unsafe fn input_bytes<'a>(data: *const u8, len: usize) -> Result<&'a [u8], MerkleStatus> {
+    if len == 0 {
+        Ok(&[])
+    } else if data.is_null() {
+        Err(MerkleStatus::NullPointer)
+    } else {
+        Ok(std::slice::from_raw_parts(data, len))
+    }
+}
+
+/// Copies `bytes` into a caller-provided buffer, always reporting the length
+unsafe fn write_output(bytes: &[u8], out: *mut u8, out_capacity: usize, out_len: *mut usize) -> MerkleStatus {
+    if out_len.is_null() {
+        return MerkleStatus::NullPointer;
+    }
+    *out_len = bytes.len();
+    if out_capacity < bytes.len() {
+        return MerkleStatus::BufferTooSmall;
+    }
+    if !bytes.is_empty() {
+        if out.is_null() {
+            return MerkleStatus::NullPointer;
+        }
+        std::ptr::copy_nonoverlapping(bytes.as_ptr(), out, bytes.len());
+    }
+    MerkleStatus::Ok
+}
+
+/// Builds a tree from `leaf_count` raw leaves
+///
+/// On success `*out_tree` receives a new tree handle, owned by the caller and
+/// released with [`merkle_tree_free`].
+///
+/// # Safety
+///
+/// `leaves` and `leaf_lens` must point to `leaf_count` entries each, where
+/// `leaves[i]` points to `leaf_lens[i]` readable bytes. `hasher_name` must be
+/// a NUL-terminated string and `out_tree` a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn merkle_tree_new(
+    leaves: *const *const u8,
+    leaf_lens: *const usize,
+    leaf_count: usize,
+    hasher_name: *const c_char,
+    out_tree: *mut *mut MerkleTreeHandle,
+) -> MerkleStatus {
+    guard(|| {
+        if out_tree.is_null() || leaves.is_null() || leaf_lens.is_null() {
+            return MerkleStatus::NullPointer;
+        }
+        if leaf_count == 0 {
+            return MerkleStatus::InvalidArgument;
+        }
+        let hasher = match hasher_from_name(hasher_name) {
+            Ok(hasher) => hasher,
+            Err(status) => return status,
+        };
+
+        let leaf_ptrs = std::slice::from_raw_parts(leaves, leaf_count);
+        let lens = std::slice::from_raw_parts(leaf_lens, leaf_count);
+        let mut hashed = Vec::with_capacity(leaf_count);
+        for (&ptr, &len) in leaf_ptrs.iter().zip(lens) {
+            match input_bytes(ptr, len) {
+                Ok(data) => hashed.push(hasher.hash_leaf(data)),
+                Err(status) => return status,
+            }
+        }
+
+        let tree = MerkleTree::new(hashed, hasher);
+        *out_tree = Box::into_raw(Box::new(MerkleTreeHandle(tree)));
+        MerkleStatus::Ok
+    })
+}
+
+/// Releases a tree handle; a null handle is ignored
+///
+/// # Safety
+///
+/// `tree` must be null or a handle from [`merkle_tree_new`] that hasn't been freed.
+#[no_mangle]
+pub unsafe extern "C" fn merkle_tree_free(tree: *mut MerkleTreeHandle) {
+    if !tree.is_null() {
+        drop(Box::from_raw(tree));
+    }
+}
+
+/// Copies the tree's root into `out`
+///
+/// # Safety
+///
+/// `tree` must be a live tree handle, `out` must point to `out_capacity`
+/// writable bytes (or be null with zero capacity) and `out_len` must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn merkle_tree_root(
+    tree: *const MerkleTreeHandle,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MerkleStatus {
+    guard(|| {
+        let Some(tree) = tree.as_ref() else {
+            return MerkleStatus::NullPointer;
+        };
+        write_output(tree.0.root(), out, out_capacity, out_len)
+    })
+}
+
+/// Generates the proof for the leaf at `index`
+///
+/// On success `*out_proof` receives a new proof handle, owned by the caller
+/// and released with [`merkle_proof_free`].
+///
+/// # Safety
+///
+/// `tree` must be a live tree handle and `out_proof` a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn merkle_tree_generate_proof(
+    tree: *const MerkleTreeHandle,
+    index: usize,
+    out_proof: *mut *mut MerkleProofHandle,
+) -> MerkleStatus {
+    guard(|| {
+        let Some(tree) = tree.as_ref() else {
+            return MerkleStatus::NullPointer;
+        };
+        if out_proof.is_null() {
+            return MerkleStatus::NullPointer;
+        }
+        match tree.0.generate_proof(index as LeafIndex) {
+            Ok(proof) => {
+                *out_proof = Box::into_raw(Box::new(MerkleProofHandle(proof)));
+                MerkleStatus::Ok
+            }
+            Err(_) => MerkleStatus::IndexOutOfBounds,
+        }
+    })
+}
+
+/// Releases a proof handle; a null handle is ignored
+///
+/// # Safety
+///
+/// `proof` must be null or a handle from [`merkle_tree_generate_proof`] that
+/// hasn't been freed.
+#[no_mangle]
+pub unsafe extern "C" fn merkle_proof_free(proof: *mut MerkleProofHandle) {
+    if !proof.is_null() {
+        drop(Box::from_raw(proof));
+    }
+}
+
+/// Serializes a proof (see [`MerkleProof::to_bytes`]) into `out`
+///
+/// # Safety
+///
+/// `proof` must be a live proof handle, `out` must point to `out_capacity`
+/// writable bytes (or be null with zero capacity) and `out_len` must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn merkle_proof_serialize(
+    proof: *const MerkleProofHandle,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> MerkleStatus {
+    guard(|| {
+        let Some(proof) = proof.as_ref() else {
+            return MerkleStatus::NullPointer;
+        };
+        write_output(&proof.0.to_bytes(), out, out_capacity, out_len)
+    })
+}
+
+/// Verifies a serialized proof against a root, writing the result to `out_valid`
+///
+/// A proof that parses but doesn't lead to `root` is not an error: the call
+/// returns `Ok` with `*out_valid` set to false.
+///
+/// # Safety
+///
+/// `proof` and `root` must point to `proof_len` and `root_len` readable
+/// bytes, `hasher_name` must be a NUL-terminated string and `out_valid` a
+/// valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn merkle_proof_verify(
+    proof: *const u8,
+    proof_len: usize,
+    root: *const u8,
+    root_len: usize,
+    hasher_name: *const c_char,
+    out_valid: *mut bool,
+) -> MerkleStatus {
+    guard(|| {
+        if out_valid.is_null() {
+            return MerkleStatus::NullPointer;
+        }
+        let hasher = match hasher_from_name(hasher_name) {
+            Ok(hasher) => hasher,
+            Err(status) => return status,
+        };
+        let (proof, root) = match (input_bytes(proof, proof_len), input_bytes(root, root_len)) {
+            (Ok(proof), Ok(root)) => (proof, root),
+            (Err(status), _) | (_, Err(status)) => return status,
+        };
+        match MerkleProof::from_bytes(proof, hasher) {
+            Ok(proof) => {
+                *out_valid = proof.verify(root);
+                MerkleStatus::Ok
+            }
+            Err(_) => MerkleStatus::InvalidProof,
+        }
+    })
+}
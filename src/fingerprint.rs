@@ -0,0 +1,82 @@
+//! Fingerprints identifying a tree and how it was built
+//!
+//! A fingerprint digests the root with the hasher name, output size,
+//! padding strategy and sort mode, so roots kept in a catalog or cache can't
+//! be confused with ones built another way. It is an identifier, not a
+//! commitment: proofs are checked against the root or the bound root (see
+//! [`crate::bound`]), never against a fingerprint.
+//!
+//! The digest is always SHA-256, whatever the tree's hasher, so fingerprints
+//! of all trees have the same form: a format version byte, then the digest.
+use alloc::string::String;
+use alloc::vec::Vec;
+use sha2::{Digest, Sha256};
+use crate::hasher::Hasher;
+use crate::store::NodeStore;
+use crate::tree::MerkleTree;
+
+/// The version of the fingerprint encoding, its first byte
+pub const FINGERPRINT_VERSION: u8 = 1;
+
+/// A tree fingerprint, as produced by [`MerkleTree::fingerprint`]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Fingerprint(Vec<u8>);
+
+impl Fingerprint {
+    /// Computes the fingerprint of `tree`
+    pub fn of<H: Hasher, S: NodeStore>(tree: &MerkleTree<H, S>) -> Self {
+        Fingerprint(tree.fingerprint())
+    }
+
+    /// Wraps fingerprint bytes, checking their version and length
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        (bytes.len() == 33 && bytes[0] == FINGERPRINT_VERSION).then(|| Fingerprint(bytes.to_vec()))
+    }
+
+    /// Parses a fingerprint written by [`Fingerprint::to_hex`]
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        Self::from_bytes(&hex::decode(hex).ok()?)
+    }
+
+    /// Gets the fingerprint bytes
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Encodes the fingerprint as lowercase hex
+    pub fn to_hex(&self) -> String {
+        hex::encode(&self.0)
+    }
+
+    /// Checks whether `tree` has this fingerprint
+    pub fn matches<H: Hasher, S: NodeStore>(&self, tree: &MerkleTree<H, S>) -> bool {
+        tree.fingerprint() == self.0
+    }
+}
+
+impl<H: Hasher, S: NodeStore> MerkleTree<H, S> {
+    /// Computes the tree's fingerprint over its root and construction parameters
+    ///
+    /// Rebuilding the same leaves the same way gives the same fingerprint.
+    /// Like [`MerkleTree::params`], trees reopened from a store or file
+    /// report their leaves as ordered.
+    pub fn fingerprint(&self) -> Vec<u8> {
+        let params = self.params();
+        let root = self.root();
+
+        let mut digest = Sha256::new();
+        digest.update(b"merkle_tree.fingerprint\0");
+        digest.update([FINGERPRINT_VERSION]);
+        digest.update((params.hasher.len() as u32).to_le_bytes());
+        digest.update(params.hasher.as_bytes());
+        digest.update((root.len() as u32).to_le_bytes());
+        digest.update(b"duplicate-last");
+        digest.update([u8::from(params.sorted), u8::from(params.index_bound)]);
+        digest.update(root);
+
+        let mut fingerprint = Vec::with_capacity(33);
+        fingerprint.push(FINGERPRINT_VERSION);
+        fingerprint.extend_from_slice(&digest.finalize());
+        fingerprint
+    }
+}
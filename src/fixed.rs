@@ -0,0 +1,218 @@
+//! Merkle trees over fixed 32-byte hashes
+//!
+//! [`MerkleTree32`] keeps every node in one contiguous `Vec<[u8; 32]>` and
+//! its proofs carry `[u8; 32]` hashes, so neither building nor verifying
+//! allocates per hash. Roots and proofs match [`MerkleTree`] for the same
+//! leaves and hasher, and both convert to and from the `Vec`-based types.
+use alloc::vec::Vec;
+use crate::error::MerkleTreeError;
+use crate::hasher::Hasher;
+use crate::index::{self, LeafIndex};
+use crate::proof::{MerkleProof, ProofItem};
+use crate::store::NodeStore;
+use crate::tree::MerkleTree;
+
+/// A 32-byte node hash
+pub type Hash32 = [u8; 32];
+
+/// A Merkle tree whose nodes are 32-byte hashes stored contiguously
+///
+/// The hasher must produce 32-byte outputs.
+pub struct MerkleTree32<H: Hasher> {
+    /// Every level back to back, leaves first and the root last
+    nodes: Vec<Hash32>,
+    /// The number of leaves, including padding
+    leaf_count: usize,
+    /// The number of leaves given on construction; the rest are padding
+    unpadded_leaf_count: usize,
+    /// The height of the tree
+    height: usize,
+    /// The hasher for the tree
+    hasher: H,
+}
+
+impl<H: Hasher> MerkleTree32<H> {
+    /// Creates a tree, sorting the leaves as [`MerkleTree::new`] does
+    pub fn new(mut leaves: Vec<Hash32>, hasher: H) -> Result<Self, MerkleTreeError> {
+        leaves.sort_unstable();
+        Self::new_ordered(leaves, hasher)
+    }
+
+    /// Creates a tree that keeps the leaves in the given order
+    pub fn new_ordered(mut leaves: Vec<Hash32>, hasher: H) -> Result<Self, MerkleTreeError> {
+        let last_leaf = *leaves.last().ok_or(MerkleTreeError::EmptyLeaves)?;
+        crate::tree::check_leaf_count(leaves.len())?;
+        let unpadded_leaf_count = leaves.len();
+        let leaf_count = leaves.len().next_power_of_two();
+        let height = leaf_count.trailing_zeros() as usize + 1;
+
+        // Pad like `MerkleTree`, then grow the same vector into the whole tree
+        leaves.reserve_exact(2 * leaf_count - 1 - leaves.len());
+        leaves.resize(leaf_count, last_leaf);
+        let mut nodes = leaves;
+
+        let mut buffer = Vec::with_capacity(32);
+        for level in 0..height - 1 {
+            let start = level_offset(leaf_count, level);
+            for index in 0..(leaf_count >> level) / 2 {
+                let (left, right) = (nodes[start + 2 * index], nodes[start + 2 * index + 1]);
+                nodes.push(hash_pair32(&hasher, &left, &right, &mut buffer)?);
+            }
+        }
+
+        Ok(MerkleTree32 {
+            nodes,
+            leaf_count,
+            unpadded_leaf_count,
+            height,
+            hasher,
+        })
+    }
+
+    /// Converts a `Vec`-based tree, failing if any node is not 32 bytes
+    pub fn from_tree<S: NodeStore>(tree: &MerkleTree<H, S>) -> Result<Self, MerkleTreeError> {
+        let leaves = (0..tree.unpadded_leaf_count())
+            .map(|index| {
+                let leaf = tree.get_leaf(index).ok_or(MerkleTreeError::MissingNode { level: 0, index })?;
+                hash32(leaf.as_ref())
+            })
+            .collect::<Result<Vec<Hash32>, _>>()?;
+        Self::new_ordered(leaves, tree.get_hasher())
+    }
+
+    /// Converts into a `Vec`-based tree with the same leaves and root
+    pub fn to_tree(&self) -> MerkleTree<H> {
+        let leaves = self.nodes[..self.unpadded_leaf_count].iter().map(|leaf| leaf.to_vec()).collect();
+        MerkleTree::new_ordered(leaves, self.hasher.clone())
+    }
+
+    /// Gets the root of the tree
+    pub fn root(&self) -> &Hash32 {
+        &self.nodes[self.nodes.len() - 1]
+    }
+
+    /// Gets the leaf at the given index
+    pub fn get_leaf(&self, index: LeafIndex) -> Option<&Hash32> {
+        self.nodes[..self.leaf_count].get(index::to_usize(index)?)
+    }
+
+    /// Gets the number of leaves in the tree, including padding
+    pub fn leaf_count(&self) -> u64 {
+        self.leaf_count as u64
+    }
+
+    /// Gets the number of leaves given on construction, without padding
+    pub fn unpadded_leaf_count(&self) -> u64 {
+        self.unpadded_leaf_count as u64
+    }
+
+    /// Generates a proof for the leaf at the given index
+    ///
+    /// As with [`MerkleTree::generate_proof`], padding leaves have no proof.
+    pub fn generate_proof(&self, index: LeafIndex) -> Result<MerkleProof32<H>, MerkleTreeError> {
+        let Some(leaf_index) = index::to_usize(index).filter(|&position| position < self.leaf_count) else {
+            return Err(MerkleTreeError::LeafIndexOutOfBounds { index, len: self.leaf_count() });
+        };
+        if leaf_index >= self.unpadded_leaf_count {
+            return Err(MerkleTreeError::PaddingLeaf { index });
+        }
+
+        let mut proof_items = Vec::with_capacity(self.height - 1);
+        let mut current_index = leaf_index;
+        for level in 0..self.height - 1 {
+            let start = level_offset(self.leaf_count, level);
+            proof_items.push(ProofItem32 {
+                hash: self.nodes[start + (current_index ^ 1)],
+                is_left: current_index % 2 == 1,  // If current is right, sibling is left
+            });
+            current_index /= 2;
+        }
+
+        Ok(MerkleProof32 {
+            leaf: self.nodes[leaf_index],
+            proof_items,
+            hasher: self.hasher.clone(),
+        })
+    }
+
+    /// Verifies a proof against the root of this tree
+    pub fn verify_proof(&self, proof: &MerkleProof32<H>) -> bool {
+        proof.verify(self.root())
+    }
+}
+
+/// A sibling hash and its direction
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ProofItem32 {
+    /// The hash of the sibling node
+    pub hash: Hash32,
+    /// Whether the sibling is on the left (true) or right (false)
+    pub is_left: bool,
+}
+
+/// A proof from a [`MerkleTree32`]
+pub struct MerkleProof32<H: Hasher> {
+    /// The leaf being proven
+    pub leaf: Hash32,
+    /// The proof items (sibling hashes and their positions)
+    pub proof_items: Vec<ProofItem32>,
+    /// The hasher for the proof
+    pub hasher: H,
+}
+
+impl<H: Hasher> MerkleProof32<H> {
+    /// Calculates the root, failing if the hasher does not produce 32-byte outputs
+    pub fn calculate_root(&self) -> Result<Hash32, MerkleTreeError> {
+        let mut buffer = Vec::with_capacity(32);
+        self.proof_items.iter().try_fold(self.leaf, |current, item| {
+            if item.is_left {
+                hash_pair32(&self.hasher, &item.hash, &current, &mut buffer)
+            } else {
+                hash_pair32(&self.hasher, &current, &item.hash, &mut buffer)
+            }
+        })
+    }
+
+    /// Verifies the proof against a given root
+    pub fn verify(&self, root: &Hash32) -> bool {
+        self.calculate_root().is_ok_and(|calculated| calculated == *root)
+    }
+
+    /// Converts a `Vec`-based proof, failing if any hash is not 32 bytes
+    pub fn from_proof(proof: &MerkleProof<H>) -> Result<Self, MerkleTreeError> {
+        let leaf = hash32(&proof.leaf)?;
+        let proof_items = proof.proof_items.iter()
+            .map(|item| Ok(ProofItem32 { hash: hash32(&item.hash)?, is_left: item.is_left }))
+            .collect::<Result<_, MerkleTreeError>>()?;
+        Ok(MerkleProof32 { leaf, proof_items, hasher: proof.hasher.clone() })
+    }
+
+    /// Converts into a `Vec`-based proof
+    pub fn to_proof(&self) -> MerkleProof<H> {
+        let proof_items = self.proof_items.iter()
+            .map(|item| ProofItem { hash: item.hash.as_slice().into(), is_left: item.is_left })
+            .collect();
+        MerkleProof::new(self.leaf.to_vec(), proof_items, self.hasher.clone())
+    }
+}
+
+/// The index of the first node of `level` in the contiguous node vector
+fn level_offset(leaf_count: usize, level: usize) -> usize {
+    2 * (leaf_count - (leaf_count >> level))
+}
+
+/// Hashes a pair through a reused buffer into a fixed-size hash
+fn hash_pair32<H: Hasher>(
+    hasher: &H,
+    left: &Hash32,
+    right: &Hash32,
+    buffer: &mut Vec<u8>
+) -> Result<Hash32, MerkleTreeError> {
+    hasher.hash_pair_into(left, right, buffer);
+    buffer.as_slice().try_into().map_err(|_| MerkleTreeError::HasherMismatch)
+}
+
+/// Copies a leaf or proof hash that must be 32 bytes
+fn hash32(hash: &[u8]) -> Result<Hash32, MerkleTreeError> {
+    hash.try_into().map_err(|_| MerkleTreeError::InvalidHashLength { expected: 32, actual: hash.len() })
+}
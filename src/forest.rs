@@ -0,0 +1,130 @@
+//! Several trees committed to under one root
+//!
+//! [`MerkleForest`] keeps its subtrees in order and builds a top tree over
+//! their roots, so publishing the top root commits to every subtree. A
+//! [`ForestProof`] chains a leaf's proof within its subtree to that
+//! subtree's proof in the top tree.
+use alloc::string::String;
+use alloc::vec::Vec;
+use crate::error::MerkleTreeError;
+use crate::hasher::Hasher;
+use crate::index::LeafIndex;
+use crate::proof::MerkleProof;
+use crate::tree::MerkleTree;
+
+/// Subtrees with a top tree over their roots
+pub struct MerkleForest<H: Hasher> {
+    /// The subtrees, in the order of their roots in the top tree
+    trees: Vec<MerkleTree<H>>,
+    /// The name of each subtree, if it was given one
+    names: Vec<Option<String>>,
+    /// The tree over the subtree roots; an empty forest has none
+    top: Option<MerkleTree<H>>,
+    /// The hasher for the top tree
+    hasher: H,
+}
+
+impl<H: Hasher> MerkleForest<H> {
+    /// Creates an empty forest
+    pub fn new(hasher: H) -> Self {
+        MerkleForest { trees: Vec::new(), names: Vec::new(), top: None, hasher }
+    }
+
+    /// Adds a subtree after the others, returning its index
+    pub fn push(&mut self, tree: MerkleTree<H>) -> usize {
+        self.push_tree(tree, None)
+    }
+
+    /// Adds a subtree with a name it can be looked up by, returning its index
+    pub fn push_named<N: Into<String>>(&mut self, name: N, tree: MerkleTree<H>) -> usize {
+        self.push_tree(tree, Some(name.into()))
+    }
+
+    fn push_tree(&mut self, tree: MerkleTree<H>, name: Option<String>) -> usize {
+        self.trees.push(tree);
+        self.names.push(name);
+        self.rebuild_top();
+        self.trees.len() - 1
+    }
+
+    /// Replaces the subtree at `index`, returning the old one
+    ///
+    /// Only the top tree is rebuilt; the other subtrees are untouched.
+    pub fn replace(&mut self, index: usize, tree: MerkleTree<H>) -> Result<MerkleTree<H>, MerkleTreeError> {
+        let len = self.trees.len();
+        let slot = self.trees.get_mut(index).ok_or(MerkleTreeError::TreeIndexOutOfBounds { index, len })?;
+        let old = core::mem::replace(slot, tree);
+        self.rebuild_top();
+        Ok(old)
+    }
+
+    fn rebuild_top(&mut self) {
+        // A subtree's position is its identity, so the roots keep their order
+        let roots: Vec<Vec<u8>> = self.trees.iter().map(|tree| tree.root().to_vec()).collect();
+        self.top = (!roots.is_empty()).then(|| MerkleTree::new_ordered(roots, self.hasher.clone()));
+    }
+
+    /// Gets the number of subtrees
+    pub fn len(&self) -> usize {
+        self.trees.len()
+    }
+
+    /// Checks whether the forest has no subtrees
+    pub fn is_empty(&self) -> bool {
+        self.trees.is_empty()
+    }
+
+    /// Gets the subtree at `index`
+    pub fn tree(&self, index: usize) -> Option<&MerkleTree<H>> {
+        self.trees.get(index)
+    }
+
+    /// Finds the index of the first subtree with the given name
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.names.iter().position(|candidate| candidate.as_deref() == Some(name))
+    }
+
+    /// Gets the root of the top tree, or `None` for an empty forest
+    pub fn root(&self) -> Option<&[u8]> {
+        self.top.as_ref().map(MerkleTree::root)
+    }
+
+    /// Generates a proof for a leaf of the subtree at `tree_index`, up to the forest root
+    pub fn prove(&self, tree_index: usize, leaf_index: LeafIndex) -> Result<ForestProof<H>, MerkleTreeError> {
+        let len = self.trees.len();
+        let tree = self.trees.get(tree_index).ok_or(MerkleTreeError::TreeIndexOutOfBounds { index: tree_index, len })?;
+        let top = self.top.as_ref().ok_or(MerkleTreeError::EmptyLeaves)?;
+        Ok(ForestProof {
+            tree_index,
+            leaf_proof: tree.generate_proof(leaf_index)?,
+            root_proof: top.generate_proof(tree_index as LeafIndex)?,
+        })
+    }
+}
+
+/// A proof of a leaf in one subtree of a [`MerkleForest`]
+pub struct ForestProof<H: Hasher> {
+    /// The index of the subtree in the forest
+    pub tree_index: usize,
+    /// The proof of the leaf within its subtree
+    pub leaf_proof: MerkleProof<H>,
+    /// The proof of the subtree's root within the top tree
+    pub root_proof: MerkleProof<H>,
+}
+
+impl<H: Hasher> ForestProof<H> {
+    /// Gets the leaf being proven
+    pub fn leaf(&self) -> &[u8] {
+        &self.leaf_proof.leaf
+    }
+
+    /// Verifies the proof against a forest root
+    ///
+    /// The leaf proof must hash up to the subtree root that the root proof
+    /// proves, and the root proof must lead to position `tree_index`.
+    pub fn verify(&self, forest_root: &[u8]) -> bool {
+        self.root_proof.path_index() == Some(self.tree_index as LeafIndex)
+            && self.leaf_proof.calculate_root() == self.root_proof.leaf
+            && self.root_proof.verify(forest_root)
+    }
+}
@@ -1,5 +1,9 @@
 // hasher.rs
 
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
 /// Trait for hash functions used in the Merkle tree
 pub trait Hasher: Clone {
     /// Hashes a leaf before inserting it into the tree
@@ -7,10 +11,47 @@ pub trait Hasher: Clone {
     
     /// Hashes two nodes together to create a parent node
     fn hash_pair(&self, left: &[u8], right: &[u8]) -> Vec<u8>;
+
+    /// Hashes two nodes together into `out`, replacing its contents
+    ///
+    /// Produces the same bytes as `hash_pair`. The default implementation
+    /// allocates through `hash_pair`; the built-in hashers override it to
+    /// write straight into `out`, so a reused buffer costs no allocation.
+    fn hash_pair_into(&self, left: &[u8], right: &[u8], out: &mut Vec<u8>) {
+        *out = self.hash_pair(left, right);
+    }
+
+    /// Hashes a leaf read from a stream, producing the same hash as `hash_leaf`
+    ///
+    /// The default implementation buffers the whole stream; the built-in
+    /// hashers override it to hash incrementally in bounded memory.
+    #[cfg(feature = "std")]
+    fn hash_leaf_reader<R: std::io::Read>(&self, mut reader: R) -> std::io::Result<Vec<u8>> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        Ok(self.hash_leaf(&data))
+    }
+
+    /// The length of the hashes this hasher produces, in bytes
+    ///
+    /// The default hashes an empty input to find out; the built-in hashers
+    /// override it.
+    fn output_size(&self) -> usize {
+        self.hash_leaf(&[]).len()
+    }
+
+    /// Names the hash function, for commitments that record how a tree was built
+    ///
+    /// The default is the Rust type name, which can change between compiler
+    /// and crate versions; hashers used with [`crate::bound`] should return a
+    /// fixed name.
+    fn name(&self) -> String {
+        core::any::type_name::<Self>().to_string()
+    }
 }
 
 // Default implementation using SHA-256
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct Sha256Hasher;
 
 impl Sha256Hasher {
@@ -19,6 +60,12 @@ impl Sha256Hasher {
     }
 }
 
+impl Default for Sha256Hasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Hasher for Sha256Hasher {
     fn hash_leaf(&self, data: &[u8]) -> Vec<u8> {
         use sha2::{Digest, Sha256};
@@ -34,10 +81,35 @@ impl Hasher for Sha256Hasher {
         hasher.update(right);
         hasher.finalize().to_vec()
     }
+
+    fn hash_pair_into(&self, left: &[u8], right: &[u8], out: &mut Vec<u8>) {
+        use sha2::{Digest, Sha256};
+        let mut hasher = Sha256::new();
+        hasher.update(left);
+        hasher.update(right);
+        out.clear();
+        out.extend_from_slice(&hasher.finalize());
+    }
+
+    #[cfg(feature = "std")]
+    fn hash_leaf_reader<R: std::io::Read>(&self, mut reader: R) -> std::io::Result<Vec<u8>> {
+        use sha2::{Digest, Sha256};
+        let mut hasher = Sha256::new();
+        std::io::copy(&mut reader, &mut hasher)?;
+        Ok(hasher.finalize().to_vec())
+    }
+
+    fn output_size(&self) -> usize {
+        32
+    }
+
+    fn name(&self) -> String {
+        "sha256".to_string()
+    }
 }
 
 // Example of a configurable hasher implementation
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct Blake2bHasher {
     // Configuration parameters
     output_size: usize,
@@ -64,4 +136,222 @@ impl Hasher for Blake2bHasher {
         hasher.update(right);
         hasher.finalize().to_vec()[..self.output_size].to_vec()
     }
-}
\ No newline at end of file
+
+    fn hash_pair_into(&self, left: &[u8], right: &[u8], out: &mut Vec<u8>) {
+        use blake2::{Blake2b, Digest};
+        let mut hasher = Blake2b::<blake2::digest::consts::U64>::new();
+        hasher.update(left);
+        hasher.update(right);
+        out.clear();
+        out.extend_from_slice(&hasher.finalize()[..self.output_size]);
+    }
+
+    #[cfg(feature = "std")]
+    fn hash_leaf_reader<R: std::io::Read>(&self, mut reader: R) -> std::io::Result<Vec<u8>> {
+        use blake2::{Blake2b, Digest};
+        let mut hasher = Blake2b::<blake2::digest::consts::U64>::new();
+        std::io::copy(&mut reader, &mut hasher)?;
+        Ok(hasher.finalize().to_vec()[..self.output_size].to_vec())
+    }
+
+    fn output_size(&self) -> usize {
+        self.output_size
+    }
+
+    fn name(&self) -> String {
+        format!("blake2b{}", self.output_size * 8)
+    }
+}
+
+/// Keccak-256, as Ethereum uses it, for trees that must match on-chain verifiers
+///
+/// Leaves and pairs are hashed like the SHA-256 hasher's: `keccak256(data)`
+/// and `keccak256(left || right)`.
+#[derive(Clone, Debug, Default)]
+pub struct Keccak256Hasher;
+
+impl Keccak256Hasher {
+    pub fn new() -> Self {
+        Keccak256Hasher
+    }
+}
+
+impl Hasher for Keccak256Hasher {
+    fn hash_leaf(&self, data: &[u8]) -> Vec<u8> {
+        use sha3::{Digest, Keccak256};
+        let mut hasher = Keccak256::new();
+        hasher.update(data);
+        hasher.finalize().to_vec()
+    }
+
+    fn hash_pair(&self, left: &[u8], right: &[u8]) -> Vec<u8> {
+        use sha3::{Digest, Keccak256};
+        let mut hasher = Keccak256::new();
+        hasher.update(left);
+        hasher.update(right);
+        hasher.finalize().to_vec()
+    }
+
+    fn hash_pair_into(&self, left: &[u8], right: &[u8], out: &mut Vec<u8>) {
+        use sha3::{Digest, Keccak256};
+        let mut hasher = Keccak256::new();
+        hasher.update(left);
+        hasher.update(right);
+        out.clear();
+        out.extend_from_slice(&hasher.finalize());
+    }
+
+    #[cfg(feature = "std")]
+    fn hash_leaf_reader<R: std::io::Read>(&self, mut reader: R) -> std::io::Result<Vec<u8>> {
+        use sha3::{Digest, Keccak256};
+        let mut hasher = Keccak256::new();
+        std::io::copy(&mut reader, &mut hasher)?;
+        Ok(hasher.finalize().to_vec())
+    }
+
+    fn output_size(&self) -> usize {
+        32
+    }
+
+    fn name(&self) -> String {
+        "keccak256".to_string()
+    }
+}
+
+/// One of the built-in hashers, chosen at runtime by name
+///
+/// Used where the hasher comes from configuration or another language, such
+/// as the bindings.
+#[derive(Clone, Debug)]
+pub enum BuiltinHasher {
+    Sha256(Sha256Hasher),
+    Blake2b(Blake2bHasher),
+    Keccak256(Keccak256Hasher),
+}
+
+impl BuiltinHasher {
+    /// Looks up a hasher by name: `sha256`, `blake2b256`, `blake2b512` or `keccak256`
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "sha256" => Some(BuiltinHasher::Sha256(Sha256Hasher::new())),
+            "blake2b256" => Some(BuiltinHasher::Blake2b(Blake2bHasher::new(32))),
+            "blake2b512" => Some(BuiltinHasher::Blake2b(Blake2bHasher::new(64))),
+            "keccak256" => Some(BuiltinHasher::Keccak256(Keccak256Hasher::new())),
+            _ => None,
+        }
+    }
+}
+
+impl Hasher for BuiltinHasher {
+    fn hash_leaf(&self, data: &[u8]) -> Vec<u8> {
+        match self {
+            BuiltinHasher::Sha256(hasher) => hasher.hash_leaf(data),
+            BuiltinHasher::Blake2b(hasher) => hasher.hash_leaf(data),
+            BuiltinHasher::Keccak256(hasher) => hasher.hash_leaf(data),
+        }
+    }
+
+    fn hash_pair(&self, left: &[u8], right: &[u8]) -> Vec<u8> {
+        match self {
+            BuiltinHasher::Sha256(hasher) => hasher.hash_pair(left, right),
+            BuiltinHasher::Blake2b(hasher) => hasher.hash_pair(left, right),
+            BuiltinHasher::Keccak256(hasher) => hasher.hash_pair(left, right),
+        }
+    }
+
+    fn hash_pair_into(&self, left: &[u8], right: &[u8], out: &mut Vec<u8>) {
+        match self {
+            BuiltinHasher::Sha256(hasher) => hasher.hash_pair_into(left, right, out),
+            BuiltinHasher::Blake2b(hasher) => hasher.hash_pair_into(left, right, out),
+            BuiltinHasher::Keccak256(hasher) => hasher.hash_pair_into(left, right, out),
+        }
+    }
+
+    #[cfg(feature = "std")]
+    fn hash_leaf_reader<R: std::io::Read>(&self, reader: R) -> std::io::Result<Vec<u8>> {
+        match self {
+            BuiltinHasher::Sha256(hasher) => hasher.hash_leaf_reader(reader),
+            BuiltinHasher::Blake2b(hasher) => hasher.hash_leaf_reader(reader),
+            BuiltinHasher::Keccak256(hasher) => hasher.hash_leaf_reader(reader),
+        }
+    }
+
+    fn output_size(&self) -> usize {
+        match self {
+            BuiltinHasher::Sha256(hasher) => hasher.output_size(),
+            BuiltinHasher::Blake2b(hasher) => hasher.output_size(),
+            BuiltinHasher::Keccak256(hasher) => hasher.output_size(),
+        }
+    }
+
+    /// The name [`BuiltinHasher::from_name`] looks this hasher up by
+    fn name(&self) -> String {
+        match self {
+            BuiltinHasher::Sha256(hasher) => hasher.name(),
+            BuiltinHasher::Blake2b(hasher) => hasher.name(),
+            BuiltinHasher::Keccak256(hasher) => hasher.name(),
+        }
+    }
+}
+
+/// An object-safe view of a hasher, for picking hashers at runtime
+///
+/// [`Hasher`] needs `Clone` and has a generic method, so there is no
+/// `dyn Hasher`; every thread-safe hasher is a `DynHasher` instead, and
+/// `&dyn DynHasher` is itself a [`Hasher`] that trees and utilities accept.
+/// The methods are named apart from [`Hasher`]'s so both traits can be in scope.
+pub trait DynHasher: Send + Sync {
+    /// [`Hasher::hash_leaf`]
+    fn dyn_hash_leaf(&self, data: &[u8]) -> Vec<u8>;
+
+    /// [`Hasher::hash_pair_into`]
+    fn dyn_hash_pair_into(&self, left: &[u8], right: &[u8], out: &mut Vec<u8>);
+
+    /// [`Hasher::output_size`]
+    fn dyn_output_size(&self) -> usize;
+
+    /// [`Hasher::name`]
+    fn dyn_name(&self) -> String;
+}
+
+impl<H: Hasher + Send + Sync> DynHasher for H {
+    fn dyn_hash_leaf(&self, data: &[u8]) -> Vec<u8> {
+        self.hash_leaf(data)
+    }
+
+    fn dyn_hash_pair_into(&self, left: &[u8], right: &[u8], out: &mut Vec<u8>) {
+        self.hash_pair_into(left, right, out)
+    }
+
+    fn dyn_output_size(&self) -> usize {
+        self.output_size()
+    }
+
+    fn dyn_name(&self) -> String {
+        self.name()
+    }
+}
+
+impl Hasher for &dyn DynHasher {
+    fn hash_leaf(&self, data: &[u8]) -> Vec<u8> {
+        (**self).dyn_hash_leaf(data)
+    }
+
+    fn hash_pair(&self, left: &[u8], right: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        (**self).dyn_hash_pair_into(left, right, &mut out);
+        out
+    }
+
+    fn hash_pair_into(&self, left: &[u8], right: &[u8], out: &mut Vec<u8>) {
+        (**self).dyn_hash_pair_into(left, right, out)
+    }
+
+    fn output_size(&self) -> usize {
+        (**self).dyn_output_size()
+    }
+
+    fn name(&self) -> String {
+        (**self).dyn_name()
+    }
+}
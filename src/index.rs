@@ -0,0 +1,77 @@
+//! Index arithmetic for leaf positions and node coordinates
+//!
+//! Positions here are [`LeafIndex`], a `u64`, so the arithmetic is the same
+//! on 32- and 64-bit targets and matches the fixed-width counts of the
+//! on-disk formats. The public tree API takes and returns them too; trees
+//! address their memory with `usize`, converted with [`to_usize`], which
+//! can't address more leaves than fit in memory anyway. [`max_leaf_count`]
+//! is the bound combining the two.
+//!
+//! Nodes are addressed by `(level, index)` with the leaves on level 0, as in
+//! [`crate::tree::MerkleTree::get_node`], or by generalized index: the root
+//! is 1 and the children of node `g` are `2g` and `2g + 1`. Every function
+//! returns `None` rather than overflowing.
+//!
+//! The crate's own checks go through these: tree sizes through
+//! [`padded_leaf_count`], proof lengths through [`depth`], the leaf a proof
+//! is for through [`from_gindex`], and the leaves under a mismatched node
+//! in [`crate::utils::testing`] through [`leaf_range`].
+use core::ops::Range;
+
+/// A leaf's position in a tree, or a node's index within its level
+pub type LeafIndex = u64;
+
+/// The deepest tree supported, so at most 2^63 leaves and 63 items per proof
+pub const MAX_DEPTH: u32 = 63;
+
+/// The most leaves a tree can have on this target
+///
+/// This is 2^[`MAX_DEPTH`] where `usize` is 64 bits, and 2^31 where it is 32.
+pub const fn max_leaf_count() -> u64 {
+    let bits = if usize::BITS - 1 < MAX_DEPTH { usize::BITS - 1 } else { MAX_DEPTH };
+    1 << bits
+}
+
+/// Converts an index to address memory with, `None` if it doesn't fit this target's `usize`
+///
+/// This is the one place indices narrow; widening a `usize` to a
+/// [`LeafIndex`] is lossless on every target Rust supports.
+pub fn to_usize(index: LeafIndex) -> Option<usize> {
+    usize::try_from(index).ok()
+}
+
+/// The leaf count padded to a power of two, `None` for no leaves or more than [`max_leaf_count`]
+pub fn padded_leaf_count(count: u64) -> Option<u64> {
+    if count == 0 {
+        return None;
+    }
+    count.checked_next_power_of_two().filter(|&padded| padded <= max_leaf_count())
+}
+
+/// The depth of a tree over `count` leaves, which is the length of its proofs
+pub fn depth(count: u64) -> Option<u32> {
+    padded_leaf_count(count).map(u64::trailing_zeros)
+}
+
+/// The `(level, index)` of a generalized index in a tree of the given depth
+///
+/// A proof's directions, read from the root down, are the bits of its
+/// leaf's generalized index below the leading 1.
+pub fn from_gindex(depth: u32, gindex: u64) -> Option<(u32, u64)> {
+    if depth > MAX_DEPTH || gindex == 0 {
+        return None;
+    }
+    // The distance from the root is the position of the highest set bit
+    let row = u64::BITS - 1 - gindex.leading_zeros();
+    if row > depth {
+        return None;
+    }
+    Some((depth - row, gindex - (1 << row)))
+}
+
+/// The leaves under the node at `(level, index)`, `None` if the range would end past `u64::MAX`
+pub fn leaf_range(level: u32, index: u64) -> Option<Range<u64>> {
+    let width = 1u64.checked_shl(level)?;
+    let start = index.checked_mul(width)?;
+    Some(start..start.checked_add(width)?)
+}
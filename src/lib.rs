@@ -1,7 +1,62 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub mod error;
 pub mod utils;
 pub mod tree;
+pub mod builder;
 pub mod proof;
 pub mod hasher;
+pub mod bound;
+pub mod index;
+pub mod fingerprint;
+pub mod bundle;
+pub mod prefix;
+#[cfg(feature = "signing")]
+pub mod signing;
+#[cfg(feature = "salted")]
+pub mod salted;
+pub mod render;
+#[cfg(feature = "server")]
+pub mod server;
+#[cfg(feature = "rs_merkle")]
+pub mod compat;
+#[cfg(feature = "std")]
+pub mod persist;
+#[cfg(feature = "std")]
+pub mod dump;
+#[cfg(feature = "msgpack")]
+pub mod msgpack;
+#[cfg(feature = "proptest")]
+pub mod strategy;
+pub mod store;
+mod sort;
+#[cfg(feature = "tracing")]
+mod trace;
+pub mod fixed;
+pub mod typed;
+pub mod map;
+pub mod sparse;
+pub mod mmr;
+pub mod forest;
+#[cfg(feature = "vectors")]
+pub mod vectors;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(feature = "tokio")]
+pub mod async_utils;
+pub mod prelude;
+
+pub use builder::MerkleTreeBuilder;
+pub use error::MerkleTreeError;
+pub use hasher::{Blake2bHasher, Hasher, Sha256Hasher};
+pub use proof::{MerkleProof, ProofItem};
+pub use tree::MerkleTree;
 
 
 #[cfg(test)]
@@ -12,7 +67,7 @@ mod tests {
     #[test]
     fn test_merkle_tree() {
         let hasher = Sha256Hasher::new();
-        let leaves = vec![
+        let leaves = [
             utils::string_to_bytes("leaf1"),
             utils::string_to_bytes("leaf2"),
             utils::string_to_bytes("leaf3"),
@@ -84,4 +139,2788 @@ mod tests {
         let proof = tree.generate_proof(2).unwrap();
         assert!(tree.verify_proof(&proof));
     }
-}
\ No newline at end of file
+    
+    #[cfg(feature = "csv")]
+    #[test]
+    fn test_tree_from_csv() {
+        use crate::utils::{CsvOptions, CsvTreeError, DuplicatePolicy};
+
+        let data = "address,amount\n0xAbC,10\n0xdef,20\n 0xabc ,30\n,40\n";
+        let options = CsvOptions { lowercase: true, ..CsvOptions::default() };
+        let (tree, rows) = utils::create_tree_from_csv_with_options(
+            data.as_bytes(), "address", Sha256Hasher::new(), options
+        ).unwrap();
+
+        // The empty cell is skipped and the duplicate keeps its own leaf
+        assert_eq!(rows.len(), 4);
+        assert_eq!(rows[3], None);
+        assert_ne!(rows[0], rows[2]);
+
+        let expected = Sha256Hasher::new().hash_leaf(b"0xabc");
+        for row in [0, 1, 2] {
+            let index = rows[row].unwrap();
+            let proof = tree.generate_proof(index).unwrap();
+            assert!(tree.verify_proof(&proof));
+            if row != 1 {
+                assert_eq!(tree.get_leaf(index).unwrap(), &expected);
+            }
+        }
+
+        // Merging maps both rows onto the same leaf
+        let options = CsvOptions { lowercase: true, duplicates: DuplicatePolicy::Merge, ..CsvOptions::default() };
+        let (_, rows) = utils::create_tree_from_csv_with_options(
+            data.as_bytes(), "address", Sha256Hasher::new(), options
+        ).unwrap();
+        assert_eq!(rows[0], rows[2]);
+
+        // Rejection reports the offending row
+        let options = CsvOptions { lowercase: true, duplicates: DuplicatePolicy::Reject, ..CsvOptions::default() };
+        let result = utils::create_tree_from_csv_with_options(
+            data.as_bytes(), "address", Sha256Hasher::new(), options
+        );
+        assert!(matches!(result, Err(CsvTreeError::DuplicateValue { row: 2 })));
+
+        let result = utils::create_tree_from_csv(data.as_bytes(), "missing", Sha256Hasher::new());
+        assert!(matches!(result, Err(CsvTreeError::MissingColumn(_))));
+    }
+
+    #[test]
+    fn test_tree_from_lines() {
+        let input: String = (0..100_000).map(|i| format!("entry-{}\n", i)).collect();
+        let tree = utils::create_tree_from_lines(input.as_bytes(), Sha256Hasher::new()).unwrap();
+        assert_eq!(tree.leaf_count(), 131_072);
+        assert!(utils::verify_element_in_tree(&tree, "entry-4242"));
+        assert!(!utils::verify_element_in_tree(&tree, "entry-100000"));
+
+        // Blank lines and CRLF terminators match the string-based constructor
+        let tree = utils::create_tree_from_lines("leaf1\r\n\nleaf2\nleaf3".as_bytes(), Sha256Hasher::new()).unwrap();
+        let expected = utils::create_tree_from_strings(vec!["leaf1", "leaf2", "leaf3"]);
+        assert_eq!(tree.root(), expected.root());
+
+        let options = utils::LineOptions { max_line_len: 4, ..utils::LineOptions::default() };
+        let result = utils::create_tree_from_lines_with_options("ok\ntoo long\n".as_bytes(), Sha256Hasher::new(), options);
+        assert!(matches!(result, Err(error::MerkleTreeError::LineTooLong { line: 2, max: 4 })));
+
+        let result = utils::create_tree_from_lines("\n\n".as_bytes(), Sha256Hasher::new());
+        assert!(matches!(result, Err(error::MerkleTreeError::EmptyLeaves)));
+    }
+
+    #[test]
+    fn test_tree_from_file_chunks() {
+        use std::io::Write;
+
+        let chunk_size = 1024;
+        let data: Vec<u8> = (0..3 * chunk_size + 100).map(|i| (i % 251) as u8).collect();
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(&data).unwrap();
+
+        let hasher = Sha256Hasher::new();
+        let (tree, chunk_count) = utils::create_tree_from_file(file.path(), chunk_size, hasher.clone()).unwrap();
+        assert_eq!(chunk_count, 4);
+
+        // The last chunk is hashed unpadded
+        let last_chunk = &data[3 * chunk_size..];
+        assert_eq!(last_chunk.len(), 100);
+        let proof = tree.generate_proof(3).unwrap();
+        assert!(utils::verify_file_chunk(tree.root(), 3, chunk_count, last_chunk, &proof, &hasher));
+
+        // Wrong position or wrong bytes are rejected
+        assert!(!utils::verify_file_chunk(tree.root(), 2, chunk_count, last_chunk, &proof, &hasher));
+        assert!(!utils::verify_file_chunk(tree.root(), 3, chunk_count, &data[..100], &proof, &hasher));
+
+        let first_chunk = &data[..chunk_size];
+        let proof = tree.generate_proof(0).unwrap();
+        assert!(utils::verify_file_chunk(tree.root(), 0, chunk_count, first_chunk, &proof, &hasher));
+    }
+
+    #[test]
+    fn test_file_chunk_padding_is_not_a_chunk() {
+        use std::io::Write;
+
+        // Three chunks, padded to four with a copy of the last
+        let chunk_size = 64;
+        let data: Vec<u8> = (0..2 * chunk_size + 10).map(|i| i as u8).collect();
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(&data).unwrap();
+        let hasher = Sha256Hasher::new();
+        let (tree, chunk_count) = utils::create_tree_from_file(file.path(), chunk_size, hasher.clone()).unwrap();
+        assert_eq!(chunk_count, 3);
+
+        // The proof of the padding at position 3 is the last chunk's, mirrored
+        let last_chunk = &data[2 * chunk_size..];
+        let mut phantom = tree.generate_proof(2).unwrap();
+        phantom.proof_items[0].is_left = true;
+        assert!(phantom.verify(tree.root()));
+        assert!(!utils::verify_file_chunk(tree.root(), 3, chunk_count, last_chunk, &phantom, &hasher));
+        assert!(utils::verify_file_chunk(tree.root(), 2, chunk_count, last_chunk, &tree.generate_proof(2).unwrap(), &hasher));
+    }
+
+    #[test]
+    fn test_tree_from_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("sub/nested")).unwrap();
+        std::fs::write(dir.path().join("a.txt"), b"alpha").unwrap();
+        std::fs::write(dir.path().join("sub/b.txt"), b"bravo").unwrap();
+        std::fs::write(dir.path().join("sub/nested/c.txt"), b"charlie").unwrap();
+        std::fs::write(dir.path().join("sub/debug.log"), b"noise").unwrap();
+
+        let hasher = Sha256Hasher::new();
+        let options = utils::DirOptions { exclude: vec!["*.log".to_string()], ..Default::default() };
+        let (tree, files) = utils::create_tree_from_dir_with_options(dir.path(), hasher.clone(), &options).unwrap();
+        assert_eq!(files, vec!["a.txt", "sub/b.txt", "sub/nested/c.txt"]);
+
+        // The leaf encoding is part of the format, so pin the root
+        assert_eq!(
+            hex::encode(tree.root()),
+            "c091d8d1998fa22424c97601db36b0333ea1fa318d0f0c1f62bf68268e57e6c9"
+        );
+
+        let mut leaf_data = b"sub/b.txt".to_vec();
+        leaf_data.extend(hasher.hash_leaf(b"bravo"));
+        let proof = tree.generate_proof(1).unwrap();
+        assert_eq!(proof.leaf, hasher.hash_leaf(&leaf_data));
+        assert!(proof.verify(tree.root()));
+
+        // Excluded files do not affect the root
+        std::fs::write(dir.path().join("other.log"), b"more noise").unwrap();
+        let (rebuilt, _) = utils::create_tree_from_dir_with_options(dir.path(), hasher, &options).unwrap();
+        assert_eq!(rebuilt.root(), tree.root());
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_async_tree_matches_sync() {
+        use std::io::Write;
+
+        let data: Vec<u8> = (0..5000).map(|i| (i % 253) as u8).collect();
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(&data).unwrap();
+
+        let (sync_tree, sync_chunks) = utils::create_tree_from_file(file.path(), 1024, Sha256Hasher::new()).unwrap();
+        let (async_tree, async_chunks) = async_utils::create_tree_from_async_reader(
+            data.as_slice(), 1024, Sha256Hasher::new()
+        ).await.unwrap();
+        assert_eq!(async_chunks, sync_chunks);
+        assert_eq!(async_tree.root(), sync_tree.root());
+
+        let strings: Vec<String> = (0..3000).map(|i| format!("leaf{}", i)).collect();
+        let sync_tree = utils::create_tree_from_strings(strings.iter().map(|s| s.as_str()).collect());
+        let stream = futures::stream::iter(strings.iter().map(|s| s.as_bytes().to_vec()));
+        let async_tree = async_utils::build_from_stream(stream, Sha256Hasher::new()).await.unwrap();
+        assert_eq!(async_tree.root(), sync_tree.root());
+    }
+
+    #[test]
+    fn test_save_and_load_tree() {
+        use crate::persist::{FormatError, PersistError};
+
+        let tree = utils::create_tree_from_strings(vec!["leaf1", "leaf2", "leaf3", "leaf4", "leaf5"]);
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tree.bin");
+
+        tree.save_to_file(&path).unwrap();
+        let loaded = tree::MerkleTree::load_from_file(&path, Sha256Hasher::new()).unwrap();
+        assert_eq!(loaded.root(), tree.root());
+        assert_eq!(loaded.leaf_count(), tree.leaf_count());
+        let proof = loaded.generate_proof(4).unwrap();
+        assert!(tree.verify_proof(&proof));
+
+        // A single flipped byte in a leaf no longer matches the stored root
+        let bytes = std::fs::read(&path).unwrap();
+        let mut corrupted = bytes.clone();
+        corrupted[20] ^= 0x01;
+        std::fs::write(&path, &corrupted).unwrap();
+        let result = tree::MerkleTree::load_from_file(&path, Sha256Hasher::new());
+        assert!(matches!(result, Err(PersistError::Format(FormatError::RootMismatch))));
+
+        // A partial write is detected from the length
+        std::fs::write(&path, &bytes[..bytes.len() - 7]).unwrap();
+        let result = tree::MerkleTree::load_from_file(&path, Sha256Hasher::new());
+        assert!(matches!(result, Err(PersistError::Format(FormatError::Truncated))));
+
+        let result = tree::MerkleTree::load_from_file(dir.path().join("missing.bin"), Sha256Hasher::new());
+        assert!(matches!(result, Err(PersistError::Io(_))));
+    }
+
+    #[test]
+    fn test_from_bytes_rejects_hash_length_before_allocating() {
+        use crate::persist::FormatError;
+
+        // A bare header claiming 2^40 empty leaves, which used to be allocated
+        let header = |leaf_count: u64, hash_len: u32| {
+            let mut bytes = b"MRKL\x01".to_vec();
+            bytes.extend_from_slice(&leaf_count.to_le_bytes());
+            bytes.extend_from_slice(&hash_len.to_le_bytes());
+            bytes.push(0);
+            bytes
+        };
+        let bytes = header(1 << 40, 0);
+        assert_eq!(bytes.len(), 18);
+        assert_eq!(
+            tree::MerkleTree::from_bytes(&bytes, Sha256Hasher::new()).err(),
+            Some(FormatError::HashLengthMismatch { expected: 32, actual: 0 })
+        );
+
+        // A well-formed body of hashes the hasher doesn't produce
+        let mut bytes = header(3, 16);
+        bytes.extend_from_slice(&[7; 4 * 16]);
+        assert_eq!(
+            tree::MerkleTree::from_bytes(&bytes, Sha256Hasher::new()).err(),
+            Some(FormatError::HashLengthMismatch { expected: 32, actual: 16 })
+        );
+        assert!(matches!(
+            tree::MerkleTree::from_bytes(&header(1 << 40, 32), Sha256Hasher::new()),
+            Err(FormatError::Truncated)
+        ));
+        let mut future = header(3, 32);
+        future[4] = 2;
+        assert_eq!(tree::MerkleTree::from_bytes(&future, Sha256Hasher::new()).err(), Some(FormatError::UnsupportedVersion(2)));
+    }
+
+    /// An in-memory node store that counts how many nodes are read
+    #[derive(Default)]
+    struct CountingStore {
+        inner: store::MemoryNodeStore,
+        reads: std::cell::Cell<usize>,
+    }
+
+    impl store::NodeStore for CountingStore {
+        type Node<'a> = &'a Vec<u8>;
+        type Error = std::convert::Infallible;
+
+        fn get(&self, level: usize, index: usize) -> Option<&Vec<u8>> {
+            self.reads.set(self.reads.get() + 1);
+            self.inner.get(level, index)
+        }
+
+        fn put(&mut self, level: usize, index: usize, hash: Vec<u8>) -> Result<(), Self::Error> {
+            self.inner.put(level, index, hash)
+        }
+    }
+
+    #[test]
+    fn test_custom_node_store() {
+        let hasher = Sha256Hasher::new();
+        let leaves: Vec<Vec<u8>> = (0..1024)
+            .map(|i| hasher.hash_leaf(format!("leaf{}", i).as_bytes()))
+            .collect();
+        let expected = tree::MerkleTree::new(leaves.clone(), hasher.clone());
+        let tree = tree::MerkleTree::with_store(leaves, hasher, CountingStore::default()).unwrap();
+        assert_eq!(tree.root(), expected.root());
+
+        // A proof reads one sibling per level plus the leaf itself
+        tree.store().reads.set(0);
+        let proof = tree.generate_proof(517).unwrap();
+        assert_eq!(tree.store().reads.get(), 11);
+        assert!(proof.verify(expected.root()));
+    }
+
+    #[cfg(feature = "sled")]
+    #[test]
+    fn test_sled_node_store_reopen() {
+        use crate::store::SledNodeStore;
+
+        let dir = tempfile::tempdir().unwrap();
+        let hasher = Sha256Hasher::new();
+        let leaves: Vec<Vec<u8>> = (0..100)
+            .map(|i| hasher.hash_leaf(format!("leaf{}", i).as_bytes()))
+            .collect();
+        let expected = tree::MerkleTree::new(leaves.clone(), hasher.clone());
+
+        let proof = {
+            let store = SledNodeStore::open(dir.path()).unwrap();
+            let mut tree = tree::MerkleTree::with_store(leaves, hasher.clone(), store).unwrap();
+            tree.flush().unwrap();
+            tree.generate_proof(42).unwrap()
+        };
+
+        // The reopened tree serves the persisted root and new proofs without a rebuild
+        let store = SledNodeStore::open(dir.path()).unwrap();
+        let tree = tree::MerkleTree::from_store(store, hasher).unwrap();
+        assert_eq!(tree.leaf_count(), 128);
+        assert_eq!(tree.unpadded_leaf_count(), 100);
+        assert_eq!(tree.store().len(), 255);
+        assert_eq!(tree.root(), expected.root());
+        assert!(proof.verify(tree.root()));
+        let reopened_proof = tree.generate_proof(7).unwrap();
+        assert!(tree.verify_proof(&reopened_proof));
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn test_mmap_leaf_store() {
+        use crate::store::MmapLeafStore;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("leaves.bin");
+        let hasher = Sha256Hasher::new();
+        let leaves: Vec<Vec<u8>> = (0..1000)
+            .map(|i| hasher.hash_leaf(format!("leaf{}", i).as_bytes()))
+            .collect();
+        let expected = tree::MerkleTree::new(leaves.clone(), hasher.clone());
+
+        let store = MmapLeafStore::create(&path, leaves.len(), 32).unwrap();
+        let mut tree = tree::MerkleTree::with_store(leaves, hasher, store).unwrap();
+        tree.flush().unwrap();
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), 1024 * 32);
+        assert_eq!(tree.root(), expected.root());
+
+        // Leaves are borrowed from the mapping
+        let leaf: &[u8] = tree.get_leaf(321).unwrap();
+        assert_eq!(leaf, expected.get_leaf(321).unwrap().as_slice());
+        assert!(tree.get_leaf(1024).is_none());
+
+        let proof = tree.generate_proof(321).unwrap();
+        assert!(proof.verify(expected.root()));
+        assert_eq!(tree.find_leaf_index(&proof.leaf), Some(321));
+
+        // Leaves of the wrong size are rejected
+        let store = MmapLeafStore::create(&path, 2, 32).unwrap();
+        assert!(tree::MerkleTree::with_store(vec![vec![0u8; 32], vec![1u8; 16]], Sha256Hasher::new(), store).is_err());
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_proof_json_round_trip() {
+        let tree = utils::create_tree_from_strings(vec!["leaf1", "leaf2", "leaf3"]);
+        let proof = tree.generate_proof(1).unwrap();
+
+        let json = proof.to_json();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["leaf"], hex::encode(&proof.leaf));
+        assert_eq!(value["proof"][0]["hash"], proof.to_debug_format()[0]["hash"]);
+        assert_eq!(value["proof"][0]["direction"], proof.to_debug_format()[0]["direction"]);
+
+        let parsed = proof::MerkleProof::from_json(&json, Sha256Hasher::new()).unwrap();
+        assert!(tree.verify_proof(&parsed));
+        let parsed: proof::MerkleProof<Sha256Hasher> = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.calculate_root(), tree.root());
+
+        let bad = json.replace("\"left\"", "\"up\"").replace("\"right\"", "\"up\"");
+        assert!(proof::MerkleProof::from_json(&bad, Sha256Hasher::new()).is_err());
+    }
+
+    #[test]
+    fn test_proof_bytes_round_trip() {
+        let tree = utils::create_tree_from_strings(vec!["leaf1", "leaf2", "leaf3", "leaf4", "leaf5"]);
+        let proof = tree.generate_proof(2).unwrap();
+        let bytes = proof.to_bytes();
+
+        let parsed = proof::MerkleProof::from_bytes(&bytes, Sha256Hasher::new()).unwrap();
+        assert_eq!(parsed.leaf, proof.leaf);
+        assert!(tree.verify_proof(&parsed));
+
+        assert!(matches!(
+            proof::MerkleProof::from_bytes(&bytes[..bytes.len() - 1], Sha256Hasher::new()),
+            Err(error::MerkleTreeError::ProofParse(error::ProofParseError::Truncated))
+        ));
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert!(matches!(
+            proof::MerkleProof::from_bytes(&trailing, Sha256Hasher::new()),
+            Err(error::MerkleTreeError::ProofParse(error::ProofParseError::TrailingBytes))
+        ));
+    }
+
+    #[test]
+    fn test_root_is_borrowed() {
+        let tree = utils::create_tree_from_strings(vec!["a", "b", "c"]);
+
+        // Every call lends out the same bytes instead of allocating a copy
+        let first = tree.root();
+        let second = tree.root();
+        assert_eq!(first.as_ptr(), second.as_ptr());
+        assert_eq!(first, second);
+        assert_eq!(tree.root(), tree.generate_proof(2).unwrap().calculate_root());
+    }
+
+    #[test]
+    fn test_borrowed_proofs() {
+        let tree = utils::create_tree_from_strings(vec!["a", "b", "c", "d", "e"]);
+
+        for index in 0..tree.unpadded_leaf_count() {
+            let proof = tree.generate_proof_ref(index).unwrap();
+            assert_eq!(proof.leaf, tree.get_leaf(index).unwrap().as_slice());
+            assert!(proof.verify(tree.root()));
+
+            let owned = proof.to_owned();
+            assert!(owned.verify(tree.root()));
+            assert!(tree.verify_proof(&owned));
+            assert_eq!(owned.to_bytes(), tree.generate_proof(index).unwrap().to_bytes());
+        }
+
+        let mut tampered = tree.generate_proof_ref(1).unwrap();
+        tampered.proof_items[0].is_left = !tampered.proof_items[0].is_left;
+        assert!(!tampered.verify(tree.root()));
+        assert!(matches!(
+            tree.generate_proof_ref(tree.leaf_count()),
+            Err(error::MerkleTreeError::LeafIndexOutOfBounds { index: 8, len: 8 })
+        ));
+    }
+
+    #[test]
+    fn test_parallel_hashing_matches_sequential() {
+        use std::io::Write;
+
+        // Roots computed by hashing one leaf at a time, which the utils must
+        // reproduce whether or not the `rayon` feature hashes in parallel
+        let hasher = Sha256Hasher::new();
+        let data: Vec<String> = (0..20_000).map(|i| format!("entry{}", i)).collect();
+        let sequential: Vec<Vec<u8>> = data.iter().map(|item| hasher.hash_leaf(item.as_bytes())).collect();
+        let expected = tree::MerkleTree::new(sequential.clone(), hasher.clone());
+
+        let strings: Vec<&str> = data.iter().map(String::as_str).collect();
+        assert_eq!(utils::create_tree_from_strings(strings).root(), expected.root());
+        let bytes: Vec<&[u8]> = data.iter().map(String::as_bytes).collect();
+        assert_eq!(utils::create_tree_from_bytes(bytes, hasher.clone()).root(), expected.root());
+
+        // Enough lines and chunks to span several hashing batches
+        let input = data.join("\n");
+        let lines = utils::create_tree_from_lines(input.as_bytes(), hasher.clone()).unwrap();
+        assert_eq!(lines.root(), expected.root());
+
+        let contents: Vec<u8> = (0..3 * utils::HASH_BATCH_BYTES + 100).map(|i| (i % 251) as u8).collect();
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(&contents).unwrap();
+        let chunks: Vec<Vec<u8>> = contents.chunks(1024).map(|chunk| hasher.hash_leaf(chunk)).collect();
+        let (chunked, chunk_count) = utils::create_tree_from_file(file.path(), 1024, hasher.clone()).unwrap();
+        assert_eq!(chunk_count, chunks.len());
+        assert_eq!(chunked.root(), tree::MerkleTree::new_ordered(chunks, hasher).root());
+    }
+
+    #[test]
+    fn test_batch_proof_generation() {
+        let strings: Vec<String> = (0..100).map(|i| format!("leaf{}", i)).collect();
+        let tree = utils::create_tree_from_strings(strings.iter().map(String::as_str).collect());
+
+        let proofs = tree.generate_proofs(&[5, 0, 99]).unwrap();
+        assert_eq!(proofs[0].to_bytes(), tree.generate_proof(5).unwrap().to_bytes());
+        assert!(proofs.iter().all(|proof| tree.verify_proof(proof)));
+        assert!(matches!(tree.generate_proofs(&[1, tree.leaf_count()]), Err(error::MerkleTreeError::LeafIndexOutOfBounds { .. })));
+
+        let first = tree.get_leaf(0).unwrap().clone();
+        let matching = tree.generate_proofs_where(|leaf| leaf == first.as_slice()).unwrap();
+        assert_eq!(matching.len(), 1);
+        assert_eq!(matching[0].0, 0);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_parallel_proof_generation() {
+        let strings: Vec<String> = (0..3000).map(|i| format!("leaf{}", i)).collect();
+        let tree = utils::create_tree_from_strings(strings.iter().map(String::as_str).collect());
+        let sequential: Vec<Vec<u8>> = (0..tree.unpadded_leaf_count())
+            .map(|index| tree.generate_proof(index).unwrap().to_bytes())
+            .collect();
+
+        let parallel = tree.generate_all_proofs_parallel().unwrap();
+        assert_eq!(parallel.len() as u64, tree.unpadded_leaf_count());
+        assert!(parallel.iter().all(|proof| proof.verify(tree.root())));
+        let parallel: Vec<Vec<u8>> = parallel.iter().map(|proof| proof.to_bytes()).collect();
+        assert_eq!(parallel, sequential);
+
+        let indices = [2999, 7, 1024];
+        let by_index: Vec<Vec<u8>> = tree.generate_proofs_parallel(&indices).unwrap()
+            .iter().map(|proof| proof.to_bytes()).collect();
+        assert_eq!(by_index, indices.iter().map(|&index| sequential[index as usize].clone()).collect::<Vec<_>>());
+
+        let predicate = |leaf: &[u8]| leaf[0] < 0x10;
+        let expected: Vec<(u64, Vec<u8>)> = tree.generate_proofs_where(predicate).unwrap().into_iter()
+            .map(|(index, proof)| (index, proof.to_bytes())).collect();
+        let matching: Vec<(u64, Vec<u8>)> = tree.generate_proofs_where_parallel(predicate).unwrap().into_iter()
+            .map(|(index, proof)| (index, proof.to_bytes())).collect();
+        assert!(!matching.is_empty());
+        assert_eq!(matching, expected);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_parallel_proof_verification() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<proof::MerkleProof<Sha256Hasher>>();
+        assert_send_sync::<proof::ProofItem>();
+
+        let strings: Vec<String> = (0..500).map(|i| format!("leaf{}", i)).collect();
+        let tree = utils::create_tree_from_strings(strings.iter().map(String::as_str).collect());
+        let mut proofs = tree.generate_all_proofs_parallel().unwrap();
+        // Break every third proof in a different way
+        for (index, proof) in proofs.iter_mut().enumerate().filter(|(index, _)| index % 3 == 0) {
+            match index % 2 {
+                0 => proof.leaf[0] ^= 1,
+                _ => proof.proof_items[index % 9].is_left ^= true,
+            }
+        }
+
+        let sequential = proof::verify_proofs(tree.root(), &proofs);
+        assert_eq!(proof::verify_proofs_parallel(tree.root(), &proofs), sequential);
+        assert_eq!(sequential.iter().filter(|valid| !**valid).count(), proofs.len().div_ceil(3));
+    }
+
+    #[test]
+    fn test_compute_root_streaming() {
+        let hasher = Sha256Hasher::new();
+
+        for count in 1..=17 {
+            // Reverse order, so sorting would change the root for count > 1
+            let leaves: Vec<Vec<u8>> = (0..count)
+                .rev()
+                .map(|i: u32| hasher.hash_leaf(&i.to_be_bytes()))
+                .collect();
+
+            let ordered = tree::MerkleTree::new_ordered(leaves.clone(), hasher.clone());
+            let streamed = utils::compute_root_streaming(leaves.clone(), &hasher).unwrap();
+            assert_eq!(streamed, ordered.root(), "{} leaves", count);
+
+            let mut sorted = leaves;
+            sorted.sort();
+            let tree = tree::MerkleTree::new(sorted.clone(), hasher.clone());
+            assert_eq!(utils::compute_root_streaming(sorted, &hasher).unwrap(), tree.root(), "{} sorted leaves", count);
+        }
+
+        assert!(matches!(utils::compute_root_streaming(Vec::new(), &hasher), Err(error::MerkleTreeError::EmptyLeaves)));
+    }
+
+    #[test]
+    fn test_tree_from_leaf_iter() {
+        let hasher = Sha256Hasher::new();
+        // Leaves are produced on demand, as from a database cursor
+        let generator = (0..1000u32).map(|i| hasher.hash_leaf(format!("row{}", i).as_bytes()));
+        let tree = tree::MerkleTree::from_leaf_iter(generator.clone(), hasher.clone()).unwrap();
+
+        let expected = tree::MerkleTree::new(generator.collect(), hasher.clone());
+        assert_eq!(tree.root(), expected.root());
+        assert_eq!(tree.leaf_count(), 1024);
+
+        assert!(matches!(tree::MerkleTree::from_leaf_iter(std::iter::empty(), hasher), Err(error::MerkleTreeError::EmptyLeaves)));
+    }
+
+    #[test]
+    fn test_fixed_size_tree_matches_dynamic() {
+        use crate::fixed::{MerkleProof32, MerkleTree32};
+
+        let hasher = Sha256Hasher::new();
+        for count in [1u32, 2, 5, 8, 13] {
+            let leaves: Vec<Vec<u8>> = (0..count).map(|i| hasher.hash_leaf(&i.to_le_bytes())).collect();
+            let fixed_leaves = leaves.iter().map(|leaf| leaf.as_slice().try_into().unwrap()).collect();
+
+            let dynamic = tree::MerkleTree::new(leaves, hasher.clone());
+            let fixed = MerkleTree32::new(fixed_leaves, hasher.clone()).unwrap();
+            assert_eq!(fixed.root().as_slice(), dynamic.root());
+            assert_eq!(MerkleTree32::from_tree(&dynamic).unwrap().root(), fixed.root());
+            assert_eq!(fixed.to_tree().root(), dynamic.root());
+
+            for index in 0..fixed.unpadded_leaf_count() {
+                let proof = fixed.generate_proof(index).unwrap();
+                assert!(fixed.verify_proof(&proof));
+                let converted = proof.to_proof();
+                assert_eq!(converted.to_bytes(), dynamic.generate_proof(index).unwrap().to_bytes());
+                assert!(MerkleProof32::from_proof(&converted).unwrap().verify(fixed.root()));
+            }
+        }
+
+        // 32-byte nodes are required throughout
+        let short = tree::MerkleTree::new(vec![vec![1; 16], vec![2; 16]], hasher.clone());
+        assert!(matches!(MerkleTree32::from_tree(&short), Err(error::MerkleTreeError::InvalidHashLength { expected: 32, actual: 16 })));
+        assert!(matches!(MerkleTree32::new(vec![[0; 32]; 3], hasher::Blake2bHasher::new(64)), Err(error::MerkleTreeError::HasherMismatch)));
+        assert!(matches!(MerkleTree32::new(Vec::new(), hasher), Err(error::MerkleTreeError::EmptyLeaves)));
+    }
+
+    #[test]
+    fn test_indexed_leaf_lookup() {
+        let hasher = Sha256Hasher::new();
+        // Duplicates, and 5 leaves so the last one is repeated as padding
+        let values = ["a", "b", "b", "c", "d"];
+        let leaves: Vec<Vec<u8>> = values.iter().map(|value| hasher.hash_leaf(value.as_bytes())).collect();
+
+        let scanned = tree::MerkleTree::new(leaves.clone(), hasher.clone());
+        let indexed = tree::MerkleTree::builder(hasher.clone()).index_leaves(true).build(leaves).unwrap();
+        assert_eq!(indexed.root(), scanned.root());
+
+        for value in values.iter().chain(&["missing"]) {
+            let leaf = hasher.hash_leaf(value.as_bytes());
+            assert_eq!(indexed.find_leaf_index(&leaf), scanned.find_leaf_index(&leaf), "{}", value);
+            assert_eq!(indexed.contains(&leaf), *value != "missing");
+        }
+
+        // The padding copies resolve to the first occurrence
+        let last = scanned.get_leaf(scanned.leaf_count() - 1).unwrap();
+        assert_eq!(indexed.find_leaf_index(last), Some(4));
+        assert_eq!(
+            indexed.generate_proof_by_value(last).unwrap().to_bytes(),
+            scanned.generate_proof_by_value(last).unwrap().to_bytes()
+        );
+
+        assert!(matches!(tree::MerkleTree::builder(hasher).build(Vec::new()), Err(error::MerkleTreeError::EmptyLeaves)));
+    }
+
+    #[test]
+    fn test_sorted_leaf_lookup() {
+        let hasher = Sha256Hasher::new();
+        let leaf = |byte: u8| vec![byte; 4];
+        let leaves = vec![leaf(9), leaf(3), leaf(3), leaf(7), leaf(3), leaf(1)];
+
+        // Sorted: [1, 3, 3, 3, 7, 9, 9, 9], searched with binary search
+        let sorted = tree::MerkleTree::new(leaves.clone(), hasher.clone());
+        assert_eq!(sorted.find_leaf_index(&leaf(1)), Some(0));
+        assert_eq!(sorted.find_leaf_index(&leaf(3)), Some(1));
+        assert_eq!(sorted.find_leaf_index(&leaf(7)), Some(4));
+        assert_eq!(sorted.find_leaf_index(&leaf(9)), Some(5));
+
+        // Values one byte away from a leaf, or a prefix of one, are misses
+        for near_miss in [vec![3, 3, 3, 2], vec![3, 3, 3, 4], vec![3, 3, 3], vec![3, 3, 3, 3, 0], vec![0], vec![10; 4]] {
+            assert_eq!(sorted.find_leaf_index(&near_miss), None, "{:?}", near_miss);
+        }
+
+        // Ordered trees keep the scan and its first-match semantics
+        let ordered = tree::MerkleTree::builder(hasher).sort_leaves(false).check_leaf_sizes(false).build(leaves).unwrap();
+        assert_eq!(ordered.find_leaf_index(&leaf(3)), Some(1));
+        assert_eq!(ordered.find_leaf_index(&leaf(1)), Some(5));
+        assert_eq!(ordered.find_leaf_index(&leaf(9)), Some(0));
+        assert_eq!(ordered.find_leaf_index(&[3, 3, 3, 2]), None);
+    }
+
+    #[test]
+    fn test_approx_memory_bytes() {
+        let hasher = Sha256Hasher::new();
+        let build = |count: u32, index_leaves: bool| {
+            let leaves = (0..count).map(|i| hasher.hash_leaf(&i.to_le_bytes())).collect();
+            tree::MerkleTree::builder(hasher.clone()).index_leaves(index_leaves).build(leaves).unwrap()
+        };
+
+        // 1024 leaves and 1023 interior nodes, each a 32-byte hash plus a slot
+        let tree = build(1024, false);
+        let breakdown = tree.memory_breakdown();
+        let per_node = 32 + std::mem::size_of::<Vec<u8>>();
+        assert!((1024 * per_node..2 * 1024 * per_node).contains(&breakdown.leaves), "{:?}", breakdown);
+        assert!((1023 * per_node..2 * 1024 * per_node).contains(&breakdown.nodes), "{:?}", breakdown);
+        assert_eq!(breakdown.indexes, 0);
+        assert_eq!(tree.approx_memory_bytes(), breakdown.total());
+
+        // Doubling the leaves doubles the estimate
+        let ratio = build(2048, false).approx_memory_bytes() as f64 / tree.approx_memory_bytes() as f64;
+        assert!((1.9..2.1).contains(&ratio), "{}", ratio);
+
+        // The leaf index holds a key per leaf
+        let indexed = build(1024, true).memory_breakdown();
+        assert!(indexed.indexes > 1024 * 32, "{:?}", indexed);
+        assert_eq!(indexed.leaves, breakdown.leaves);
+    }
+
+    #[test]
+    fn test_build_stats_and_progress() {
+        let hasher = Sha256Hasher::new();
+        let leaves: Vec<Vec<u8>> = (0..8u32).map(|i| hasher.hash_leaf(&i.to_le_bytes())).collect();
+
+        let mut calls = Vec::new();
+        let tree = tree::MerkleTree::builder(hasher.clone())
+            .collect_stats(true)
+            .on_progress(|level, done, total| calls.push((level, done, total)))
+            .build(leaves.clone())
+            .unwrap();
+        assert_eq!(tree.root(), tree::MerkleTree::new(leaves.clone(), hasher.clone()).root());
+
+        // 4 + 2 + 1 pair hashes, reported once per level above the leaves
+        let stats = tree.build_stats().unwrap();
+        assert_eq!(stats.pair_hashes, vec![0, 4, 2, 1]);
+        assert_eq!(stats.total_pair_hashes(), 7);
+        assert!(stats.elapsed.is_some());
+        assert_eq!(calls, vec![(1, 4, 7), (2, 6, 7), (3, 7, 7)]);
+
+        // Without the options there is nothing recorded
+        let plain = tree::MerkleTree::builder(hasher).build(leaves).unwrap();
+        assert!(plain.build_stats().is_none());
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn test_tracing_spans_and_events() {
+        use std::fmt::Debug;
+        use std::sync::{Arc, Mutex};
+        use tracing::field::{Field, Visit};
+        use tracing_subscriber::layer::{Context, SubscriberExt};
+
+        /// Records "name field=value ..." for every span and event
+        #[derive(Clone, Default)]
+        struct Recorder(Arc<Mutex<Vec<String>>>);
+
+        struct Fields(String);
+
+        impl Visit for Fields {
+            fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+                self.0.push_str(&format!(" {}={:?}", field.name(), value));
+            }
+        }
+
+        impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for Recorder {
+            fn on_new_span(&self, attrs: &tracing::span::Attributes<'_>, _: &tracing::span::Id, _: Context<'_, S>) {
+                let mut fields = Fields(attrs.metadata().name().to_string());
+                attrs.record(&mut fields);
+                self.0.lock().unwrap().push(fields.0);
+            }
+
+            fn on_event(&self, event: &tracing::Event<'_>, _: Context<'_, S>) {
+                let mut fields = Fields(event.metadata().target().to_string());
+                event.record(&mut fields);
+                self.0.lock().unwrap().push(fields.0);
+            }
+        }
+
+        let recorder = Recorder::default();
+        let subscriber = tracing_subscriber::registry().with(recorder.clone());
+        tracing::subscriber::with_default(subscriber, || {
+            let tree = utils::create_tree_from_strings(vec!["a", "b", "c"]);
+            let proof = tree.generate_proof(2).unwrap();
+            assert!(tree.verify_proof(&proof));
+        });
+
+        let lines = recorder.0.lock().unwrap().clone();
+        let find = |prefix: &str| lines.iter().find(|line| line.starts_with(prefix)).cloned()
+            .unwrap_or_else(|| panic!("no {} in {:?}", prefix, lines));
+        assert!(find("merkle_tree.build").contains("leaf_count=3 sorted=true"));
+        assert!(find("merkle_tree.generate_proof").contains("index=2 depth=2"));
+        let verify = find("merkle_tree.verify");
+        assert!(verify.contains("valid=true depth=2"));
+
+        // Hashes are truncated to their first 4 bytes
+        let root = hex::encode(utils::create_tree_from_strings(vec!["a", "b", "c"]).root());
+        assert!(verify.contains(&format!("root={}..", &root[..8])));
+        assert!(!lines.iter().any(|line| line.contains(&root)));
+    }
+
+    #[test]
+    fn test_lazy_tree_matches_eager() {
+        use crate::tree::MerkleTree;
+
+        let hasher = Sha256Hasher::new();
+        for count in [1, 2, 5, 8, 13] {
+            let leaves: Vec<Vec<u8>> = (0..count).map(|i: u32| hasher.hash_leaf(&i.to_le_bytes())).collect();
+            let eager = MerkleTree::new(leaves.clone(), hasher.clone());
+            let lazy = MerkleTree::builder(hasher.clone()).lazy(true).build(leaves).unwrap();
+
+            assert_eq!(lazy.root(), eager.root());
+            assert_eq!(lazy.leaf_count(), eager.leaf_count());
+            // Nothing above the leaves is held until a proof needs it
+            assert_eq!(lazy.memory_breakdown().nodes, lazy.root().len());
+
+            // Twice, so the second pass reads from the cache
+            for _ in 0..2 {
+                for index in 0..eager.unpadded_leaf_count() {
+                    let proof = lazy.generate_proof(index).unwrap();
+                    assert_eq!(proof.to_bytes(), eager.generate_proof(index).unwrap().to_bytes());
+                    assert!(lazy.verify_proof(&proof));
+                }
+            }
+            for level in 0..4 {
+                for index in 0..8 {
+                    assert_eq!(lazy.get_node(level, index), eager.get_node(level, index));
+                }
+            }
+            assert!(matches!(lazy.generate_proof_ref(0), Err(error::MerkleTreeError::NodesNotStored)));
+        }
+    }
+
+    #[test]
+    fn test_prune_keeps_proofs_working() {
+        use crate::store::NodeStore;
+
+        let mut tree = utils::create_tree_from_strings(vec!["a", "b", "c", "d", "e"]);
+        let root = tree.root().to_vec();
+        let proofs: Vec<_> = (0..tree.unpadded_leaf_count()).map(|index| tree.generate_proof(index).unwrap()).collect();
+        let before = tree.memory_breakdown();
+
+        tree.prune();
+
+        // Only the leaves are stored; the root is kept beside the store
+        assert!((1..4).all(|level| tree.store().get(level, 0).is_none()));
+        assert_eq!(tree.memory_breakdown().nodes, root.len());
+        assert_eq!(tree.memory_breakdown().leaves, before.leaves);
+        assert_eq!(tree.root(), root);
+        assert_eq!(tree.leaf_count(), 8);
+
+        for (proof, index) in proofs.iter().zip(0..) {
+            let regenerated = tree.generate_proof(index).unwrap();
+            assert_eq!(regenerated.to_bytes(), proof.to_bytes());
+            assert!(tree.verify_proof(&regenerated));
+        }
+        assert!(matches!(tree.generate_proof_ref(0), Err(error::MerkleTreeError::NodesNotStored)));
+    }
+
+    #[test]
+    fn test_leaf_sorts_match_sort_unstable() {
+        // xorshift, so the inputs are random but the same on every run
+        let mut state = 0x9e37_79b9_7f4a_7c15u64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+
+        for (count, len) in [(1, 8), (2, 32), (100, 32), (1000, 12), (5000, 64)] {
+            let mut leaves: Vec<Vec<u8>> = (0..count)
+                .map(|_| (0..len).map(|_| next() as u8).collect())
+                .collect();
+            // Duplicates, and distinct leaves sharing their first 8 bytes
+            for i in (0..count).step_by(7) {
+                leaves.push(leaves[i].clone());
+                let mut sibling = leaves[i].clone();
+                sibling[len - 1] ^= 1;
+                leaves.push(sibling);
+            }
+
+            let mut expected = leaves.clone();
+            expected.sort_unstable();
+            let mut radix = leaves.clone();
+            sort::radix_sort_leaves(&mut radix);
+            assert_eq!(radix, expected);
+            let mut sorted = leaves.clone();
+            sort::sort_leaves(&mut sorted);
+            assert_eq!(sorted, expected);
+        }
+
+        // Large enough to take the radix path through the constructor
+        let hasher = Sha256Hasher::new();
+        let mut leaves: Vec<Vec<u8>> = (0..1u32 << 16).map(|i| hasher.hash_leaf(&(i % 60_000).to_le_bytes())).collect();
+        let tree = tree::MerkleTree::new(leaves.clone(), hasher.clone());
+        leaves.sort_unstable();
+        assert_eq!(tree.root(), tree::MerkleTree::new_ordered(leaves, hasher).root());
+    }
+
+    #[test]
+    fn test_error_variants() {
+        use crate::error::{MerkleTreeError, ProofParseError};
+
+        let hasher = Sha256Hasher::new();
+        let tree = utils::create_tree_from_strings(vec!["a", "b", "c"]);
+        let Err(err) = tree.generate_proof(4) else { panic!("index 4 is out of bounds") };
+        assert!(matches!(err, MerkleTreeError::LeafIndexOutOfBounds { index: 4, len: 4 }));
+        assert_eq!(err.to_string(), "Leaf index out of bounds: index 4, 4 leaves");
+        let Err(err) = tree.generate_proof(3) else { panic!("index 3 is padding") };
+        assert_eq!(err.to_string(), "Leaf index refers to padding: index 3");
+        assert!(matches!(tree.generate_proof_by_value(b"missing"), Err(MerkleTreeError::LeafNotFound)));
+
+        let mut bytes = tree.generate_proof(0).unwrap().to_bytes();
+        let direction = 4 + 32 + 4;
+        bytes[direction] = 7;
+        assert!(matches!(
+            proof::MerkleProof::from_bytes(&bytes, hasher.clone()),
+            Err(MerkleTreeError::ProofParse(ProofParseError::InvalidDirection(7)))
+        ));
+
+        assert!(matches!(
+            tree::MerkleTree::from_store(store::MemoryNodeStore::new(), hasher.clone()),
+            Err(MerkleTreeError::IncompleteStore)
+        ));
+        assert!(matches!(
+            utils::create_tree_from_file("/nonexistent/merkle_tree_input", 1024, hasher.clone()),
+            Err(MerkleTreeError::Io(_))
+        ));
+        assert!(matches!(
+            utils::create_tree_from_file("/nonexistent/merkle_tree_input", 0, hasher.clone()),
+            Err(MerkleTreeError::InvalidChunkSize)
+        ));
+
+        // The old string errors stay reachable for one release
+        #[allow(deprecated)]
+        let message = MerkleTreeError::EmptyLeaves.as_str();
+        assert_eq!(message, "Cannot create a Merkle tree with no leaves");
+        fn legacy(tree: &tree::MerkleTree<Sha256Hasher>) -> Result<(), &'static str> {
+            tree.generate_proof(u64::MAX)?;
+            Ok(())
+        }
+        assert_eq!(legacy(&tree), Err("Leaf index out of bounds"));
+        #[allow(deprecated)]
+        for err in [MerkleTreeError::HasherMismatch, MerkleTreeError::InvalidHashLength { expected: 64, actual: 32 }] {
+            assert!(err.to_string().starts_with(err.as_str()));
+        }
+        assert!(matches!(tree::MerkleTree::try_new(Vec::new(), hasher.clone()), Err(MerkleTreeError::EmptyLeaves)));
+        assert!(matches!(tree::MerkleTree::try_new_ordered(Vec::new(), hasher), Err(MerkleTreeError::EmptyLeaves)));
+    }
+
+    #[test]
+    fn test_formatted_proof_errors_instead_of_panicking() {
+        use crate::error::ProofParseError;
+        use std::collections::HashMap;
+
+        let tree = utils::create_tree_from_strings(vec!["a", "b", "c", "d", "e"]);
+        let hasher = tree.get_hasher();
+        let leaf = hasher.hash_leaf(b"c");
+        let proof_data = tree.generate_proof_by_value(&leaf).unwrap().to_debug_format();
+        let verify = |data: Vec<HashMap<String, String>>| {
+            utils::verify_with_formatted_proof(tree.root(), leaf.clone(), data, hasher.clone())
+        };
+        assert_eq!(verify(proof_data.clone()), Ok(true));
+        assert_eq!(verify(proof_data[1..].to_vec()), Ok(false));
+
+        // Every way of breaking each item is reported against that item
+        let bad_values = ["", "zz", "0", "abc", "LEFT", "up", "\u{0}"];
+        for item in 0..proof_data.len() {
+            for field in ["hash", "direction"] {
+                let mut missing = proof_data.clone();
+                missing[item].remove(field);
+                assert_eq!(verify(missing), Err(ProofParseError::MissingField { item, field }));
+
+                for value in bad_values {
+                    let mut broken = proof_data.clone();
+                    broken[item].insert(field.to_string(), value.to_string());
+                    match verify(broken) {
+                        Err(ProofParseError::InvalidHex { item: bad, field: "hash" }) => assert_eq!(bad, item),
+                        Err(ProofParseError::UnknownDirection { item: bad, direction }) => {
+                            assert_eq!((bad, direction.as_str()), (item, value));
+                        }
+                        // Even-length hex is a well-formed hash that doesn't verify
+                        Ok(valid) => assert!(field == "hash" && !valid),
+                        other => panic!("unexpected {:?} for {}={:?}", other, field, value),
+                    }
+                }
+            }
+        }
+
+        let mut extra = proof_data.clone();
+        extra[0].insert("note".to_string(), "ignored".to_string());
+        assert_eq!(verify(extra), Ok(true));
+        assert_eq!(verify(vec![HashMap::new()]), Err(ProofParseError::MissingField { item: 0, field: "hash" }));
+    }
+
+    #[test]
+    fn test_no_panics_from_any_constructor() {
+        use crate::error::MerkleTreeError;
+        use crate::store::{MemoryNodeStore, NodeStore};
+        use crate::tree::MerkleTree;
+
+        fn exercise<S: NodeStore>(tree: &MerkleTree<Sha256Hasher, S>) {
+            let count = tree.leaf_count();
+            for index in 0..tree.unpadded_leaf_count() {
+                let proof = tree.generate_proof(index).unwrap();
+                assert!(tree.verify_proof(&proof));
+                assert!(tree.get_leaf(index).is_some());
+            }
+            for index in tree.unpadded_leaf_count()..count {
+                assert!(matches!(tree.generate_proof(index), Err(MerkleTreeError::PaddingLeaf { .. })));
+            }
+            for index in [count, count + 1, u64::MAX] {
+                assert!(matches!(tree.generate_proof(index), Err(MerkleTreeError::LeafIndexOutOfBounds { .. })));
+                assert!(tree.get_leaf(index).is_none());
+                assert!(tree.get_node(0, index).is_none());
+            }
+            assert!(tree.get_node(usize::MAX, 0).is_none());
+            assert_eq!(tree.get_node(count.trailing_zeros() as usize, 0).as_deref(), Some(tree.root()));
+            assert!(!tree.contains(b"not a leaf"));
+            assert!(matches!(tree.generate_proof_by_value(b"not a leaf"), Err(MerkleTreeError::LeafNotFound)));
+        }
+
+        let hasher = Sha256Hasher::new();
+        for count in [1u32, 2, 3, 7, 16] {
+            let leaves: Vec<Vec<u8>> = (0..count).map(|i| hasher.hash_leaf(&i.to_le_bytes())).collect();
+            let builder = || MerkleTree::builder(hasher.clone());
+
+            exercise(&MerkleTree::new(leaves.clone(), hasher.clone()));
+            exercise(&MerkleTree::new_ordered(leaves.clone(), hasher.clone()));
+            exercise(&MerkleTree::from_leaf_iter(leaves.clone(), hasher.clone()).unwrap());
+            exercise(&MerkleTree::with_store(leaves.clone(), hasher.clone(), MemoryNodeStore::new()).unwrap());
+            exercise(&builder().index_leaves(true).sort_leaves(false).build(leaves.clone()).unwrap());
+            exercise(&builder().lazy(true).build(leaves.clone()).unwrap());
+            let bytes = MerkleTree::new(leaves.clone(), hasher.clone()).to_bytes().unwrap();
+            exercise(&MerkleTree::from_bytes(&bytes, hasher.clone()).unwrap());
+
+            let mut pruned = builder().lazy(true).build(leaves.clone()).unwrap();
+            pruned.prune();
+            exercise(&pruned);
+            let mut pruned = MerkleTree::new(leaves.clone(), hasher.clone());
+            pruned.prune();
+            exercise(&pruned);
+
+            let reopened = MerkleTree::new(leaves.clone(), hasher.clone()).into_store();
+            exercise(&MerkleTree::from_store(reopened, hasher.clone()).unwrap());
+        }
+
+        // A store holding only the leaves and root reopens, but its proofs
+        // report the missing nodes instead of panicking
+        let tree = MerkleTree::new(vec![vec![1; 32], vec![2; 32], vec![3; 32], vec![4; 32]], hasher.clone());
+        let mut store = MemoryNodeStore::new();
+        for index in 0..4 {
+            let Ok(()) = store.put(0, index, tree.get_leaf(index as u64).unwrap().clone());
+        }
+        let Ok(()) = store.put(2, 0, tree.root().to_vec());
+        let Ok(()) = store.put_meta(tree.store().get_meta().unwrap().unwrap());
+        let partial = MerkleTree::from_store(store, hasher.clone()).unwrap();
+        assert_eq!(partial.root(), tree.root());
+        assert!(matches!(partial.generate_proof(0), Err(MerkleTreeError::MissingNode { level: 1, .. })));
+        assert!(matches!(partial.generate_proof_ref(0), Err(MerkleTreeError::MissingNode { level: 1, .. })));
+
+        assert!(matches!(MerkleTree::from_store(MemoryNodeStore::new(), hasher), Err(MerkleTreeError::IncompleteStore)));
+    }
+
+    #[test]
+    fn test_verify_element_with_byte_like_inputs() {
+        let tree = utils::create_tree_from_strings(vec!["alpha", "beta", "gamma"]);
+
+        let present = "beta";
+        assert!(utils::verify_element_in_tree(&tree, present));
+        assert!(utils::verify_element_in_tree(&tree, String::from(present)));
+        assert!(utils::verify_element_in_tree(&tree, Vec::from(present.as_bytes())));
+        assert!(utils::verify_element_in_tree(&tree, present.as_bytes()));
+        assert!(utils::verify_element_in_tree(&tree, b"beta"));
+
+        let absent = "delta";
+        assert!(!utils::verify_element_in_tree(&tree, absent));
+        assert!(!utils::verify_element_in_tree(&tree, String::from(absent)));
+        assert!(!utils::verify_element_in_tree(&tree, Vec::from(absent.as_bytes())));
+        assert!(!utils::verify_element_in_tree(&tree, absent.as_bytes()));
+
+        // Binary keys that aren't UTF-8
+        let keys: Vec<&[u8]> = vec![&[0xff, 0x00], &[0xfe, 0x01, 0x02]];
+        let tree = utils::create_tree_from_bytes(keys.clone(), Sha256Hasher::new());
+        for key in keys {
+            assert!(utils::verify_element_in_tree(&tree, key));
+            assert!(utils::verify_element_in_tree(&tree, Vec::from(key)));
+        }
+        assert!(!utils::verify_element_in_tree(&tree, [0xff]));
+    }
+
+    #[test]
+    fn test_prove_element() {
+        use crate::error::MerkleTreeError;
+
+        let tree = utils::create_tree_from_strings(vec!["alpha", "beta", "gamma"]);
+        let proof = utils::prove_element(&tree, "gamma").unwrap();
+        assert_eq!(proof.leaf, Sha256Hasher::new().hash_leaf(b"gamma"));
+        assert!(proof.verify(tree.root()));
+        assert_eq!(utils::prove_element(&tree, b"gamma").unwrap().to_bytes(), proof.to_bytes());
+
+        assert!(matches!(utils::prove_element(&tree, "delta"), Err(MerkleTreeError::LeafNotFound)));
+    }
+
+    #[test]
+    fn test_verify_proof_checks_membership() {
+        let tree = utils::create_tree_from_strings(vec!["a", "b", "c", "d"]);
+        let proof = tree.generate_proof(1).unwrap();
+        assert!(tree.verify_proof(&proof));
+        assert!(tree.verify_proof_root_only(&proof));
+
+        // The parent of leaves 0 and 1 with the rest of the path hashes up to
+        // the root, but it isn't a leaf
+        let parent = tree.get_node(1, 0).unwrap();
+        let shortened = proof::MerkleProof::new(parent, proof.proof_items[1..].to_vec(), tree.get_hasher());
+        assert!(tree.verify_proof_root_only(&shortened));
+        assert!(!tree.verify_proof(&shortened));
+
+        // The same through the leaf index and for ordered trees
+        let hasher = Sha256Hasher::new();
+        let leaves: Vec<Vec<u8>> = ["a", "b", "c", "d"].iter().map(|s| hasher.hash_leaf(s.as_bytes())).collect();
+        for tree in [
+            tree::MerkleTree::builder(hasher.clone()).index_leaves(true).build(leaves.clone()).unwrap(),
+            tree::MerkleTree::new_ordered(leaves, hasher.clone()),
+        ] {
+            let proof = tree.generate_proof(2).unwrap();
+            assert!(tree.verify_proof(&proof));
+            let shortened = proof::MerkleProof::new(tree.get_node(1, 1).unwrap(), proof.proof_items[1..].to_vec(), hasher.clone());
+            assert!(tree.verify_proof_root_only(&shortened));
+            assert!(!tree.verify_proof(&shortened));
+        }
+    }
+
+    #[test]
+    fn test_padding_leaves_have_no_proofs() {
+        let hasher = Sha256Hasher::new();
+        let leaves: Vec<Vec<u8>> = (0..5).map(|i| hasher.hash_leaf(format!("leaf{}", i).as_bytes())).collect();
+        let tree = tree::MerkleTree::new_ordered(leaves.clone(), hasher.clone());
+        assert_eq!(tree.leaf_count(), 8);
+        assert_eq!(tree.unpadded_leaf_count(), 5);
+        assert!(!tree.is_padding(4));
+        assert!((5..8).all(|index| tree.is_padding(index)));
+
+        assert!(matches!(tree.generate_proof(5), Err(error::MerkleTreeError::PaddingLeaf { index: 5 })));
+        assert!(matches!(tree.generate_proof_ref(7), Err(error::MerkleTreeError::PaddingLeaf { index: 7 })));
+        assert!(matches!(tree.generate_proof(8), Err(error::MerkleTreeError::LeafIndexOutOfBounds { index: 8, len: 8 })));
+        let fixed = fixed::MerkleTree32::new(
+            leaves.iter().map(|leaf| leaf.as_slice().try_into().unwrap()).collect(),
+            hasher.clone(),
+        ).unwrap();
+        assert!(matches!(fixed.generate_proof(6), Err(error::MerkleTreeError::PaddingLeaf { index: 6 })));
+
+        // The duplicated last leaf resolves to its real position
+        assert_eq!(tree.find_leaf_index(&leaves[4]), Some(4));
+        let proof = tree.generate_proof(4).unwrap();
+        assert!(tree.verify_proof(&proof));
+        assert_eq!(tree.generate_proofs_where(|leaf| leaf == leaves[4].as_slice()).unwrap().len(), 1);
+        #[cfg(feature = "rayon")]
+        assert_eq!(tree.generate_all_proofs_parallel().unwrap().len(), 5);
+
+        // Saved trees and stores keep the count
+        let restored = tree::MerkleTree::from_bytes(&tree.to_bytes().unwrap(), hasher.clone()).unwrap();
+        assert_eq!(restored.unpadded_leaf_count(), 5);
+        assert_eq!(restored.root(), tree.root());
+        let reopened = tree::MerkleTree::from_store(tree.into_store(), hasher).unwrap();
+        assert_eq!(reopened.unpadded_leaf_count(), 5);
+        assert!(matches!(reopened.generate_proof(5), Err(error::MerkleTreeError::PaddingLeaf { .. })));
+    }
+
+    #[test]
+    fn test_bound_root_separates_configurations() {
+        let hasher = Sha256Hasher::new();
+        let mut leaves: Vec<Vec<u8>> = (0..5).map(|i| hasher.hash_leaf(format!("leaf{}", i).as_bytes())).collect();
+        leaves.sort();
+
+        // Already-sorted leaves give one root whether or not the tree sorts them
+        let sorted = tree::MerkleTree::new(leaves.clone(), hasher.clone());
+        let ordered = tree::MerkleTree::new_ordered(leaves.clone(), hasher.clone());
+        assert_eq!(sorted.root(), ordered.root());
+        assert_ne!(sorted.bound_root(), ordered.bound_root());
+
+        let proof = sorted.generate_proof(2).unwrap();
+        assert!(proof.verify_bound(&sorted.bound_root(), 5, &sorted.params()));
+        assert!(!proof.verify_bound(&ordered.bound_root(), 5, &sorted.params()));
+        assert!(!proof.verify_bound(&sorted.bound_root(), 8, &sorted.params()));
+
+        // Eight leaves spelling out the padding share the root of the five
+        let mut explicit = leaves.clone();
+        explicit.resize(8, leaves[4].clone());
+        let explicit = tree::MerkleTree::new_ordered(explicit, hasher.clone());
+        assert_eq!(explicit.root(), ordered.root());
+        let proof = explicit.generate_proof(6).unwrap();
+        assert!(proof.verify(ordered.root()));
+        assert!(proof.verify_bound(&explicit.bound_root(), 8, &explicit.params()));
+        assert!(!proof.verify_bound(&ordered.bound_root(), 5, &ordered.params()));
+        assert!(explicit.generate_proof(4).unwrap().verify_bound(&explicit.bound_root(), 8, &explicit.params()));
+        assert!(!explicit.generate_proof(4).unwrap().verify_bound(&explicit.bound_root(), 5, &explicit.params()));
+
+        // The hasher name is bound in as well
+        let params = bound::TreeParams { hasher: "blake2b256".into(), sorted: false, index_bound: false };
+        assert_ne!(bound::bound_root(&hasher, ordered.root(), 5, &params), ordered.bound_root());
+
+        let proofs = vec![ordered.generate_proof(0).unwrap(), explicit.generate_proof(7).unwrap()];
+        assert_eq!(bound::verify_proofs_bound(&ordered.bound_root(), 5, &ordered.params(), &proofs), vec![true, false]);
+    }
+
+    #[test]
+    fn test_typed_tree_keeps_values() {
+        #[derive(Clone, Debug, PartialEq)]
+        struct Account {
+            name: String,
+            encoded: Vec<u8>,
+        }
+        impl Account {
+            fn new(name: &str, balance: u64) -> Self {
+                let mut encoded = name.as_bytes().to_vec();
+                encoded.extend_from_slice(&balance.to_le_bytes());
+                Account { name: name.to_string(), encoded }
+            }
+        }
+        impl AsRef<[u8]> for Account {
+            fn as_ref(&self) -> &[u8] {
+                &self.encoded
+            }
+        }
+
+        let hasher = Sha256Hasher::new();
+        let accounts: Vec<Account> = ["alice", "bob", "carol", "dave", "erin"].iter()
+            .enumerate()
+            .map(|(i, name)| Account::new(name, 100 * i as u64))
+            .collect();
+
+        for tree in [
+            typed::TypedMerkleTree::new(accounts.clone(), hasher.clone()).unwrap(),
+            typed::TypedMerkleTree::new_ordered(accounts.clone(), hasher.clone()).unwrap(),
+        ] {
+            assert_eq!(tree.len(), 5);
+            assert_eq!(tree.tree().leaf_count(), 8);
+            for (value, leaf, index) in tree.iter() {
+                assert_eq!(leaf, hasher.hash_leaf(value.as_ref()).as_slice());
+                assert_eq!(tree.value(index), Some(value));
+                let (proven, proof) = tree.prove(index).unwrap();
+                assert_eq!(proven, value);
+                assert_eq!(proof.leaf, leaf);
+                assert!(proof.verify(tree.root()));
+            }
+            assert_eq!(tree.iter().count(), 5);
+            assert!(tree.value(5).is_none());
+            assert!(matches!(tree.prove(5), Err(error::MerkleTreeError::PaddingLeaf { index: 5 })));
+
+            let proof = tree.prove_value(&accounts[2]).unwrap();
+            assert!(tree.tree().verify_proof(&proof));
+            let index = tree.find_value(&accounts[2]).unwrap();
+            assert_eq!(tree.value(index).unwrap().name, "carol");
+            assert!(matches!(tree.prove_value(&Account::new("carol", 1)), Err(error::MerkleTreeError::LeafNotFound)));
+        }
+
+        // Sorting is by leaf hash, with the values following their leaves
+        let sorted = typed::TypedMerkleTree::new(accounts.clone(), hasher.clone()).unwrap();
+        let leaves: Vec<Vec<u8>> = accounts.iter().map(|account| hasher.hash_leaf(account.as_ref())).collect();
+        assert_eq!(sorted.root(), tree::MerkleTree::new(leaves.clone(), hasher.clone()).root());
+        let ordered = typed::TypedMerkleTree::new_ordered(accounts.clone(), hasher.clone()).unwrap();
+        assert_eq!(ordered.root(), tree::MerkleTree::new_ordered(leaves, hasher.clone()).root());
+        assert_eq!(ordered.values(), accounts.as_slice());
+        let (values, _) = sorted.into_parts();
+        assert!(values.windows(2).all(|pair| hasher.hash_leaf(pair[0].as_ref()) <= hasher.hash_leaf(pair[1].as_ref())));
+
+        assert!(matches!(
+            typed::TypedMerkleTree::new(Vec::<Account>::new(), hasher),
+            Err(error::MerkleTreeError::EmptyLeaves)
+        ));
+    }
+
+    #[test]
+    fn test_reverse_lookup_from_leaf_hash() {
+        let strings = vec!["alice", "bob", "carol", "bob", "dave"];
+        let plain = utils::create_tree_from_strings(strings.clone());
+        let typed = utils::create_typed_tree_from_strings(strings.clone()).unwrap();
+        assert_eq!(typed.root(), plain.root());
+
+        for tree in [typed, utils::create_typed_tree_from_strings(strings.clone()).unwrap().with_leaf_index()] {
+            for string in ["alice", "bob", "carol", "dave"] {
+                // A verifier returns only the proof; its leaf hash leads back to the string
+                let proof = plain.generate_proof_by_value(&Sha256Hasher::new().hash_leaf(string.as_bytes())).unwrap();
+                assert!(proof.verify(tree.root()));
+                assert_eq!(tree.original_for_leaf(&proof.leaf).map(String::as_str), Some(string));
+            }
+
+            // Duplicates: the first by index, or all of them
+            let bob = Sha256Hasher::new().hash_leaf(b"bob");
+            let matches: Vec<(u64, &String)> = tree.originals_for_leaf(&bob).collect();
+            assert_eq!(matches.len(), 2);
+            assert!(matches.iter().all(|(_, value)| value.as_str() == "bob"));
+            assert_eq!(tree.find_value(&"bob".to_string()), Some(matches[0].0));
+            assert!(tree.original_for_leaf(&[0u8; 32]).is_none());
+            assert_eq!(tree.originals_for_leaf(&[0u8; 32]).count(), 0);
+        }
+
+        // Metadata held by the caller, in the order of an ordered tree's leaves
+        let hasher = Sha256Hasher::new();
+        let leaves: Vec<Vec<u8>> = strings.iter().map(|s| hasher.hash_leaf(s.as_bytes())).collect();
+        let ordered = tree::MerkleTree::new_ordered(leaves, hasher.clone());
+        let rows = [10, 11, 12, 13, 14];
+        let proof = ordered.generate_proof(2).unwrap();
+        assert_eq!(ordered.metadata_for_leaf(&proof.leaf, &rows), Some(&12));
+        assert_eq!(ordered.metadata_for_leaf(&hasher.hash_leaf(b"bob"), &rows), Some(&11));
+        assert_eq!(ordered.metadata_for_leaf(&[0u8; 32], &rows), None);
+    }
+
+    #[test]
+    fn test_merkle_map_mutations() {
+        type Map = map::MerkleMap<String, Vec<u8>, Sha256Hasher>;
+        let balance = |amount: u64| amount.to_le_bytes().to_vec();
+        let check = |map: &Map| {
+            let root = map.root().unwrap();
+            for (key, value) in map.iter() {
+                let proof = map.prove(key).unwrap();
+                assert!(Map::verify(root, key.as_bytes(), value, &proof));
+                assert!(!Map::verify(root, key.as_bytes(), &balance(u64::MAX), &proof));
+                assert!(!Map::verify(root, b"mallory", value, &proof));
+            }
+        };
+
+        let mut map = Map::new(Sha256Hasher::new());
+        assert!(map.root().is_none());
+        assert!(matches!(map.prove("alice"), Err(error::MerkleTreeError::LeafNotFound)));
+
+        let root = map.insert("carol".to_string(), balance(30));
+        assert_eq!(map.root(), Some(root.as_slice()));
+        map.insert("alice".to_string(), balance(10));
+        map.insert("bob".to_string(), balance(20));
+        assert_eq!(map.len(), 3);
+        assert_eq!(map.iter().map(|(key, _)| key.as_str()).collect::<Vec<_>>(), ["alice", "bob", "carol"]);
+        check(&map);
+
+        // An update changes the root and invalidates the old proof
+        let old_root = map.root().unwrap().to_vec();
+        let old_proof = map.prove("bob").unwrap();
+        let new_root = map.insert("bob".to_string(), balance(25));
+        assert_ne!(new_root, old_root);
+        assert_eq!(map.len(), 3);
+        assert_eq!(map.get("bob"), Some(&balance(25)));
+        assert!(Map::verify(&old_root, b"bob", &balance(20), &old_proof));
+        assert!(!Map::verify(&new_root, b"bob", &balance(20), &old_proof));
+        check(&map);
+
+        // The root depends only on the entries, not the order they were inserted in
+        let mut other = Map::new(Sha256Hasher::new());
+        for (key, amount) in [("bob", 25), ("carol", 30), ("alice", 10)] {
+            other.insert(key.to_string(), balance(amount));
+        }
+        assert_eq!(other.root(), map.root());
+
+        assert_eq!(map.remove("alice"), Some(balance(10)));
+        assert_eq!(map.remove("alice"), None);
+        assert!(!map.contains_key("alice"));
+        assert!(matches!(map.prove("alice"), Err(error::MerkleTreeError::LeafNotFound)));
+        check(&map);
+        map.remove("bob");
+        map.remove("carol");
+        assert!(map.is_empty() && map.root().is_none());
+
+        // The key length prefix keeps entries that concatenate alike apart
+        assert_ne!(map::encode_entry(b"ab", b"c"), map::encode_entry(b"a", b"bc"));
+    }
+
+    #[test]
+    fn test_sparse_tree_membership_and_absence() {
+        let mut tree = sparse::SparseMerkleTree::new(Sha256Hasher::new());
+        assert_eq!(tree.depth(), 256);
+        let empty_root = tree.root().to_vec();
+        let proof = tree.prove(b"alice");
+        assert!(proof.siblings.is_empty());
+        assert!(proof.verify(&empty_root, b"alice", None));
+        assert!(!proof.verify(&empty_root, b"alice", Some(b"10")));
+
+        tree.update(b"alice", b"10");
+        let root = tree.update(b"bob", b"20");
+        assert_eq!(tree.root(), root.as_slice());
+        assert_eq!(tree.get(b"alice"), Some(&b"10"[..]));
+        assert_eq!(tree.len(), 2);
+
+        let alice = tree.prove(b"alice");
+        assert!(alice.verify(&root, b"alice", Some(b"10")));
+        assert!(!alice.verify(&root, b"alice", Some(b"11")));
+        assert!(!alice.verify(&root, b"alice", None));
+        assert!(!alice.verify(&root, b"bob", Some(b"10")));
+        let carol = tree.prove(b"carol");
+        assert!(carol.verify(&root, b"carol", None));
+        assert!(!carol.verify(&root, b"carol", Some(b"")));
+
+        // Updating another key makes old proofs stale, but carol is still
+        // provably absent, and undoing an update restores the root
+        let before = tree.prove(b"carol");
+        let root = tree.update(b"bob", b"25");
+        assert!(!before.verify(&root, b"carol", None));
+        assert!(tree.prove(b"carol").verify(&root, b"carol", None));
+        let dave = tree.prove(b"dave");
+        tree.update(b"erin", b"5");
+        tree.remove(b"erin");
+        assert_eq!(tree.root(), root.as_slice());
+        assert!(dave.verify(tree.root(), b"dave", None));
+
+        // Proofs only carry the siblings of set keys, and survive encoding
+        let alice = tree.prove(b"alice");
+        let bytes = alice.to_bytes();
+        assert!(alice.siblings.len() <= 2);
+        assert!(bytes.len() < 4 + 32 + 4 + 2 * 32 + 1);
+        let decoded = sparse::SparseProof::from_bytes(&bytes, Sha256Hasher::new()).unwrap();
+        assert!(decoded.verify(tree.root(), b"alice", Some(b"10")));
+        assert!(matches!(
+            sparse::SparseProof::from_bytes(&bytes[..bytes.len() - 1], Sha256Hasher::new()),
+            Err(error::MerkleTreeError::ProofParse(error::ProofParseError::Truncated))
+        ));
+
+        assert_eq!(tree.remove(b"alice"), Some(b"10".to_vec()));
+        tree.remove(b"bob");
+        assert!(tree.is_empty());
+        assert_eq!(tree.root(), empty_root.as_slice());
+    }
+
+    #[test]
+    fn test_merkle_mountain_range() {
+        let hasher = Sha256Hasher::new();
+        let leaf = |i: u64| hasher.hash_leaf(&i.to_le_bytes());
+        let mut mmr = mmr::MerkleMountainRange::new(hasher.clone());
+        assert!(mmr.root().is_none());
+
+        let positions: Vec<u64> = (0..40).map(|i| mmr.push(leaf(i))).collect();
+        assert_eq!(&positions[..8], &[0, 1, 3, 4, 7, 8, 10, 11]);
+        assert!(positions.iter().enumerate().all(|(i, &pos)| pos == mmr::leaf_index_to_position(i as u64)));
+        // 40 leaves are trees of 32 and 8 leaves: 63 + 15 nodes
+        assert_eq!(mmr.size(), 78);
+        assert_eq!(mmr.peaks().len(), 2);
+
+        let root = mmr.root().unwrap();
+        for index in [0, 1, 17, 31, 32, 38, 39] {
+            let position = positions[index as usize];
+            let proof = mmr.gen_proof(position).unwrap();
+            assert!(mmr::MerkleMountainRange::verify(&root, &leaf(index), position, &proof));
+            assert!(!proof.verify(&root, &leaf(index + 1), position));
+            assert!(!proof.verify(&root, &leaf(index), position + 1));
+        }
+        assert!(matches!(mmr.gen_proof(2), Err(error::MerkleTreeError::NotALeaf { position: 2 })));
+        assert!(matches!(mmr.gen_proof(78), Err(error::MerkleTreeError::LeafIndexOutOfBounds { .. })));
+
+        // A leaf that is its own peak has no siblings, only the other peaks
+        let position = mmr.push(leaf(40));
+        let proof = mmr.gen_proof(position).unwrap();
+        assert!(proof.siblings.is_empty());
+        assert_eq!(proof.peaks.len(), 2);
+        assert!(proof.verify(&mmr.root().unwrap(), &leaf(40), position));
+        // Old proofs are for the old size
+        assert!(!mmr.gen_proof(positions[3]).unwrap().verify(&root, &leaf(3), positions[3]));
+
+        // Eleven leaves, computed by hand: peaks over leaves 0..8, 8..10 and 10
+        let mut small = mmr::MerkleMountainRange::new(hasher.clone());
+        for i in 0..11 {
+            small.push(leaf(i));
+        }
+        let pair = |a: &[u8], b: &[u8]| hasher.hash_pair(a, b);
+        let level1: Vec<Vec<u8>> = (0..4).map(|i| pair(&leaf(2 * i), &leaf(2 * i + 1))).collect();
+        let level2 = [pair(&level1[0], &level1[1]), pair(&level1[2], &level1[3])];
+        let peaks = [pair(&level2[0], &level2[1]), pair(&leaf(8), &leaf(9)), leaf(10)];
+        assert_eq!(small.size(), 19);
+        assert_eq!(small.peaks(), peaks.iter().map(Vec::as_slice).collect::<Vec<_>>());
+        assert_eq!(small.root().unwrap(), pair(&peaks[0], &pair(&peaks[1], &peaks[2])));
+    }
+
+    #[test]
+    fn test_merkle_forest() {
+        let epoch = |n: usize, count: usize| {
+            let strings: Vec<String> = (0..count).map(|i| format!("epoch{}-{}", n, i)).collect();
+            utils::create_tree_from_strings(strings.iter().map(String::as_str).collect())
+        };
+        let mut forest = forest::MerkleForest::new(Sha256Hasher::new());
+        assert!(forest.root().is_none());
+        for n in 0..3 {
+            assert_eq!(forest.push_named(format!("epoch{}", n), epoch(n, 3 + n)), n);
+        }
+        assert_eq!(forest.index_of("epoch1"), Some(1));
+        let root = forest.root().unwrap().to_vec();
+
+        let proofs: Vec<forest::ForestProof<Sha256Hasher>> = (0..3).map(|n| forest.prove(n, 1).unwrap()).collect();
+        for proof in &proofs {
+            assert!(proof.verify(&root));
+        }
+        // The subtree proof can't be passed off as another subtree's
+        let mut moved = forest.prove(0, 1).unwrap();
+        moved.tree_index = 1;
+        assert!(!moved.verify(&root));
+        assert!(matches!(forest.prove(3, 0), Err(error::MerkleTreeError::TreeIndexOutOfBounds { index: 3, len: 3 })));
+        assert!(matches!(forest.prove(0, 9), Err(error::MerkleTreeError::LeafIndexOutOfBounds { .. })));
+
+        // Replacing a subtree changes the forest root and that subtree's leaf
+        // proofs; the other subtrees' leaf proofs stay the same
+        let old = forest.replace(1, epoch(7, 4)).unwrap();
+        assert_eq!(old.leaf_count(), 4);
+        let new_root = forest.root().unwrap().to_vec();
+        assert_ne!(new_root, root);
+        for (n, old_proof) in proofs.iter().enumerate() {
+            let proof = forest.prove(n, 1).unwrap();
+            assert!(proof.verify(&new_root));
+            assert!(!old_proof.verify(&new_root));
+            let unchanged = proof.leaf_proof.to_bytes() == old_proof.leaf_proof.to_bytes();
+            assert_eq!(unchanged, n != 1);
+        }
+        assert!(matches!(forest.replace(5, epoch(0, 1)), Err(error::MerkleTreeError::TreeIndexOutOfBounds { .. })));
+    }
+
+    #[test]
+    fn test_index_bound_leaves() {
+        let hasher = Sha256Hasher::new();
+        let data = ["same", "other", "same", "last"];
+        let tree = tree::MerkleTree::builder(hasher.clone()).bind_indices(true).build_from_data(&data).unwrap();
+        assert!(tree.is_index_bound());
+        assert_eq!(tree.get_leaf(1).unwrap(), &utils::hash_indexed_leaf(&hasher, 1, b"other"));
+        let root = tree.root();
+
+        // Equal data at two indices gives two different leaves
+        let first = tree.generate_proof_for_data(0, b"same").unwrap();
+        let second = tree.generate_proof_for_data(2, b"same").unwrap();
+        assert_ne!(first.leaf, second.leaf);
+        assert!(utils::verify_indexed_leaf(root, 0, b"same", &first));
+        assert!(utils::verify_indexed_leaf(root, 2, b"same", &second));
+
+        // The same data at another claimed index doesn't verify
+        assert!(!utils::verify_indexed_leaf(root, 2, b"same", &first));
+        assert!(!utils::verify_indexed_leaf(root, 1, b"other", &second));
+        assert!(matches!(tree.generate_proof_for_data(1, b"same"), Err(error::MerkleTreeError::LeafNotFound)));
+        assert!(matches!(tree.generate_proof_for_data(4, b"same"), Err(error::MerkleTreeError::LeafIndexOutOfBounds { .. })));
+
+        // Sorting can't be combined with it, and plain trees hash the data alone
+        assert!(matches!(
+            tree::MerkleTree::builder(hasher.clone()).bind_indices(true).sort_leaves(true).build_from_data(&data),
+            Err(error::MerkleTreeError::SortedIndexBinding)
+        ));
+        let plain = tree::MerkleTree::builder(hasher.clone()).sort_leaves(false).build_from_data(&data).unwrap();
+        assert!(!plain.is_index_bound());
+        assert!(plain.generate_proof_for_data(3, b"last").unwrap().verify(plain.root()));
+        let ordered = tree::MerkleTree::new_ordered(
+            data.iter().map(|item| hasher.hash_leaf(item.as_bytes())).collect(), hasher.clone()
+        );
+        assert_eq!(plain.bound_root(), ordered.bound_root());
+        assert!(!tree.params().sorted && tree.params().index_bound);
+    }
+
+    #[test]
+    fn test_fingerprint_identifies_construction() {
+        let strings = vec!["a", "b", "c"];
+        let sha = utils::create_tree_from_strings(strings.clone());
+        let blake = utils::create_tree_from_strings_with_hasher(strings.clone(), hasher::Blake2bHasher::new(32));
+        assert_eq!(sha.fingerprint().len(), 33);
+        assert_eq!(sha.fingerprint()[0], fingerprint::FINGERPRINT_VERSION);
+        assert_ne!(sha.fingerprint(), blake.fingerprint());
+
+        // Same leaves built the same way again, or with another output size or sort mode
+        let rebuilt = utils::create_tree_from_strings(strings.clone());
+        assert_eq!(sha.fingerprint(), rebuilt.fingerprint());
+        let wide = utils::create_tree_from_strings_with_hasher(strings.clone(), hasher::Blake2bHasher::new(64));
+        assert_ne!(blake.fingerprint(), wide.fingerprint());
+        let hasher = Sha256Hasher::new();
+        let mut leaves: Vec<Vec<u8>> = strings.iter().map(|s| hasher.hash_leaf(s.as_bytes())).collect();
+        leaves.sort();
+        let ordered = tree::MerkleTree::new_ordered(leaves, hasher);
+        assert_eq!(ordered.root(), sha.root());
+        assert_ne!(ordered.fingerprint(), sha.fingerprint());
+
+        let stored = fingerprint::Fingerprint::of(&sha).to_hex();
+        let parsed = fingerprint::Fingerprint::from_hex(&stored).unwrap();
+        assert!(parsed.matches(&rebuilt));
+        assert!(!parsed.matches(&blake));
+        assert!(fingerprint::Fingerprint::from_hex("zz").is_none());
+        assert!(fingerprint::Fingerprint::from_bytes(sha.root()).is_none());
+    }
+
+    #[test]
+    fn test_types_are_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<tree::MerkleTree<Sha256Hasher>>();
+        assert_send_sync::<tree::MerkleTree<hasher::BuiltinHasher>>();
+        assert_send_sync::<proof::MerkleProof<Sha256Hasher>>();
+        assert_send_sync::<proof::MerkleProofRef<'static, Sha256Hasher>>();
+        assert_send_sync::<fixed::MerkleTree32<Sha256Hasher>>();
+        assert_send_sync::<typed::TypedMerkleTree<String, Sha256Hasher>>();
+        assert_send_sync::<map::MerkleMap<String, Vec<u8>, Sha256Hasher>>();
+        assert_send_sync::<sparse::SparseMerkleTree<Sha256Hasher>>();
+        assert_send_sync::<sparse::SparseProof<Sha256Hasher>>();
+        assert_send_sync::<mmr::MerkleMountainRange<Sha256Hasher>>();
+        assert_send_sync::<forest::MerkleForest<Sha256Hasher>>();
+        assert_send_sync::<error::MerkleTreeError>();
+        #[cfg(feature = "sled")]
+        assert_send_sync::<tree::MerkleTree<Sha256Hasher, store::SledNodeStore>>();
+        #[cfg(feature = "mmap")]
+        assert_send_sync::<tree::MerkleTree<Sha256Hasher, store::MmapLeafStore>>();
+
+        // Eager and lazy trees shared across threads give the same proofs
+        let hasher = Sha256Hasher::new();
+        let leaves: Vec<Vec<u8>> = (0..200).map(|i| hasher.hash_leaf(format!("leaf{}", i).as_bytes())).collect();
+        let eager = std::sync::Arc::new(tree::MerkleTree::new(leaves.clone(), hasher.clone()));
+        let lazy = std::sync::Arc::new(tree::MerkleTree::builder(hasher).lazy(true).build(leaves).unwrap());
+        let workers: Vec<_> = (0..8).map(|worker| {
+            let (eager, lazy) = (eager.clone(), lazy.clone());
+            std::thread::spawn(move || {
+                for index in (worker..200).step_by(8) {
+                    let proof = lazy.generate_proof(index).unwrap();
+                    assert!(eager.verify_proof(&proof));
+                    assert_eq!(proof.to_bytes(), eager.generate_proof(index).unwrap().to_bytes());
+                }
+            })
+        }).collect();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_leaf_sizes_are_checked() {
+        let hasher = Sha256Hasher::new();
+        let mut leaves: Vec<Vec<u8>> = ["a", "b", "c"].iter().map(|s| hasher.hash_leaf(s.as_bytes())).collect();
+        leaves.insert(2, b"raw value".to_vec());
+
+        assert!(matches!(
+            tree::MerkleTree::builder(hasher.clone()).build(leaves.clone()),
+            Err(error::MerkleTreeError::LeafSizeMismatch { index: 2, expected: 32, actual: 9 })
+        ));
+        assert!(matches!(
+            tree::MerkleTree::from_leaf_iter(leaves.clone(), hasher.clone()),
+            Err(error::MerkleTreeError::LeafSizeMismatch { index: 2, .. })
+        ));
+        // Uniform but unhashed leaves are caught too, against the hasher's output size
+        let blake = hasher::Blake2bHasher::new(64);
+        let short: Vec<Vec<u8>> = ["a", "b"].iter().map(|s| Sha256Hasher::new().hash_leaf(s.as_bytes())).collect();
+        assert!(matches!(
+            tree::MerkleTree::builder(blake.clone()).build(short.clone()),
+            Err(error::MerkleTreeError::LeafSizeMismatch { index: 0, expected: 64, actual: 32 })
+        ));
+
+        let tree = tree::MerkleTree::builder(hasher.clone()).check_leaf_sizes(false).build(leaves.clone()).unwrap();
+        assert_eq!(tree.unpadded_leaf_count(), 4);
+        assert!(tree::MerkleTree::builder(blake).check_leaf_sizes(false).build(short).is_ok());
+        let unchecked = tree::MerkleTree::builder(hasher.clone()).check_leaf_sizes(false).build_from_iter(leaves.clone()).unwrap();
+        assert_eq!(unchecked.root(), tree.root());
+        assert_eq!(hasher.output_size(), hasher.hash_leaf(b"").len());
+        assert_eq!(hasher::BuiltinHasher::from_name("blake2b512").unwrap().output_size(), 64);
+    }
+
+    #[cfg(feature = "proptest")]
+    mod generated {
+        use super::*;
+        use proptest::prelude::*;
+
+        proptest! {
+            #[test]
+            fn test_generated_proofs_properties(
+                (tree, proof) in strategy::any_tree(strategy::any_hasher(), 1..=33)
+                    .prop_flat_map(|tree| {
+                        let proofs = strategy::any_proof_for(&tree);
+                        (Just(std::sync::Arc::new(tree)), proofs)
+                    })
+            ) {
+                // Any proof of a real leaf verifies, before and after a round trip
+                let root = tree.root();
+                prop_assert!(proof.verify(root));
+                prop_assert_eq!(proof.proof_items.len(), tree.leaf_count().trailing_zeros() as usize);
+                prop_assert!(proof::MerkleProof::from_bytes(&proof.to_bytes(), proof.hasher.clone()).unwrap().verify(root));
+            }
+
+            #[test]
+            fn test_mutated_proofs_fail(
+                (proof, position) in strategy::any_proof()
+                    .prop_flat_map(|proof| {
+                        let len = proof.to_bytes().len();
+                        (Just(proof), 0..len)
+                    })
+            ) {
+                // Flipping the top bit of any byte of an encoded proof breaks it:
+                // lengths overrun, directions become invalid, hashes change
+                let root = proof.calculate_root();
+                let mut mutated = proof.to_bytes();
+                mutated[position] ^= 0x80;
+                let parsed = proof::MerkleProof::from_bytes(&mutated, proof.hasher.clone());
+                prop_assert!(!parsed.is_ok_and(|proof| proof.verify(&root)));
+            }
+
+            #[test]
+            fn test_arbitrary_proofs_round_trip(proof in any::<proof::MerkleProof<hasher::BuiltinHasher>>()) {
+                let parsed = proof::MerkleProof::from_bytes(&proof.to_bytes(), proof.hasher.clone()).unwrap();
+                prop_assert_eq!(parsed.leaf, proof.leaf);
+                prop_assert_eq!(parsed.proof_items, proof.proof_items);
+            }
+        }
+    }
+
+    #[test]
+    fn test_keccak256_hasher() {
+        let hasher = hasher::Keccak256Hasher::new();
+        assert_eq!(hex::encode(hasher.hash_leaf(b"")), "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470");
+        assert_eq!(hex::encode(hasher.hash_leaf(b"abc")), "4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45");
+        assert_eq!(
+            hex::encode(hasher.hash_leaf(b"The quick brown fox jumps over the lazy dog")),
+            "4d741b6f1eb29cb2a9b9911c82f56fa8d73b04959d3d9d222895df6c0b28aa15"
+        );
+
+        // Runs of 'a' one short of, exactly and one past the 136-byte rate, and two blocks
+        for (len, expected) in [
+            (135, "34367dc248bbd832f4e3e69dfaac2f92638bd0bbd18f2912ba4ef454919cf446"),
+            (136, "a6c4d403279fe3e0af03729caada8374b5ca54d8065329a3ebcaeb4b60aa386e"),
+            (137, "d869f639c7046b4929fc92a4d988a8b22c55fbadb802c0c66ebcd484f1915f39"),
+            (272, "cf7fcd4f705ee749930d19ca84561a9bf62516bd90a471545fa2f49fdc7e63c8"),
+        ] {
+            assert_eq!(hex::encode(hasher.hash_leaf(&vec![b'a'; len])), expected, "{} bytes", len);
+        }
+
+        // Inputs spanning the 136-byte block boundary hash the same streamed
+        let data: Vec<u8> = (0..=255u8).cycle().take(300).collect();
+        assert_eq!(hasher.hash_leaf_reader(&data[..]).unwrap(), hasher.hash_leaf(&data));
+        assert_eq!(hasher.hash_pair(&data[..136], &data[136..]), hasher.hash_leaf(&data));
+        let named = hasher::BuiltinHasher::from_name("keccak256").unwrap();
+        assert_eq!(named.name(), "keccak256");
+        assert_eq!(named.hash_leaf(&data), hasher.hash_leaf(&data));
+    }
+
+    #[cfg(feature = "vectors")]
+    #[test]
+    fn test_default_vectors_match_fixture() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/vectors/default.json");
+        let generated = vectors::generate_vectors(&vectors::VectorConfig::default());
+        assert_eq!(generated.len(), 2 * 9 * 2);
+
+        // Any change to roots, proofs or the format shows up as a diff to the fixture
+        let shipped = std::fs::read_to_string(path).unwrap();
+        assert_eq!(vectors::vectors_to_json(&generated), shipped);
+        assert_eq!(vectors::verify_vectors(path).unwrap(), generated.len());
+
+        // A fixture with one changed root is caught
+        let dir = tempfile::tempdir().unwrap();
+        let mut tampered = generated.clone();
+        tampered[5].root = hex::encode([0u8; 32]);
+        let tampered_path = dir.path().join("tampered.json");
+        vectors::write_vectors(&tampered_path, &tampered).unwrap();
+        assert!(matches!(
+            vectors::verify_vectors(&tampered_path),
+            Err(vectors::VectorError::Mismatch { vector: 5, reason: "root differs" })
+        ));
+        assert!(matches!(
+            vectors::vectors_from_json(&shipped.replacen("\"version\": 1", "\"version\": 2", 1)),
+            Err(vectors::VectorError::UnsupportedVersion(2))
+        ));
+    }
+
+    #[test]
+    fn test_random_tree_is_deterministic() {
+        // Pinned so a change to the generator or to construction is caught
+        let leaves = utils::random_leaves(42, 2, 12);
+        assert_eq!(hex::encode(&leaves[0]), "956eeb2f2632d7bd03f166b2");
+        assert_eq!(hex::encode(&leaves[1]), "529f0f135767524794e34a0e");
+
+        let tree = utils::random_tree(42, 1000, 64, Sha256Hasher::new()).unwrap();
+        assert_eq!(hex::encode(tree.root()), "c6e2f7926d0bb2df9f53c23e829d840b5bcc011afdd76a205811fd034b1b62aa");
+        assert_eq!(tree.unpadded_leaf_count(), 1000);
+        assert_eq!(utils::random_tree(42, 1000, 64, Sha256Hasher::new()).unwrap().root(), tree.root());
+        assert_ne!(utils::random_tree(43, 1000, 64, Sha256Hasher::new()).unwrap().root(), tree.root());
+
+        // The first generator output starts a leaf whatever its size
+        assert_eq!(utils::random_leaves(7, 3, 0), vec![Vec::<u8>::new(); 3]);
+        assert_eq!(&utils::random_leaves(7, 1, 16)[0][..8], &utils::random_leaves(7, 1, 8)[0][..]);
+        assert!(matches!(
+            utils::random_tree(42, 0, 64, Sha256Hasher::new()),
+            Err(error::MerkleTreeError::EmptyLeaves)
+        ));
+    }
+
+    #[test]
+    fn test_root_mismatch_report() {
+        use utils::testing::{assert_same_root, explain_root_mismatch};
+        let hasher = Sha256Hasher::new();
+        let leaves: Vec<Vec<u8>> = utils::random_leaves(3, 11, 32);
+        let tree = tree::MerkleTree::new_ordered(leaves.clone(), hasher.clone());
+        let lazy = tree::MerkleTree::builder(hasher.clone()).sort_leaves(false).lazy(true).build(leaves.clone()).unwrap();
+        assert_eq!(explain_root_mismatch(&tree, &lazy), None);
+        assert_same_root(&tree, &lazy);
+
+        let mut perturbed = leaves.clone();
+        perturbed[5][0] ^= 1;
+        let other = tree::MerkleTree::new_ordered(perturbed.clone(), hasher.clone());
+        let report = explain_root_mismatch(&tree, &other).unwrap();
+        assert_eq!((report.level, report.index, report.leaves.clone()), (0, 5, 5..6));
+        assert_eq!(report.left.as_deref(), Some(&leaves[5][..]));
+        assert_eq!(report.right.as_deref(), Some(&perturbed[5][..]));
+
+        let panic = std::panic::catch_unwind(|| assert_same_root(&tree, &other)).unwrap_err();
+        let message = panic.downcast_ref::<String>().unwrap();
+        assert!(message.contains("level 0, index 5 (leaves 5..6)"), "{}", message);
+        assert!(message.contains(&hex::encode(&perturbed[5])), "{}", message);
+
+        // The same leaves under another hasher first differ one level up
+        let blake = tree::MerkleTree::new_ordered(leaves.clone(), hasher::Blake2bHasher::new(32));
+        let report = explain_root_mismatch(&tree, &blake).unwrap();
+        assert_eq!((report.level, report.index, report.leaves), (1, 0, 0..2));
+
+        // A tree with an extra leaf differs where the other has only padding
+        let longer = tree::MerkleTree::new_ordered(utils::random_leaves(3, 12, 32), hasher);
+        assert_eq!(explain_root_mismatch(&tree, &longer).unwrap().index, 11);
+    }
+
+    #[test]
+    fn test_dot_export() {
+        let hasher = Sha256Hasher::new();
+        let leaves: Vec<Vec<u8>> = ["a", "b", "c", "d"].iter().map(|s| hasher.hash_leaf(s.as_bytes())).collect();
+        let tree = tree::MerkleTree::new_ordered(leaves, hasher.clone());
+
+        let options = render::DotOptions { highlight: Some(2), ..Default::default() };
+        let expected = "\
+digraph merkle_tree {
+  node [shape=box, fontname=\"monospace\"];
+  n2_0 [label=\"14ede5e8\", color=red];
+  n1_0 [label=\"e5a01fee\", color=blue];
+  n1_1 [label=\"bffe0b34\", color=red];
+  n0_0 [label=\"ca978112\"];
+  n0_1 [label=\"3e23e816\"];
+  n0_2 [label=\"2e7d2c03\", color=red];
+  n0_3 [label=\"18ac3e73\", color=blue];
+  { rank=same; n0_0; n0_1; n0_2; n0_3; }
+  n2_0 -> n1_0;
+  n2_0 -> n1_1 [color=red, penwidth=2];
+  n1_0 -> n0_0;
+  n1_0 -> n0_1;
+  n1_1 -> n0_2 [color=red, penwidth=2];
+  n1_1 -> n0_3;
+}
+";
+        assert_eq!(tree.to_dot(&options).unwrap(), expected);
+
+        // Without a highlight nothing is colored; full hashes on request
+        let plain = tree.to_dot(&render::DotOptions { hash_chars: 0, ..Default::default() }).unwrap();
+        assert!(!plain.contains("color="));
+        assert!(plain.contains(&hex::encode(tree.root())));
+
+        // Padding leaves are dashed and have no proof path to highlight
+        let padded = tree::MerkleTree::new_ordered(
+            ["a", "b", "c"].iter().map(|s| hasher.hash_leaf(s.as_bytes())).collect(),
+            hasher.clone(),
+        );
+        assert!(padded.to_dot(&Default::default()).unwrap().contains("n0_3 [label=\"2e7d2c03\", style=dashed];"));
+        assert!(matches!(
+            padded.to_dot(&render::DotOptions { highlight: Some(3), ..Default::default() }),
+            Err(error::MerkleTreeError::PaddingLeaf { index: 3 })
+        ));
+
+        let large = utils::random_tree(1, 600, 32, hasher).unwrap();
+        assert!(matches!(
+            large.to_dot(&Default::default()),
+            Err(error::MerkleTreeError::TooManyNodes { nodes: 2047, max: 1023 })
+        ));
+    }
+
+    #[test]
+    fn test_ascii_rendering() {
+        let hasher = Sha256Hasher::new();
+        let tree_of = |values: &str| {
+            let leaves = values.bytes().map(|value| hasher.hash_leaf(&[value])).collect();
+            tree::MerkleTree::new_ordered(leaves, hasher.clone())
+        };
+        let options = render::AsciiOptions { hash_chars: 4, ..Default::default() };
+
+        let four = [
+            "            14ed",
+            "       ┌──────┴──────┐",
+            "     e5a0          bffe",
+            "   ┌───┴──┐      ┌───┴──┐",
+            " ca97   3e23   2e7d   18ac",
+        ];
+        assert_eq!(tree_of("abcd").render_ascii(&options), four.join("\n") + "\n");
+
+        // The three padding copies of the last leaf are in parentheses
+        // The three padding copies of the last leaf are in parentheses, and
+        // the cells are wide enough for them
+        let five = [
+            "                                  dd14",
+            "                  ┌─────────────────┴─────────────────┐",
+            "                14ed                                de91",
+            "         ┌────────┴────────┐                 ┌────────┴────────┐",
+            "       e5a0              bffe              75de              75de",
+            "    ┌────┴───┐        ┌────┴───┐        ┌────┴───┐        ┌────┴───┐",
+            "  ca97     3e23     2e7d     18ac     3f79    (3f79)   (3f79)   (3f79)",
+        ];
+        assert_eq!(tree_of("abcde").render_ascii(&options), five.join("\n") + "\n");
+
+        // Leaves longer than the root are drawn whole, in cells of 200 + 3 columns
+        let long = tree::MerkleTree::new_ordered(vec![vec![0xaa; 100], vec![0xbb; 100]], hasher.clone());
+        let rendered = long.render_ascii(&render::AsciiOptions { hash_chars: 0, ..Default::default() });
+        let leaves = rendered.lines().last().unwrap();
+        assert_eq!(leaves.split_whitespace().collect::<Vec<_>>(), ["aa".repeat(100), "bb".repeat(100)]);
+        assert!(rendered.starts_with(&format!("{}{}\n", " ".repeat(203 - 32), hex::encode(long.root()))), "{}", rendered);
+
+        // Levels wider than the limit are summarized; Display uses the defaults
+        let wide = tree_of("abcdefghijklmnopq");
+        let rendered = wide.render_ascii(&render::AsciiOptions { hash_chars: 4, max_width: 4 });
+        assert_eq!(rendered.lines().count(), 2 * 3 - 1 + 1);
+        assert!(rendered.ends_with("... 3 more levels down to 17 leaves (32 with padding)\n"), "{}", rendered);
+        assert_eq!(format!("{}", wide), wide.render_ascii(&Default::default()));
+    }
+
+    #[cfg(feature = "signing")]
+    #[test]
+    fn test_signed_roots() {
+        use signing::{sign_root, sign_root_at, SigningKey};
+        // The first RFC 8032 test key
+        let seed: [u8; 32] = hex::decode("9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60").unwrap().try_into().unwrap();
+        let key = SigningKey::from_bytes(&seed);
+        let public = key.verifying_key();
+        assert_eq!(hex::encode(public.as_bytes()), "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a");
+
+        let tree = utils::create_tree_from_strings(vec!["a", "b", "c"]);
+        let signed = sign_root_at(&tree, &key, 1_700_000_000);
+        assert!(signed.verify(&public));
+        assert!(signed.matches(&tree));
+        assert_eq!((signed.leaf_count, signed.signature.len()), (3, 64));
+        // Ed25519 is deterministic, so the same head signs the same way
+        assert_eq!(sign_root_at(&tree, &key, 1_700_000_000), signed);
+        assert!(sign_root(&tree, &key).verify(&public));
+
+        let mut tampered = signed.clone();
+        tampered.leaf_count = 4;
+        assert!(!tampered.verify(&public));
+        let mut tampered = signed.clone();
+        tampered.timestamp += 1;
+        assert!(!tampered.verify(&public));
+        assert!(!signed.verify(&SigningKey::from_bytes(&[7; 32]).verifying_key()));
+        let mut truncated = signed.clone();
+        truncated.signature.pop();
+        assert!(!truncated.verify(&public));
+
+        // A bundle carries the proof, the root and the signed head
+        let bundle = bundle::ProofBundle::from_tree(&tree, 1).unwrap().with_signed_root(signed.clone());
+        assert!(bundle.verify() && bundle.verify_signed(&public));
+        assert!(!bundle::ProofBundle::from_tree(&tree, 1).unwrap().verify_signed(&public));
+        let other = utils::create_tree_from_strings(vec!["x", "y"]);
+        let mismatched = bundle::ProofBundle::from_tree(&other, 0).unwrap().with_signed_root(signed);
+        assert!(!mismatched.verify() && !mismatched.verify_signed(&public));
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[test]
+    fn test_msgpack_encodings() {
+        use msgpack::MsgpackError;
+        let tree = utils::create_tree_from_strings(vec!["a", "b", "c", "d", "e"]);
+        let proof = tree.generate_proof(2).unwrap();
+
+        // Hashes are bin strings, not hex, and the frame names the version and hasher
+        let bytes = proof.to_msgpack();
+        let mut prefix = vec![0x83, 0xa7];
+        prefix.extend_from_slice(b"version");
+        prefix.extend_from_slice(&[0x01, 0xa6]);
+        prefix.extend_from_slice(b"hasher");
+        prefix.push(0xa6);
+        prefix.extend_from_slice(b"sha256");
+        prefix.push(0xa5);
+        prefix.extend_from_slice(b"proof");
+        prefix.extend_from_slice(&[0x82, 0xa4]);
+        prefix.extend_from_slice(b"leaf");
+        prefix.extend_from_slice(&[0xc4, 0x20]);
+        prefix.extend_from_slice(&proof.leaf);
+        assert!(bytes.starts_with(&prefix));
+
+        let decoded = proof::MerkleProof::from_msgpack(&bytes, Sha256Hasher::new()).unwrap();
+        assert_eq!((&decoded.leaf, &decoded.proof_items), (&proof.leaf, &proof.proof_items));
+        assert!(decoded.verify(tree.root()));
+        #[cfg(feature = "json")]
+        {
+            // The binary and JSON paths carry the same proof
+            let from_json = proof::MerkleProof::from_json(&proof.to_json(), Sha256Hasher::new()).unwrap();
+            assert_eq!((decoded.leaf, decoded.proof_items), (from_json.leaf, from_json.proof_items));
+        }
+
+        let blake = hasher::Blake2bHasher::new(32);
+        assert_eq!(
+            proof::MerkleProof::from_msgpack(&bytes, blake.clone()).err(),
+            Some(MsgpackError::HasherMismatch { expected: blake.name(), found: "sha256".into() })
+        );
+        let mut future = bytes.clone();
+        future[9] = 2;
+        assert_eq!(proof::MerkleProof::from_msgpack(&future, Sha256Hasher::new()).err(), Some(MsgpackError::UnsupportedVersion(2)));
+        assert_eq!(proof::MerkleProof::from_msgpack(&bytes[..bytes.len() - 1], Sha256Hasher::new()).err(), Some(MsgpackError::Truncated));
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert_eq!(proof::MerkleProof::from_msgpack(&trailing, Sha256Hasher::new()).err(), Some(MsgpackError::TrailingBytes));
+        // A leaf claiming 4 GiB fails once the data runs out, and 0xc1 is never a value
+        let mut huge = prefix[..prefix.len() - 34].to_vec();
+        huge.extend_from_slice(&[0xc6, 0xff, 0xff, 0xff, 0xff, 0x00]);
+        assert_eq!(proof::MerkleProof::from_msgpack(&huge, Sha256Hasher::new()).err(), Some(MsgpackError::Truncated));
+        let mut reserved = prefix[..prefix.len() - 34].to_vec();
+        reserved.push(0xc1);
+        assert_eq!(proof::MerkleProof::from_msgpack(&reserved, Sha256Hasher::new()).err(), Some(MsgpackError::InvalidMarker(0xc1)));
+
+        // A frame assembled from the MessagePack spec: minimal encodings are
+        // written exactly, and the wider ones other encoders pick are read too
+        let frame = |uint: &[u8], str_marker: &dyn Fn(usize) -> Vec<u8>, bin_marker: &[u8]| {
+            let key = |out: &mut Vec<u8>, text: &str| {
+                out.extend(str_marker(text.len()));
+                out.extend_from_slice(text.as_bytes());
+            };
+            let mut out = vec![0x83];
+            key(&mut out, "version");
+            out.extend_from_slice(uint);
+            key(&mut out, "hasher");
+            key(&mut out, "sha256");
+            key(&mut out, "proof");
+            out.push(0x82);
+            key(&mut out, "leaf");
+            out.extend_from_slice(bin_marker);
+            out.extend_from_slice(&[0x11; 32]);
+            key(&mut out, "proof");
+            out.extend_from_slice(&[0x91, 0x82]);
+            key(&mut out, "hash");
+            out.extend_from_slice(bin_marker);
+            out.extend_from_slice(&[0x22; 32]);
+            key(&mut out, "direction");
+            key(&mut out, "left");
+            out
+        };
+        let minimal = frame(&[0x01], &|len| vec![0xa0 | len as u8], &[0xc4, 0x20]);
+        let wide = frame(&[0xcd, 0x00, 0x01], &|len| vec![0xd9, len as u8], &[0xc5, 0x00, 0x20]);
+        let item = proof::ProofItem { hash: proof::ProofHash::from_slice(&[0x22; 32]), is_left: true };
+        let expected = proof::MerkleProof::new(vec![0x11; 32], vec![item], Sha256Hasher::new());
+        assert_eq!(expected.to_msgpack(), minimal);
+        for bytes in [&minimal, &wide] {
+            let decoded = proof::MerkleProof::from_msgpack(bytes, Sha256Hasher::new()).unwrap();
+            assert_eq!((&decoded.leaf, &decoded.proof_items), (&expected.leaf, &expected.proof_items));
+        }
+
+        let bundle = bundle::ProofBundle::from_tree(&tree, 4).unwrap();
+        let decoded = bundle::ProofBundle::from_msgpack(&bundle.to_msgpack(), Sha256Hasher::new()).unwrap();
+        assert_eq!((&decoded.root, &decoded.proof.proof_items), (&bundle.root, &bundle.proof.proof_items));
+        assert!(decoded.verify());
+        #[cfg(feature = "signing")]
+        {
+            let key = signing::SigningKey::from_bytes(&[3; 32]);
+            let signed = bundle.with_signed_root(signing::sign_root_at(&tree, &key, 1_700_000_000));
+            let decoded = bundle::ProofBundle::from_msgpack(&signed.to_msgpack(), Sha256Hasher::new()).unwrap();
+            assert_eq!(decoded.signed_root, signed.signed_root);
+            assert!(decoded.verify_signed(&key.verifying_key()));
+        }
+
+        let bytes = tree.to_msgpack().unwrap();
+        let loaded = tree::MerkleTree::from_msgpack(&bytes, Sha256Hasher::new()).unwrap();
+        assert_eq!((loaded.root(), loaded.unpadded_leaf_count()), (tree.root(), 5));
+        // Changing the stored root is caught when the tree is rebuilt
+        let mut tampered = bytes.clone();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 1;
+        assert!(matches!(
+            tree::MerkleTree::from_msgpack(&tampered, Sha256Hasher::new()),
+            Err(MsgpackError::Tree(persist::FormatError::RootMismatch))
+        ));
+    }
+
+    #[cfg(feature = "salted")]
+    #[test]
+    fn test_salted_trees() {
+        use salted::SaltedTreeBuilder;
+        let members = ["alice@example.com", "bob@example.com", "carol@example.com"];
+        let builder = SaltedTreeBuilder::new(b"master secret", Sha256Hasher::new());
+        let tree = builder.build(&members).unwrap();
+
+        for (member, index) in members.iter().zip(0..) {
+            let proof = tree.generate_proof(index).unwrap();
+            assert_eq!(proof.salt, builder.salt_for_index(index));
+            assert!(proof.verify(member.as_bytes(), tree.root()));
+            // Without the right salt the data doesn't match the leaf
+            let mut wrong = tree.generate_proof(index).unwrap();
+            wrong.salt[0] ^= 1;
+            assert!(!wrong.verify(member.as_bytes(), tree.root()));
+            assert!(!proof.verify(b"mallory@example.com", tree.root()));
+            // An outsider hashing the plain data finds nothing
+            assert!(!tree.tree().contains(&Sha256Hasher::new().hash_leaf(member.as_bytes())));
+        }
+
+        // Another master secret gives unrelated salts, leaves and root
+        let other = SaltedTreeBuilder::new(b"another secret", Sha256Hasher::new()).build(&members).unwrap();
+        assert_ne!(other.root(), tree.root());
+        for index in 0..members.len() as u64 {
+            assert_ne!(other.salt(index), tree.salt(index));
+            assert!(!tree.tree().contains(other.tree().get_leaf(index).unwrap()));
+        }
+
+        // Keyed salts follow the entry, not its position
+        let keyed = builder.build_keyed(&[("bob", "bob@example.com"), ("alice", "alice@example.com")]).unwrap();
+        let reordered = builder.build_keyed(&[("alice", "alice@example.com"), ("bob", "bob@example.com")]).unwrap();
+        assert_eq!(keyed.salt(0), reordered.salt(1));
+        assert_eq!(keyed.salt(0), Some(&builder.salt_for_key(b"bob")));
+        assert!(keyed.generate_proof(1).unwrap().verify(b"alice@example.com", keyed.root()));
+
+        // The master secret is in neither the published tree nor the proofs
+        let published = tree.tree().to_bytes().unwrap();
+        let proof = tree.generate_proof(0).unwrap().proof.to_bytes();
+        for bytes in [&published, &proof] {
+            assert!(!bytes.windows(13).any(|window| window == b"master secret"));
+        }
+        assert!(matches!(builder.build::<&str>(&[]), Err(error::MerkleTreeError::EmptyLeaves)));
+    }
+
+    #[cfg(feature = "zeroize")]
+    #[test]
+    fn test_zeroize_leaves() {
+        use zeroize::Zeroize;
+        use error::MerkleTreeError;
+        let mut tree = tree::MerkleTree::builder(Sha256Hasher::new())
+            .index_leaves(true)
+            .build_from_data(&["a", "b", "c"])
+            .unwrap();
+        let root = tree.root().to_vec();
+        let proof = tree.generate_proof(1).unwrap();
+        let leaf = Sha256Hasher::new().hash_leaf(b"b");
+
+        tree.zeroize_leaves();
+        assert!(tree.is_zeroized());
+        // The root still serves proofs made beforehand
+        assert_eq!((tree.root(), tree.leaf_count()), (&root[..], 4));
+        assert!(tree.verify_proof_root_only(&proof));
+        // Nothing that needs the leaves hands out stale data
+        assert!(matches!(tree.generate_proof(1), Err(MerkleTreeError::LeavesZeroized)));
+        assert!(matches!(tree.generate_proof_for_data(1, b"b"), Err(MerkleTreeError::LeavesZeroized)));
+        assert!(tree.get_leaf(0).is_none() && tree.get_node(1, 0).is_none());
+        assert!(!tree.contains(&leaf) && !tree.verify_proof(&proof));
+        assert_eq!(tree.to_bytes().err(), Some(persist::FormatError::LeavesZeroized));
+
+        // Lazy trees drop their cache too
+        let mut lazy = tree::MerkleTree::builder(Sha256Hasher::new()).lazy(true).build_from_data(&["a", "b", "c"]).unwrap();
+        assert!(lazy.generate_proof(0).is_ok());
+        lazy.zeroize_leaves();
+        assert!(lazy.get_node(1, 1).is_none());
+        assert_eq!(lazy.root(), &root[..]);
+
+        let mut typed = typed::TypedMerkleTree::new_ordered(vec![b"a".to_vec(), b"b".to_vec()], Sha256Hasher::new()).unwrap();
+        typed.zeroize();
+        assert!(typed.values().is_empty() && typed.tree().is_zeroized());
+        assert!(typed.prove(0).is_err());
+
+        #[cfg(feature = "salted")]
+        {
+            let mut builder = salted::SaltedTreeBuilder::new(b"master secret", Sha256Hasher::new());
+            let salted_root = builder.build(&["a"]).unwrap().root().to_vec();
+            builder.zeroize();
+            assert_ne!(builder.build(&["a"]).unwrap().root(), &salted_root[..]);
+        }
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_verify_json_proof_shapes() {
+        use crate::error::ProofParseError;
+        use serde_json::json;
+        let tree = utils::create_tree_from_strings(vec!["a", "b", "c", "d", "e"]);
+        let hasher = tree.get_hasher();
+        let index = 2;
+        let proof = tree.generate_proof(index).unwrap();
+        let verify = |value: serde_json::Value| utils::verify_json_proof(tree.root(), &value, &hasher);
+
+        // The stable schema, the debug format with extra keys, `is_left`, and index-based siblings
+        let stable: serde_json::Value = serde_json::from_str(&proof.to_json()).unwrap();
+        assert_eq!(verify(stable.clone()), Ok(true));
+        let leaf = hex::encode(&proof.leaf);
+        let mut legacy: Vec<serde_json::Value> = proof.to_debug_format().into_iter().map(|item| json!(item)).collect();
+        legacy[0]["note"] = json!("ignored");
+        assert_eq!(verify(json!({ "leaf": leaf, "proof": legacy, "root": "ignored" })), Ok(true));
+        let flags: Vec<_> = proof.proof_items.iter().map(|item| json!({ "hash": hex::encode(&item.hash), "is_left": item.is_left })).collect();
+        assert_eq!(verify(json!({ "leaf": leaf, "proof": flags })), Ok(true));
+        let siblings: Vec<_> = proof.proof_items.iter().map(|item| hex::encode(&item.hash)).collect();
+        assert_eq!(verify(json!({ "leaf": leaf, "index": index, "siblings": siblings })), Ok(true));
+        assert_eq!(verify(json!({ "leaf": leaf, "index": 3, "siblings": siblings })), Ok(false));
+
+        let wrong = |field: &str, expected| Err(ProofParseError::WrongType { field: field.into(), expected });
+        for (value, expected) in [
+            (json!(42), Err(ProofParseError::UnrecognizedShape)),
+            (json!([[1, 2], [3]]), Err(ProofParseError::UnrecognizedShape)),
+            (json!({ "leaf": leaf }), Err(ProofParseError::UnrecognizedShape)),
+            (json!({ "proof": [] }), Err(ProofParseError::MissingKey("leaf"))),
+            (json!({ "leaf": leaf, "siblings": siblings }), Err(ProofParseError::MissingKey("index"))),
+            (json!({ "leaf": 7, "proof": [] }), wrong("leaf", "a hex string")),
+            (json!({ "leaf": "xyz", "proof": [] }), wrong("leaf", "a hex string")),
+            (json!({ "leaf": leaf, "proof": [[1, 2]] }), wrong("proof[0]", "an object")),
+            (json!({ "leaf": leaf, "proof": { "hash": "00" } }), wrong("proof", "an array of proof items")),
+            (json!({ "leaf": leaf, "proof": [{ "hash": 5, "direction": "left" }] }), wrong("proof[0].hash", "a hex string")),
+            (json!({ "leaf": leaf, "proof": [{ "hash": "00", "direction": true }] }), wrong("proof[0].direction", "\"left\" or \"right\"")),
+            (json!({ "leaf": leaf, "proof": [{ "hash": "00", "is_left": "yes" }] }), wrong("proof[0].is_left", "a bool")),
+            (json!({ "leaf": leaf, "proof": [{ "hash": "00", "direction": "up" }] }), Err(ProofParseError::UnknownDirection { item: 0, direction: "up".into() })),
+            (json!({ "leaf": leaf, "proof": [{ "direction": "left" }] }), Err(ProofParseError::MissingField { item: 0, field: "hash" })),
+            (json!({ "leaf": leaf, "index": -1, "siblings": siblings }), wrong("index", "a non-negative integer")),
+            (json!({ "leaf": leaf, "index": 8, "siblings": siblings }), wrong("index", "a position within the proof's depth")),
+            (json!({ "leaf": leaf, "index": 0, "siblings": [1] }), wrong("siblings[0]", "a hex string")),
+            (json!({ "leaf": leaf, "index": 0, "siblings": ["zz"] }), Err(ProofParseError::InvalidHex { item: 0, field: "hash" })),
+        ] {
+            assert_eq!(verify(value.clone()), expected, "{}", value);
+        }
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_async_tree_builder() {
+        let hasher = Sha256Hasher::new();
+        let leaves: Vec<Vec<u8>> = (0..2500).map(|i| hasher.hash_leaf(format!("row {}", i).as_bytes())).collect();
+
+        let mut builder = async_utils::AsyncTreeBuilder::new(hasher.clone());
+        assert_eq!(builder.current_root(), None);
+        builder.feed_stream(futures::stream::iter(leaves[..1000].to_vec())).await;
+        // The running root is the root of the leaves so far
+        assert_eq!(builder.current_root().unwrap(), tree::MerkleTree::new_ordered(leaves[..1000].to_vec(), hasher.clone()).root());
+        for leaf in &leaves[1000..] {
+            builder.feed(leaf.clone()).await;
+        }
+        assert_eq!(builder.leaf_count(), 2500);
+
+        let batch = tree::MerkleTree::new_ordered(leaves.clone(), hasher.clone());
+        assert_eq!(builder.current_root().unwrap(), batch.root());
+        let tree = builder.finish().await.unwrap();
+        assert_eq!(tree.root(), batch.root());
+        assert!(tree.generate_proof(1234).unwrap().verify(batch.root()));
+
+        assert!(matches!(
+            async_utils::AsyncTreeBuilder::new(hasher.clone()).finish().await,
+            Err(error::MerkleTreeError::EmptyLeaves)
+        ));
+        let mut raw = async_utils::AsyncTreeBuilder::new(hasher);
+        raw.feed(b"not a hash".to_vec()).await;
+        assert!(matches!(raw.finish().await, Err(error::MerkleTreeError::LeafSizeMismatch { index: 0, .. })));
+    }
+
+    #[test]
+    fn test_index_math_at_u32_boundaries() {
+        use index::*;
+        let max = u64::from(u32::MAX);
+        assert_eq!(padded_leaf_count(max - 1), Some(max + 1));
+        assert_eq!(padded_leaf_count(max), Some(max + 1));
+        assert_eq!(padded_leaf_count(max + 1), Some(max + 1));
+        assert_eq!(padded_leaf_count(max + 2), Some(2 * (max + 1)));
+        assert_eq!((depth(max), depth(max + 2)), (Some(32), Some(33)));
+        assert_eq!((padded_leaf_count(0), depth(0)), (None, None));
+        assert_eq!((depth(1), depth(2), depth(3)), (Some(0), Some(1), Some(2)));
+
+        // The deepest supported tree, and one leaf past it
+        #[cfg(target_pointer_width = "64")]
+        {
+            assert_eq!(max_leaf_count(), 1 << MAX_DEPTH);
+            assert_eq!(depth(1 << 63), Some(MAX_DEPTH));
+        }
+        assert_eq!(padded_leaf_count(max_leaf_count() + 1), None);
+        assert_eq!(padded_leaf_count(u64::MAX), None);
+
+        // Generalized indices at and around u32::MAX
+        for index in [0, max - 1, max, max + 1] {
+            assert_eq!(from_gindex(40, (1 << 40) + index), Some((0, index)));
+        }
+        assert_eq!(from_gindex(33, (1 << 32) + max), Some((1, max)));
+        assert_eq!(from_gindex(40, 1), Some((40, 0)));
+        assert_eq!(from_gindex(MAX_DEPTH, u64::MAX), Some((0, (1 << 63) - 1)));
+        assert_eq!((from_gindex(MAX_DEPTH + 1, 1), from_gindex(4, 0), from_gindex(4, 1 << 5)), (None, None, None));
+
+        // A proof's directions are its leaf's generalized index
+        let item = |is_left| proof::ProofItem { hash: Default::default(), is_left };
+        let deep = proof::MerkleProof::new(Vec::new(), vec![item(true), item(false), item(true)], Sha256Hasher::new());
+        assert_eq!(deep.path_index(), Some(0b101));
+        let too_deep = proof::MerkleProof::new(Vec::new(), vec![item(false); MAX_DEPTH as usize + 1], Sha256Hasher::new());
+        assert_eq!(too_deep.path_index(), None);
+
+        assert_eq!(leaf_range(1, max), Some(2 * max..2 * max + 2));
+        assert_eq!(leaf_range(32, max - 1), Some((max - 1) << 32..max << 32));
+        // The last node's range would end at 2^64
+        assert_eq!((leaf_range(32, max), leaf_range(64, 0)), (None, None));
+
+        // A publisher's leaf count is untrusted input, so extreme ones are rejected, not overflowed
+        let tree = utils::create_tree_from_strings(vec!["a", "b", "c"]);
+        let proof = tree.generate_proof(0).unwrap();
+        let bound = tree.bound_root();
+        for leaf_count in [0, u64::MAX, u64::MAX / 2 + 2] {
+            assert!(!proof.verify_bound(&bound, leaf_count, &tree.params()));
+        }
+        assert!(proof.verify_bound(&bound, 3, &tree.params()));
+    }
+
+    #[test]
+    fn test_prefix_range_proofs() {
+        let words = ["cherry", "apple", "band", "apricot", "bandana", "banana"];
+        let tree = MerkleTree::new(words.iter().map(|word| word.as_bytes().to_vec()).collect(), Sha256Hasher::new());
+        let root = tree.root().to_vec();
+
+        // Six leaves pad to eight with copies of "cherry"
+        for (prefix, range, found) in [
+            ("ap", 0..2, vec!["apple", "apricot"]),
+            ("ban", 2..5, vec!["banana", "band", "bandana"]),
+            ("band", 3..5, vec!["band", "bandana"]),
+            ("banana", 2..3, vec!["banana"]),
+            ("c", 5..6, vec!["cherry"]),
+            ("", 0..6, words.iter().copied().collect::<std::collections::BTreeSet<_>>().into_iter().collect()),
+            ("aa", 0..0, vec![]),
+            ("bo", 5..5, vec![]),
+            ("zz", 6..6, vec![]),
+        ] {
+            let (found_range, leaves) = tree.leaves_with_prefix(prefix.as_bytes());
+            assert_eq!(found_range, range, "{:?}", prefix);
+            assert_eq!(leaves.iter().map(|leaf| std::str::from_utf8(leaf).unwrap()).collect::<Vec<_>>(), found);
+
+            let proof = tree.prove_prefix_range(prefix.as_bytes()).unwrap();
+            assert_eq!(proof.range, range);
+            assert!(proof.leaves().eq(leaves.iter().map(Vec::as_slice)));
+            assert!(proof.verify(&root), "{:?}", prefix);
+        }
+
+        // Dropping a match or a neighbour, or moving the range, is caught
+        let mut proof = tree.prove_prefix_range(b"ban").unwrap();
+        proof.matches.pop();
+        proof.range.end -= 1;
+        assert!(!proof.verify(&root));
+        let mut proof = tree.prove_prefix_range(b"ban").unwrap();
+        proof.right = None;
+        assert!(!proof.verify(&root));
+        let mut proof = tree.prove_prefix_range(b"bo").unwrap();
+        proof.left = None;
+        assert!(!proof.verify(&root));
+        let mut proof = tree.prove_prefix_range(b"c").unwrap();
+        proof.prefix = b"b".to_vec();
+        assert!(!proof.verify(&root));
+
+        // Trees in a given order can't prove completeness
+        let ordered = MerkleTree::new_ordered(words.iter().map(|word| word.as_bytes().to_vec()).collect(), Sha256Hasher::new());
+        assert!(matches!(ordered.prove_prefix_range(b"ap"), Err(MerkleTreeError::LeavesNotSorted)));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_node_dump_round_trip_and_corruption() {
+        use dump::{DumpError, DumpFormat};
+        let tree = utils::create_tree_from_strings(vec!["a", "b", "c", "d", "e"]);
+        let hasher = Sha256Hasher::new();
+
+        let mut text = Vec::new();
+        tree.export_nodes(&mut text, DumpFormat::Text).unwrap();
+        let text = String::from_utf8(text).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        // 8 padded leaves, then 4, 2 and the root
+        assert_eq!(lines.len(), 15);
+        assert_eq!(lines[0], format!("0,0,{}", hex::encode(tree.get_leaf(0).unwrap())));
+        assert_eq!(lines[14], format!("3,0,{}", hex::encode(tree.root())));
+        assert_eq!(dump::verify_node_dump(text.as_bytes(), &hasher).unwrap(), tree.root());
+
+        let mut binary = Vec::new();
+        tree.export_nodes(&mut binary, DumpFormat::Binary).unwrap();
+        assert_eq!(&binary[..5], b"MRKD\x01");
+        assert_eq!(dump::verify_node_dump(binary.as_slice(), &hasher).unwrap(), tree.root());
+
+        // A corrupted interior line is reported at its own position, a leaf at its parent
+        let corrupt = |line: usize| {
+            let mut lines = lines.clone();
+            let (position, hash) = lines[line].rsplit_once(',').unwrap();
+            let flipped = if hash.starts_with('0') { "1" } else { "0" };
+            let replaced = format!("{},{}{}", position, flipped, &hash[1..]);
+            lines[line] = &replaced;
+            dump::verify_node_dump(lines.join("\n").as_bytes(), &hasher)
+        };
+        assert!(matches!(corrupt(9), Err(DumpError::Mismatch { level: 1, index: 1 })));
+        assert!(matches!(corrupt(13), Err(DumpError::Mismatch { level: 2, index: 1 })));
+        assert!(matches!(corrupt(14), Err(DumpError::Mismatch { level: 3, index: 0 })));
+        assert!(matches!(corrupt(6), Err(DumpError::Mismatch { level: 1, index: 3 })));
+
+        // Binary corruption is caught the same way
+        let record = 5 + 12 * (16 + 32);
+        binary[record + 16] ^= 1;
+        assert!(matches!(dump::verify_node_dump(binary.as_slice(), &hasher), Err(DumpError::Mismatch { level: 2, index: 0 })));
+        binary[record + 16] ^= 1;
+
+        // Missing, misplaced and malformed records
+        let truncated = lines[..13].join("\n");
+        assert!(matches!(dump::verify_node_dump(truncated.as_bytes(), &hasher), Err(DumpError::Incomplete { level: 2 })));
+        let skipped = [&lines[..3], &lines[4..]].concat().join("\n");
+        assert!(matches!(dump::verify_node_dump(skipped.as_bytes(), &hasher), Err(DumpError::OutOfOrder { level: 0, index: 4 })));
+        let malformed = text.replacen("0,1,", "0,1,zz", 1);
+        assert!(matches!(dump::verify_node_dump(malformed.as_bytes(), &hasher), Err(DumpError::Malformed { record: 2 })));
+        assert!(matches!(dump::verify_node_dump(&binary[..binary.len() - 1], &hasher), Err(DumpError::Malformed { record: 15 })));
+    }
+
+    #[test]
+    fn test_borrowed_leaves_are_not_copied() {
+        use std::borrow::Cow;
+        use std::sync::Arc;
+        let hasher = Sha256Hasher::new();
+        // One arena holding every leaf hash back to back
+        let arena: Vec<u8> = ["e", "b", "d", "a", "c"].iter().flat_map(|item| hasher.hash_leaf(item.as_bytes())).collect();
+        let leaves: Vec<&[u8]> = arena.chunks(32).collect();
+        let copies: Vec<Vec<u8>> = leaves.iter().map(|leaf| leaf.to_vec()).collect();
+        let owned = || copies.clone();
+
+        let tree = MerkleTree::from_borrowed_leaves(leaves.clone(), hasher.clone()).unwrap();
+        assert_eq!(tree.root(), MerkleTree::new(owned(), hasher.clone()).root());
+        assert_eq!(tree.unpadded_leaf_count(), 5);
+
+        // Every leaf, padding included, points into the arena
+        let span = arena.as_ptr_range();
+        for index in 0..tree.leaf_count() {
+            assert!(span.contains(&tree.get_leaf(index).unwrap().as_ptr()));
+        }
+
+        // Lookups and proofs work as for owned leaves, and proofs outlive the leaves
+        let index = tree.find_leaf_index(leaves[2]).unwrap();
+        let proof = tree.generate_proof(index).unwrap();
+        assert!(tree.verify_proof(&proof));
+        assert!(matches!(tree.generate_proof(6), Err(MerkleTreeError::PaddingLeaf { index: 6 })));
+        let root = tree.root().to_vec();
+        drop(tree);
+        drop(leaves);
+        drop(arena);
+        assert!(proof.verify(&root));
+
+        // Cow and Arc leaves build the same trees, sorted or in order
+        let cows: Vec<Cow<[u8]>> = owned().into_iter().map(Cow::Owned).collect();
+        assert_eq!(MerkleTree::from_borrowed_leaves(cows, hasher.clone()).unwrap().root(), root);
+        let arcs: Vec<Arc<[u8]>> = owned().into_iter().map(Arc::from).collect();
+        let ordered = MerkleTree::from_borrowed_leaves_ordered(arcs, hasher.clone()).unwrap();
+        assert_eq!(ordered.root(), MerkleTree::new_ordered(owned(), hasher.clone()).root());
+        assert!(ordered.verify_proof(&ordered.generate_proof(4).unwrap()));
+        assert!(matches!(MerkleTree::from_borrowed_leaves(Vec::<&[u8]>::new(), hasher), Err(MerkleTreeError::EmptyLeaves)));
+    }
+
+    #[cfg(feature = "rs_merkle")]
+    #[test]
+    fn test_rs_merkle_proof_conversions() {
+        use ::rs_merkle::algorithms::Sha256 as RsSha256;
+        use ::rs_merkle::Hasher as _;
+        use compat::rs_merkle::{self as compat_rs, CompatError};
+        let hasher = Sha256Hasher::new();
+        let leaves: Vec<Vec<u8>> = (0..8u8).map(|i| hasher.hash_leaf(&[i])).collect();
+        let tree = MerkleTree::new_ordered(leaves.clone(), hasher.clone());
+        let hash = |leaf: &[u8]| -> [u8; 32] { leaf.try_into().unwrap() };
+        let hashes: Vec<[u8; 32]> = leaves.iter().map(|leaf| hash(leaf)).collect();
+        let reference = ::rs_merkle::MerkleTree::<RsSha256>::from_leaves(&hashes);
+        assert_eq!(reference.root().unwrap(), tree.root());
+        assert_eq!(RsSha256::concat_and_hash(&hashes[0], Some(&hashes[1])), hash(&tree.get_node(1, 0).unwrap()));
+
+        // Single proofs survive rs_merkle's bytes, verify with its verifier and come back identical
+        for (index, leaf) in hashes.iter().enumerate() {
+            let proof = tree.generate_proof(index as u64).unwrap();
+            let converted: ::rs_merkle::MerkleProof<RsSha256> = compat_rs::to_rs_merkle_proof(&proof).unwrap();
+            let converted = ::rs_merkle::MerkleProof::<RsSha256>::from_bytes(&converted.to_bytes()).unwrap();
+            assert_eq!(converted.proof_hashes(), reference.proof(&[index]).proof_hashes());
+            assert_eq!(compat_rs::leaf_position(&proof).unwrap(), (index, 8));
+            assert!(converted.verify(hash(tree.root()), &[index], &[*leaf], 8));
+            assert!(!converted.verify(hash(tree.root()), &[index ^ 1], &[*leaf], 8));
+            let back = compat_rs::from_rs_merkle_proof(&converted, &[index], &[*leaf], 8, hasher.clone()).unwrap();
+            assert_eq!(back[0].proof_items, proof.proof_items);
+        }
+
+        // rs_merkle's proof of leaves 1, 2 and 6: siblings 0, 3 and 7, then node (1, 2);
+        // it wants the indices sorted, the conversion takes them in any order
+        let multi = reference.proof(&[1, 2, 6]);
+        let (indices, proven) = ([6, 1, 2], [hashes[6], hashes[1], hashes[2]]);
+        let node = |level, index| hash(&tree.get_node(level, index).unwrap());
+        assert_eq!(multi.proof_hashes(), [node(0, 0), node(0, 3), node(0, 7), node(1, 2)]);
+        let proofs = compat_rs::from_rs_merkle_proof(&multi, &indices, &proven, 8, hasher.clone()).unwrap();
+        for (proof, index) in proofs.iter().zip(indices) {
+            assert_eq!(proof.proof_items, tree.generate_proof(index as u64).unwrap().proof_items);
+        }
+
+        // With five leaves this tree pads and rs_merkle carries the odd node
+        // up, so the roots differ; over the padded leaves they agree
+        let odd = MerkleTree::new_ordered(leaves[..5].to_vec(), hasher.clone());
+        let padded = compat_rs::rs_merkle_leaves(&odd).unwrap();
+        assert_eq!((padded.len(), padded[7]), (8, hashes[4]));
+        let padded_reference = ::rs_merkle::MerkleTree::<RsSha256>::from_leaves(&padded);
+        assert_eq!(padded_reference.root().unwrap(), odd.root());
+        let five = ::rs_merkle::MerkleTree::<RsSha256>::from_leaves(&hashes[..5]);
+        assert_ne!(five.root().unwrap(), odd.root());
+        for (index, leaf) in hashes[..5].iter().enumerate() {
+            let proof = odd.generate_proof(index as u64).unwrap();
+            let (position, total) = compat_rs::leaf_position(&proof).unwrap();
+            let converted: ::rs_merkle::MerkleProof<RsSha256> = compat_rs::to_rs_merkle_proof(&proof).unwrap();
+            assert!(converted.verify(hash(odd.root()), &[position], &[*leaf], total));
+
+            // rs_merkle's own five-leaf proofs convert, without the layers leaf 4 is carried up
+            let theirs = five.proof(&[index]);
+            let back = compat_rs::from_rs_merkle_proof(&theirs, &[index], &[*leaf], 5, hasher.clone()).unwrap();
+            assert!(back[0].verify(&five.root().unwrap()), "leaf {}", index);
+            assert_eq!(back[0].proof_items.len(), if index == 4 { 1 } else { 3 });
+        }
+        let all = five.proof(&[0, 2, 4]);
+        let back = compat_rs::from_rs_merkle_proof(&all, &[0, 2, 4], &[hashes[0], hashes[2], hashes[4]], 5, hasher.clone()).unwrap();
+        assert!(back.iter().all(|proof| proof.verify(&five.root().unwrap())));
+
+        // What can't be represented is an error
+        let short = ::rs_merkle::MerkleProof::<RsSha256>::new(multi.proof_hashes()[..3].to_vec());
+        assert_eq!(
+            compat_rs::from_rs_merkle_proof(&short, &indices, &proven, 8, hasher.clone()).err(),
+            Some(CompatError::WrongProofLength { expected: 4, actual: 3 })
+        );
+        let convert = |indices: &[usize], proven: &[[u8; 32]]| compat_rs::from_rs_merkle_proof(&multi, indices, proven, 8, hasher.clone()).err();
+        assert_eq!(convert(&[8], &proven[..1]), Some(CompatError::IndexOutOfRange { index: 8, total: 8 }));
+        assert_eq!(convert(&[1, 1], &proven[..2]), Some(CompatError::DuplicateIndex(1)));
+        assert_eq!(convert(&[1], &proven[..2]), Some(CompatError::LeafCountMismatch { indices: 1, hashes: 2 }));
+        let wide = hasher::Blake2bHasher::new(64);
+        let wide_tree = MerkleTree::new_ordered((0..4u8).map(|i| wide.hash_leaf(&[i])).collect(), wide.clone());
+        assert_eq!(
+            compat_rs::to_rs_merkle_proof::<RsSha256, _>(&wide_tree.generate_proof(0).unwrap()).err(),
+            Some(CompatError::InvalidHashLength { expected: 32, actual: 64 })
+        );
+    }
+
+    #[test]
+    fn test_compute_roots_multi_matches_each_hasher() {
+        use hasher::{Blake2bHasher, DynHasher, Keccak256Hasher};
+        let (sha, blake, keccak) = (Sha256Hasher::new(), Blake2bHasher::new(64), Keccak256Hasher::new());
+        let hashers: [&dyn DynHasher; 3] = [&sha, &blake, &keccak];
+
+        // Counts around a power of two and across batches, so padding and batch ends are covered
+        for count in [1, 2, 5, 1024, 2500] {
+            let data = utils::random_leaves(7, count, 20);
+            let roots = utils::compute_roots_multi(data.iter().cloned(), &hashers).unwrap();
+            assert_eq!(roots.len(), 3);
+            // Each root against a tree built on its own, through the dyn hasher
+            for (root, hasher) in roots.iter().zip(hashers) {
+                let leaves = data.iter().map(|item| hasher.hash_leaf(item)).collect();
+                assert_eq!(root, MerkleTree::new_ordered(leaves, hasher).root(), "{}", hasher.name());
+            }
+            let leaves = data.iter().map(|item| keccak.hash_leaf(item)).collect();
+            assert_eq!(roots[2], MerkleTree::new_ordered(leaves, keccak.clone()).root());
+        }
+        assert!(matches!(utils::compute_roots_multi(std::iter::empty(), &hashers), Err(MerkleTreeError::EmptyLeaves)));
+    }
+
+    #[test]
+    fn test_update_leaf_with_delta_patches_old_proofs() {
+        use crate::proof::ProofDelta;
+
+        let leaves: Vec<Vec<u8>> = (0u8..37).map(|i| Sha256Hasher::new().hash_leaf(&[i])).collect();
+        let mut tree = MerkleTree::new_ordered(leaves.clone(), Sha256Hasher::new());
+        let old_proofs: Vec<_> = (0..37).map(|index| tree.generate_proof(index).unwrap().to_bytes()).collect();
+        let old_proof = |index: u64| MerkleProof::from_bytes(&old_proofs[index as usize], Sha256Hasher::new()).unwrap();
+
+        let new_leaf = Sha256Hasher::new().hash_leaf(b"updated");
+        let (root, delta) = tree.update_leaf_with_delta(10, new_leaf.clone()).unwrap();
+        assert_eq!(root, tree.root());
+        assert_eq!(root, MerkleTree::new_ordered({
+            let mut leaves = leaves.clone();
+            leaves[10] = new_leaf.clone();
+            leaves
+        }, Sha256Hasher::new()).root());
+        // The leaf and one node per level above it
+        assert_eq!(delta.changes.len(), 7);
+
+        for index in [0, 9, 10, 11, 20, 36] {
+            let mut proof = old_proof(index);
+            assert!(!proof.verify(&root));
+            assert!(proof.apply_delta(&delta, index).unwrap());
+            assert!(proof.verify(&root));
+            assert_eq!(proof.to_bytes(), tree.generate_proof(index).unwrap().to_bytes());
+        }
+        assert_eq!(tree.generate_proof(10).unwrap().leaf, new_leaf);
+
+        // A proof is patched for its own leaf only
+        let mut proof = old_proof(3);
+        assert!(matches!(proof.apply_delta(&delta, 4), Err(MerkleTreeError::ProofIndexMismatch { index: 4 })));
+        assert!(proof.apply_delta(&ProofDelta { depth: 5, changes: Vec::new() }, 3).is_err());
+
+        // Writing the same leaf back changes nothing, so proofs stay byte-identical
+        let current = tree.generate_proof(20).unwrap();
+        let (same_root, empty) = tree.update_leaf_with_delta(20, leaves[20].clone()).unwrap();
+        assert_eq!(same_root, root);
+        assert!(empty.changes.is_empty());
+        let mut unchanged = MerkleProof::from_bytes(&current.to_bytes(), Sha256Hasher::new()).unwrap();
+        assert!(!unchanged.apply_delta(&empty, 20).unwrap());
+        assert_eq!(unchanged.to_bytes(), current.to_bytes());
+
+        // The last leaf takes its padding copies with it
+        let before = tree.generate_proof(0).unwrap();
+        let (root, delta) = tree.update_leaf_with_delta(36, Sha256Hasher::new().hash_leaf(b"last")).unwrap();
+        assert_eq!(tree.get_node(0, 63), Some(Sha256Hasher::new().hash_leaf(b"last")));
+        let mut proof = before;
+        assert!(proof.apply_delta(&delta, 0).unwrap());
+        assert!(proof.verify(&root));
+        assert!(tree.verify_proof(&tree.generate_proof(36).unwrap()));
+
+        assert!(matches!(tree.update_leaf_with_delta(40, new_leaf), Err(MerkleTreeError::PaddingLeaf { index: 40 })));
+    }
+
+    #[test]
+    fn test_check_membership_batch() {
+        use crate::utils::{check_membership_batch, MembershipResult};
+
+        let values: Vec<String> = (0..50).map(|i| format!("value-{}", i)).collect();
+        let leaves: Vec<Vec<u8>> = values.iter().map(|value| Sha256Hasher::new().hash_leaf(value.as_bytes())).collect();
+        let queries = ["value-7", "missing", "value-49", "value-7", "value-0", "also missing", "value-49"];
+
+        let indexed = MerkleTree::builder(Sha256Hasher::new()).index_leaves(true).build(leaves.clone()).unwrap();
+        let sorted = MerkleTree::new(leaves.clone(), Sha256Hasher::new());
+        let ordered = MerkleTree::new_ordered(leaves, Sha256Hasher::new());
+        for tree in [&indexed, &sorted, &ordered] {
+            let results = check_membership_batch(tree, &queries).unwrap();
+            assert_eq!(results.len(), queries.len());
+            for (query, result) in queries.iter().zip(&results) {
+                match result {
+                    MembershipResult::Present { index, proof } => {
+                        assert!(values.iter().any(|value| value == query));
+                        assert_eq!(tree.find_leaf_index(&Sha256Hasher::new().hash_leaf(query.as_bytes())), Some(*index));
+                        assert_eq!(proof.leaf, Sha256Hasher::new().hash_leaf(query.as_bytes()));
+                        assert!(proof.verify(tree.root()));
+                    }
+                    MembershipResult::Absent => assert!(query.starts_with("missing") || query.starts_with("also")),
+                }
+            }
+            // Each duplicate query gets its own proof of the same leaf
+            let index_of = |result: &MembershipResult<Sha256Hasher>| match result {
+                MembershipResult::Present { index, .. } => Some(*index),
+                MembershipResult::Absent => None,
+            };
+            assert_eq!(index_of(&results[0]), index_of(&results[3]));
+            assert_eq!(index_of(&results[2]), index_of(&results[6]));
+            assert!(index_of(&results[0]).is_some() && index_of(&results[1]).is_none());
+        }
+        assert!(check_membership_batch(&ordered, &[] as &[&str]).unwrap().is_empty());
+
+        // A leaf that is found but can't be proven is an error, not absent
+        use crate::store::{MemoryNodeStore, NodeStore};
+        let small = utils::create_tree_from_strings(vec!["value-0", "value-1", "value-2", "value-3"]);
+        let mut store = MemoryNodeStore::new();
+        for index in 0..4 {
+            let Ok(()) = store.put(0, index, small.get_leaf(index as u64).unwrap().clone());
+        }
+        let Ok(()) = store.put(2, 0, small.root().to_vec());
+        let Ok(()) = store.put_meta(small.store().get_meta().unwrap().unwrap());
+        let partial = MerkleTree::from_store(store, Sha256Hasher::new()).unwrap();
+        assert!(matches!(check_membership_batch(&partial, &["missing"]).unwrap()[..], [MembershipResult::Absent]));
+        assert!(matches!(check_membership_batch(&partial, &queries), Err(MerkleTreeError::MissingNode { level: 1, .. })));
+    }
+
+    #[test]
+    fn test_build_limits() {
+        use crate::builder::BuildLimits;
+        use crate::error::LimitExceeded;
+        use std::io::Cursor;
+
+        let limits = BuildLimits { max_leaves: Some(4), max_leaf_bytes: Some(6), max_total_bytes: Some(20) };
+        let builder = || MerkleTree::builder(Sha256Hasher::new()).limits(limits);
+        let limit_of = |result: Result<MerkleTree<Sha256Hasher>, MerkleTreeError>| match result {
+            Err(MerkleTreeError::LimitExceeded(limit)) => limit,
+            Err(err) => panic!("unexpected error {}", err),
+            Ok(_) => panic!("built past the limits"),
+        };
+
+        // Just within every limit, then one over each
+        assert!(builder().build_from_data(&["aaaaaa", "bbbbbb", "cccccc", "dd"]).is_ok());
+        assert_eq!(limit_of(builder().build_from_data(&["a", "b", "c", "d", "e"])), LimitExceeded::Leaves { max: 4 });
+        assert_eq!(limit_of(builder().build_from_data(&["a", "bbbbbbb"])), LimitExceeded::LeafBytes { item: 1, len: 7, max: 6 });
+        assert_eq!(limit_of(builder().build_from_data(&["aaaaaa", "bbbbbb", "cccccc", "ddd"])), LimitExceeded::TotalBytes { max: 20 });
+        // Leaves passed to `build` are measured themselves
+        let leaves: Vec<Vec<u8>> = (0u8..5).map(|i| Sha256Hasher::new().hash_leaf(&[i])).collect();
+        let hash_limits = BuildLimits { max_leaves: Some(4), ..BuildLimits::default() };
+        assert_eq!(
+            limit_of(MerkleTree::builder(Sha256Hasher::new()).limits(hash_limits).build(leaves.clone())),
+            LimitExceeded::Leaves { max: 4 }
+        );
+        assert!(MerkleTree::builder(Sha256Hasher::new()).limits(BuildLimits::default()).build(leaves).is_ok());
+
+        let lines = |input: &str| {
+            let options = utils::LineOptions { limits, ..utils::LineOptions::default() };
+            utils::create_tree_from_lines_with_options(Cursor::new(input.to_string()), Sha256Hasher::new(), options)
+        };
+        // Blank lines and terminators don't count
+        assert!(lines("aaaaaa\r\n\nbbbbbb\ncccccc\n\ndd\n").is_ok());
+        assert_eq!(limit_of(lines("a\nb\nc\nd\ne\n")), LimitExceeded::Leaves { max: 4 });
+        assert!(matches!(limit_of(lines("a\nbbbbbbbbbbbb\nc\n")), LimitExceeded::LeafBytes { item: 1, max: 6, .. }));
+        assert_eq!(limit_of(lines("aaaaaa\nbbbbbb\ncccccc\nddd")), LimitExceeded::TotalBytes { max: 20 });
+    }
+
+    #[cfg(feature = "csv")]
+    #[test]
+    fn test_csv_build_limits() {
+        use crate::builder::BuildLimits;
+        use crate::error::LimitExceeded;
+        use crate::utils::{CsvOptions, CsvTreeError, DuplicatePolicy};
+
+        let csv = "address\n0xaa\n0xbb\n0xaa\n0xcc\n";
+        let options = |limits, duplicates| CsvOptions { duplicates, limits, ..CsvOptions::default() };
+        let build = |options| utils::create_tree_from_csv_with_options(csv.as_bytes(), "address", Sha256Hasher::new(), options);
+
+        let three = BuildLimits { max_leaves: Some(3), ..BuildLimits::default() };
+        assert!(matches!(
+            build(options(three, DuplicatePolicy::Keep)),
+            Err(CsvTreeError::LimitExceeded(LimitExceeded::Leaves { max: 3 }))
+        ));
+        // A merged duplicate adds no leaf but its bytes still count
+        assert!(build(options(three, DuplicatePolicy::Merge)).is_ok());
+        let bytes = BuildLimits { max_total_bytes: Some(15), ..BuildLimits::default() };
+        assert!(matches!(
+            build(options(bytes, DuplicatePolicy::Merge)),
+            Err(CsvTreeError::LimitExceeded(LimitExceeded::TotalBytes { max: 15 }))
+        ));
+        let short = BuildLimits { max_leaf_bytes: Some(3), ..BuildLimits::default() };
+        assert!(matches!(
+            build(options(short, DuplicatePolicy::Keep)),
+            Err(CsvTreeError::LimitExceeded(LimitExceeded::LeafBytes { item: 0, len: 4, max: 3 }))
+        ));
+    }
+
+    /// A node store whose reads of one level fail
+    struct FailingStore {
+        inner: store::MemoryNodeStore,
+        failing_level: usize,
+    }
+
+    impl store::NodeStore for FailingStore {
+        type Node<'a> = &'a Vec<u8>;
+        type Error = std::convert::Infallible;
+
+        fn get(&self, level: usize, index: usize) -> Option<&Vec<u8>> {
+            self.try_get(level, index).ok().flatten()
+        }
+
+        fn try_get(&self, level: usize, index: usize) -> Result<Option<&Vec<u8>>, store::StoreReadError> {
+            if level == self.failing_level {
+                return Err(store::StoreReadError::new(level, index, std::io::Error::other("disk failure")));
+            }
+            Ok(self.inner.get(level, index))
+        }
+
+        fn put(&mut self, level: usize, index: usize, hash: Vec<u8>) -> Result<(), Self::Error> {
+            self.inner.put(level, index, hash)
+        }
+
+        fn get_meta(&self) -> Result<Option<Vec<u8>>, store::StoreReadError> {
+            self.inner.get_meta()
+        }
+
+        fn put_meta(&mut self, meta: Vec<u8>) -> Result<(), Self::Error> {
+            self.inner.put_meta(meta)
+        }
+    }
+
+    #[test]
+    fn test_store_read_errors_are_not_missing_nodes() {
+        let hasher = Sha256Hasher::new();
+        let leaves: Vec<Vec<u8>> = (0..8).map(|i| hasher.hash_leaf(&[i])).collect();
+        let store = FailingStore { inner: store::MemoryNodeStore::new(), failing_level: 99 };
+        let mut tree = tree::MerkleTree::with_store(leaves, hasher.clone(), store).unwrap();
+        tree.generate_proof(3).unwrap();
+
+        tree = tree::MerkleTree::from_store(FailingStore { failing_level: 2, ..tree.into_store() }, hasher.clone()).unwrap();
+        assert!(matches!(
+            tree.generate_proof(3),
+            Err(MerkleTreeError::StoreRead(store::StoreReadError { node: Some((2, 1)), .. }))
+        ));
+        assert!(tree.try_get_node(2, 1).is_err());
+        assert_eq!(tree.get_node(2, 1), None);
+
+        let store = FailingStore { failing_level: 0, ..tree.into_store() };
+        assert!(matches!(tree::MerkleTree::from_store(store, hasher), Err(MerkleTreeError::StoreRead(_))));
+    }
+
+    #[test]
+    fn test_bulk_proofs_report_errors() {
+        let hasher = Sha256Hasher::new();
+        let leaves: Vec<Vec<u8>> = (0..5).map(|i| hasher.hash_leaf(&[i])).collect();
+        let store = FailingStore { inner: store::MemoryNodeStore::new(), failing_level: 99 };
+        let tree = tree::MerkleTree::with_store(leaves, hasher.clone(), store).unwrap();
+        // Padding is skipped, not reported
+        assert_eq!(tree.generate_proofs_where(|_| true).unwrap().len(), 5);
+        #[cfg(feature = "rayon")]
+        assert_eq!(tree.generate_all_proofs_parallel().unwrap().len(), 5);
+
+        let tree = tree::MerkleTree::from_store(FailingStore { failing_level: 1, ..tree.into_store() }, hasher).unwrap();
+        assert!(matches!(tree.generate_proofs_where(|_| true), Err(MerkleTreeError::StoreRead(_))));
+        #[cfg(feature = "rayon")]
+        {
+            assert!(matches!(tree.generate_all_proofs_parallel(), Err(MerkleTreeError::StoreRead(_))));
+            assert!(matches!(tree.generate_proofs_where_parallel(|_| true), Err(MerkleTreeError::StoreRead(_))));
+        }
+    }
+
+    #[test]
+    fn test_reopened_store_keeps_repeated_real_leaves() {
+        let hasher = Sha256Hasher::new();
+        let (a, b) = (hasher.hash_leaf(b"a"), hasher.hash_leaf(b"b"));
+        let leaves = vec![a, b.clone(), b.clone(), b];
+        let tree = tree::MerkleTree::new_ordered(leaves.clone(), hasher.clone());
+        let reopened = tree::MerkleTree::from_store(tree.into_store(), hasher.clone()).unwrap();
+        assert_eq!(reopened.unpadded_leaf_count(), 4);
+        assert!(reopened.generate_proof(3).unwrap().verify(reopened.root()));
+        let restored = tree::MerkleTree::from_bytes(&reopened.to_bytes().unwrap(), hasher.clone()).unwrap();
+        assert_eq!(restored.unpadded_leaf_count(), 4);
+
+        // A store without the record can't tell real copies from padding, so it doesn't open
+        let store = tree::MerkleTree::with_store_ordered(leaves.clone(), hasher.clone(), CountingStore::default()).unwrap().into_store();
+        assert!(matches!(tree::MerkleTree::from_store(store, hasher.clone()), Err(MerkleTreeError::IncompleteStore)));
+        // Nor does one whose record doesn't pad to the stored leaves
+        let mut store = tree::MerkleTree::new_ordered(leaves, hasher.clone()).into_store();
+        let mut meta = store::NodeStore::get_meta(&store).unwrap().unwrap();
+        meta[..8].copy_from_slice(&2u64.to_le_bytes());
+        let Ok(()) = store::NodeStore::put_meta(&mut store, meta);
+        assert!(matches!(tree::MerkleTree::from_store(store, hasher), Err(MerkleTreeError::IncompleteStore)));
+    }
+
+    #[test]
+    fn test_params_survive_saving_reopening_and_updates() {
+        let hasher = Sha256Hasher::new();
+        let leaves: Vec<Vec<u8>> = (0..5u8).map(|i| hasher.hash_leaf(&[i])).collect();
+        let sorted = MerkleTree::new(leaves.clone(), hasher.clone());
+        let bound = MerkleTree::builder(hasher.clone()).bind_indices(true)
+            .build((0..5).map(|i| utils::hash_indexed_leaf(&hasher, i, b"x")).collect()).unwrap();
+
+        for tree in [&sorted, &bound] {
+            let restored = MerkleTree::from_bytes(&tree.to_bytes().unwrap(), hasher.clone()).unwrap();
+            assert_eq!(restored.params(), tree.params());
+            assert_eq!(restored.bound_root(), tree.bound_root());
+        }
+        let restored = MerkleTree::from_bytes(&sorted.to_bytes().unwrap(), hasher.clone()).unwrap();
+        assert!(restored.prove_prefix_range(&sorted.get_leaf(0).unwrap()[..1]).is_ok());
+        let reopened = MerkleTree::from_store(restored.into_store(), hasher.clone()).unwrap();
+        assert_eq!(reopened.bound_root(), sorted.bound_root());
+        assert!(reopened.prove_prefix_range(&sorted.get_leaf(0).unwrap()[..1]).is_ok());
+
+        // An update unsorts the leaves but not the options the tree was built with
+        let mut updated = MerkleTree::from_bytes(&sorted.to_bytes().unwrap(), hasher.clone()).unwrap();
+        updated.update_leaf_with_delta(0, vec![0xff; 32]).unwrap();
+        assert_eq!(updated.params(), sorted.params());
+        assert!(matches!(updated.prove_prefix_range(b"\xff"), Err(MerkleTreeError::LeavesNotSorted)));
+        let saved = updated.to_bytes().unwrap();
+        for tree in [
+            MerkleTree::from_bytes(&saved, hasher.clone()).unwrap(),
+            MerkleTree::from_store(updated.into_store(), hasher.clone()).unwrap(),
+        ] {
+            assert_eq!(tree.params(), sorted.params());
+            assert!(matches!(tree.prove_prefix_range(b"\xff"), Err(MerkleTreeError::LeavesNotSorted)));
+        }
+
+        // A file claiming a sorted and index-bound tree is malformed
+        let mut bytes = sorted.to_bytes().unwrap();
+        bytes[17] = 3;
+        assert_eq!(MerkleTree::from_bytes(&bytes, hasher).err(), Some(persist::FormatError::InvalidFlags(3)));
+    }
+}
@@ -1,41 +1,131 @@
-use merkle_tree::{hasher::Hasher, utils};
-
-fn main() {
-    // Create a tree from strings using the default SHA-256 hasher
-    let data = vec!["Create", "a", "tree", "from", "strings"];
-    let tree = utils::create_tree_from_strings(data);
-    
-    // Get the root of the tree
-    let root = tree.root();
-    println!("Merkle Root: {:?}", hex::encode(&root));
-    
-    // Generate a proof for 'from'
-    let proof = tree.generate_proof(3).unwrap();
-    
-    // Verify the proof
-    let is_valid = tree.verify_proof(&proof);
-    println!("Proof is valid: {}", is_valid);
-    
-    // Calculate the root from the proof
-    let calculated_root = proof.calculate_root();
-    println!("Calculated Root: {:?}", hex::encode(&calculated_root));
-    
-    // Verify the proof against the root
-    let is_valid = proof.verify(&root);
-    println!("Proof verifies against root: {}", is_valid);
-
-    // Verify the proof if 'from' is present in tree
-    let is_present = utils::verify_element_in_tree(&tree, "from");
-    println!("Proof verifies against presence of element in tree: {}", is_present);
-
-    // Verify the proof if leaf is present in tree with given proof data
-    let leaf = tree.get_hasher().hash_leaf(utils::string_to_bytes("from").as_slice());
-    let proof_data = tree.generate_proof_by_value(&leaf).expect("Couldn't generate proof").to_debug_format();
-    println!("{:#?}", proof_data);
-    let is_valid = utils::verify_with_formatted_proof(&tree.root(), leaf, proof_data, tree.get_hasher());
-    println!("Proof verify agaist given proof data: {}", is_valid);
-
-    // Example of using a custom hasher
-    // let custom_hasher = merkle_tree::hasher::Blake2bHasher::new(32);
-    // let custom_tree = utils::create_tree_from_strings_with_hasher(data, custom_hasher);
-}
\ No newline at end of file
+//! `merkle`: build trees from line-delimited files and prove and verify their leaves
+//!
+//! Each line of the input is a leaf, hashed with `hash_leaf` and sorted as
+//! in `MerkleTree::new`. Proofs are read and written in the JSON proof
+//! format, so other implementations can check them.
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::{Args, Parser, Subcommand};
+use merkle_tree::hasher::BuiltinHasher;
+use merkle_tree::index::LeafIndex;
+use merkle_tree::proof::MerkleProof;
+use merkle_tree::tree::MerkleTree;
+use merkle_tree::utils;
+
+#[derive(Parser)]
+#[command(name = "merkle", version, about = "Build Merkle trees and prove and verify their leaves")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print the root of the tree over the input lines, in hex
+    Root {
+        #[command(flatten)]
+        tree: TreeArgs,
+    },
+    /// Write the proof of one leaf in the JSON proof format
+    Prove {
+        #[command(flatten)]
+        tree: TreeArgs,
+        /// The leaf's index among the sorted leaves
+        #[arg(long, conflicts_with = "value", required_unless_present = "value")]
+        index: Option<LeafIndex>,
+        /// The line whose leaf to prove
+        #[arg(long)]
+        value: Option<String>,
+        /// Where to write the proof; stdout if not given
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+    /// Check a proof against a root, exiting with status 1 if it doesn't verify
+    Verify {
+        /// The root, in hex
+        #[arg(long)]
+        root: String,
+        /// The proof file, in the JSON proof format
+        #[arg(long)]
+        proof: PathBuf,
+        #[arg(long, default_value = "sha256")]
+        hasher: String,
+    },
+}
+
+#[derive(Args)]
+struct TreeArgs {
+    /// The leaves, one per line; stdin if not given or `-`
+    #[arg(long)]
+    input: Option<PathBuf>,
+    /// sha256, blake2b256, blake2b512 or keccak256
+    #[arg(long, default_value = "sha256")]
+    hasher: String,
+}
+
+impl TreeArgs {
+    fn build(&self) -> Result<MerkleTree<BuiltinHasher>, String> {
+        let hasher = parse_hasher(&self.hasher)?;
+        let reader: Box<dyn BufRead> = match &self.input {
+            Some(path) if path.as_os_str() != "-" => {
+                let file = File::open(path).map_err(|err| format!("cannot open {}: {}", path.display(), err))?;
+                Box::new(BufReader::new(file))
+            }
+            _ => Box::new(io::stdin().lock()),
+        };
+        utils::create_tree_from_lines(reader, hasher).map_err(|err| format!("cannot build the tree: {}", err))
+    }
+}
+
+fn parse_hasher(name: &str) -> Result<BuiltinHasher, String> {
+    BuiltinHasher::from_name(name).ok_or_else(|| format!("unknown hasher '{}'", name))
+}
+
+/// Runs a command, returning whether it succeeded; only `verify` can fail without an error
+fn run(command: Command) -> Result<bool, String> {
+    match command {
+        Command::Root { tree } => {
+            println!("{}", hex::encode(tree.build()?.root()));
+            Ok(true)
+        }
+        Command::Prove { tree, index, value, out } => {
+            let tree = tree.build()?;
+            let proof = match (index, value) {
+                (Some(index), _) => tree.generate_proof(index),
+                (None, Some(value)) => utils::prove_element(&tree, &value),
+                (None, None) => unreachable!("clap requires --index or --value"),
+            };
+            let json = proof.map_err(|err| format!("cannot generate the proof: {}", err))?.to_json();
+            match out {
+                Some(path) => std::fs::write(&path, json + "\n")
+                    .map_err(|err| format!("cannot write {}: {}", path.display(), err))?,
+                None => writeln!(io::stdout(), "{}", json).map_err(|err| err.to_string())?,
+            }
+            Ok(true)
+        }
+        Command::Verify { root, proof, hasher } => {
+            let hasher = parse_hasher(&hasher)?;
+            let root = hex::decode(root.trim()).map_err(|err| format!("invalid root: {}", err))?;
+            let json = std::fs::read_to_string(&proof)
+                .map_err(|err| format!("cannot read {}: {}", proof.display(), err))?;
+            let proof = MerkleProof::from_json(&json, hasher).map_err(|err| format!("invalid proof: {}", err))?;
+            let valid = proof.verify(&root);
+            println!("{}", if valid { "valid" } else { "invalid" });
+            Ok(valid)
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse().command) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(message) => {
+            eprintln!("error: {}", message);
+            ExitCode::from(2)
+        }
+    }
+}
@@ -0,0 +1,148 @@
+//! A committed key-value map
+//!
+//! [`MerkleMap`] keeps its entries ordered by key bytes and commits to them
+//! with a tree whose leaves are `hash_leaf(encode_entry(key, value))`. A
+//! proof for a key therefore binds its value too: [`MerkleMap::verify`]
+//! checks that a key maps to a value under a root.
+//!
+//! Every mutation rebuilds the tree from the stored leaf hashes, which costs
+//! one pair hash per node but no rehashing of keys or values.
+use alloc::vec::Vec;
+use crate::error::MerkleTreeError;
+use crate::hasher::Hasher;
+use crate::index::LeafIndex;
+use crate::proof::MerkleProof;
+use crate::tree::MerkleTree;
+
+/// Encodes an entry as the leaf data: the key length as a `u32` LE, the key, then the value
+///
+/// The length prefix keeps `("ab", "c")` and `("a", "bc")` apart.
+pub fn encode_entry(key: &[u8], value: &[u8]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(4 + key.len() + value.len());
+    data.extend_from_slice(&(key.len() as u32).to_le_bytes());
+    data.extend_from_slice(key);
+    data.extend_from_slice(value);
+    data
+}
+
+/// A map from keys to values committed to by a Merkle root
+pub struct MerkleMap<K: AsRef<[u8]>, V: AsRef<[u8]>, H: Hasher> {
+    /// The entries, ordered by key bytes
+    entries: Vec<(K, V)>,
+    /// The tree over the entries; an empty map has none
+    tree: Option<MerkleTree<H>>,
+    /// The hasher for the entries and the tree
+    hasher: H,
+}
+
+impl<K: AsRef<[u8]>, V: AsRef<[u8]>, H: Hasher> MerkleMap<K, V, H> {
+    /// Creates an empty map
+    pub fn new(hasher: H) -> Self {
+        MerkleMap { entries: Vec::new(), tree: None, hasher }
+    }
+
+    /// Gets the number of entries
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Checks whether the map has no entries
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Gets the root hash, or `None` for an empty map
+    pub fn root(&self) -> Option<&[u8]> {
+        self.tree.as_ref().map(MerkleTree::root)
+    }
+
+    /// Gets the tree over the entries, or `None` for an empty map
+    pub fn tree(&self) -> Option<&MerkleTree<H>> {
+        self.tree.as_ref()
+    }
+
+    /// Finds the position of `key`, or where it would be inserted
+    fn search(&self, key: &[u8]) -> Result<usize, usize> {
+        self.entries.binary_search_by(|(probe, _)| probe.as_ref().cmp(key))
+    }
+
+    /// Gets the value for a key
+    pub fn get<Q: AsRef<[u8]> + ?Sized>(&self, key: &Q) -> Option<&V> {
+        let index = self.search(key.as_ref()).ok()?;
+        Some(&self.entries[index].1)
+    }
+
+    /// Checks whether the map has an entry for a key
+    pub fn contains_key<Q: AsRef<[u8]> + ?Sized>(&self, key: &Q) -> bool {
+        self.search(key.as_ref()).is_ok()
+    }
+
+    /// Iterates over the entries in key order
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.entries.iter().map(|(key, value)| (key, value))
+    }
+
+    /// Sets the value for a key, returning the new root
+    pub fn insert(&mut self, key: K, value: V) -> Vec<u8> {
+        let leaf = self.hasher.hash_leaf(&encode_entry(key.as_ref(), value.as_ref()));
+        let mut leaves = self.leaves();
+        match self.search(key.as_ref()) {
+            Ok(index) => {
+                self.entries[index].1 = value;
+                leaves[index] = leaf;
+            }
+            Err(index) => {
+                self.entries.insert(index, (key, value));
+                leaves.insert(index, leaf);
+            }
+        }
+        self.rebuild(leaves);
+        self.tree.as_ref().map_or_else(Vec::new, |tree| tree.root().to_vec())
+    }
+
+    /// Removes the entry for a key, returning its value
+    pub fn remove<Q: AsRef<[u8]> + ?Sized>(&mut self, key: &Q) -> Option<V> {
+        let index = self.search(key.as_ref()).ok()?;
+        let mut leaves = self.leaves();
+        leaves.remove(index);
+        let (_, value) = self.entries.remove(index);
+        self.rebuild(leaves);
+        Some(value)
+    }
+
+    /// Generates a proof that the key maps to its current value
+    ///
+    /// The proof's leaf is the hash of the encoded entry; check it with
+    /// [`MerkleMap::verify`].
+    pub fn prove<Q: AsRef<[u8]> + ?Sized>(&self, key: &Q) -> Result<MerkleProof<H>, MerkleTreeError> {
+        let index = self.search(key.as_ref()).map_err(|_| MerkleTreeError::LeafNotFound)?;
+        self.tree.as_ref().ok_or(MerkleTreeError::LeafNotFound)?.generate_proof(index as LeafIndex)
+    }
+
+    /// Verifies that `key` maps to `value` under `root`
+    ///
+    /// The proof's leaf must be the hash of the encoded entry, so a proof for
+    /// the key with another value, or for another key, fails.
+    pub fn verify(root: &[u8], key: &[u8], value: &[u8], proof: &MerkleProof<H>) -> bool {
+        proof.leaf == proof.hasher.hash_leaf(&encode_entry(key, value)) && proof.verify(root)
+    }
+
+    /// Copies the leaf hashes of the current tree, in key order
+    fn leaves(&self) -> Vec<Vec<u8>> {
+        let Some(tree) = &self.tree else {
+            return Vec::new();
+        };
+        (0..tree.unpadded_leaf_count())
+            .filter_map(|index| Some(tree.get_leaf(index)?.clone()))
+            .collect()
+    }
+
+    fn rebuild(&mut self, leaves: Vec<Vec<u8>>) {
+        self.tree = if leaves.is_empty() {
+            None
+        } else {
+            // Entries are ordered by key, not by leaf hash, so the leaves keep that order
+            Some(MerkleTree::new_ordered(leaves, self.hasher.clone()))
+        };
+    }
+}
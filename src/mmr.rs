@@ -0,0 +1,223 @@
+//! A Merkle Mountain Range: an append-only commitment that never rebalances
+//!
+//! Nodes are numbered in the order they are appended, starting at 0, as in
+//! other MMR implementations: pushing a leaf appends it and then every
+//! parent it completes. The range is a list of perfect trees ("peaks") of
+//! decreasing height, and the root bags them from right to left:
+//! `hash_pair(p0, hash_pair(p1, ... hash_pair(p(n-2), p(n-1))))`.
+use alloc::vec::Vec;
+use crate::error::MerkleTreeError;
+use crate::hasher::Hasher;
+use crate::proof::ProofHash;
+
+/// The number of nodes in a perfect tree of the given height
+fn tree_size(height: u32) -> u64 {
+    (2u64 << height) - 1
+}
+
+/// Gets the height of the node at `position`, 0 for leaves
+fn position_height(position: u64) -> u32 {
+    // On the 1-based numbering, the leftmost node of each height is all ones;
+    // jumping left by a subtree at a time reaches one of them
+    let mut position = position + 1;
+    while position.count_zeros() != position.leading_zeros() {
+        let top = 1u64 << (63 - position.leading_zeros());
+        position -= top - 1;
+    }
+    63 - position.leading_zeros()
+}
+
+/// Gets the position of the leaf with the given index
+pub fn leaf_index_to_position(index: u64) -> u64 {
+    2 * index - u64::from(index.count_ones())
+}
+
+/// Gets the positions and heights of the peaks of a range of `size` nodes, left to right
+///
+/// `None` if no range has that many nodes.
+fn peak_positions(size: u64) -> Option<Vec<(u64, u32)>> {
+    let mut peaks = Vec::new();
+    let mut offset = 0u64;
+    for height in (0..63).rev() {
+        let subtree = tree_size(height);
+        if size - offset >= subtree {
+            offset += subtree;
+            peaks.push((offset - 1, height));
+        }
+    }
+    (offset == size).then_some(peaks)
+}
+
+/// Bags the peaks from right to left into the root
+fn bag_peaks<'a, H: Hasher, I>(hasher: &H, peaks: I) -> Option<Vec<u8>>
+where
+    I: DoubleEndedIterator<Item = &'a [u8]>,
+{
+    let mut peaks = peaks.rev();
+    let mut root = peaks.next()?.to_vec();
+    for peak in peaks {
+        root = hasher.hash_pair(peak, &root);
+    }
+    Some(root)
+}
+
+/// An append-only Merkle Mountain Range
+pub struct MerkleMountainRange<H: Hasher> {
+    /// Every node, by position
+    nodes: Vec<Vec<u8>>,
+    /// The number of leaves pushed
+    leaf_count: u64,
+    /// The hasher for parent nodes
+    hasher: H,
+}
+
+impl<H: Hasher> MerkleMountainRange<H> {
+    /// Creates an empty range
+    pub fn new(hasher: H) -> Self {
+        MerkleMountainRange { nodes: Vec::new(), leaf_count: 0, hasher }
+    }
+
+    /// Appends a leaf hash, returning its position
+    pub fn push(&mut self, leaf: Vec<u8>) -> u64 {
+        let position = self.nodes.len() as u64;
+        self.nodes.push(leaf);
+
+        // A leaf with index ending in k one bits completes k parents
+        let mut right = position as usize;
+        for height in 0..self.leaf_count.trailing_ones() {
+            let left = right - tree_size(height) as usize;
+            let parent = self.hasher.hash_pair(&self.nodes[left], &self.nodes[right]);
+            self.nodes.push(parent);
+            right = self.nodes.len() - 1;
+        }
+        self.leaf_count += 1;
+        position
+    }
+
+    /// Gets the number of leaves
+    pub fn leaf_count(&self) -> u64 {
+        self.leaf_count
+    }
+
+    /// Gets the number of nodes, leaves included
+    pub fn size(&self) -> u64 {
+        self.nodes.len() as u64
+    }
+
+    /// Checks whether no leaf has been pushed
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Gets the node at a position
+    pub fn get(&self, position: u64) -> Option<&[u8]> {
+        self.nodes.get(usize::try_from(position).ok()?).map(Vec::as_slice)
+    }
+
+    /// Gets the peaks, left to right
+    pub fn peaks(&self) -> Vec<&[u8]> {
+        peak_positions(self.size())
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(position, _)| self.nodes[position as usize].as_slice())
+            .collect()
+    }
+
+    /// Gets the root, bagging the peaks from right to left, or `None` for an empty range
+    pub fn root(&self) -> Option<Vec<u8>> {
+        bag_peaks(&self.hasher, self.peaks().into_iter())
+    }
+
+    /// Generates a proof for the leaf at `position`
+    pub fn gen_proof(&self, position: u64) -> Result<MmrProof<H>, MerkleTreeError> {
+        if position >= self.size() {
+            return Err(MerkleTreeError::LeafIndexOutOfBounds { index: position, len: self.size() });
+        }
+        if position_height(position) != 0 {
+            return Err(MerkleTreeError::NotALeaf { position });
+        }
+
+        let peaks = peak_positions(self.size()).ok_or(MerkleTreeError::IncompleteStore)?;
+        let mut siblings = Vec::new();
+        let mut current = position;
+        let mut height = 0;
+        while !peaks.iter().any(|&(peak, _)| peak == current) {
+            let sibling = if position_height(current + 1) > height {
+                // The next position is the parent, so this is a right child
+                let sibling = current - tree_size(height);
+                current += 1;
+                sibling
+            } else {
+                let sibling = current + tree_size(height);
+                current = sibling + 1;
+                sibling
+            };
+            siblings.push(ProofHash::from_slice(&self.nodes[sibling as usize]));
+            height += 1;
+        }
+
+        let other_peaks = peaks.iter()
+            .filter(|&&(peak, _)| peak != current)
+            .map(|&(peak, _)| ProofHash::from_slice(&self.nodes[peak as usize]))
+            .collect();
+        Ok(MmrProof { size: self.size(), siblings, peaks: other_peaks, hasher: self.hasher.clone() })
+    }
+
+    /// Verifies that `leaf` is at `position` under `root`
+    pub fn verify(root: &[u8], leaf: &[u8], position: u64, proof: &MmrProof<H>) -> bool {
+        proof.verify(root, leaf, position)
+    }
+}
+
+/// A proof of a leaf in a [`MerkleMountainRange`]
+#[derive(Clone)]
+pub struct MmrProof<H: Hasher> {
+    /// The number of nodes in the range the proof is for
+    pub size: u64,
+    /// The siblings from the leaf up to its peak
+    pub siblings: Vec<ProofHash>,
+    /// The other peaks, left to right
+    pub peaks: Vec<ProofHash>,
+    /// The hasher for the proof
+    pub hasher: H,
+}
+
+impl<H: Hasher> MmrProof<H> {
+    /// Verifies that `leaf` is at `position` under `root`
+    pub fn verify(&self, root: &[u8], leaf: &[u8], position: u64) -> bool {
+        let Some(peaks) = peak_positions(self.size) else {
+            return false;
+        };
+        if position >= self.size || position_height(position) != 0 || peaks.len() != self.peaks.len() + 1 {
+            return false;
+        }
+
+        let mut node = leaf.to_vec();
+        let mut current = position;
+        let mut height = 0;
+        let mut siblings = self.siblings.iter();
+        while !peaks.iter().any(|&(peak, _)| peak == current) {
+            let Some(sibling) = siblings.next() else {
+                return false;
+            };
+            if position_height(current + 1) > height {
+                node = self.hasher.hash_pair(sibling, &node);
+                current += 1;
+            } else {
+                node = self.hasher.hash_pair(&node, sibling);
+                current += tree_size(height) + 1;
+            }
+            height += 1;
+        }
+        if siblings.next().is_some() {
+            return false;
+        }
+
+        let Some(slot) = peaks.iter().position(|&(peak, _)| peak == current) else {
+            return false;
+        };
+        let mut all_peaks: Vec<&[u8]> = self.peaks.iter().map(|peak| peak.as_slice()).collect();
+        all_peaks.insert(slot, &node);
+        bag_peaks(&self.hasher, all_peaks.into_iter()).is_some_and(|bagged| bagged == root)
+    }
+}
@@ -0,0 +1,247 @@
+//! MessagePack encodings of proofs, proof bundles and the compact tree format
+//!
+//! [`to_vec`] and [`from_slice`] wrap `rmp-serde`: structs become maps keyed
+//! by field name, as with `rmp_serde::to_vec_named`, and the crate's byte
+//! fields become `bin` strings, since the serde derives only use hex in
+//! human-readable formats.
+//!
+//! The framed encodings wrap each object in a map with a format `version`
+//! and the `hasher` name, so a reader can tell which hasher to check with:
+//!
+//! - [`MerkleProof::to_msgpack`]: `{version, hasher, proof: {leaf, proof: [{hash, direction}]}}`
+//! - [`ProofBundle::to_msgpack`]: `{version, hasher, root, proof, signed_root}`,
+//!   with `signed_root` nil or `{root, leaf_count, timestamp, signature}`
+//! - [`MerkleTree::to_msgpack`]: `{version, hasher, tree}`, `tree` being the
+//!   compact binary format of [`crate::persist`] as one `bin`
+use std::fmt;
+use std::io::ErrorKind;
+use rmp_serde::decode::Error as DecodeError;
+use serde::de::DeserializeOwned;
+use serde::ser::Serialize;
+use serde::Deserialize;
+use crate::bundle::ProofBundle;
+use crate::hasher::Hasher;
+use crate::persist::FormatError;
+use crate::proof::{hex_bytes, MerkleProof, OwnedProofFields};
+use crate::store::NodeStore;
+use crate::tree::MerkleTree;
+
+/// The version of the framed encodings
+pub const FRAME_VERSION: u8 = 1;
+
+/// The deepest nesting [`from_slice`] decodes; the frames are three deep
+const MAX_DEPTH: usize = 32;
+
+/// Errors from encoding or decoding MessagePack
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MsgpackError {
+    /// The data ends in the middle of a value
+    Truncated,
+    /// A byte that doesn't start any supported value
+    InvalidMarker(u8),
+    /// There is data after the value
+    TrailingBytes,
+    /// The frame was written by a format version this crate doesn't read
+    UnsupportedVersion(u8),
+    /// The frame names a different hasher from the one given to decode it
+    HasherMismatch { expected: String, found: String },
+    /// The embedded compact tree is invalid
+    Tree(FormatError),
+    /// Serde or `rmp-serde` reported an error, such as a missing field
+    Message(String),
+}
+
+impl fmt::Display for MsgpackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MsgpackError::Truncated => write!(f, "Truncated MessagePack data"),
+            MsgpackError::InvalidMarker(marker) => write!(f, "Invalid MessagePack marker 0x{:02x}", marker),
+            MsgpackError::TrailingBytes => write!(f, "Trailing bytes after MessagePack value"),
+            MsgpackError::UnsupportedVersion(version) => write!(f, "Unsupported frame version {}", version),
+            MsgpackError::HasherMismatch { expected, found } => {
+                write!(f, "Frame is for hasher '{}', expected '{}'", found, expected)
+            }
+            MsgpackError::Tree(err) => write!(f, "Invalid tree: {}", err),
+            MsgpackError::Message(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for MsgpackError {}
+
+impl From<FormatError> for MsgpackError {
+    fn from(err: FormatError) -> Self {
+        MsgpackError::Tree(err)
+    }
+}
+
+impl From<DecodeError> for MsgpackError {
+    fn from(err: DecodeError) -> Self {
+        match err {
+            DecodeError::InvalidMarkerRead(err) | DecodeError::InvalidDataRead(err) if err.kind() == ErrorKind::UnexpectedEof => {
+                MsgpackError::Truncated
+            }
+            // 0xc1 is the one byte MessagePack never uses
+            DecodeError::TypeMismatch(marker) if marker.to_u8() == 0xc1 => MsgpackError::InvalidMarker(0xc1),
+            DecodeError::Syntax(message) => MsgpackError::Message(message),
+            err => MsgpackError::Message(err.to_string()),
+        }
+    }
+}
+
+/// Encodes a value as MessagePack
+pub fn to_vec<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, MsgpackError> {
+    rmp_serde::to_vec_named(value).map_err(|err| MsgpackError::Message(err.to_string()))
+}
+
+/// Decodes a value from MessagePack, rejecting trailing bytes
+///
+/// Lengths in the data are never trusted for allocation: a `bin` or string
+/// that claims more than the remaining bytes fails as
+/// [`MsgpackError::Truncated`] before it is copied, and an array reserves
+/// at most 64 bytes for a hash, or 1 MiB for other sequences, growing only
+/// as its elements are read.
+pub fn from_slice<T: DeserializeOwned>(mut bytes: &[u8]) -> Result<T, MsgpackError> {
+    let mut decoder = rmp_serde::Deserializer::new(&mut bytes);
+    decoder.set_max_depth(MAX_DEPTH);
+    let value = T::deserialize(&mut decoder)?;
+    if !bytes.is_empty() {
+        return Err(MsgpackError::TrailingBytes);
+    }
+    Ok(value)
+}
+
+/// The frame fields every encoding starts with, checked before the body is used
+fn check_frame<H: Hasher>(version: u8, hasher_name: &str, hasher: &H) -> Result<(), MsgpackError> {
+    if version != FRAME_VERSION {
+        return Err(MsgpackError::UnsupportedVersion(version));
+    }
+    if hasher_name != hasher.name() {
+        return Err(MsgpackError::HasherMismatch { expected: hasher.name(), found: hasher_name.into() });
+    }
+    Ok(())
+}
+
+#[derive(serde::Serialize)]
+#[serde(bound = "")]
+struct ProofFrame<'a, H: Hasher> {
+    version: u8,
+    hasher: String,
+    proof: &'a MerkleProof<H>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct OwnedProofFrame {
+    version: u8,
+    hasher: String,
+    proof: OwnedProofFields,
+}
+
+impl<H: Hasher> MerkleProof<H> {
+    /// Encodes the proof as a MessagePack frame with the format version and hasher name
+    pub fn to_msgpack(&self) -> Vec<u8> {
+        let frame = ProofFrame { version: FRAME_VERSION, hasher: self.hasher.name(), proof: self };
+        to_vec(&frame).expect("proof encoding cannot fail")
+    }
+
+    /// Decodes a frame written by [`MerkleProof::to_msgpack`], which must name `hasher`
+    pub fn from_msgpack(bytes: &[u8], hasher: H) -> Result<Self, MsgpackError> {
+        let frame: OwnedProofFrame = from_slice(bytes)?;
+        check_frame(frame.version, &frame.hasher, &hasher)?;
+        Ok(MerkleProof::new(frame.proof.leaf, frame.proof.proof, hasher))
+    }
+}
+
+#[cfg(feature = "signing")]
+type SignedRootField = crate::signing::SignedRoot;
+/// Without `signing` a bundle's signed head is skipped when decoding
+#[cfg(not(feature = "signing"))]
+type SignedRootField = serde::de::IgnoredAny;
+
+#[derive(serde::Serialize)]
+#[serde(bound = "")]
+struct BundleFrame<'a, H: Hasher> {
+    version: u8,
+    hasher: String,
+    #[serde(with = "hex_bytes")]
+    root: &'a Vec<u8>,
+    proof: &'a MerkleProof<H>,
+    #[cfg(feature = "signing")]
+    signed_root: &'a Option<crate::signing::SignedRoot>,
+    #[cfg(not(feature = "signing"))]
+    signed_root: Option<()>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct OwnedBundleFrame {
+    version: u8,
+    hasher: String,
+    #[serde(with = "hex_bytes")]
+    root: Vec<u8>,
+    proof: OwnedProofFields,
+    #[cfg_attr(not(feature = "signing"), allow(dead_code))]
+    signed_root: Option<SignedRootField>,
+}
+
+impl<H: Hasher> ProofBundle<H> {
+    /// Encodes the bundle as a MessagePack frame with the format version and hasher name
+    pub fn to_msgpack(&self) -> Vec<u8> {
+        let frame = BundleFrame {
+            version: FRAME_VERSION,
+            hasher: self.proof.hasher.name(),
+            root: &self.root,
+            proof: &self.proof,
+            #[cfg(feature = "signing")]
+            signed_root: &self.signed_root,
+            #[cfg(not(feature = "signing"))]
+            signed_root: None,
+        };
+        to_vec(&frame).expect("bundle encoding cannot fail")
+    }
+
+    /// Decodes a frame written by [`ProofBundle::to_msgpack`], which must name `hasher`
+    ///
+    /// Without the `signing` feature a signed head in the frame is skipped.
+    pub fn from_msgpack(bytes: &[u8], hasher: H) -> Result<Self, MsgpackError> {
+        let frame: OwnedBundleFrame = from_slice(bytes)?;
+        check_frame(frame.version, &frame.hasher, &hasher)?;
+        let bundle = ProofBundle::new(MerkleProof::new(frame.proof.leaf, frame.proof.proof, hasher), frame.root);
+        #[cfg(feature = "signing")]
+        let bundle = match frame.signed_root {
+            Some(signed_root) => bundle.with_signed_root(signed_root),
+            None => bundle,
+        };
+        Ok(bundle)
+    }
+}
+
+#[derive(serde::Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TreeFrame {
+    version: u8,
+    hasher: String,
+    #[serde(with = "hex_bytes")]
+    tree: Vec<u8>,
+}
+
+impl<H: Hasher, S: NodeStore> MerkleTree<H, S> {
+    /// Encodes the tree as a MessagePack frame around its compact binary format
+    pub fn to_msgpack(&self) -> Result<Vec<u8>, MsgpackError> {
+        let frame = TreeFrame { version: FRAME_VERSION, hasher: self.get_hasher().name(), tree: self.to_bytes()? };
+        to_vec(&frame)
+    }
+}
+
+impl<H: Hasher> MerkleTree<H> {
+    /// Decodes a frame written by [`MerkleTree::to_msgpack`], which must name `hasher`
+    ///
+    /// As with [`MerkleTree::from_bytes`], the root is rebuilt from the
+    /// leaves and checked against the stored one.
+    pub fn from_msgpack(bytes: &[u8], hasher: H) -> Result<Self, MsgpackError> {
+        let frame: TreeFrame = from_slice(bytes)?;
+        check_frame(frame.version, &frame.hasher, &hasher)?;
+        Ok(MerkleTree::from_bytes(&frame.tree, hasher)?)
+    }
+}
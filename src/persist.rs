@@ -0,0 +1,242 @@
+//! Compact binary format for saving and loading trees
+//!
+//! Layout (integers little-endian):
+//!
+//! | bytes      | field                                  |
+//! |------------|----------------------------------------|
+//! | 4          | magic `MRKL`                           |
+//! | 1          | format version (1)                     |
+//! | 8          | leaf count, excluding padding leaves   |
+//! | 4          | hash length                            |
+//! | 1          | flags: 1 = sorted, 2 = index-bound     |
+//! | count×len  | leaves in tree order                   |
+//! | len        | root                                   |
+//!
+//! The flags are the construction options [`MerkleTree::params`] reports,
+//! so a loaded tree has the same [`MerkleTree::bound_root`] as the saved
+//! one.
+//!
+//! Interior nodes are not stored. Loading rebuilds them from the leaves and
+//! checks the result against the stored root, so corrupted or truncated
+//! files fail to load instead of producing a different tree. The hash length
+//! must be the loading hasher's output size, which is checked before the
+//! leaf count is trusted for anything.
+use std::fmt;
+use std::io::Write;
+use std::path::Path;
+use crate::hasher::Hasher;
+use crate::store::NodeStore;
+use crate::tree::MerkleTree;
+
+const MAGIC: &[u8; 4] = b"MRKL";
+const VERSION: u8 = 1;
+const HEADER_LEN: usize = 4 + 1 + 8 + 4 + 1;
+const FLAG_SORTED: u8 = 1;
+const FLAG_INDEX_BOUND: u8 = 2;
+
+/// Reasons a byte sequence is not a valid serialized tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FormatError {
+    /// The data does not start with the expected magic bytes
+    BadMagic,
+    /// The data was written by an unsupported format version
+    UnsupportedVersion(u8),
+    /// The data is shorter than its header says
+    Truncated,
+    /// The data is longer than its header says
+    TrailingBytes,
+    /// The header describes a tree with no leaves
+    EmptyTree,
+    /// The tree's leaves don't all have the same length
+    NonUniformLeaves,
+    /// The header's hash length isn't the hasher's output size
+    HashLengthMismatch { expected: usize, actual: usize },
+    /// The flags byte has unknown bits set, or both sorted and index-bound
+    InvalidFlags(u8),
+    /// The root rebuilt from the leaves differs from the stored root
+    RootMismatch,
+    /// The tree's leaves were zeroized, so there is nothing to serialize
+    LeavesZeroized,
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FormatError::BadMagic => write!(f, "Not a serialized Merkle tree"),
+            FormatError::UnsupportedVersion(version) => write!(f, "Unsupported format version {}", version),
+            FormatError::Truncated => write!(f, "Serialized tree is truncated"),
+            FormatError::TrailingBytes => write!(f, "Serialized tree has trailing bytes"),
+            FormatError::EmptyTree => write!(f, "Serialized tree has no leaves"),
+            FormatError::NonUniformLeaves => write!(f, "Leaves have different lengths"),
+            FormatError::HashLengthMismatch { expected, actual } => write!(f, "Hash length is {}, expected {}", actual, expected),
+            FormatError::InvalidFlags(flags) => write!(f, "Invalid flags {:#04x}", flags),
+            FormatError::RootMismatch => write!(f, "Stored root does not match the leaves"),
+            FormatError::LeavesZeroized => write!(f, "Tree leaves have been zeroized"),
+        }
+    }
+}
+
+impl std::error::Error for FormatError {}
+
+/// Errors from saving or loading a tree file
+#[derive(Debug)]
+pub enum PersistError {
+    /// Reading or writing the file failed
+    Io(std::io::Error),
+    /// The file contents are not a valid tree
+    Format(FormatError),
+}
+
+impl fmt::Display for PersistError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PersistError::Io(err) => write!(f, "I/O error: {}", err),
+            PersistError::Format(err) => write!(f, "Format error: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for PersistError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PersistError::Io(err) => Some(err),
+            PersistError::Format(err) => Some(err),
+        }
+    }
+}
+
+impl From<std::io::Error> for PersistError {
+    fn from(err: std::io::Error) -> Self {
+        PersistError::Io(err)
+    }
+}
+
+impl From<FormatError> for PersistError {
+    fn from(err: FormatError) -> Self {
+        PersistError::Format(err)
+    }
+}
+
+impl<H: Hasher, S: NodeStore> MerkleTree<H, S> {
+    /// Serializes the tree into the compact binary format
+    pub fn to_bytes(&self) -> Result<Vec<u8>, FormatError> {
+        #[cfg(feature = "zeroize")]
+        if self.is_zeroized() {
+            return Err(FormatError::LeavesZeroized);
+        }
+        let root = self.root();
+        let hash_len = root.len();
+        let leaf_count = self.unpadded_leaf_count;
+
+        let params = self.params();
+        let mut flags = 0;
+        if params.sorted {
+            flags |= FLAG_SORTED;
+        }
+        if params.index_bound {
+            flags |= FLAG_INDEX_BOUND;
+        }
+
+        let mut bytes = Vec::with_capacity(HEADER_LEN + (leaf_count + 1) * hash_len);
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.extend_from_slice(&(leaf_count as u64).to_le_bytes());
+        bytes.extend_from_slice(&(hash_len as u32).to_le_bytes());
+        bytes.push(flags);
+
+        for index in 0..self.unpadded_leaf_count() {
+            let leaf = self.get_leaf(index).ok_or(FormatError::Truncated)?;
+            let leaf = leaf.as_ref();
+            if leaf.len() != hash_len {
+                return Err(FormatError::NonUniformLeaves);
+            }
+            bytes.extend_from_slice(leaf);
+        }
+
+        bytes.extend_from_slice(root);
+        Ok(bytes)
+    }
+
+    /// Writes the tree to a file in the compact binary format
+    ///
+    /// The file is synced to disk before this returns.
+    pub fn save_to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), PersistError> {
+        let bytes = self.to_bytes()?;
+        let mut file = std::fs::File::create(path)?;
+        file.write_all(&bytes)?;
+        file.sync_all()?;
+        Ok(())
+    }
+}
+
+impl<H: Hasher> MerkleTree<H> {
+    /// Deserializes a tree from the compact binary format, checking the stored root
+    pub fn from_bytes(bytes: &[u8], hasher: H) -> Result<Self, FormatError> {
+        if !bytes.starts_with(MAGIC) && !MAGIC.starts_with(bytes) {
+            return Err(FormatError::BadMagic);
+        }
+        if bytes.len() < 5 {
+            return Err(FormatError::Truncated);
+        }
+        let version = bytes[4];
+        if version != VERSION {
+            return Err(FormatError::UnsupportedVersion(version));
+        }
+        if bytes.len() < HEADER_LEN {
+            return Err(FormatError::Truncated);
+        }
+        let flags = bytes[HEADER_LEN - 1];
+        if flags & !(FLAG_SORTED | FLAG_INDEX_BOUND) != 0 || flags == FLAG_SORTED | FLAG_INDEX_BOUND {
+            return Err(FormatError::InvalidFlags(flags));
+        }
+
+        let leaf_count = u64::from_le_bytes(bytes[5..13].try_into().unwrap());
+        let hash_len = u32::from_le_bytes(bytes[13..17].try_into().unwrap()) as u64;
+        if leaf_count == 0 {
+            return Err(FormatError::EmptyTree);
+        }
+        // The root is one hash long, so any other length can't load, and
+        // checking before the body keeps a forged count from sizing anything
+        let expected_hash_len = hasher.output_size();
+        if hash_len != expected_hash_len as u64 || hash_len == 0 {
+            return Err(FormatError::HashLengthMismatch { expected: expected_hash_len, actual: hash_len as usize });
+        }
+
+        let expected_len = leaf_count
+            .checked_add(1)
+            .and_then(|count| count.checked_mul(hash_len))
+            .and_then(|len| len.checked_add(HEADER_LEN as u64))
+            .ok_or(FormatError::Truncated)?;
+        let actual_len = bytes.len() as u64;
+        if actual_len < expected_len {
+            return Err(FormatError::Truncated);
+        }
+        if actual_len > expected_len {
+            return Err(FormatError::TrailingBytes);
+        }
+
+        let hash_len = hash_len as usize;
+        let body = &bytes[HEADER_LEN..];
+        let (leaf_bytes, stored_root) = body.split_at(body.len() - hash_len);
+        let leaves: Vec<Vec<u8>> = leaf_bytes.chunks(hash_len).map(|leaf| leaf.to_vec()).collect();
+
+        // Leaves are stored in tree order, so they must not be re-sorted
+        let still_sorted = leaves.is_sorted();
+        let mut tree = MerkleTree::new_ordered(leaves, hasher);
+        if tree.root() != stored_root {
+            return Err(FormatError::RootMismatch);
+        }
+        let Ok(()) = tree.restore_params(flags & FLAG_SORTED != 0, flags & FLAG_INDEX_BOUND != 0, still_sorted);
+
+        Ok(tree)
+    }
+
+    /// Reads a tree written by [`MerkleTree::save_to_file`]
+    ///
+    /// The interior nodes are rebuilt and the recomputed root must match the
+    /// stored one, so a partially written or corrupted file is an error.
+    pub fn load_from_file<P: AsRef<Path>>(path: P, hasher: H) -> Result<Self, PersistError> {
+        let bytes = std::fs::read(path)?;
+        Ok(MerkleTree::from_bytes(&bytes, hasher)?)
+    }
+}
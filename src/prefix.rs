@@ -0,0 +1,124 @@
+//! Prefix queries over sorted leaves, with proofs that the answer is complete
+//!
+//! In a sorted tree the leaves sharing a prefix sit next to each other, so
+//! [`MerkleTree::leaves_with_prefix`] finds them with two binary searches. A
+//! [`PrefixRangeProof`] proves each matching leaf and the neighbours just
+//! outside the range, which don't share the prefix. With no matches the
+//! neighbours alone show where the prefix would sit.
+//!
+//! At the right end of the tree there may be no neighbour, only padding
+//! copies of the last leaf; the proof then shows from the last leaf's own
+//! siblings that everything to its right is such a copy.
+use alloc::vec::Vec;
+use core::ops::Range;
+use crate::error::MerkleTreeError;
+use crate::hasher::Hasher;
+use crate::index::LeafIndex;
+use crate::proof::MerkleProof;
+use crate::tree::MerkleTree;
+
+impl<H: Hasher> MerkleTree<H> {
+    /// Finds the leaves starting with `prefix`, with their index range
+    ///
+    /// This binary searches the leaves, so like [`slice::binary_search`] it
+    /// assumes they are sorted, as [`MerkleTree::new`] leaves them; for a
+    /// tree built in a given order the result is unspecified. Padding leaves
+    /// are never included, and a zeroized tree has no leaves to search.
+    pub fn leaves_with_prefix(&self, prefix: &[u8]) -> (Range<LeafIndex>, &[Vec<u8>]) {
+        let leaves = self.store().level(0)
+            .and_then(|leaves| leaves.get(..self.unpadded_leaf_count))
+            .unwrap_or_default();
+        let start = leaves.partition_point(|leaf| leaf.as_slice() < prefix);
+        let end = start + leaves[start..].partition_point(|leaf| leaf.starts_with(prefix));
+        (start as LeafIndex..end as LeafIndex, &leaves[start..end])
+    }
+
+    /// Proves which leaves start with `prefix`, and that no other leaf does
+    ///
+    /// Fails with [`MerkleTreeError::LeavesNotSorted`] for trees built in a
+    /// given order, where matching leaves needn't be adjacent.
+    pub fn prove_prefix_range(&self, prefix: &[u8]) -> Result<PrefixRangeProof<H>, MerkleTreeError> {
+        if !self.is_sorted() {
+            return Err(MerkleTreeError::LeavesNotSorted);
+        }
+        #[cfg(feature = "zeroize")]
+        if self.is_zeroized() {
+            return Err(MerkleTreeError::LeavesZeroized);
+        }
+        let (range, _) = self.leaves_with_prefix(prefix);
+        let matches = range.clone().map(|index| self.generate_proof(index)).collect::<Result<_, _>>()?;
+        let left = (range.start > 0).then(|| self.generate_proof(range.start - 1)).transpose()?;
+        let right = (range.end < self.unpadded_leaf_count()).then(|| self.generate_proof(range.end)).transpose()?;
+        Ok(PrefixRangeProof { prefix: prefix.to_vec(), range, matches, left, right })
+    }
+}
+
+/// The leaves of a sorted tree that start with a prefix, proven complete
+pub struct PrefixRangeProof<H: Hasher> {
+    /// The prefix queried
+    pub prefix: Vec<u8>,
+    /// The indices of the matching leaves; empty where the prefix would sit if there are none
+    pub range: Range<LeafIndex>,
+    /// A proof of each matching leaf, in order
+    pub matches: Vec<MerkleProof<H>>,
+    /// The leaf just before the range, absent when the range starts at 0
+    pub left: Option<MerkleProof<H>>,
+    /// The leaf just after the range, absent when only padding follows
+    pub right: Option<MerkleProof<H>>,
+}
+
+impl<H: Hasher> PrefixRangeProof<H> {
+    /// Gets the matching leaves, in order
+    pub fn leaves(&self) -> impl Iterator<Item = &[u8]> {
+        self.matches.iter().map(|proof| proof.leaf.as_slice())
+    }
+
+    /// Checks the proof against the root of a sorted tree
+    ///
+    /// Every proof must lead to `root` from the position it claims, the
+    /// matches must fill [`PrefixRangeProof::range`] and share the prefix,
+    /// and the neighbours must fall just outside it without sharing it.
+    pub fn verify(&self, root: &[u8]) -> bool {
+        let Range { start, end } = self.range;
+        if end.checked_sub(start) != Some(self.matches.len() as u64) || (start > 0) != self.left.is_some() {
+            return false;
+        }
+        // Every proof with the position it must sit at
+        let proofs = self.left.iter().map(|proof| (proof, start - 1))
+            .chain(self.matches.iter().zip(start..end))
+            .chain(self.right.iter().map(|proof| (proof, end)));
+        let Some(depth) = self.left.iter().chain(&self.matches).chain(&self.right).next().map(|proof| proof.proof_items.len()) else {
+            return false;
+        };
+        for (proof, position) in proofs {
+            if proof.proof_items.len() != depth || proof.path_index() != Some(position) || !proof.verify(root) {
+                return false;
+            }
+        }
+
+        let prefix = self.prefix.as_slice();
+        let before = |proof: &MerkleProof<H>| !proof.leaf.starts_with(prefix) && proof.leaf.as_slice() < prefix;
+        let after = |proof: &MerkleProof<H>| !proof.leaf.starts_with(prefix) && proof.leaf.as_slice() > prefix;
+        if !self.matches.iter().all(|proof| proof.leaf.starts_with(prefix)) || !self.left.iter().all(before) {
+            return false;
+        }
+        match (&self.right, self.matches.last().or(self.left.as_ref())) {
+            (Some(right), _) => after(right),
+            (None, Some(last)) => followed_by_padding(last),
+            (None, None) => false,
+        }
+    }
+}
+
+/// Checks that every leaf right of the proven one is a padding copy of it
+fn followed_by_padding<H: Hasher>(proof: &MerkleProof<H>) -> bool {
+    // The subtree of 2^level copies of the leaf
+    let mut padding = proof.leaf.clone();
+    for item in &proof.proof_items {
+        if !item.is_left && item.hash.as_slice() != padding.as_slice() {
+            return false;
+        }
+        padding = proof.hasher.hash_pair(&padding, &padding);
+    }
+    true
+}
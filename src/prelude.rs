@@ -0,0 +1,24 @@
+//! The types most programs need, for a single glob import
+//!
+//! ```
+//! use merkle_tree::prelude::*;
+//!
+//! let tree = create_tree_from_strings(vec!["alice", "bob", "carol"]);
+//! let leaf = Sha256Hasher::new().hash_leaf(b"bob");
+//! let proof: MerkleProof<Sha256Hasher> = tree.generate_proof_by_value(&leaf).unwrap();
+//! assert!(proof.verify(tree.root()));
+//! assert!(matches!(tree.generate_proof(3), Err(MerkleTreeError::PaddingLeaf { .. })));
+//! ```
+pub use crate::builder::MerkleTreeBuilder;
+pub use crate::error::{MerkleTreeError, ProofParseError};
+pub use crate::hasher::{Blake2bHasher, BuiltinHasher, Hasher, Sha256Hasher};
+pub use crate::proof::{MerkleProof, ProofItem};
+pub use crate::tree::MerkleTree;
+pub use crate::utils::{
+    compute_root_streaming,
+    create_tree_from_bytes,
+    create_tree_from_strings,
+    create_tree_from_strings_with_hasher,
+    prove_element,
+    verify_element_in_tree,
+};
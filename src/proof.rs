@@ -1,15 +1,32 @@
+use crate::error::{MerkleTreeError, ProofParseError};
 use crate::hasher::Hasher;
+use crate::index::LeafIndex;
+use alloc::vec::Vec;
+use smallvec::SmallVec;
+#[cfg(feature = "std")]
 use std::collections::HashMap;
 
+/// A sibling hash in a proof, kept inline for hashes of up to 64 bytes
+///
+/// Every built-in hasher fits, so a proof costs no allocation per item; it
+/// derefs to `[u8]` and converts from `Vec<u8>` with `.into()`.
+pub type ProofHash = SmallVec<[u8; 64]>;
+
 /// Represents a single item in a Merkle proof (sibling hash and direction)
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(deny_unknown_fields))]
 pub struct ProofItem {
     /// The hash of the sibling node
-    pub hash: Vec<u8>,
+    #[cfg_attr(feature = "serde", serde(with = "hex_bytes"))]
+    pub hash: ProofHash,
     /// Whether the sibling is on the left (true) or right (false)
+    #[cfg_attr(feature = "serde", serde(rename = "direction", with = "direction"))]
     pub is_left: bool,
 }
 
 /// Represents a Merkle proof
+#[derive(Clone, Debug)]
 pub struct MerkleProof<H: Hasher> {
     /// The leaf being proven
     pub leaf: Vec<u8>,
@@ -31,27 +48,50 @@ impl<H: Hasher> MerkleProof<H> {
     
     /// Calculates the root using the proof items with direction information
     pub fn calculate_root(&self) -> Vec<u8> {
-        let mut current = self.leaf.clone();
-        
-        for item in &self.proof_items {
-            current = if item.is_left {
-                // Sibling is left, current is right
-                self.hasher.hash_pair(&item.hash, &current)
-            } else {
-                // Sibling is right, current is left
-                self.hasher.hash_pair(&current, &item.hash)
-            };
-        }
-        
-        current
+        fold_root(
+            &self.hasher,
+            &self.leaf,
+            self.proof_items.iter().map(|item| (item.hash.as_slice(), item.is_left)),
+        )
+    }
+    
+    /// Calculates the root as [`MerkleProof::calculate_root`] does, with another hasher
+    pub(crate) fn calculate_root_with(&self, hasher: &H) -> Vec<u8> {
+        fold_root(
+            hasher,
+            &self.leaf,
+            self.proof_items.iter().map(|item| (item.hash.as_slice(), item.is_left)),
+        )
+    }
+
+    /// Gets the leaf index the proof's directions spell out
+    ///
+    /// A sibling on the left means the node on the path is a right child, so
+    /// read from the root down the directions are the bits of the leaf's
+    /// generalized index. `None` for proofs deeper than [`crate::index::MAX_DEPTH`].
+    pub(crate) fn path_index(&self) -> Option<LeafIndex> {
+        let depth = u32::try_from(self.proof_items.len()).ok().filter(|&depth| depth <= crate::index::MAX_DEPTH)?;
+        let gindex = self.proof_items.iter().rev().fold(1u64, |gindex, item| gindex << 1 | u64::from(item.is_left));
+        let (_, index) = crate::index::from_gindex(depth, gindex)?;
+        Some(index)
     }
     
     /// Verifies the proof against a given root
     pub fn verify(&self, root: &[u8]) -> bool {
-        self.calculate_root() == root
+        let valid = self.calculate_root() == root;
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            target: "merkle_tree.verify",
+            valid,
+            depth = self.proof_items.len(),
+            root = %crate::trace::short_hex(root),
+            "verified proof"
+        );
+        valid
     }
     
     /// Converts the proof to a JSON-like format for debugging or serialization
+    #[cfg(feature = "std")]
     pub fn to_debug_format(&self) -> Vec<HashMap<String, String>> {
         self.proof_items.iter().map(|item| {
             let mut map = HashMap::new();
@@ -63,4 +103,377 @@ impl<H: Hasher> MerkleProof<H> {
             map
         }).collect()
     }
+
+    /// Parses proof items in the format of [`MerkleProof::to_debug_format`]
+    ///
+    /// Each item needs a hex `hash` and a `direction` of `left` or `right`;
+    /// other keys are ignored.
+    #[cfg(feature = "std")]
+    pub fn from_debug_format(
+        leaf: Vec<u8>,
+        proof_data: &[HashMap<String, String>],
+        hasher: H
+    ) -> Result<Self, ProofParseError> {
+        let proof_items = proof_data.iter().enumerate().map(|(item, fields)| {
+            let field = |field| fields.get(field).ok_or(ProofParseError::MissingField { item, field });
+            let hash = hex::decode(field("hash")?)
+                .map_err(|_| ProofParseError::InvalidHex { item, field: "hash" })?;
+            let is_left = match field("direction")?.as_str() {
+                "left" => true,
+                "right" => false,
+                direction => return Err(ProofParseError::UnknownDirection { item, direction: direction.to_string() }),
+            };
+            Ok(ProofItem { hash: hash.into(), is_left })
+        }).collect::<Result<_, _>>()?;
+
+        Ok(MerkleProof::new(leaf, proof_items, hasher))
+    }
+
+    /// Serializes the proof into a portable binary form
+    ///
+    /// Layout (integers little-endian): `u32` leaf length and the leaf,
+    /// `u32` item count, then per item a direction byte (1 = left,
+    /// 0 = right), a `u32` hash length and the hash.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let items_len: usize = self.proof_items.iter().map(|item| 5 + item.hash.len()).sum();
+        let mut bytes = Vec::with_capacity(8 + self.leaf.len() + items_len);
+
+        bytes.extend_from_slice(&(self.leaf.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&self.leaf);
+        bytes.extend_from_slice(&(self.proof_items.len() as u32).to_le_bytes());
+        for item in &self.proof_items {
+            bytes.push(item.is_left as u8);
+            bytes.extend_from_slice(&(item.hash.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(&item.hash);
+        }
+
+        bytes
+    }
+
+    /// Parses a proof written by [`MerkleProof::to_bytes`], using the given hasher
+    pub fn from_bytes(bytes: &[u8], hasher: H) -> Result<Self, MerkleTreeError> {
+        let mut rest = bytes;
+
+        let leaf = take_bytes(&mut rest)?.to_vec();
+        let count = take_u32(&mut rest)? as usize;
+        // Each item takes at least 5 bytes, which bounds the allocation
+        let mut proof_items = Vec::with_capacity(count.min(rest.len() / 5));
+        for _ in 0..count {
+            let (&direction, tail) = rest.split_first().ok_or(ProofParseError::Truncated)?;
+            rest = tail;
+            let is_left = match direction {
+                0 => false,
+                1 => true,
+                _ => return Err(ProofParseError::InvalidDirection(direction).into()),
+            };
+            let hash = ProofHash::from_slice(take_bytes(&mut rest)?);
+            proof_items.push(ProofItem { hash, is_left });
+        }
+
+        if !rest.is_empty() {
+            return Err(ProofParseError::TrailingBytes.into());
+        }
+
+        Ok(MerkleProof::new(leaf, proof_items, hasher))
+    }
+}
+
+/// A node whose hash changed in an update, addressed as in [`crate::tree::MerkleTree::get_node`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NodeChange {
+    pub level: usize,
+    pub index: LeafIndex,
+    /// The node's hash after the update
+    pub hash: Vec<u8>,
+}
+
+/// The nodes changed by [`crate::tree::MerkleTree::update_leaf_with_delta`]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ProofDelta {
+    /// The length of the tree's proofs
+    pub depth: usize,
+    /// The changed nodes, sorted by level and then index
+    pub changes: Vec<NodeChange>,
+}
+
+impl ProofDelta {
+    /// Gets the new hash of the node at `(level, index)`, if it changed
+    pub fn get(&self, level: usize, index: LeafIndex) -> Option<&[u8]> {
+        self.changes.binary_search_by_key(&(level, index), |change| (change.level, change.index))
+            .ok()
+            .map(|found| self.changes[found].hash.as_slice())
+    }
+}
+
+impl<H: Hasher> MerkleProof<H> {
+    /// Brings a proof of the leaf at `my_index` up to date with a [`ProofDelta`]
+    ///
+    /// Replaces the sibling hashes the update changed, and the leaf if it was
+    /// the one updated, so the proof verifies against the new root. Returns
+    /// whether anything changed; a proof the update didn't touch is left as
+    /// it was. Fails if the proof is for another tree's depth or another leaf.
+    pub fn apply_delta(&mut self, delta: &ProofDelta, my_index: LeafIndex) -> Result<bool, MerkleTreeError> {
+        if self.proof_items.len() != delta.depth {
+            return Err(MerkleTreeError::InvalidProofLength { expected: delta.depth, actual: self.proof_items.len() });
+        }
+        if self.path_index() != Some(my_index) {
+            return Err(MerkleTreeError::ProofIndexMismatch { index: my_index });
+        }
+        let mut patched = false;
+        if let Some(leaf) = delta.get(0, my_index) {
+            self.leaf = leaf.to_vec();
+            patched = true;
+        }
+        for (level, item) in self.proof_items.iter_mut().enumerate() {
+            if let Some(sibling) = delta.get(level, (my_index >> level) ^ 1) {
+                item.hash = ProofHash::from_slice(sibling);
+                patched = true;
+            }
+        }
+        Ok(patched)
+    }
+}
+
+/// A sibling hash borrowed from the tree, with its direction
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ProofItemRef<'a> {
+    /// The hash of the sibling node
+    pub hash: &'a [u8],
+    /// Whether the sibling is on the left (true) or right (false)
+    pub is_left: bool,
+}
+
+/// A Merkle proof that borrows its hashes from the tree it was generated from
+///
+/// Produced by [`crate::tree::MerkleTree::generate_proof_ref`]; it verifies
+/// without copying any node, and [`MerkleProofRef::to_owned`] turns it into a
+/// [`MerkleProof`] that can outlive the tree.
+#[derive(Clone, Debug)]
+pub struct MerkleProofRef<'a, H: Hasher> {
+    /// The leaf being proven
+    pub leaf: &'a [u8],
+    /// The proof items (sibling hashes and their positions)
+    pub proof_items: Vec<ProofItemRef<'a>>,
+    /// The hasher for the proof
+    pub hasher: &'a H,
+}
+
+impl<'a, H: Hasher> MerkleProofRef<'a, H> {
+    /// Calculates the root using the proof items with direction information
+    pub fn calculate_root(&self) -> Vec<u8> {
+        fold_root(
+            self.hasher,
+            self.leaf,
+            self.proof_items.iter().map(|item| (item.hash, item.is_left)),
+        )
+    }
+
+    /// Verifies the proof against a given root
+    pub fn verify(&self, root: &[u8]) -> bool {
+        self.calculate_root() == root
+    }
+
+    /// Copies the borrowed hashes into an owned proof
+    pub fn to_owned(&self) -> MerkleProof<H> {
+        let proof_items = self.proof_items.iter()
+            .map(|item| ProofItem { hash: ProofHash::from_slice(item.hash), is_left: item.is_left })
+            .collect();
+        MerkleProof::new(self.leaf.to_vec(), proof_items, self.hasher.clone())
+    }
+}
+
+/// Verifies each proof against the same root, returning the results in order
+pub fn verify_proofs<H: Hasher>(root: &[u8], proofs: &[MerkleProof<H>]) -> Vec<bool> {
+    proofs.iter().map(|proof| proof.verify(root)).collect()
+}
+
+/// The parallel counterpart of [`verify_proofs`]
+///
+/// Proofs are `Send + Sync` whenever their hasher is, so callers can also
+/// spread their own verification across threads.
+#[cfg(feature = "rayon")]
+pub fn verify_proofs_parallel<H: Hasher + Sync>(root: &[u8], proofs: &[MerkleProof<H>]) -> Vec<bool> {
+    use rayon::prelude::*;
+
+    proofs.par_iter().map(|proof| proof.verify(root)).collect()
+}
+
+/// Hashes a leaf up through `(sibling, is_left)` pairs to the root
+fn fold_root<'a, H: Hasher>(
+    hasher: &H,
+    leaf: &[u8],
+    items: impl Iterator<Item = (&'a [u8], bool)>
+) -> Vec<u8> {
+    // Two buffers are swapped level by level, so the built-in hashers
+    // allocate nothing after the first level
+    let mut current = leaf.to_vec();
+    let mut next = Vec::with_capacity(current.len());
+
+    for (sibling, is_left) in items {
+        if is_left {
+            // Sibling is left, current is right
+            hasher.hash_pair_into(sibling, &current, &mut next);
+        } else {
+            // Sibling is right, current is left
+            hasher.hash_pair_into(&current, sibling, &mut next);
+        }
+        core::mem::swap(&mut current, &mut next);
+    }
+
+    current
+}
+
+/// Reads a little-endian `u32` from the front of `bytes`
+fn take_u32(bytes: &mut &[u8]) -> Result<u32, ProofParseError> {
+    if bytes.len() < 4 {
+        return Err(ProofParseError::Truncated);
+    }
+    let (value, rest) = bytes.split_at(4);
+    *bytes = rest;
+    Ok(u32::from_le_bytes(value.try_into().unwrap()))
+}
+
+/// Reads a `u32` length-prefixed byte string from the front of `bytes`
+fn take_bytes<'a>(bytes: &mut &'a [u8]) -> Result<&'a [u8], ProofParseError> {
+    let len = take_u32(bytes)? as usize;
+    if bytes.len() < len {
+        return Err(ProofParseError::Truncated);
+    }
+    let (value, rest) = bytes.split_at(len);
+    *bytes = rest;
+    Ok(value)
+}
+
+
+/// Serde helpers for byte strings: hex in human-readable formats, raw bytes otherwise
+#[cfg(feature = "serde")]
+pub(crate) mod hex_bytes {
+    use alloc::vec::Vec;
+    use serde::de::{self, Deserializer, SeqAccess, Visitor};
+    use serde::Serializer;
+
+    /// The most bytes an array of bytes is given up front, enough for any built-in hash;
+    /// its length comes from the data and may claim gigabytes that aren't there
+    const MAX_PREALLOCATED: usize = 64;
+
+    pub fn serialize<T: AsRef<[u8]>, S: Serializer>(bytes: &T, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&hex::encode(bytes.as_ref()))
+        } else {
+            serializer.serialize_bytes(bytes.as_ref())
+        }
+    }
+
+    pub fn deserialize<'de, T: From<Vec<u8>>, D: Deserializer<'de>>(deserializer: D) -> Result<T, D::Error> {
+        struct BytesVisitor;
+
+        impl<'de> Visitor<'de> for BytesVisitor {
+            type Value = Vec<u8>;
+
+            fn expecting(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+                write!(f, "a hex string or a byte string")
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Vec<u8>, E> {
+                hex::decode(value).map_err(E::custom)
+            }
+
+            fn visit_bytes<E: de::Error>(self, value: &[u8]) -> Result<Vec<u8>, E> {
+                Ok(value.to_vec())
+            }
+
+            fn visit_byte_buf<E: de::Error>(self, value: Vec<u8>) -> Result<Vec<u8>, E> {
+                Ok(value)
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<u8>, A::Error> {
+                let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(MAX_PREALLOCATED));
+                while let Some(byte) = seq.next_element()? {
+                    bytes.push(byte);
+                }
+                Ok(bytes)
+            }
+        }
+
+        let bytes = if deserializer.is_human_readable() {
+            deserializer.deserialize_str(BytesVisitor)?
+        } else {
+            deserializer.deserialize_bytes(BytesVisitor)?
+        };
+        Ok(T::from(bytes))
+    }
+}
+
+/// Serde helpers mapping `is_left` to a `"left"`/`"right"` direction string
+#[cfg(feature = "serde")]
+mod direction {
+    use alloc::string::String;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(is_left: &bool, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(if *is_left { "left" } else { "right" })
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+        match String::deserialize(deserializer)?.as_str() {
+            "left" => Ok(true),
+            "right" => Ok(false),
+            other => Err(serde::de::Error::unknown_variant(other, &["left", "right"])),
+        }
+    }
+}
+
+/// The serialized fields of a proof; the hasher is supplied by the reader
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize)]
+struct ProofFields<'a> {
+    #[serde(with = "hex_bytes")]
+    leaf: &'a Vec<u8>,
+    proof: &'a [ProofItem],
+}
+
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct OwnedProofFields {
+    #[serde(with = "hex_bytes")]
+    pub(crate) leaf: Vec<u8>,
+    pub(crate) proof: Vec<ProofItem>,
+}
+
+/// Proofs serialize as `{"leaf": <hex>, "proof": [{"hash": <hex>, "direction": "left" | "right"}, ...]}`
+///
+/// This is the JSON proof format; the items match [`MerkleProof::to_debug_format`].
+/// Binary formats carry the hashes as byte strings instead of hex.
+#[cfg(feature = "serde")]
+impl<H: Hasher> serde::Serialize for MerkleProof<H> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ProofFields { leaf: &self.leaf, proof: &self.proof_items }.serialize(serializer)
+    }
+}
+
+/// Deserializing needs a default-constructible hasher; use [`MerkleProof::from_json`] otherwise
+#[cfg(feature = "serde")]
+impl<'de, H: Hasher + Default> serde::Deserialize<'de> for MerkleProof<H> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let fields = OwnedProofFields::deserialize(deserializer)?;
+        Ok(MerkleProof::new(fields.leaf, fields.proof, H::default()))
+    }
+}
+
+#[cfg(feature = "json")]
+use alloc::string::String;
+
+#[cfg(feature = "json")]
+impl<H: Hasher> MerkleProof<H> {
+    /// Converts the proof to the JSON proof format
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("proof serialization cannot fail")
+    }
+
+    /// Parses a proof in the JSON proof format, using the given hasher
+    pub fn from_json(json: &str, hasher: H) -> Result<Self, serde_json::Error> {
+        let fields: OwnedProofFields = serde_json::from_str(json)?;
+        Ok(MerkleProof::new(fields.leaf, fields.proof, hasher))
+    }
 }
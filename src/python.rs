@@ -0,0 +1,234 @@
+//! PyO3 bindings exposing a `MerkleTree` class and a `verify` function
+//!
+//! Proofs are dicts in the JSON proof format:
+//! `{"leaf": <hex>, "proof": [{"hash": <hex>, "direction": "left" | "right"}, ...]}`.
+//! Build the extension module with maturin, enabling the `python` feature and
+//! `pyo3/extension-module`.
+use pyo3::exceptions::{PyIndexError, PyKeyError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+use crate::hasher::{BuiltinHasher, Hasher};
+use crate::index::LeafIndex;
+use crate::proof::{MerkleProof, ProofItem};
+use crate::tree::MerkleTree;
+
+/// A leaf given from Python as `bytes` or `str` (encoded as UTF-8)
+#[derive(FromPyObject)]
+enum LeafInput {
+    Bytes(Vec<u8>),
+    Str(String),
+}
+
+impl LeafInput {
+    fn into_bytes(self) -> Vec<u8> {
+        match self {
+            LeafInput::Bytes(bytes) => bytes,
+            LeafInput::Str(string) => string.into_bytes(),
+        }
+    }
+}
+
+/// Errors from the binding glue, mapped onto Python exceptions
+#[derive(Debug, PartialEq)]
+enum BindingError {
+    UnknownHasher(String),
+    EmptyLeaves,
+    IndexOutOfBounds(LeafIndex),
+    NotFound,
+    InvalidProof(String),
+}
+
+impl From<BindingError> for PyErr {
+    fn from(err: BindingError) -> PyErr {
+        match err {
+            BindingError::UnknownHasher(name) => PyValueError::new_err(format!("Unknown hasher '{}'", name)),
+            BindingError::EmptyLeaves => PyValueError::new_err("Cannot create a Merkle tree with no leaves"),
+            BindingError::IndexOutOfBounds(index) => PyIndexError::new_err(format!("Leaf index {} out of bounds", index)),
+            BindingError::NotFound => PyKeyError::new_err("Leaf value not found in the tree"),
+            BindingError::InvalidProof(reason) => PyValueError::new_err(format!("Invalid proof: {}", reason)),
+        }
+    }
+}
+
+fn hasher_by_name(name: &str) -> Result<BuiltinHasher, BindingError> {
+    BuiltinHasher::from_name(name).ok_or_else(|| BindingError::UnknownHasher(name.to_string()))
+}
+
+fn build_tree(leaves: Vec<Vec<u8>>, hasher_name: &str) -> Result<MerkleTree<BuiltinHasher>, BindingError> {
+    let hasher = hasher_by_name(hasher_name)?;
+    if leaves.is_empty() {
+        return Err(BindingError::EmptyLeaves);
+    }
+    let leaves = leaves.iter().map(|leaf| hasher.hash_leaf(leaf)).collect();
+    Ok(MerkleTree::new(leaves, hasher))
+}
+
+/// Rebuilds a proof from its hex leaf and `(hash, direction)` items
+fn parse_proof(
+    leaf_hex: &str,
+    items: Vec<(String, String)>,
+    hasher: BuiltinHasher
+) -> Result<MerkleProof<BuiltinHasher>, BindingError> {
+    let leaf = hex::decode(leaf_hex).map_err(|err| BindingError::InvalidProof(format!("leaf: {}", err)))?;
+    let proof_items = items.into_iter()
+        .enumerate()
+        .map(|(index, (hash, direction))| {
+            let hash = hex::decode(hash)
+                .map_err(|err| BindingError::InvalidProof(format!("item {} hash: {}", index, err)))?;
+            let is_left = match direction.as_str() {
+                "left" => true,
+                "right" => false,
+                other => return Err(BindingError::InvalidProof(format!("item {} direction '{}'", index, other))),
+            };
+            Ok(ProofItem { hash: hash.into(), is_left })
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(MerkleProof::new(leaf, proof_items, hasher))
+}
+
+fn proof_to_dict<'py>(py: Python<'py>, proof: &MerkleProof<BuiltinHasher>) -> PyResult<Bound<'py, PyDict>> {
+    let items = PyList::empty(py);
+    for item in &proof.proof_items {
+        let entry = PyDict::new(py);
+        entry.set_item("hash", hex::encode(&item.hash))?;
+        entry.set_item("direction", if item.is_left { "left" } else { "right" })?;
+        items.append(entry)?;
+    }
+
+    let dict = PyDict::new(py);
+    dict.set_item("leaf", hex::encode(&proof.leaf))?;
+    dict.set_item("proof", items)?;
+    Ok(dict)
+}
+
+/// A Merkle tree over raw leaves, hashed with a built-in hasher
+#[pyclass(name = "MerkleTree", module = "merkle_tree")]
+pub struct PyMerkleTree {
+    tree: MerkleTree<BuiltinHasher>,
+}
+
+#[pymethods]
+impl PyMerkleTree {
+    /// Builds a tree from a list of `bytes` or `str` leaves
+    #[new]
+    #[pyo3(signature = (leaves, hasher = "sha256"))]
+    fn new(leaves: Vec<LeafInput>, hasher: &str) -> PyResult<Self> {
+        let leaves = leaves.into_iter().map(LeafInput::into_bytes).collect();
+        Ok(PyMerkleTree { tree: build_tree(leaves, hasher)? })
+    }
+
+    /// The root as a hex string
+    fn root(&self) -> String {
+        hex::encode(self.tree.root())
+    }
+
+    /// The proof for the leaf at `index`
+    fn prove<'py>(&self, py: Python<'py>, index: LeafIndex) -> PyResult<Bound<'py, PyDict>> {
+        let proof = self.tree.generate_proof(index)
+            .map_err(|_| BindingError::IndexOutOfBounds(index))?;
+        proof_to_dict(py, &proof)
+    }
+
+    /// The proof for a raw leaf value
+    fn prove_value<'py>(&self, py: Python<'py>, value: LeafInput) -> PyResult<Bound<'py, PyDict>> {
+        let leaf = self.tree.get_hasher().hash_leaf(&value.into_bytes());
+        let proof = self.tree.generate_proof_by_value(&leaf)
+            .map_err(|_| BindingError::NotFound)?;
+        proof_to_dict(py, &proof)
+    }
+
+    fn __len__(&self) -> usize {
+        self.tree.unpadded_leaf_count
+    }
+}
+
+/// Verifies a proof dict against a hex root
+#[pyfunction]
+#[pyo3(signature = (root, proof, hasher_name = "sha256"))]
+fn verify(root: &str, proof: &Bound<'_, PyDict>, hasher_name: &str) -> PyResult<bool> {
+    let hasher = hasher_by_name(hasher_name)?;
+    let root = hex::decode(root).map_err(|err| PyValueError::new_err(format!("Invalid root: {}", err)))?;
+
+    let leaf: String = proof.get_item("leaf")?
+        .ok_or_else(|| BindingError::InvalidProof("missing 'leaf'".to_string()))?
+        .extract()?;
+    let entries: Vec<Bound<'_, PyDict>> = proof.get_item("proof")?
+        .ok_or_else(|| BindingError::InvalidProof("missing 'proof'".to_string()))?
+        .extract()?;
+    let mut items = Vec::with_capacity(entries.len());
+    for (index, entry) in entries.iter().enumerate() {
+        let field = |name: &str| -> PyResult<String> {
+            entry.get_item(name)?
+                .ok_or_else(|| BindingError::InvalidProof(format!("item {} missing '{}'", index, name)))?
+                .extract()
+        };
+        items.push((field("hash")?, field("direction")?));
+    }
+
+    Ok(parse_proof(&leaf, items, hasher)?.verify(&root))
+}
+
+/// The `merkle_tree` Python module
+#[pymodule]
+fn merkle_tree(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyMerkleTree>()?;
+    module.add_function(wrap_pyfunction!(verify, module)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CString;
+
+    #[test]
+    fn test_glue_errors() {
+        assert_eq!(build_tree(Vec::new(), "sha256").err(), Some(BindingError::EmptyLeaves));
+        assert!(matches!(build_tree(vec![b"a".to_vec()], "md5"), Err(BindingError::UnknownHasher(_))));
+
+        let hasher = BuiltinHasher::from_name("sha256").unwrap();
+        let bad_direction = vec![("00".to_string(), "up".to_string())];
+        assert!(matches!(parse_proof("00", bad_direction, hasher.clone()), Err(BindingError::InvalidProof(_))));
+        assert!(matches!(parse_proof("zz", Vec::new(), hasher), Err(BindingError::InvalidProof(_))));
+    }
+
+    #[test]
+    fn test_python_round_trip() {
+        Python::initialize();
+        Python::attach(|py| {
+            let module = PyModule::new(py, "merkle_tree").unwrap();
+            merkle_tree(&module).unwrap();
+            let globals = PyDict::new(py);
+            globals.set_item("merkle_tree", module).unwrap();
+
+            let script = CString::new(r#"
+tree = merkle_tree.MerkleTree(["alice", b"bob", "carol"], "sha256")
+assert len(tree) == 3
+root = tree.root()
+for index in range(3):
+    assert merkle_tree.verify(root, tree.prove(index), "sha256")
+
+proof = tree.prove_value("bob")
+assert set(proof) == {"leaf", "proof"}
+assert proof["proof"][0]["direction"] in ("left", "right")
+assert merkle_tree.verify(root, proof)
+assert not merkle_tree.verify(root, proof, "blake2b256")
+
+for call, error in [
+    (lambda: tree.prove(10), IndexError),
+    (lambda: tree.prove_value("mallory"), KeyError),
+    (lambda: merkle_tree.MerkleTree([], "sha256"), ValueError),
+    (lambda: merkle_tree.MerkleTree(["a"], "md5"), ValueError),
+    (lambda: merkle_tree.verify(root, {"leaf": proof["leaf"]}), ValueError),
+]:
+    try:
+        call()
+    except error:
+        pass
+    else:
+        raise AssertionError("expected " + error.__name__)
+"#).unwrap();
+            py.run(&script, Some(&globals), None).unwrap();
+        });
+    }
+}
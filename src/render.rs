@@ -0,0 +1,211 @@
+//! Text renderings of a tree for documentation and debugging
+//!
+//! [`MerkleTree::to_dot`] writes a Graphviz digraph, optionally marking the
+//! path of one leaf's proof. [`MerkleTree::render_ascii`] draws the tree
+//! for a terminal, and is what the tree's `Display` prints.
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::{self, Write};
+use crate::error::MerkleTreeError;
+use crate::hasher::Hasher;
+use crate::index::LeafIndex;
+use crate::store::NodeStore;
+use crate::tree::MerkleTree;
+
+/// Options for [`MerkleTree::to_dot`]
+#[derive(Clone, Copy, Debug)]
+pub struct DotOptions {
+    /// Hex characters of each hash to show in its label; 0 shows the whole hash
+    pub hash_chars: usize,
+    /// A leaf whose proof path is drawn in red, with its siblings in blue
+    pub highlight: Option<LeafIndex>,
+    /// The most nodes to render; larger trees are an error
+    pub max_nodes: usize,
+}
+
+impl Default for DotOptions {
+    fn default() -> Self {
+        DotOptions {
+            hash_chars: 8,
+            highlight: None,
+            max_nodes: 1023,
+        }
+    }
+}
+
+/// Options for [`MerkleTree::render_ascii`]
+#[derive(Clone, Copy, Debug)]
+pub struct AsciiOptions {
+    /// Hex characters of each hash to show; 0 shows the whole hash
+    pub hash_chars: usize,
+    /// The widest level to draw; the levels below the first wider one are summarized
+    pub max_width: usize,
+}
+
+impl Default for AsciiOptions {
+    fn default() -> Self {
+        AsciiOptions {
+            hash_chars: 6,
+            max_width: 16,
+        }
+    }
+}
+
+/// Encodes `hash` as hex, keeping the first `chars` characters unless `chars` is 0
+pub(crate) fn short_hex(hash: &[u8], chars: usize) -> String {
+    let mut hex = hex::encode(hash);
+    if chars > 0 && chars < hex.len() {
+        hex.truncate(chars);
+    }
+    hex
+}
+
+impl<H: Hasher, S: NodeStore> MerkleTree<H, S> {
+    /// Renders the tree as a Graphviz DOT digraph
+    ///
+    /// Each node is `n<level>_<index>`, with the leaves on level 0, labeled
+    /// with its truncated hash; edges run from parents to children and the
+    /// leaves share a rank. Padding leaves are dashed. Fails if the tree has
+    /// more than `options.max_nodes` nodes, or if the highlighted leaf has
+    /// no proof.
+    pub fn to_dot(&self, options: &DotOptions) -> Result<String, MerkleTreeError> {
+        let leaf_count = self.leaf_count;
+        let nodes = 2 * leaf_count - 1;
+        if nodes > options.max_nodes {
+            return Err(MerkleTreeError::TooManyNodes { nodes, max: options.max_nodes });
+        }
+        let highlight = options.highlight.map(|leaf| self.check_provable(leaf)).transpose()?;
+        let levels = leaf_count.trailing_zeros() as usize + 1;
+        let on_path = |level: usize, index: usize| highlight.is_some_and(|leaf| leaf >> level == index);
+        let is_sibling = |level: usize, index: usize| {
+            level + 1 < levels && highlight.is_some_and(|leaf| (leaf >> level) ^ 1 == index)
+        };
+
+        let mut dot = String::new();
+        dot.push_str("digraph merkle_tree {\n");
+        dot.push_str("  node [shape=box, fontname=\"monospace\"];\n");
+        for level in (0..levels).rev() {
+            for index in 0..leaf_count >> level {
+                let node = self.get_node(level, index as LeafIndex).ok_or(MerkleTreeError::MissingNode { level, index: index as LeafIndex })?;
+                let mut attributes = format!("label=\"{}\"", short_hex(&node, options.hash_chars));
+                if level == 0 && index >= self.unpadded_leaf_count {
+                    attributes.push_str(", style=dashed");
+                }
+                if on_path(level, index) {
+                    attributes.push_str(", color=red");
+                } else if is_sibling(level, index) {
+                    attributes.push_str(", color=blue");
+                }
+                let _ = writeln!(dot, "  n{}_{} [{}];", level, index, attributes);
+            }
+        }
+
+        dot.push_str("  { rank=same;");
+        for index in 0..leaf_count {
+            let _ = write!(dot, " n0_{};", index);
+        }
+        dot.push_str(" }\n");
+
+        for level in (1..levels).rev() {
+            for index in 0..leaf_count >> level {
+                for child in [2 * index, 2 * index + 1] {
+                    let style = if on_path(level - 1, child) { " [color=red, penwidth=2]" } else { "" };
+                    let _ = writeln!(dot, "  n{}_{} -> n{}_{}{};", level, index, level - 1, child, style);
+                }
+            }
+        }
+        dot.push_str("}\n");
+        Ok(dot)
+    }
+
+    /// Draws the tree level by level, root first, with box-drawing connectors
+    ///
+    /// Padding leaves are shown in parentheses. Levels with more than
+    /// `options.max_width` nodes aren't drawn; a line under the last drawn
+    /// level says how many were left out.
+    pub fn render_ascii(&self, options: &AsciiOptions) -> String {
+        let leaf_count = self.leaf_count;
+        let levels = leaf_count.trailing_zeros() as usize + 1;
+        // The lowest drawn level; its nodes each get one cell
+        let bottom = (0..levels)
+            .find(|&level| leaf_count >> level <= options.max_width.max(1))
+            .unwrap_or(levels - 1);
+
+        // Labels of the drawn levels, bottom first; leaves needn't be the
+        // hasher's output size, so the cells fit the widest label
+        let labels: Vec<Vec<String>> = (bottom..levels)
+            .map(|level| {
+                (0..leaf_count >> level)
+                    .map(|index| {
+                        let hash = self
+                            .get_node(level, index as LeafIndex)
+                            .map_or_else(|| String::from("?"), |node| short_hex(&node, options.hash_chars));
+                        if level == 0 && index >= self.unpadded_leaf_count {
+                            format!("({})", hash)
+                        } else {
+                            hash
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        let label_len = labels.iter().flatten().map(String::len).max().unwrap_or(0);
+        let cell = label_len + 3;
+        let width = (leaf_count >> bottom) * cell;
+        // The column under the middle of a node's label
+        let center = |level: usize, index: usize| {
+            let span = 1 << (level - bottom);
+            (2 * index + 1) * span * cell / 2
+        };
+
+        let mut lines = Vec::new();
+        for level in (bottom..levels).rev() {
+            let mut line = vec![' '; width];
+            for (index, text) in labels[level - bottom].iter().enumerate() {
+                let start = center(level, index) - text.len() / 2;
+                for (column, ch) in text.chars().enumerate() {
+                    line[start + column] = ch;
+                }
+            }
+            lines.push(line);
+
+            if level > bottom {
+                let mut connectors = vec![' '; width];
+                for index in 0..leaf_count >> level {
+                    let (left, right) = (center(level - 1, 2 * index), center(level - 1, 2 * index + 1));
+                    connectors[left] = '┌';
+                    connectors[left + 1..right].fill('─');
+                    connectors[right] = '┐';
+                    connectors[center(level, index)] = '┴';
+                }
+                lines.push(connectors);
+            }
+        }
+
+        let mut out = String::new();
+        for line in lines {
+            let line: String = line.into_iter().collect();
+            out.push_str(line.trim_end());
+            out.push('\n');
+        }
+        if bottom > 0 {
+            let _ = writeln!(
+                out,
+                "... {} more levels down to {} leaves ({} with padding)",
+                bottom,
+                self.unpadded_leaf_count(),
+                leaf_count,
+            );
+        }
+        out
+    }
+}
+
+/// Draws the tree with the default [`AsciiOptions`]
+impl<H: Hasher, S: NodeStore> fmt::Display for MerkleTree<H, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.render_ascii(&AsciiOptions::default()))
+    }
+}
@@ -0,0 +1,172 @@
+//! Salted leaves, so a published root doesn't let outsiders test membership
+//!
+//! Anyone holding the root of a plain allowlist tree can hash a candidate
+//! and check it against a proof. A [`SaltedTreeBuilder`] instead hashes each
+//! leaf as `hash_leaf(salt || data)`, with a 32-byte salt derived by
+//! HKDF-SHA256 from a master secret and the leaf's index or key. Each
+//! [`SaltedProof`] carries only its own salt, so its owner can check it while
+//! the master secret stays with the builder; it's never stored in the tree.
+use ring::hkdf::{KeyType, Prk, Salt, HKDF_SHA256};
+use crate::error::MerkleTreeError;
+use crate::hasher::Hasher;
+use crate::index::{self, LeafIndex};
+use crate::proof::MerkleProof;
+use crate::tree::MerkleTree;
+
+/// The HKDF salt, fixing the derivation to this scheme
+const HKDF_DOMAIN: &[u8] = b"merkle_tree.salted.v1";
+const INDEX_INFO: &[u8] = b"merkle_tree.salt.index\0";
+const KEY_INFO: &[u8] = b"merkle_tree.salt.key\0";
+
+/// The length of a derived salt
+pub const SALT_LEN: usize = 32;
+
+struct SaltLen;
+
+impl KeyType for SaltLen {
+    fn len(&self) -> usize {
+        SALT_LEN
+    }
+}
+
+fn expand(prk: &Prk, info: &[&[u8]]) -> [u8; SALT_LEN] {
+    let mut salt = [0u8; SALT_LEN];
+    prk.expand(info, SaltLen)
+        .and_then(|okm| okm.fill(&mut salt))
+        .expect("32 bytes is within HKDF-SHA256's output limit");
+    salt
+}
+
+/// Hashes a leaf with its salt: `hash_leaf(salt || data)`
+pub fn hash_salted_leaf<H: Hasher>(hasher: &H, salt: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut salted = Vec::with_capacity(salt.len() + data.len());
+    salted.extend_from_slice(salt);
+    salted.extend_from_slice(data);
+    hasher.hash_leaf(&salted)
+}
+
+/// Builds trees of salted leaves from a master secret
+///
+/// With the `zeroize` feature the master secret is overwritten when the
+/// builder is dropped or zeroized; a zeroized builder derives salts from an
+/// empty secret, so its trees no longer match.
+pub struct SaltedTreeBuilder<H: Hasher> {
+    master_secret: Vec<u8>,
+    hasher: H,
+}
+
+impl<H: Hasher> SaltedTreeBuilder<H> {
+    /// Creates a builder that derives salts from `master_secret`
+    pub fn new(master_secret: &[u8], hasher: H) -> Self {
+        SaltedTreeBuilder { master_secret: master_secret.to_vec(), hasher }
+    }
+
+    fn prk(&self) -> Prk {
+        Salt::new(HKDF_SHA256, HKDF_DOMAIN).extract(&self.master_secret)
+    }
+
+    /// Derives the salt for the leaf at `index`
+    pub fn salt_for_index(&self, index: LeafIndex) -> [u8; SALT_LEN] {
+        expand(&self.prk(), &[INDEX_INFO, &index.to_le_bytes()])
+    }
+
+    /// Derives the salt for the leaf identified by `key`
+    pub fn salt_for_key(&self, key: &[u8]) -> [u8; SALT_LEN] {
+        expand(&self.prk(), &[KEY_INFO, key])
+    }
+
+    /// Builds a tree over `data` in the given order, salting each item by its index
+    pub fn build<T: AsRef<[u8]>>(&self, data: &[T]) -> Result<SaltedTree<H>, MerkleTreeError> {
+        let prk = self.prk();
+        let salts = (0..data.len() as LeafIndex)
+            .map(|index| expand(&prk, &[INDEX_INFO, &index.to_le_bytes()]))
+            .collect();
+        self.build_with_salts(data.iter().map(AsRef::as_ref), salts)
+    }
+
+    /// Builds a tree over `(key, data)` entries in the given order, salting each item by its key
+    ///
+    /// A key's salt doesn't depend on where the entry sits, so it stays the
+    /// same as the list is reordered or grows.
+    pub fn build_keyed<K: AsRef<[u8]>, T: AsRef<[u8]>>(&self, entries: &[(K, T)]) -> Result<SaltedTree<H>, MerkleTreeError> {
+        let prk = self.prk();
+        let salts = entries.iter().map(|(key, _)| expand(&prk, &[KEY_INFO, key.as_ref()])).collect();
+        self.build_with_salts(entries.iter().map(|(_, data)| data.as_ref()), salts)
+    }
+
+    fn build_with_salts<'a>(
+        &self,
+        data: impl Iterator<Item = &'a [u8]>,
+        salts: Vec<[u8; SALT_LEN]>,
+    ) -> Result<SaltedTree<H>, MerkleTreeError> {
+        if salts.is_empty() {
+            return Err(MerkleTreeError::EmptyLeaves);
+        }
+        let leaves = data.zip(&salts).map(|(item, salt)| hash_salted_leaf(&self.hasher, salt, item)).collect();
+        Ok(SaltedTree { tree: MerkleTree::new_ordered(leaves, self.hasher.clone()), salts })
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<H: Hasher> zeroize::Zeroize for SaltedTreeBuilder<H> {
+    fn zeroize(&mut self) {
+        self.master_secret.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<H: Hasher> Drop for SaltedTreeBuilder<H> {
+    fn drop(&mut self) {
+        zeroize::Zeroize::zeroize(self);
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<H: Hasher> zeroize::ZeroizeOnDrop for SaltedTreeBuilder<H> {}
+
+/// A tree of salted leaves with the salts needed to hand out proofs
+///
+/// The tree holds only salted leaf hashes, so it can be serialized and
+/// published like any other; the salts stay here.
+pub struct SaltedTree<H: Hasher> {
+    tree: MerkleTree<H>,
+    salts: Vec<[u8; SALT_LEN]>,
+}
+
+impl<H: Hasher> SaltedTree<H> {
+    /// Gets the underlying tree of salted leaves
+    pub fn tree(&self) -> &MerkleTree<H> {
+        &self.tree
+    }
+
+    /// Gets the root of the tree
+    pub fn root(&self) -> &[u8] {
+        self.tree.root()
+    }
+
+    /// Gets the salt of the leaf at `index`
+    pub fn salt(&self, index: LeafIndex) -> Option<&[u8; SALT_LEN]> {
+        self.salts.get(index::to_usize(index)?)
+    }
+
+    /// Generates the proof of the leaf at `index`, with its salt, for the leaf's owner
+    pub fn generate_proof(&self, index: LeafIndex) -> Result<SaltedProof<H>, MerkleTreeError> {
+        let proof = self.tree.generate_proof(index)?;
+        // Every provable index has a salt
+        let salt = self.salt(index).ok_or(MerkleTreeError::PaddingLeaf { index })?;
+        Ok(SaltedProof { salt: salt.to_vec(), proof })
+    }
+}
+
+/// A proof of a salted leaf, with the salt its owner needs to check it
+pub struct SaltedProof<H: Hasher> {
+    pub salt: Vec<u8>,
+    pub proof: MerkleProof<H>,
+}
+
+impl<H: Hasher> SaltedProof<H> {
+    /// Checks that `data` salted with this proof's salt is the proven leaf, and that the proof leads to `root`
+    pub fn verify(&self, data: &[u8], root: &[u8]) -> bool {
+        hash_salted_leaf(&self.proof.hasher, &self.salt, data) == self.proof.leaf && self.proof.verify(root)
+    }
+}
@@ -0,0 +1,129 @@
+//! An HTTP service handing out proofs from a shared tree
+//!
+//! [`router`] builds an `axum::Router` over an `Arc<MerkleTree<H>>` owned by
+//! the embedding application:
+//!
+//! | route                   | response                                                 |
+//! |-------------------------|----------------------------------------------------------|
+//! | `GET /root`             | `{"root": <hex>, "leaf_count": <leaves without padding>}` |
+//! | `GET /proof/{index}`    | the proof of the leaf at `index`, in the JSON proof format |
+//! | `POST /proof/by-value`  | the proof of the leaf hashed from the body               |
+//! | `POST /verify`          | `{"valid": <bool>}` for a JSON proof against the root    |
+//!
+//! The `by-value` body is hex, or raw bytes when sent as
+//! `application/octet-stream`. Errors are `{"error": <message>}` with status
+//! 400 for a bad index or hex body, 404 for a leaf that isn't in the tree or
+//! an unknown route, 405 for the wrong method, 413 for a body over
+//! [`MAX_BODY_BYTES`] and 422 for a proof that doesn't parse.
+//!
+//! Nest the router under a prefix with `Router::nest`, or serve it with
+//! `axum::serve` (axum's `tokio` feature).
+use std::sync::Arc;
+use axum::body::Bytes;
+use axum::extract::rejection::{BytesRejection, PathRejection};
+use axum::extract::{DefaultBodyLimit, Path, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::Router;
+use serde_json::json;
+use crate::error::MerkleTreeError;
+use crate::hasher::Hasher;
+use crate::proof::MerkleProof;
+use crate::tree::MerkleTree;
+
+/// The largest request body the service reads
+pub const MAX_BODY_BYTES: usize = 1 << 20;
+
+type Tree<H> = State<Arc<MerkleTree<H>>>;
+
+/// Builds the proof service's router over `tree`
+pub fn router<H: Hasher + Send + Sync + 'static>(tree: Arc<MerkleTree<H>>) -> Router {
+    Router::new()
+        .route("/root", get(root::<H>))
+        .route("/proof/{index}", get(proof_at::<H>))
+        .route("/proof/by-value", post(proof_by_value::<H>))
+        .route("/verify", post(verify::<H>))
+        .fallback(|| async { error(StatusCode::NOT_FOUND, "not found") })
+        .method_not_allowed_fallback(|| async { error(StatusCode::METHOD_NOT_ALLOWED, "method not allowed") })
+        .layer(DefaultBodyLimit::max(MAX_BODY_BYTES))
+        .with_state(tree)
+}
+
+fn json_response(status: StatusCode, body: String) -> Response {
+    (status, [(header::CONTENT_TYPE, "application/json")], body).into_response()
+}
+
+fn error(status: StatusCode, message: &str) -> Response {
+    json_response(status, json!({ "error": message }).to_string())
+}
+
+/// A body that couldn't be read: 413 when it is over the limit
+fn body_error(rejection: BytesRejection) -> Response {
+    match rejection.status() {
+        StatusCode::PAYLOAD_TOO_LARGE => error(StatusCode::PAYLOAD_TOO_LARGE, "body too large"),
+        _ => error(StatusCode::BAD_REQUEST, "cannot read the body"),
+    }
+}
+
+async fn root<H: Hasher>(State(tree): Tree<H>) -> Response {
+    let body = json!({
+        "root": hex::encode(tree.root()),
+        "leaf_count": tree.unpadded_leaf_count(),
+    });
+    json_response(StatusCode::OK, body.to_string())
+}
+
+async fn proof_at<H: Hasher>(State(tree): Tree<H>, index: Result<Path<u64>, PathRejection>) -> Response {
+    let Ok(Path(index)) = index else {
+        return error(StatusCode::BAD_REQUEST, "leaf index is not a number");
+    };
+    proof_response(tree.generate_proof(index))
+}
+
+async fn proof_by_value<H: Hasher>(
+    State(tree): Tree<H>,
+    headers: HeaderMap,
+    body: Result<Bytes, BytesRejection>,
+) -> Response {
+    let body = match body {
+        Ok(body) => body,
+        Err(rejection) => return body_error(rejection),
+    };
+    let raw = headers.get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|content_type| content_type.starts_with("application/octet-stream"));
+    let value = if raw {
+        body.to_vec()
+    } else {
+        match core::str::from_utf8(&body).ok().and_then(|text| hex::decode(text.trim()).ok()) {
+            Some(value) => value,
+            None => return error(StatusCode::BAD_REQUEST, "body is not hex"),
+        }
+    };
+    proof_response(crate::utils::prove_element(&tree, value))
+}
+
+async fn verify<H: Hasher>(State(tree): Tree<H>, body: Result<Bytes, BytesRejection>) -> Response {
+    let body = match body {
+        Ok(body) => body,
+        Err(rejection) => return body_error(rejection),
+    };
+    let Ok(json) = core::str::from_utf8(&body) else {
+        return error(StatusCode::UNPROCESSABLE_ENTITY, "proof is not UTF-8");
+    };
+    match MerkleProof::from_json(json, tree.get_hasher()) {
+        Ok(proof) => json_response(StatusCode::OK, json!({ "valid": tree.verify_proof_root_only(&proof) }).to_string()),
+        Err(err) => error(StatusCode::UNPROCESSABLE_ENTITY, &format!("invalid proof: {}", err)),
+    }
+}
+
+fn proof_response<H: Hasher>(proof: Result<MerkleProof<H>, MerkleTreeError>) -> Response {
+    match proof {
+        Ok(proof) => json_response(StatusCode::OK, proof.to_json()),
+        Err(err @ (MerkleTreeError::LeafIndexOutOfBounds { .. }
+            | MerkleTreeError::PaddingLeaf { .. }
+            | MerkleTreeError::LeafNotFound)) => error(StatusCode::NOT_FOUND, &err.to_string()),
+        Err(err) => error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string()),
+    }
+}
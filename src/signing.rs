@@ -0,0 +1,81 @@
+//! Ed25519-signed tree heads
+//!
+//! A [`SignedRoot`] is a root with its leaf count and a timestamp, signed by
+//! the tree's publisher, so clients can trust a root that reached them over
+//! an unauthenticated channel. The signature covers
+//! [`SignedRoot::signed_payload`], a fixed byte encoding, so signing the
+//! same head twice gives the same signature.
+//!
+//! Keys are `ed25519_dalek`'s, re-exported here so callers can use the same
+//! version the crate signs with.
+use std::time::{SystemTime, UNIX_EPOCH};
+use ed25519_dalek::{Signature, Signer};
+pub use ed25519_dalek::{SigningKey, VerifyingKey};
+use crate::hasher::Hasher;
+use crate::store::NodeStore;
+use crate::tree::MerkleTree;
+
+/// The domain string at the start of every signed payload
+const PAYLOAD_DOMAIN: &[u8] = b"merkle_tree.signed_root.v1\0";
+
+/// A root, its leaf count and the time it was signed, with the publisher's signature
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(deny_unknown_fields))]
+pub struct SignedRoot {
+    #[cfg_attr(feature = "serde", serde(with = "crate::proof::hex_bytes"))]
+    pub root: Vec<u8>,
+    /// The number of leaves given on construction, padding excluded
+    pub leaf_count: u64,
+    /// Seconds since the Unix epoch
+    pub timestamp: u64,
+    /// The 64-byte Ed25519 signature of [`SignedRoot::signed_payload`]
+    #[cfg_attr(feature = "serde", serde(with = "crate::proof::hex_bytes"))]
+    pub signature: Vec<u8>,
+}
+
+impl SignedRoot {
+    /// Encodes the signed fields as they are signed
+    ///
+    /// Layout (integers little-endian): the domain string
+    /// `merkle_tree.signed_root.v1\0`, `u32` root length, the root, `u64`
+    /// leaf count, `u64` timestamp.
+    pub fn signed_payload(&self) -> Vec<u8> {
+        payload(&self.root, self.leaf_count, self.timestamp)
+    }
+
+    /// Checks the signature against `key`, rejecting non-canonical signatures and weak keys
+    pub fn verify(&self, key: &VerifyingKey) -> bool {
+        Signature::from_slice(&self.signature)
+            .is_ok_and(|signature| key.verify_strict(&self.signed_payload(), &signature).is_ok())
+    }
+
+    /// Checks whether the signed head is for `tree`: the same root and leaf count
+    pub fn matches<H: Hasher, S: NodeStore>(&self, tree: &MerkleTree<H, S>) -> bool {
+        self.root == tree.root() && self.leaf_count == tree.unpadded_leaf_count()
+    }
+}
+
+fn payload(root: &[u8], leaf_count: u64, timestamp: u64) -> Vec<u8> {
+    let mut payload = Vec::with_capacity(PAYLOAD_DOMAIN.len() + 4 + root.len() + 16);
+    payload.extend_from_slice(PAYLOAD_DOMAIN);
+    payload.extend_from_slice(&(root.len() as u32).to_le_bytes());
+    payload.extend_from_slice(root);
+    payload.extend_from_slice(&leaf_count.to_le_bytes());
+    payload.extend_from_slice(&timestamp.to_le_bytes());
+    payload
+}
+
+/// Signs the tree's root and leaf count with the current time
+pub fn sign_root<H: Hasher, S: NodeStore>(tree: &MerkleTree<H, S>, key: &SigningKey) -> SignedRoot {
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs());
+    sign_root_at(tree, key, timestamp)
+}
+
+/// Signs the tree's root and leaf count with the given timestamp, in seconds since the Unix epoch
+pub fn sign_root_at<H: Hasher, S: NodeStore>(tree: &MerkleTree<H, S>, key: &SigningKey, timestamp: u64) -> SignedRoot {
+    let root = tree.root().to_vec();
+    let leaf_count = tree.unpadded_leaf_count();
+    let signature = key.sign(&payload(&root, leaf_count, timestamp)).to_vec();
+    SignedRoot { root, leaf_count, timestamp, signature }
+}
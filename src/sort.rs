@@ -0,0 +1,93 @@
+//! Leaf sorting for the sorted constructors
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Below this many leaves a comparison sort is faster than setting up the radix passes
+const RADIX_SORT_MIN_LEAVES: usize = 1 << 16;
+
+/// Sorts the leaves into the same order as `sort_unstable`
+///
+/// Large sets of leaves that are all the same length, as hashes are, take a
+/// radix sort on their first 8 bytes; the rest use a comparison sort, in
+/// parallel with the `rayon` feature. Equal leaves are identical, so every
+/// path gives the same order.
+pub(crate) fn sort_leaves(leaves: &mut [Vec<u8>]) {
+    let fixed_size = leaves.first().is_some_and(|first| {
+        first.len() >= 8 && leaves.iter().all(|leaf| leaf.len() == first.len())
+    });
+
+    if fixed_size && leaves.len() >= RADIX_SORT_MIN_LEAVES {
+        radix_sort_leaves(leaves);
+        return;
+    }
+
+    #[cfg(feature = "rayon")]
+    {
+        use rayon::slice::ParallelSliceMut;
+        leaves.par_sort_unstable();
+    }
+    #[cfg(not(feature = "rayon"))]
+    leaves.sort_unstable();
+}
+
+/// Sorts leaves of at least 8 bytes by a radix sort on their first 8 bytes
+///
+/// Leaves sharing those bytes are then ordered by comparing them in full.
+/// For leaves of one length this is the order of `sort_unstable`. The sort
+/// works on `u32` positions and permutes the leaves in place, so its scratch
+/// space is a fraction of the leaf layer.
+pub(crate) fn radix_sort_leaves(leaves: &mut [Vec<u8>]) {
+    if leaves.len() > u32::MAX as usize {
+        leaves.sort_unstable();
+        return;
+    }
+
+    // Least significant byte first; each pass is stable, so earlier passes
+    // order the ties of later ones
+    let mut order: Vec<u32> = (0..leaves.len() as u32).collect();
+    let mut scratch = vec![0u32; leaves.len()];
+    for byte in (0..8).rev() {
+        let mut counts = [0usize; 256];
+        for &index in &order {
+            counts[leaves[index as usize][byte] as usize] += 1;
+        }
+        // Every leaf has the same byte here, so the pass wouldn't move anything
+        if counts.contains(&leaves.len()) {
+            continue;
+        }
+
+        let mut offset = 0;
+        for count in counts.iter_mut() {
+            let bucket = *count;
+            *count = offset;
+            offset += bucket;
+        }
+        for &index in &order {
+            let bucket = &mut counts[leaves[index as usize][byte] as usize];
+            scratch[*bucket] = index;
+            *bucket += 1;
+        }
+        core::mem::swap(&mut order, &mut scratch);
+    }
+    drop(scratch);
+
+    for run in order.chunk_by_mut(|&a, &b| leaves[a as usize][..8] == leaves[b as usize][..8]) {
+        if run.len() > 1 {
+            run.sort_unstable_by(|&a, &b| leaves[a as usize].cmp(&leaves[b as usize]));
+        }
+    }
+
+    // Put the leaf at `order[i]` at position `i`, one cycle of the permutation at a time
+    for start in 0..order.len() {
+        let mut current = start;
+        loop {
+            let source = order[current] as usize;
+            order[current] = current as u32;
+            if source == start {
+                break;
+            }
+            leaves.swap(current, source);
+            current = source;
+        }
+    }
+}
@@ -0,0 +1,268 @@
+//! A sparse Merkle tree over a hashed key space
+//!
+//! Every key has a leaf, at the position given by the bits of
+//! `hash_leaf(key)`, so the tree is as deep as the hash is wide (256 levels
+//! for SHA-256). Almost all of it is empty: an empty leaf is all zero bytes
+//! and an empty subtree hashes to a default known for each level, so only
+//! the nodes on the paths of set keys are stored. A proof for any key shows
+//! either its value or that it has none.
+use alloc::vec;
+use alloc::vec::Vec;
+use crate::error::{MerkleTreeError, ProofParseError};
+use crate::hasher::Hasher;
+use crate::proof::ProofHash;
+
+/// Non-default nodes by level (0 = leaves) and the path prefix leading to them
+#[cfg(feature = "std")]
+type NodeMap = std::collections::HashMap<(usize, Vec<u8>), Vec<u8>>;
+#[cfg(not(feature = "std"))]
+type NodeMap = alloc::collections::BTreeMap<(usize, Vec<u8>), Vec<u8>>;
+
+/// Values by key path
+#[cfg(feature = "std")]
+type ValueMap = std::collections::HashMap<Vec<u8>, Vec<u8>>;
+#[cfg(not(feature = "std"))]
+type ValueMap = alloc::collections::BTreeMap<Vec<u8>, Vec<u8>>;
+
+/// Gets bit `index` of `path`, counting from the most significant bit of the first byte
+fn path_bit(path: &[u8], index: usize) -> bool {
+    path[index / 8] >> (7 - index % 8) & 1 == 1
+}
+
+/// Keeps the first `bits` bits of `path`, zeroing the rest
+fn path_prefix(path: &[u8], bits: usize) -> Vec<u8> {
+    let mut prefix = path.to_vec();
+    for (byte_index, byte) in prefix.iter_mut().enumerate() {
+        let kept = bits.saturating_sub(byte_index * 8).min(8);
+        *byte &= !(0xffu8.checked_shr(kept as u32).unwrap_or(0));
+    }
+    prefix
+}
+
+/// A sparse Merkle tree storing only the nodes that differ from the empty default
+pub struct SparseMerkleTree<H: Hasher> {
+    /// The stored non-default nodes below the root
+    nodes: NodeMap,
+    /// The value of each set key
+    values: ValueMap,
+    /// The hash of an empty subtree at each level, from the empty leaf up to the root
+    defaults: Vec<Vec<u8>>,
+    /// The root hash
+    root: Vec<u8>,
+    /// The hasher for keys, values and nodes
+    hasher: H,
+}
+
+impl<H: Hasher> SparseMerkleTree<H> {
+    /// Creates an empty tree, as deep as the hasher's output has bits
+    pub fn new(hasher: H) -> Self {
+        let hash_len = hasher.output_size();
+        let mut defaults = Vec::with_capacity(hash_len * 8 + 1);
+        defaults.push(vec![0u8; hash_len]);
+        for level in 0..hash_len * 8 {
+            let below = &defaults[level];
+            defaults.push(hasher.hash_pair(below, below));
+        }
+        let root = defaults[hash_len * 8].clone();
+
+        SparseMerkleTree {
+            nodes: NodeMap::new(),
+            values: ValueMap::new(),
+            defaults,
+            root,
+            hasher,
+        }
+    }
+
+    /// Gets the number of levels between a leaf and the root
+    pub fn depth(&self) -> usize {
+        self.defaults.len() - 1
+    }
+
+    /// Gets the root hash
+    pub fn root(&self) -> &[u8] {
+        &self.root
+    }
+
+    /// Gets the number of keys with a value
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Checks whether no key has a value
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Gets the value of a key
+    pub fn get(&self, key: &[u8]) -> Option<&[u8]> {
+        self.values.get(&self.hasher.hash_leaf(key)).map(Vec::as_slice)
+    }
+
+    /// Sets the value of a key, returning the new root
+    pub fn update(&mut self, key: &[u8], value: &[u8]) -> Vec<u8> {
+        let path = self.hasher.hash_leaf(key);
+        let leaf = self.hasher.hash_leaf(value);
+        self.values.insert(path.clone(), value.to_vec());
+        self.set_leaf(&path, leaf);
+        self.root.clone()
+    }
+
+    /// Clears the value of a key, returning it, and makes its leaf empty again
+    pub fn remove(&mut self, key: &[u8]) -> Option<Vec<u8>> {
+        let path = self.hasher.hash_leaf(key);
+        let value = self.values.remove(&path)?;
+        self.set_leaf(&path, self.defaults[0].clone());
+        Some(value)
+    }
+
+    /// Gets the node at `level` on `path`, falling back to the level's default
+    fn node(&self, level: usize, path: &[u8]) -> &[u8] {
+        let prefix = path_prefix(path, self.depth() - level);
+        self.nodes.get(&(level, prefix)).map_or(&self.defaults[level], Vec::as_slice)
+    }
+
+    /// Gets the sibling of the node at `level` on `path`
+    fn sibling(&self, level: usize, path: &[u8]) -> &[u8] {
+        let bit = self.depth() - level - 1;
+        let mut sibling_path = path.to_vec();
+        sibling_path[bit / 8] ^= 0x80 >> (bit % 8);
+        self.node(level, &sibling_path)
+    }
+
+    /// Writes the leaf on `path` and rehashes the nodes above it
+    ///
+    /// Nodes equal to their level's default are removed rather than stored.
+    fn set_leaf(&mut self, path: &[u8], leaf: Vec<u8>) {
+        let depth = self.depth();
+        let mut node = leaf;
+        for level in 0..depth {
+            let parent = {
+                let sibling = self.sibling(level, path);
+                if path_bit(path, depth - level - 1) {
+                    self.hasher.hash_pair(sibling, &node)
+                } else {
+                    self.hasher.hash_pair(&node, sibling)
+                }
+            };
+            let id = (level, path_prefix(path, depth - level));
+            if node == self.defaults[level] {
+                self.nodes.remove(&id);
+            } else {
+                self.nodes.insert(id, node);
+            }
+            node = parent;
+        }
+        self.root = node;
+    }
+
+    /// Generates a proof of the key's value, or of its absence
+    pub fn prove(&self, key: &[u8]) -> SparseProof<H> {
+        let path = self.hasher.hash_leaf(key);
+        let depth = self.depth();
+        let mut bitmap = vec![0u8; depth.div_ceil(8)];
+        let mut siblings = Vec::new();
+        for level in 0..depth {
+            let sibling = self.sibling(level, &path);
+            if sibling != self.defaults[level].as_slice() {
+                bitmap[level / 8] |= 1 << (level % 8);
+                siblings.push(ProofHash::from_slice(sibling));
+            }
+        }
+        SparseProof { bitmap, siblings, hasher: self.hasher.clone() }
+    }
+}
+
+/// A proof of a key's value, or of its absence, in a [`SparseMerkleTree`]
+///
+/// Only siblings that differ from the empty default are included. Bit `i`
+/// of `bitmap` (least significant bit of each byte first) is set when the
+/// sibling at level `i`, counting up from the leaves, is one of them.
+#[derive(Clone)]
+pub struct SparseProof<H: Hasher> {
+    /// Which levels have a non-default sibling
+    pub bitmap: Vec<u8>,
+    /// The non-default siblings, from the leaf up
+    pub siblings: Vec<ProofHash>,
+    /// The hasher for the proof
+    pub hasher: H,
+}
+
+impl<H: Hasher> SparseProof<H> {
+    /// Verifies that `key` has `value` under `root`, or no value for `None`
+    pub fn verify(&self, root: &[u8], key: &[u8], value: Option<&[u8]>) -> bool {
+        let path = self.hasher.hash_leaf(key);
+        let depth = path.len() * 8;
+        if self.bitmap.len() != depth.div_ceil(8) {
+            return false;
+        }
+
+        let mut default = vec![0u8; path.len()];
+        let mut node = value.map_or_else(|| default.clone(), |value| self.hasher.hash_leaf(value));
+        let mut siblings = self.siblings.iter();
+        for level in 0..depth {
+            let sibling = if self.bitmap[level / 8] >> (level % 8) & 1 == 1 {
+                match siblings.next() {
+                    Some(sibling) => sibling.as_slice(),
+                    None => return false,
+                }
+            } else {
+                default.as_slice()
+            };
+            node = if path_bit(&path, depth - level - 1) {
+                self.hasher.hash_pair(sibling, &node)
+            } else {
+                self.hasher.hash_pair(&node, sibling)
+            };
+            default = self.hasher.hash_pair(&default, &default);
+        }
+        siblings.next().is_none() && node == root
+    }
+
+    /// Serializes the proof compactly
+    ///
+    /// Layout (integers little-endian): `u32` bitmap length and the bitmap,
+    /// `u32` hash length, then the siblings back to back.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let hash_len = self.siblings.first().map_or(0, |sibling| sibling.len());
+        let mut bytes = Vec::with_capacity(8 + self.bitmap.len() + self.siblings.len() * hash_len);
+        bytes.extend_from_slice(&(self.bitmap.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&self.bitmap);
+        bytes.extend_from_slice(&(hash_len as u32).to_le_bytes());
+        for sibling in &self.siblings {
+            bytes.extend_from_slice(sibling);
+        }
+        bytes
+    }
+
+    /// Parses a proof written by [`SparseProof::to_bytes`], using the given hasher
+    pub fn from_bytes(bytes: &[u8], hasher: H) -> Result<Self, MerkleTreeError> {
+        let take = |rest: &mut &[u8], len: usize| -> Result<Vec<u8>, ProofParseError> {
+            if rest.len() < len {
+                return Err(ProofParseError::Truncated);
+            }
+            let (head, tail) = rest.split_at(len);
+            *rest = tail;
+            Ok(head.to_vec())
+        };
+        let take_u32 = |rest: &mut &[u8]| -> Result<usize, ProofParseError> {
+            let bytes = take(rest, 4)?;
+            Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize)
+        };
+
+        let mut rest = bytes;
+        let bitmap_len = take_u32(&mut rest)?;
+        let bitmap = take(&mut rest, bitmap_len)?;
+        let hash_len = take_u32(&mut rest)?;
+        let count = bitmap.iter().map(|byte| byte.count_ones() as usize).sum::<usize>();
+        let mut siblings = Vec::with_capacity(count);
+        for _ in 0..count {
+            siblings.push(ProofHash::from_vec(take(&mut rest, hash_len)?));
+        }
+        if !rest.is_empty() {
+            return Err(ProofParseError::TrailingBytes.into());
+        }
+
+        Ok(SparseProof { bitmap, siblings, hasher })
+    }
+}
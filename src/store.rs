@@ -0,0 +1,532 @@
+//! Storage backends for the nodes of a Merkle tree
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+/// Storage for tree nodes, addressed by level and index within the level
+///
+/// Level 0 holds the leaves and the top level holds the root. The tree reads
+/// and writes every node through this trait, so a backend decides where the
+/// hashes live (memory, disk, ...).
+pub trait NodeStore {
+    /// A stored hash, either borrowed from the store or owned
+    type Node<'a>: AsRef<[u8]> where Self: 'a;
+    /// The error returned when the store can't be written to
+    type Error;
+
+    /// Gets the node at the given level and index
+    fn get(&self, level: usize, index: usize) -> Option<Self::Node<'_>>;
+
+    /// Gets the node at the given level and index, reporting a failed read
+    ///
+    /// The default never fails. Stores whose reads can fail override this,
+    /// and the tree reads through it wherever it returns a `Result`, so a
+    /// failed read isn't mistaken for a missing node.
+    fn try_get(&self, level: usize, index: usize) -> Result<Option<Self::Node<'_>>, StoreReadError> {
+        Ok(self.get(level, index))
+    }
+
+    /// Stores the node at the given level and index, replacing any previous value
+    fn put(&mut self, level: usize, index: usize, hash: Vec<u8>) -> Result<(), Self::Error>;
+
+    /// Stores a whole level, putting `nodes[i]` at index `i`
+    ///
+    /// The default puts each node in turn; stores that can keep the vector
+    /// itself override this so a level is moved in without touching its nodes.
+    fn put_level(&mut self, level: usize, nodes: Vec<Vec<u8>>) -> Result<(), Self::Error> {
+        for (index, node) in nodes.into_iter().enumerate() {
+            self.put(level, index, node)?;
+        }
+        Ok(())
+    }
+
+    /// Makes previous writes durable; a no-op for in-memory stores
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Estimates the heap memory holding one level, in bytes
+    ///
+    /// Defaults to 0, for stores that keep their nodes outside the heap.
+    fn approx_level_bytes(&self, _level: usize) -> usize {
+        0
+    }
+
+    /// Gets the record the tree keeps beside its nodes, such as its leaf count
+    ///
+    /// The default keeps none, so trees built into such a store can't be
+    /// reopened with [`crate::tree::MerkleTree::from_store`].
+    fn get_meta(&self) -> Result<Option<Vec<u8>>, StoreReadError> {
+        Ok(None)
+    }
+
+    /// Stores the tree's record, replacing any previous one; the default drops it
+    fn put_meta(&mut self, _meta: Vec<u8>) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// The error from a node store read that failed, rather than finding no node
+#[derive(Debug)]
+pub struct StoreReadError {
+    /// The level and index of the node being read, or `None` for the tree's record
+    pub node: Option<(usize, usize)>,
+    /// The store's own error
+    pub source: Box<dyn core::error::Error + Send + Sync>,
+}
+
+impl StoreReadError {
+    /// Wraps a store's error for the node at the given level and index
+    pub fn new(level: usize, index: usize, source: impl Into<Box<dyn core::error::Error + Send + Sync>>) -> Self {
+        StoreReadError { node: Some((level, index)), source: source.into() }
+    }
+
+    /// Wraps a store's error for the record read by [`NodeStore::get_meta`]
+    pub fn meta(source: impl Into<Box<dyn core::error::Error + Send + Sync>>) -> Self {
+        StoreReadError { node: None, source: source.into() }
+    }
+}
+
+impl core::fmt::Display for StoreReadError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.node {
+            Some((level, index)) => write!(f, "Failed to read from the store at level {}, index {}: {}", level, index, self.source),
+            None => write!(f, "Failed to read from the store's tree record: {}", self.source),
+        }
+    }
+}
+
+impl core::error::Error for StoreReadError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        Some(&*self.source)
+    }
+}
+
+/// The default in-memory node store
+///
+/// Each level is a flat vector indexed by position, so it only needs `alloc`.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryNodeStore {
+    /// The nodes of each level
+    levels: Vec<Level>,
+    /// The tree's record, see [`NodeStore::get_meta`]
+    meta: Option<Vec<u8>>,
+}
+
+/// The nodes of one level of a [`MemoryNodeStore`]
+#[derive(Clone, Debug)]
+enum Level {
+    /// Written front to back, as trees write whole levels
+    Dense(Vec<Vec<u8>>),
+    /// Written out of order, with `None` for positions not yet written
+    Slotted(Vec<Option<Vec<u8>>>),
+}
+
+impl MemoryNodeStore {
+    /// Creates an empty in-memory store
+    pub fn new() -> Self {
+        MemoryNodeStore::default()
+    }
+
+    /// Drops every level from `levels` up, freeing their memory
+    pub fn truncate(&mut self, levels: usize) {
+        self.levels.truncate(levels);
+        self.levels.shrink_to_fit();
+    }
+
+    /// Gets every node of a level, if it was written front to back without gaps
+    ///
+    /// Levels a tree builds always are, so `level(0)` lends out the leaves.
+    pub fn level(&self, level: usize) -> Option<&[Vec<u8>]> {
+        match self.levels.get(level)? {
+            Level::Dense(nodes) => Some(nodes),
+            Level::Slotted(_) => None,
+        }
+    }
+}
+
+/// Overwrites every node with zeros and drops them, leaving the store empty
+#[cfg(feature = "zeroize")]
+impl zeroize::Zeroize for MemoryNodeStore {
+    fn zeroize(&mut self) {
+        for level in &mut self.levels {
+            match level {
+                Level::Dense(nodes) => nodes.zeroize(),
+                Level::Slotted(nodes) => nodes.zeroize(),
+            }
+        }
+        self.levels.clear();
+    }
+}
+
+impl NodeStore for MemoryNodeStore {
+    type Node<'a> = &'a Vec<u8>;
+    type Error = core::convert::Infallible;
+
+    fn get(&self, level: usize, index: usize) -> Option<&Vec<u8>> {
+        match self.levels.get(level)? {
+            Level::Dense(nodes) => nodes.get(index),
+            Level::Slotted(nodes) => nodes.get(index)?.as_ref(),
+        }
+    }
+
+    fn put(&mut self, level: usize, index: usize, hash: Vec<u8>) -> Result<(), Self::Error> {
+        if self.levels.len() <= level {
+            self.levels.resize_with(level + 1, || Level::Dense(Vec::new()));
+        }
+        let nodes = &mut self.levels[level];
+        match nodes {
+            Level::Dense(dense) if index < dense.len() => dense[index] = hash,
+            Level::Dense(dense) if index == dense.len() => dense.push(hash),
+            _ => {
+                // A gap, so positions need to say whether they were written
+                if let Level::Dense(dense) = nodes {
+                    *nodes = Level::Slotted(core::mem::take(dense).into_iter().map(Some).collect());
+                }
+                let Level::Slotted(slots) = nodes else { unreachable!() };
+                if slots.len() <= index {
+                    slots.resize_with(index + 1, || None);
+                }
+                slots[index] = Some(hash);
+            }
+        }
+        Ok(())
+    }
+
+    /// Replaces the level with `nodes`, keeping the vector as it is
+    fn put_level(&mut self, level: usize, nodes: Vec<Vec<u8>>) -> Result<(), Self::Error> {
+        if self.levels.len() <= level {
+            self.levels.resize_with(level + 1, || Level::Dense(Vec::new()));
+        }
+        self.levels[level] = Level::Dense(nodes);
+        Ok(())
+    }
+
+    /// The level's slots plus the hash buffer of every node
+    fn approx_level_bytes(&self, level: usize) -> usize {
+        match self.levels.get(level) {
+            None => 0,
+            Some(Level::Dense(nodes)) => {
+                let slots = nodes.capacity() * core::mem::size_of::<Vec<u8>>();
+                slots + nodes.iter().map(Vec::capacity).sum::<usize>()
+            }
+            Some(Level::Slotted(nodes)) => {
+                let slots = nodes.capacity() * core::mem::size_of::<Option<Vec<u8>>>();
+                slots + nodes.iter().flatten().map(Vec::capacity).sum::<usize>()
+            }
+        }
+    }
+
+    fn get_meta(&self) -> Result<Option<Vec<u8>>, StoreReadError> {
+        Ok(self.meta.clone())
+    }
+
+    fn put_meta(&mut self, meta: Vec<u8>) -> Result<(), Self::Error> {
+        self.meta = Some(meta);
+        Ok(())
+    }
+}
+
+/// A node store that holds leaves owned or borrowed by the caller
+///
+/// Leaves of any type that derefs to bytes, such as `&[u8]` slices of one
+/// arena, `Cow<[u8]>` or `Arc<[u8]>`, are kept as given; interior nodes are
+/// hashed into memory. Build a tree over one with
+/// [`crate::tree::MerkleTree::from_borrowed_leaves`]. The leaves are fixed
+/// on construction, so putting a leaf fails with [`LeavesReadOnly`].
+#[derive(Clone, Debug)]
+pub struct BorrowedLeafStore<L> {
+    /// The leaves, with padding
+    leaves: Vec<L>,
+    /// The interior nodes
+    interior: MemoryNodeStore,
+}
+
+impl<L: AsRef<[u8]>> BorrowedLeafStore<L> {
+    /// Creates a store over already padded leaves
+    pub(crate) fn new(leaves: Vec<L>) -> Self {
+        BorrowedLeafStore { leaves, interior: MemoryNodeStore::new() }
+    }
+
+    /// Gets the leaves, including padding, as they were given
+    pub fn leaves(&self) -> &[L] {
+        &self.leaves
+    }
+}
+
+/// The error from writing a leaf into a [`BorrowedLeafStore`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LeavesReadOnly;
+
+impl core::fmt::Display for LeavesReadOnly {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "Borrowed leaves cannot be written")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for LeavesReadOnly {}
+
+impl<L: AsRef<[u8]>> NodeStore for BorrowedLeafStore<L> {
+    type Node<'a> = &'a [u8] where L: 'a;
+    type Error = LeavesReadOnly;
+
+    fn get(&self, level: usize, index: usize) -> Option<&[u8]> {
+        if level == 0 {
+            self.leaves.get(index).map(AsRef::as_ref)
+        } else {
+            self.interior.get(level, index).map(Vec::as_slice)
+        }
+    }
+
+    fn put(&mut self, level: usize, index: usize, hash: Vec<u8>) -> Result<(), LeavesReadOnly> {
+        if level == 0 {
+            return Err(LeavesReadOnly);
+        }
+        let Ok(()) = self.interior.put(level, index, hash);
+        Ok(())
+    }
+
+    fn put_level(&mut self, level: usize, nodes: Vec<Vec<u8>>) -> Result<(), LeavesReadOnly> {
+        if level == 0 {
+            return Err(LeavesReadOnly);
+        }
+        let Ok(()) = self.interior.put_level(level, nodes);
+        Ok(())
+    }
+
+    /// The leaf slots only, since the leaf bytes belong to the caller
+    fn approx_level_bytes(&self, level: usize) -> usize {
+        if level == 0 {
+            self.leaves.capacity() * core::mem::size_of::<L>()
+        } else {
+            self.interior.approx_level_bytes(level)
+        }
+    }
+
+    fn get_meta(&self) -> Result<Option<Vec<u8>>, StoreReadError> {
+        self.interior.get_meta()
+    }
+
+    fn put_meta(&mut self, meta: Vec<u8>) -> Result<(), LeavesReadOnly> {
+        let Ok(()) = self.interior.put_meta(meta);
+        Ok(())
+    }
+}
+
+/// A persistent node store backed by a sled database
+///
+/// Nodes are keyed by big-endian `(level, index)`, and the tree's record
+/// (see [`NodeStore::get_meta`]) by the shorter key `meta`, so one database
+/// (or sled tree) holds exactly one Merkle tree. A tree written here can be reopened
+/// later with [`crate::tree::MerkleTree::from_store`] without rebuilding it.
+///
+/// Crash safety: sled buffers writes and only guarantees durability after a
+/// flush, so call [`crate::tree::MerkleTree::flush`] once construction is
+/// done. A crash before that leaves a partial tree, which `from_store`
+/// rejects if the root is missing; build into a fresh database and treat it
+/// as valid only after the flush has returned. Sled compacts its log in the
+/// background, so there is no separate compaction step.
+#[cfg(feature = "sled")]
+#[derive(Clone)]
+pub struct SledNodeStore {
+    tree: sled::Tree,
+}
+
+#[cfg(feature = "sled")]
+impl SledNodeStore {
+    /// Opens (or creates) a sled database at the given path
+    pub fn open<P: AsRef<std::path::Path>>(path: P) -> sled::Result<Self> {
+        let db = sled::open(path)?;
+        Ok(SledNodeStore { tree: (*db).clone() })
+    }
+
+    /// Uses an existing sled tree, so several Merkle trees can share a database
+    pub fn from_tree(tree: sled::Tree) -> Self {
+        SledNodeStore { tree }
+    }
+
+    /// Gets the approximate number of nodes stored
+    pub fn len(&self) -> usize {
+        let has_meta = self.tree.contains_key(Self::META_KEY).unwrap_or(false);
+        self.tree.len() - usize::from(has_meta)
+    }
+
+    /// Checks whether the store holds no nodes
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    /// The key of the tree's record; node keys are 16 bytes, so it can't clash
+    const META_KEY: &'static [u8] = b"meta";
+
+    fn key(level: usize, index: usize) -> [u8; 16] {
+        let mut key = [0u8; 16];
+        key[..8].copy_from_slice(&(level as u64).to_be_bytes());
+        key[8..].copy_from_slice(&(index as u64).to_be_bytes());
+        key
+    }
+}
+
+#[cfg(feature = "sled")]
+impl NodeStore for SledNodeStore {
+    type Node<'a> = sled::IVec;
+    type Error = sled::Error;
+
+    /// Gets a node; read errors are reported as a missing node, see `try_get`
+    fn get(&self, level: usize, index: usize) -> Option<sled::IVec> {
+        self.tree.get(Self::key(level, index)).ok().flatten()
+    }
+
+    /// Gets a node, passing sled's I/O and corruption errors on as the source
+    fn try_get(&self, level: usize, index: usize) -> Result<Option<sled::IVec>, StoreReadError> {
+        self.tree.get(Self::key(level, index)).map_err(|err| StoreReadError::new(level, index, err))
+    }
+
+    fn put(&mut self, level: usize, index: usize, hash: Vec<u8>) -> Result<(), sled::Error> {
+        self.tree.insert(Self::key(level, index), hash)?;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), sled::Error> {
+        self.tree.flush()?;
+        Ok(())
+    }
+
+    fn get_meta(&self) -> Result<Option<Vec<u8>>, StoreReadError> {
+        let meta = self.tree.get(Self::META_KEY).map_err(StoreReadError::meta)?;
+        Ok(meta.map(|meta| meta.to_vec()))
+    }
+
+    fn put_meta(&mut self, meta: Vec<u8>) -> Result<(), sled::Error> {
+        self.tree.insert(Self::META_KEY, meta)?;
+        Ok(())
+    }
+}
+
+/// A node store that keeps the leaves in a memory-mapped file
+///
+/// The leaf layer is written to and read from a file of fixed-size records,
+/// one per leaf, so it doesn't occupy heap memory; interior nodes are kept in
+/// memory. Every leaf must be exactly `hash_len` bytes. Leaves read from the
+/// tree are borrowed straight from the mapping.
+///
+/// The file must not be modified by anything else while the store is open.
+#[cfg(feature = "mmap")]
+pub struct MmapLeafStore {
+    /// The mapped leaf records
+    leaves: memmap2::MmapMut,
+    /// The size of each leaf record
+    hash_len: usize,
+    /// The number of leaf records written so far
+    leaf_len: usize,
+    /// The interior nodes
+    interior: MemoryNodeStore,
+}
+
+#[cfg(feature = "mmap")]
+impl MmapLeafStore {
+    /// Creates the leaf file at `path`, sized for `leaf_count` leaves of `hash_len` bytes
+    ///
+    /// Room is reserved for the padding leaves the tree adds, so `leaf_count`
+    /// is the number of leaves passed to the tree constructor. An existing
+    /// file is truncated.
+    pub fn create<P: AsRef<std::path::Path>>(
+        path: P,
+        leaf_count: usize,
+        hash_len: usize
+    ) -> std::io::Result<Self> {
+        use std::io::{Error, ErrorKind};
+
+        if hash_len == 0 {
+            return Err(Error::new(ErrorKind::InvalidInput, "Hash length must be non-zero"));
+        }
+        let capacity = leaf_count.max(1).next_power_of_two();
+        let file_len = capacity.checked_mul(hash_len)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "Leaf file too large"))?;
+
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        file.set_len(file_len as u64)?;
+
+        // SAFETY: the file was just created for this store and, as documented,
+        // must not be modified by anyone else while it's mapped.
+        let leaves = unsafe { memmap2::MmapMut::map_mut(&file)? };
+
+        Ok(MmapLeafStore {
+            leaves,
+            hash_len,
+            leaf_len: 0,
+            interior: MemoryNodeStore::new(),
+        })
+    }
+
+    /// Gets the size of each leaf record
+    pub fn hash_len(&self) -> usize {
+        self.hash_len
+    }
+}
+
+#[cfg(feature = "mmap")]
+impl NodeStore for MmapLeafStore {
+    type Node<'a> = &'a [u8];
+    type Error = std::io::Error;
+
+    fn get(&self, level: usize, index: usize) -> Option<&[u8]> {
+        if level == 0 {
+            if index < self.leaf_len {
+                let start = index * self.hash_len;
+                Some(&self.leaves[start..start + self.hash_len])
+            } else {
+                None
+            }
+        } else {
+            self.interior.get(level, index).map(|node| node.as_slice())
+        }
+    }
+
+    fn put(&mut self, level: usize, index: usize, hash: Vec<u8>) -> Result<(), std::io::Error> {
+        use std::io::{Error, ErrorKind};
+
+        if level > 0 {
+            let Ok(()) = self.interior.put(level, index, hash);
+            return Ok(());
+        }
+
+        if hash.len() != self.hash_len {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Leaf {} is {} bytes, expected {}", index, hash.len(), self.hash_len),
+            ));
+        }
+        let start = index.checked_mul(self.hash_len)
+            .filter(|start| start + self.hash_len <= self.leaves.len())
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "Leaf index beyond the reserved file size"))?;
+
+        self.leaves[start..start + self.hash_len].copy_from_slice(&hash);
+        self.leaf_len = self.leaf_len.max(index + 1);
+        Ok(())
+    }
+
+    /// Interior levels only; the leaves are in the mapped file
+    fn approx_level_bytes(&self, level: usize) -> usize {
+        if level == 0 { 0 } else { self.interior.approx_level_bytes(level) }
+    }
+
+    /// Kept in memory with the interior nodes, not in the mapped file
+    fn get_meta(&self) -> Result<Option<Vec<u8>>, StoreReadError> {
+        self.interior.get_meta()
+    }
+
+    fn put_meta(&mut self, meta: Vec<u8>) -> Result<(), std::io::Error> {
+        let Ok(()) = self.interior.put_meta(meta);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), std::io::Error> {
+        self.leaves.flush()
+    }
+}
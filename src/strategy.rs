@@ -0,0 +1,126 @@
+//! Proptest strategies for trees and proofs
+//!
+//! [`any_tree`] builds trees over random leaves and [`any_proof_for`] picks
+//! proofs of a tree's real leaves; both shrink towards fewer leaves and the
+//! first leaf. [`ProofItem`] and `MerkleProof<BuiltinHasher>` implement
+//! [`Arbitrary`] with hashes of a valid length, for code that parses or
+//! checks proofs it didn't generate. A proof's path is its `proof_items`;
+//! [`any_proof_path`] generates one on its own, shrinking to shorter paths.
+//!
+//! ```
+//! use merkle_tree::strategy::{any_hasher, any_proof_for, any_tree};
+//! use proptest::prelude::*;
+//! use std::sync::Arc;
+//!
+//! proptest!(|((tree, proof) in any_tree(any_hasher(), 1..=16)
+//!     .prop_flat_map(|tree| { let proofs = any_proof_for(&tree); (Just(Arc::new(tree)), proofs) }))| {
+//!     prop_assert!(proof.verify(tree.root()));
+//! });
+//! ```
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::sync::Arc;
+use proptest::arbitrary::{any, any_with, Arbitrary};
+use proptest::collection::{vec, SizeRange};
+use proptest::sample::select;
+use proptest::strategy::{BoxedStrategy, Just, Strategy};
+use crate::hasher::{BuiltinHasher, Hasher};
+use crate::proof::{MerkleProof, ProofHash, ProofItem};
+use crate::store::NodeStore;
+use crate::tree::MerkleTree;
+
+/// The number of leaves, before padding, [`any_proof`] builds its proofs' trees over
+const PROOF_LEAVES: RangeInclusive<usize> = 1..=64;
+
+/// The length of the hashes a strategy generates; 32 bytes by default
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HashLen(pub usize);
+
+impl Default for HashLen {
+    fn default() -> Self {
+        HashLen(32)
+    }
+}
+
+/// Picks one of the built-in hashers, shrinking towards SHA-256
+pub fn any_hasher() -> impl Strategy<Value = BuiltinHasher> {
+    select(&["sha256", "blake2b256", "blake2b512", "keccak256"][..])
+        .prop_map(|name| BuiltinHasher::from_name(name).expect("the names are built in"))
+}
+
+/// Builds trees over `leaves` random leaves, hashed with a hasher from `hasher`
+///
+/// Trees are sorted or ordered at random. Shrinking drops leaves, so a
+/// failure is reported for the smallest tree that still shows it.
+pub fn any_tree<H, S>(hasher: S, leaves: impl Into<SizeRange>) -> impl Strategy<Value = MerkleTree<H>>
+where
+    H: Hasher + fmt::Debug,
+    S: Strategy<Value = H>,
+{
+    let leaves = leaves.into();
+    (hasher, vec(any::<[u8; 8]>(), leaves), any::<bool>()).prop_map(|(hasher, data, sorted)| {
+        MerkleTree::builder(hasher.clone())
+            .sort_leaves(sorted)
+            .build(data.iter().map(|item| hasher.hash_leaf(item)).collect())
+            .expect("the leaves are the hasher's output and there is at least one")
+    })
+}
+
+/// Picks proofs of the tree's real leaves, shrinking towards leaf 0
+///
+/// The proofs are generated up front, so the strategy doesn't borrow the tree.
+///
+/// # Panics
+///
+/// Panics if the tree can't prove its leaves, as zeroized trees can't.
+pub fn any_proof_for<H, S>(tree: &MerkleTree<H, S>) -> impl Strategy<Value = MerkleProof<H>>
+where
+    H: Hasher + fmt::Debug,
+    S: NodeStore,
+{
+    let proofs: Arc<[MerkleProof<H>]> = (0..tree.unpadded_leaf_count())
+        .map(|index| tree.generate_proof(index).expect("real leaves have proofs"))
+        .collect();
+    (0..proofs.len()).prop_map(move |index| proofs[index].clone())
+}
+
+/// Generates proof paths of `depth` items, each hash `hash_len` bytes
+pub fn any_proof_path(depth: impl Into<SizeRange>, hash_len: HashLen) -> impl Strategy<Value = Vec<ProofItem>> {
+    vec(any_with::<ProofItem>(hash_len), depth)
+}
+
+/// A valid proof of a random tree, see [`any_tree`] and [`any_proof_for`]
+pub fn any_proof() -> impl Strategy<Value = MerkleProof<BuiltinHasher>> {
+    any_tree(any_hasher(), PROOF_LEAVES).prop_flat_map(|tree| any_proof_for(&tree))
+}
+
+/// A sibling hash of [`HashLen`] bytes on a random side
+impl Arbitrary for ProofItem {
+    type Parameters = HashLen;
+    type Strategy = BoxedStrategy<ProofItem>;
+
+    fn arbitrary_with(HashLen(hash_len): HashLen) -> Self::Strategy {
+        (vec(any::<u8>(), hash_len), any::<bool>())
+            .prop_map(|(hash, is_left)| ProofItem { hash: ProofHash::from_vec(hash), is_left })
+            .boxed()
+    }
+}
+
+/// A well-formed proof, with the leaf and every sibling the hasher's output size
+///
+/// Its path is 0 to 16 items of random hashes, so it almost never verifies;
+/// use [`any_proof`] for proofs that do.
+impl Arbitrary for MerkleProof<BuiltinHasher> {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<MerkleProof<BuiltinHasher>>;
+
+    fn arbitrary_with((): ()) -> Self::Strategy {
+        any_hasher()
+            .prop_flat_map(|hasher| {
+                let hash_len = hasher.output_size();
+                (vec(any::<u8>(), hash_len), any_proof_path(0..=16, HashLen(hash_len)), Just(hasher))
+            })
+            .prop_map(|(leaf, proof_items, hasher)| MerkleProof::new(leaf, proof_items, hasher))
+            .boxed()
+    }
+}
@@ -0,0 +1,14 @@
+//! Helpers for the `tracing` instrumentation
+use alloc::string::String;
+
+/// How many leading bytes of a hash go into span and event fields
+const SHORT_HASH_BYTES: usize = 4;
+
+/// Hex of the first few bytes of a hash, so traces identify hashes without logging them in full
+pub(crate) fn short_hex(hash: &[u8]) -> String {
+    let mut short = hex::encode(&hash[..hash.len().min(SHORT_HASH_BYTES)]);
+    if hash.len() > SHORT_HASH_BYTES {
+        short.push_str("..");
+    }
+    short
+}
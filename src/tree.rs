@@ -1,91 +1,807 @@
 // tree.rs
 
-use std::collections::HashMap;
-use crate::proof::{MerkleProof, ProofItem};
+use alloc::vec::Vec;
+use crate::proof::{MerkleProof, MerkleProofRef, NodeChange, ProofDelta, ProofHash, ProofItem, ProofItemRef};
 use crate::hasher::Hasher;
+use crate::store::{BorrowedLeafStore, MemoryNodeStore, NodeStore, StoreReadError};
+use crate::builder::MerkleTreeBuilder;
+use crate::bound::{self, TreeParams};
+use crate::error::MerkleTreeError;
+use crate::index::LeafIndex;
+
+/// Leaf value to the index of its first occurrence
+#[cfg(feature = "std")]
+pub(crate) type LeafLookup = std::collections::HashMap<Vec<u8>, usize>;
+#[cfg(not(feature = "std"))]
+pub(crate) type LeafLookup = alloc::collections::BTreeMap<Vec<u8>, usize>;
+
+/// Checks that a tree can hold `count` leaves, see [`crate::index::max_leaf_count`]
+pub(crate) fn check_leaf_count(count: usize) -> Result<(), MerkleTreeError> {
+    match crate::index::padded_leaf_count(count as u64) {
+        Some(_) => Ok(()),
+        None if count == 0 => Err(MerkleTreeError::EmptyLeaves),
+        None => Err(MerkleTreeError::TooManyLeaves { count: count as u64, max: crate::index::max_leaf_count() }),
+    }
+}
+
+/// Checks that every leaf is as long as the hasher's output, as pre-hashed leaves are
+///
+/// A raw value mixed in among leaf hashes builds a tree whose root matches
+/// nothing, so the builder rejects it unless
+/// [`MerkleTreeBuilder::check_leaf_sizes`] is turned off.
+pub(crate) fn check_leaf_sizes<H: Hasher>(leaves: &[Vec<u8>], hasher: &H) -> Result<(), MerkleTreeError> {
+    let expected = hasher.output_size();
+    match leaves.iter().position(|leaf| leaf.len() != expected) {
+        Some(index) => Err(MerkleTreeError::LeafSizeMismatch { index, expected, actual: leaves[index].len() }),
+        None => Ok(()),
+    }
+}
+
+/// The record a tree keeps in its store beside the nodes, see [`NodeStore::get_meta`]
+///
+/// Layout: the unpadded leaf count as a little-endian u64, then a flags
+/// byte. Interior nodes only commit to the padded leaves, so this is what
+/// tells a reopened tree which of its trailing copies of the last leaf are
+/// real, and which options [`MerkleTree::bound_root`] commits to.
+struct StoreMeta {
+    unpadded_leaf_count: u64,
+    /// The leaves were sorted on construction
+    built_sorted: bool,
+    /// Each leaf was hashed with its index
+    index_bound: bool,
+    /// The leaves are still sorted, as no update has moved one out of order
+    sorted: bool,
+}
+
+impl StoreMeta {
+    const BUILT_SORTED: u8 = 1;
+    const INDEX_BOUND: u8 = 2;
+    const SORTED: u8 = 4;
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut flags = 0;
+        for (set, flag) in [(self.built_sorted, Self::BUILT_SORTED), (self.index_bound, Self::INDEX_BOUND), (self.sorted, Self::SORTED)] {
+            if set {
+                flags |= flag;
+            }
+        }
+        let mut bytes = self.unpadded_leaf_count.to_le_bytes().to_vec();
+        bytes.push(flags);
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let (&flags, count) = bytes.split_last()?;
+        if flags & !(Self::BUILT_SORTED | Self::INDEX_BOUND | Self::SORTED) != 0 {
+            return None;
+        }
+        Some(StoreMeta {
+            unpadded_leaf_count: u64::from_le_bytes(count.try_into().ok()?),
+            built_sorted: flags & Self::BUILT_SORTED != 0,
+            index_bound: flags & Self::INDEX_BOUND != 0,
+            sorted: flags & Self::SORTED != 0,
+        })
+    }
+}
+
+/// An estimate of the heap memory held by a tree, in bytes
+///
+/// Counts buffers and container slots but not allocator overhead, so treat
+/// it as a lower bound for capacity planning.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MemoryBreakdown {
+    /// The leaf level, including padding leaves
+    pub leaves: usize,
+    /// The interior levels and the root
+    pub nodes: usize,
+    /// Auxiliary lookup structures such as the leaf index
+    pub indexes: usize,
+}
+
+impl MemoryBreakdown {
+    /// The sum of all parts
+    pub fn total(&self) -> usize {
+        self.leaves + self.nodes + self.indexes
+    }
+}
+
+/// Instrumentation recorded while building a tree, when requested from the builder
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BuildStats {
+    /// The number of `hash_pair` calls that produced each level; level 0
+    /// holds the leaves, which the tree doesn't hash
+    pub pair_hashes: Vec<usize>,
+    /// Wall time spent building, when a clock is available (`std`)
+    pub elapsed: Option<core::time::Duration>,
+}
+
+impl BuildStats {
+    /// The number of `hash_pair` calls over all levels
+    pub fn total_pair_hashes(&self) -> usize {
+        self.pair_hashes.iter().sum()
+    }
+}
+
+/// Notified as each level of a tree is hashed
+///
+/// The unit observer does nothing and compiles away, so plain constructors
+/// pay nothing for instrumentation.
+pub(crate) trait BuildObserver {
+    /// Called once `hashes` pair hashes have produced `level`
+    fn level_hashed(&mut self, level: usize, hashes: usize);
+}
+
+impl BuildObserver for () {
+    fn level_hashed(&mut self, _level: usize, _hashes: usize) {}
+}
+
+/// How a tree holds the nodes above its leaves
+enum Interior {
+    /// Every level is in the store
+    Stored,
+    /// Only the leaves and root are kept; other nodes are computed from the
+    /// leaves when first needed and cached
+    Lazy(NodeCache),
+    /// The interior levels were dropped by [`MerkleTree::prune`]; nodes are
+    /// recomputed from the leaves on every access
+    Pruned,
+    /// The leaves were scrubbed by [`MerkleTree::zeroize_leaves`]; only the root is left
+    #[cfg(feature = "zeroize")]
+    Zeroized,
+}
+
+/// Interior nodes computed on demand by a lazy tree
+///
+/// Proofs only borrow the tree, so the cache sits behind a read-write lock
+/// that threads proving from a shared tree can read at once; without `std`
+/// there is no lock to use and nodes are recomputed on every access.
+#[derive(Default)]
+struct NodeCache {
+    #[cfg(feature = "std")]
+    nodes: std::sync::RwLock<MemoryNodeStore>,
+}
+
+impl NodeCache {
+    fn get(&self, _level: usize, _index: usize) -> Option<Vec<u8>> {
+        // The store is never left half-written, so a poisoned lock is still usable
+        #[cfg(feature = "std")]
+        return self.nodes.read().unwrap_or_else(std::sync::PoisonError::into_inner)
+            .get(_level, _index)
+            .cloned();
+        #[cfg(not(feature = "std"))]
+        None
+    }
+
+    fn insert(&self, _level: usize, _index: usize, _node: &[u8]) {
+        #[cfg(feature = "std")]
+        {
+            let mut nodes = self.nodes.write().unwrap_or_else(std::sync::PoisonError::into_inner);
+            let Ok(()) = nodes.put(_level, _index, _node.to_vec());
+        }
+    }
+
+    /// Overwrites the cached nodes with zeros and drops them
+    #[cfg(feature = "zeroize")]
+    fn zeroize(&mut self) {
+        #[cfg(feature = "std")]
+        zeroize::Zeroize::zeroize(self.nodes.get_mut().unwrap_or_else(std::sync::PoisonError::into_inner));
+    }
+
+    fn approx_bytes(&self, _height: usize) -> usize {
+        #[cfg(feature = "std")]
+        return {
+            let nodes = self.nodes.read().unwrap_or_else(std::sync::PoisonError::into_inner);
+            (1.._height).map(|level| nodes.approx_level_bytes(level)).sum()
+        };
+        #[cfg(not(feature = "std"))]
+        0
+    }
+}
 
 /// Represents a Merkle tree data structure
-pub struct MerkleTree<H: Hasher> {
-    /// The leaves of the tree
-    leaves: Vec<Vec<u8>>,
-    /// The cached nodes of the tree, indexed by level and position
-    nodes: HashMap<(usize, usize), Vec<u8>>,
+///
+/// # Thread safety
+///
+/// A tree is `Send` and `Sync` whenever its hasher and store are, which
+/// holds for the built-in hashers and stores, so a built tree can be shared
+/// behind an `Arc` and proven from on many threads. Lazy trees cache nodes
+/// behind a lock for this; the crate's tests assert these bounds.
+pub struct MerkleTree<H: Hasher, S: NodeStore = MemoryNodeStore> {
+    /// The nodes of the tree, with the leaves on level 0
+    store: S,
+    /// The number of leaves, including padding
+    pub(crate) leaf_count: usize,
+    /// The number of leaves given on construction; the rest are padding
+    pub(crate) unpadded_leaf_count: usize,
     /// The height of the tree
     height: usize,
+    /// The root, kept alongside the store so it can be lent out
+    root: Vec<u8>,
+    /// An optional index from leaf value to position, for O(1) lookups
+    leaf_index: Option<LeafLookup>,
+    /// Whether the leaves are known to be sorted, allowing binary search
+    sorted: bool,
+    /// Whether the leaves were sorted on construction, see [`MerkleTree::params`];
+    /// unlike `sorted`, updates don't clear it
+    built_sorted: bool,
+    /// Whether each leaf was hashed with its index, see [`MerkleTreeBuilder::bind_indices`]
+    index_bound: bool,
+    /// Instrumentation from construction, if the builder was asked for it
+    build_stats: Option<BuildStats>,
+    /// Whether the interior levels are stored or computed on demand
+    interior: Interior,
     /// The hasher for the tree
     hasher: H,
 }
 
+/// A summary of the tree: its hasher, leaf counts, height and root; the
+/// nodes are left out, see the `Display` impl for a drawing of them
+impl<H: Hasher, S: NodeStore> core::fmt::Debug for MerkleTree<H, S> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("MerkleTree")
+            .field("hasher", &self.hasher.name())
+            .field("leaf_count", &self.leaf_count)
+            .field("unpadded_leaf_count", &self.unpadded_leaf_count)
+            .field("height", &self.height)
+            .field("root", &hex::encode(&self.root))
+            .finish()
+    }
+}
+
 impl<H: Hasher> MerkleTree<H> {
+    /// Starts building a tree with non-default options
+    pub fn builder(hasher: H) -> MerkleTreeBuilder<H> {
+        MerkleTreeBuilder::new(hasher)
+    }
+
     /// Creates a new Merkle tree with a specific hasher
-    pub fn new(mut leaves: Vec<Vec<u8>>, hasher: H) -> Self {
+    ///
+    /// Leaf lengths aren't checked; build through [`MerkleTree::builder`] to
+    /// reject leaves that aren't the hasher's output size.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `leaves` is empty or longer than [`crate::index::max_leaf_count`];
+    /// [`MerkleTree::try_new`] returns an error instead.
+    pub fn new(leaves: Vec<Vec<u8>>, hasher: H) -> Self {
+        match Self::with_store(leaves, hasher, MemoryNodeStore::new()) {
+            Ok(tree) => tree,
+            Err(never) => match never {},
+        }
+    }
+
+    /// Creates a new Merkle tree, failing on no leaves or too many rather than panicking
+    pub fn try_new(leaves: Vec<Vec<u8>>, hasher: H) -> Result<Self, MerkleTreeError> {
+        check_leaf_count(leaves.len())?;
+        Ok(Self::new(leaves, hasher))
+    }
+
+    /// Creates a new Merkle tree from an iterator of leaves, such as a database cursor
+    ///
+    /// Only the leaf hashes are buffered, never the source data behind them.
+    /// The leaves are sorted as in [`MerkleTree::new`]; an empty iterator is an
+    /// error instead of a panic, as is a leaf that isn't the hasher's output size.
+    /// For leaves of another size, build with
+    /// [`MerkleTreeBuilder::build_from_iter`] and turn off
+    /// [`MerkleTreeBuilder::check_leaf_sizes`].
+    pub fn from_leaf_iter<I: IntoIterator<Item = Vec<u8>>>(leaves: I, hasher: H) -> Result<Self, MerkleTreeError> {
+        Self::builder(hasher).build_from_iter(leaves)
+    }
+
+    /// Creates a new Merkle tree that keeps the leaves in the given order
+    ///
+    /// Use this when a leaf's position carries meaning, such as chunks of a file.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `leaves` is empty or longer than [`crate::index::max_leaf_count`];
+    /// [`MerkleTree::try_new_ordered`] returns an error instead.
+    pub fn new_ordered(leaves: Vec<Vec<u8>>, hasher: H) -> Self {
+        match Self::with_store_ordered(leaves, hasher, MemoryNodeStore::new()) {
+            Ok(tree) => tree,
+            Err(never) => match never {},
+        }
+    }
+
+    /// Creates a tree that keeps the leaves in the given order, failing on no leaves or too many
+    pub fn try_new_ordered(leaves: Vec<Vec<u8>>, hasher: H) -> Result<Self, MerkleTreeError> {
+        check_leaf_count(leaves.len())?;
+        Ok(Self::new_ordered(leaves, hasher))
+    }
+}
+
+impl<H: Hasher> MerkleTree<H> {
+    /// Generates a Merkle proof for the leaf at the given index, borrowing the hashes from the tree
+    ///
+    /// Unlike [`MerkleTree::generate_proof`] this copies no node, which
+    /// matters when proving every leaf of a large tree.
+    ///
+    /// Lazy and pruned trees don't store the nodes to borrow, so this fails for them.
+    pub fn generate_proof_ref(&self, leaf_index: LeafIndex) -> Result<MerkleProofRef<'_, H>, MerkleTreeError> {
+        if !matches!(self.interior, Interior::Stored) {
+            return Err(MerkleTreeError::NodesNotStored);
+        }
+        let leaf_index = self.check_provable(leaf_index)?;
+
+        let mut proof_items = Vec::with_capacity(self.height - 1);
+        let mut current_index = leaf_index;
+
+        for level in 0..self.height - 1 {
+            let is_right_child = current_index % 2 == 1;
+            let sibling_index = current_index ^ 1;
+            let sibling = match self.store.try_get(level, sibling_index)? {
+                Some(sibling) => Some(sibling),
+                None => self.store.try_get(level, current_index)?,
+            }
+            .ok_or(MerkleTreeError::MissingNode { level, index: sibling_index as LeafIndex })?;
+            proof_items.push(ProofItemRef {
+                hash: sibling,
+                is_left: is_right_child,  // If current is right, sibling is left
+            });
+
+            current_index /= 2;
+        }
+
+        let leaf = self.store.try_get(0, leaf_index)?.ok_or(MerkleTreeError::MissingNode { level: 0, index: leaf_index as LeafIndex })?;
+        Ok(MerkleProofRef {
+            leaf,
+            proof_items,
+            hasher: &self.hasher,
+        })
+    }
+
+    /// Drops every level between the leaves and the root, reclaiming their memory
+    ///
+    /// The root, leaves and leaf index are kept, so `root`, `leaf_count`,
+    /// lookups and `verify_proof` are unaffected. Nodes are no longer stored
+    /// or cached: each `generate_proof` or `get_node` recomputes what it needs
+    /// from the leaves, which takes O(n) hashes per proof.
+    pub fn prune(&mut self) {
+        self.store.truncate(1);
+        self.interior = Interior::Pruned;
+    }
+
+    /// Replaces the leaf at `index`, returning the new root and the nodes that changed
+    ///
+    /// Only the leaf's path to the root is rehashed. Replacing the last leaf
+    /// also replaces its padding copies and the nodes above them. The
+    /// [`ProofDelta`] lists every node whose hash changed, so holders of
+    /// proofs from before the update can bring them up to date with
+    /// [`MerkleProof::apply_delta`] instead of asking for new ones.
+    ///
+    /// `new_leaf` is stored as given, so for index-bound trees it must
+    /// already be bound to `index`. A sorted tree is treated as unsorted
+    /// afterwards, and a leaf index is rebuilt, which takes O(n). Lazy and
+    /// pruned trees don't store the nodes to update, so this fails for them.
+    pub fn update_leaf_with_delta(&mut self, index: LeafIndex, new_leaf: Vec<u8>) -> Result<(Vec<u8>, ProofDelta), MerkleTreeError> {
+        let index = self.check_provable(index)?;
+        if !matches!(self.interior, Interior::Stored) {
+            return Err(MerkleTreeError::NodesNotStored);
+        }
+
+        let mut changes = Vec::new();
+        // The padding copies right of the last leaf, whole subtrees at a time
+        if index + 1 == self.unpadded_leaf_count {
+            let mut padding = new_leaf.clone();
+            for level in 0..self.height - 1 {
+                let start = self.unpadded_leaf_count.div_ceil(1 << level);
+                for position in start..self.leaf_count >> level {
+                    self.replace_node(level, position, &padding, &mut changes);
+                }
+                padding = self.hasher.hash_pair(&padding, &padding);
+            }
+        }
+        self.replace_node(0, index, &new_leaf, &mut changes);
+        let mut position = index;
+        for level in 1..self.height {
+            position /= 2;
+            let (left, right) = (self.store.get(level - 1, 2 * position), self.store.get(level - 1, 2 * position + 1));
+            let (Some(left), Some(right)) = (left, right) else {
+                return Err(MerkleTreeError::MissingNode { level: level - 1, index: 2 * position as LeafIndex });
+            };
+            let parent = self.hasher.hash_pair(left, right);
+            self.replace_node(level, position, &parent, &mut changes);
+        }
+        if let Some(root) = self.store.get(self.height - 1, 0) {
+            self.root.clone_from(root);
+        }
+
+        self.sorted = false;
+        let Ok(()) = self.write_meta();
+        if self.leaf_index.is_some() {
+            self.index_leaves();
+        }
+        changes.sort_unstable_by_key(|change: &NodeChange| (change.level, change.index));
+        Ok((self.root.clone(), ProofDelta { depth: self.height - 1, changes }))
+    }
+
+    /// Stores `hash` at `(level, index)`, recording the change if it differs from the node there
+    fn replace_node(&mut self, level: usize, index: usize, hash: &[u8], changes: &mut Vec<NodeChange>) {
+        if self.store.get(level, index).is_some_and(|old| old.as_slice() == hash) {
+            return;
+        }
+        let Ok(()) = self.store.put(level, index, hash.to_vec());
+        changes.push(NodeChange { level, index: index as LeafIndex, hash: hash.to_vec() });
+    }
+
+    /// Overwrites the leaves and every stored node with zeros and drops them, keeping only the root
+    ///
+    /// For trees over sensitive leaves whose proofs are no longer needed.
+    /// Afterwards `root`, `leaf_count` and `verify_proof_root_only` still
+    /// work, while everything that reads the leaves fails or finds nothing:
+    /// proof generation returns [`MerkleTreeError::LeavesZeroized`],
+    /// `get_leaf`, `get_node` and lookups return `None`, `verify_proof`
+    /// returns false since it looks the leaf up, and serializing the tree fails.
+    /// This is best effort: copies the caller made, such as earlier proofs,
+    /// aren't touched.
+    #[cfg(feature = "zeroize")]
+    pub fn zeroize_leaves(&mut self) {
+        use zeroize::Zeroize;
+        self.store.zeroize();
+        if let Some(leaf_index) = self.leaf_index.take() {
+            for (mut leaf, _) in leaf_index {
+                leaf.zeroize();
+            }
+        }
+        if let Interior::Lazy(cache) = &mut self.interior {
+            cache.zeroize();
+        }
+        self.interior = Interior::Zeroized;
+    }
+}
+
+impl<H: Hasher, L: AsRef<[u8]> + Clone> MerkleTree<H, BorrowedLeafStore<L>> {
+    /// Creates a tree over leaves the caller owns or lends, without copying their bytes
+    ///
+    /// The leaves are sorted by their bytes, as in [`MerkleTree::new`], so the
+    /// root matches a tree built from copies of them. Lookups and proofs work
+    /// as for any store, and proofs own their hashes, so they don't borrow
+    /// the leaves. Padding clones the last leaf, which for `&[u8]` and
+    /// `Arc<[u8]>` copies a pointer, not the bytes.
+    pub fn from_borrowed_leaves(mut leaves: Vec<L>, hasher: H) -> Result<Self, MerkleTreeError> {
+        check_leaf_count(leaves.len())?;
+        leaves.sort_unstable_by(|a, b| a.as_ref().cmp(b.as_ref()));
+        Self::over_borrowed_leaves(leaves, hasher, true)
+    }
+
+    /// Creates a tree over leaves the caller owns or lends, keeping them in the given order
+    pub fn from_borrowed_leaves_ordered(leaves: Vec<L>, hasher: H) -> Result<Self, MerkleTreeError> {
+        check_leaf_count(leaves.len())?;
+        Self::over_borrowed_leaves(leaves, hasher, false)
+    }
+
+    fn over_borrowed_leaves(mut leaves: Vec<L>, hasher: H, sorted: bool) -> Result<Self, MerkleTreeError> {
+        let unpadded_leaf_count = leaves.len();
+        let last = leaves[unpadded_leaf_count - 1].clone();
+        leaves.resize(unpadded_leaf_count.next_power_of_two(), last);
+        // Only leaf writes fail, and the leaves are already in the store
+        Ok(Self::over_stored_leaves(BorrowedLeafStore::new(leaves), unpadded_leaf_count, hasher, sorted)
+            .expect("no leaf is written"))
+    }
+}
+
+impl<H: Hasher, S: NodeStore> MerkleTree<H, S> {
+    /// Creates a new Merkle tree whose nodes are kept in the given store
+    ///
+    /// # Panics
+    ///
+    /// Panics if `leaves` is empty or longer than [`crate::index::max_leaf_count`].
+    /// The store's error type can't report those, so check the count first.
+    pub fn with_store(leaves: Vec<Vec<u8>>, hasher: H, store: S) -> Result<Self, S::Error> {
+        Self::with_store_observed(leaves, hasher, store, true, false, &mut ())
+    }
+
+    /// Creates a new Merkle tree in the given store, keeping the leaves in the given order
+    ///
+    /// # Panics
+    ///
+    /// Panics as [`MerkleTree::with_store`] does.
+    pub fn with_store_ordered(leaves: Vec<Vec<u8>>, hasher: H, store: S) -> Result<Self, S::Error> {
+        Self::with_store_observed(leaves, hasher, store, false, false, &mut ())
+    }
+
+    /// Creates a tree, optionally sorting the leaves, reporting each level to `observer`
+    ///
+    /// A lazy tree stores only its leaves and folds them into the root, so
+    /// `observer` isn't called. Panics on no leaves or too many, so public
+    /// callers either document that or check with `check_leaf_count` first.
+    pub(crate) fn with_store_observed<O: BuildObserver>(
+        mut leaves: Vec<Vec<u8>>,
+        hasher: H,
+        store: S,
+        sort: bool,
+        lazy: bool,
+        observer: &mut O
+    ) -> Result<Self, S::Error> {
         if leaves.is_empty() {
             panic!("Cannot create a Merkle tree with no leaves");
         }
 
-        leaves.sort();
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("merkle_tree.build", leaf_count = leaves.len(), sorted = sort).entered();
+
+        // The height is log2(next_power_of_2(leaves.len())) + 1; the count is
+        // checked so it can't wrap to 0 where `usize` is narrow
+        let next_power_of_2 = crate::index::padded_leaf_count(leaves.len() as u64)
+            .expect("Cannot create a Merkle tree with more than index::max_leaf_count() leaves") as usize;
+        
+        if sort {
+            // Equal leaves are identical, so an unstable sort gives the same order
+            // without the stable sort's scratch buffer
+            crate::sort::sort_leaves(&mut leaves);
+        }
 
         let mut tree = MerkleTree {
-            leaves: leaves.clone(),
-            nodes: HashMap::new(),
-            height: 0,
+            store,
+            leaf_count: next_power_of_2,
+            unpadded_leaf_count: leaves.len(),
+            height: next_power_of_2.trailing_zeros() as usize + 1,
+            root: Vec::new(),
+            leaf_index: None,
+            // Padding repeats the largest leaf, so the padded level stays sorted
+            sorted: sort,
+            built_sorted: sort,
+            index_bound: false,
+            build_stats: None,
+            interior: if lazy { Interior::Lazy(NodeCache::default()) } else { Interior::Stored },
             hasher,
         };
         
-        // Calculate the height of the tree
-        // The height is log2(next_power_of_2(leaves.len())) + 1
-        let next_power_of_2 = if leaves.len().is_power_of_two() {
-            leaves.len()
-        } else {
-            leaves.len().next_power_of_two()
-        };
-        
-        tree.height = next_power_of_2.trailing_zeros() as usize + 1;
-        
         // Build the tree
-        tree.build();
+        tree.build(leaves, observer)?;
+        tree.write_meta()?;
         
-        tree
+        #[cfg(feature = "tracing")]
+        tracing::debug!(height = tree.height, root = %crate::trace::short_hex(&tree.root), "built tree");
+
+        Ok(tree)
     }
     
+    /// Opens a tree previously built into the given store, without rebuilding it
+    ///
+    /// The padded leaf count is recovered by probing level 0, so the store
+    /// must hold a single complete tree. The unpadded count and construction
+    /// options come from the record the tree wrote with
+    /// [`NodeStore::put_meta`]; a store without one, such as one using the
+    /// trait's default that drops it, can't tell real leaves from padding and
+    /// fails with [`MerkleTreeError::IncompleteStore`].
+    pub fn from_store(store: S, hasher: H) -> Result<Self, MerkleTreeError> {
+        let mut tree = Self::open_store(store, hasher)?;
+        tree.restore_meta()?;
+        Ok(tree)
+    }
+
+    /// Opens the tree in a store, counting every padded leaf as real
+    fn open_store(store: S, hasher: H) -> Result<Self, MerkleTreeError> {
+        if store.try_get(0, 0)?.is_none() {
+            return Err(MerkleTreeError::IncompleteStore);
+        }
+
+        // The leaf count is a power of two, so double until the last leaf is missing
+        let mut leaf_count: usize = 1;
+        while let Some(next) = leaf_count.checked_mul(2) {
+            if store.try_get(0, next - 1)?.is_none() {
+                break;
+            }
+            leaf_count = next;
+        }
+
+        let height = leaf_count.trailing_zeros() as usize + 1;
+        let root = match store.try_get(height - 1, 0)? {
+            Some(root) if store.try_get(height - 1, 1)?.is_none() => root.as_ref().to_vec(),
+            _ => return Err(MerkleTreeError::IncompleteStore),
+        };
+
+        Ok(MerkleTree {
+            store,
+            leaf_count,
+            unpadded_leaf_count: leaf_count,
+            height,
+            root,
+            leaf_index: None,
+            sorted: false,
+            built_sorted: false,
+            index_bound: false,
+            build_stats: None,
+            interior: Interior::Stored,
+            hasher,
+        })
+    }
+
+    /// Restores the leaf count and construction options from the store's record
+    ///
+    /// The count must pad to the stored leaves' power of two.
+    fn restore_meta(&mut self) -> Result<(), MerkleTreeError> {
+        let meta = self.store.get_meta()?.ok_or(MerkleTreeError::IncompleteStore)?;
+        let meta = StoreMeta::from_bytes(&meta).ok_or(MerkleTreeError::IncompleteStore)?;
+        let count = usize::try_from(meta.unpadded_leaf_count).map_err(|_| MerkleTreeError::IncompleteStore)?;
+        if count == 0 || count.next_power_of_two() != self.leaf_count {
+            return Err(MerkleTreeError::IncompleteStore);
+        }
+        self.unpadded_leaf_count = count;
+        self.built_sorted = meta.built_sorted;
+        self.index_bound = meta.index_bound;
+        self.sorted = meta.sorted;
+        Ok(())
+    }
+
+    /// Writes the store's record of the tree, see [`StoreMeta`]
+    fn write_meta(&mut self) -> Result<(), S::Error> {
+        let meta = StoreMeta {
+            unpadded_leaf_count: self.unpadded_leaf_count as u64,
+            built_sorted: self.built_sorted,
+            index_bound: self.index_bound,
+            sorted: self.sorted,
+        };
+        self.store.put_meta(meta.to_bytes())
+    }
+
+    /// Builds the levels above the padded leaves already in `store`
+    pub(crate) fn over_stored_leaves(
+        store: S,
+        unpadded_leaf_count: usize,
+        hasher: H,
+        sorted: bool
+    ) -> Result<Self, S::Error> {
+        let leaf_count = crate::index::padded_leaf_count(unpadded_leaf_count as u64)
+            .expect("the leaf count was checked") as usize;
+        let mut tree = MerkleTree {
+            store,
+            leaf_count,
+            unpadded_leaf_count,
+            height: leaf_count.trailing_zeros() as usize + 1,
+            root: Vec::new(),
+            leaf_index: None,
+            sorted,
+            built_sorted: sorted,
+            index_bound: false,
+            build_stats: None,
+            interior: Interior::Stored,
+            hasher,
+        };
+        tree.write_meta()?;
+
+        let leaf = |index| tree.store.get(0, index).expect("the store holds every padded leaf");
+        if tree.height == 1 {
+            tree.root = leaf(0).as_ref().to_vec();
+            return Ok(tree);
+        }
+        let first_level = (0..leaf_count / 2)
+            .map(|index| {
+                let mut node = Vec::new();
+                tree.hasher.hash_pair_into(leaf(2 * index).as_ref(), leaf(2 * index + 1).as_ref(), &mut node);
+                node
+            })
+            .collect();
+        tree.build_from(1, first_level, &mut ())?;
+        Ok(tree)
+    }
+
     /// Builds the Merkle tree
-    fn build(&mut self) {
+    fn build<O: BuildObserver>(&mut self, mut leaves: Vec<Vec<u8>>, observer: &mut O) -> Result<(), S::Error> {
         // Extend leaves to the next power of 2 if necessary
-        let target_length = 1 << (self.height - 1);
+        let target_length = self.leaf_count;
         
-        if self.leaves.len() < target_length {
-            let last_leaf = self.leaves.last().unwrap().clone();
-            while self.leaves.len() < target_length {
-                self.leaves.push(last_leaf.clone());
-            }
+        if let Some(last_leaf) = leaves.last().filter(|_| leaves.len() < target_length).cloned() {
+            leaves.reserve_exact(target_length - leaves.len());
+            leaves.resize(target_length, last_leaf);
         }
         
-        // Add leaves to the nodes map
-        for (i, leaf) in self.leaves.iter().enumerate() {
-            self.nodes.insert((0, i), leaf.clone());
+        if let Interior::Lazy(_) = self.interior {
+            self.root = crate::utils::streaming_root(&leaves, &self.hasher).unwrap_or_default();
+            return self.store.put_level(0, leaves);
         }
-        
-        // Build the tree from bottom to top
-        for level in 0..self.height - 1 {
-            let next_level_width = 1 << (self.height - 2 - level);
-            for i in 0..next_level_width {
-                let left = self.nodes.get(&(level, i * 2)).unwrap().clone();
-                let right = self.nodes.get(&(level, i * 2 + 1)).unwrap().clone();
+        self.build_from(0, leaves, observer)
+    }
+
+    /// Builds the tree from `current_level`, at `start`, to the top
+    ///
+    /// Each level moves into the store once its parents are hashed, so its
+    /// nodes are never copied.
+    fn build_from<O: BuildObserver>(
+        &mut self,
+        start: usize,
+        mut current_level: Vec<Vec<u8>>,
+        observer: &mut O
+    ) -> Result<(), S::Error> {
+        for level in start..self.height {
+            let next_level: Vec<Vec<u8>> = if level + 1 < self.height {
+                // Levels below the root have even length, so no node is left out
+                let next_level: Vec<Vec<u8>> = current_level.chunks_exact(2)
+                    .map(|pair| {
+                        // Each node keeps its own buffer, so it's allocated once at its final size
+                        let mut node = Vec::new();
+                        self.hasher.hash_pair_into(&pair[0], &pair[1], &mut node);
+                        node
+                    })
+                    .collect();
+                observer.level_hashed(level + 1, next_level.len());
+                next_level
+            } else {
+                // The height comes from the padded leaf count, so the top level is one node
+                self.root = current_level.first().expect("the top level holds the root").clone();
+                Vec::new()
+            };
                 
-                let parent = self.hash_pair(&left, &right);
-                self.nodes.insert((level + 1, i), parent);
-            }
+            self.store.put_level(level, current_level)?;
+            current_level = next_level;
         }
+
+        Ok(())
     }
     
     /// Gets the root of the Merkle tree
-    pub fn root(&self) -> Vec<u8> {
-        self.nodes.get(&(self.height - 1, 0)).unwrap().clone()
+    ///
+    /// The root is kept outside the store, so this can't fail even for lazy,
+    /// pruned or reopened trees. Use `.to_vec()` where an owned root is needed.
+    pub fn root(&self) -> &[u8] {
+        &self.root
     }
     
+    /// Gets the node at the given level and index, with the leaves on level 0
+    ///
+    /// Lazy trees compute the node from the leaves on first access, and
+    /// pruned trees on every access. A failed store read is reported as
+    /// `None`; use [`MerkleTree::try_get_node`] to tell the two apart.
+    pub fn get_node(&self, level: usize, index: LeafIndex) -> Option<Vec<u8>> {
+        self.try_get_node(level, index).ok().flatten()
+    }
+
+    /// Gets the node at the given level and index, failing if the store can't be read
+    pub fn try_get_node(&self, level: usize, index: LeafIndex) -> Result<Option<Vec<u8>>, MerkleTreeError> {
+        let Some(index) = crate::index::to_usize(index) else { return Ok(None) };
+        Ok(self.with_node(level, index, <[u8]>::to_vec)?)
+    }
+
+    /// Calls `f` with a node, borrowing it from the store where it's stored
+    fn with_node<R>(&self, level: usize, index: usize, f: impl FnOnce(&[u8]) -> R) -> Result<Option<R>, StoreReadError> {
+        if level >= self.height || index >= self.leaf_count >> level {
+            return Ok(None);
+        }
+        Ok(match &self.interior {
+            Interior::Stored => self.store.try_get(level, index)?.map(|node| f(node.as_ref())),
+            #[cfg(feature = "zeroize")]
+            Interior::Zeroized => None,
+            _ if level == 0 => self.store.try_get(0, index)?.map(|node| f(node.as_ref())),
+            _ if level == self.height - 1 => Some(f(&self.root)),
+            Interior::Lazy(cache) => self.compute_node(Some(cache), level, index)?.map(|node| f(&node)),
+            Interior::Pruned => self.compute_node(None, level, index)?.map(|node| f(&node)),
+        })
+    }
+
+    /// Computes an interior node from its children, caching it if there is a cache
+    ///
+    /// The cache is only locked for lookups and inserts, never across the
+    /// recursion.
+    fn compute_node(&self, cache: Option<&NodeCache>, level: usize, index: usize) -> Result<Option<Vec<u8>>, StoreReadError> {
+        if level == 0 {
+            return Ok(self.store.try_get(0, index)?.map(|leaf| leaf.as_ref().to_vec()));
+        }
+        if let Some(node) = cache.and_then(|cache| cache.get(level, index)) {
+            return Ok(Some(node));
+        }
+        let Some(left) = self.compute_node(cache, level - 1, 2 * index)? else { return Ok(None) };
+        let Some(right) = self.compute_node(cache, level - 1, 2 * index + 1)? else { return Ok(None) };
+        let mut node = Vec::new();
+        self.hasher.hash_pair_into(&left, &right, &mut node);
+        if let Some(cache) = cache {
+            cache.insert(level, index, &node);
+        }
+        Ok(Some(node))
+    }
+
+    /// Checks whether the leaves were scrubbed by [`MerkleTree::zeroize_leaves`]
+    #[cfg(feature = "zeroize")]
+    pub fn is_zeroized(&self) -> bool {
+        matches!(self.interior, Interior::Zeroized)
+    }
+
     /// Gets the leaf at the given index
-    pub fn get_leaf(&self, index: usize) -> Option<&Vec<u8>> {
-        self.leaves.get(index)
+    pub fn get_leaf(&self, index: LeafIndex) -> Option<S::Node<'_>> {
+        match crate::index::to_usize(index) {
+            Some(index) if index < self.leaf_count => self.store.get(0, index),
+            _ => None,
+        }
     }
 
     /// get the hasher of the tree
@@ -93,23 +809,211 @@ impl<H: Hasher> MerkleTree<H> {
         self.hasher.clone()
     }
 
-    /// Gets the number of leaves in the tree
-    pub fn leaf_count(&self) -> usize {
-        self.leaves.len()
+    /// Gets the node store backing the tree
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Consumes the tree, returning its node store
+    pub fn into_store(self) -> S {
+        self.store
+    }
+
+    /// Flushes the node store, making the tree durable for persistent stores
+    pub fn flush(&mut self) -> Result<(), S::Error> {
+        self.store.flush()
+    }
+
+    /// Gets the instrumentation recorded during construction
+    ///
+    /// Only trees built with [`MerkleTreeBuilder::collect_stats`] have stats.
+    pub fn build_stats(&self) -> Option<&BuildStats> {
+        self.build_stats.as_ref()
+    }
+
+    /// Attaches instrumentation recorded by the builder
+    pub(crate) fn set_build_stats(&mut self, stats: BuildStats) {
+        self.build_stats = Some(stats);
+    }
+
+    /// Records that the leaves were sorted before they reached the tree
+    pub(crate) fn set_sorted(&mut self) -> Result<(), S::Error> {
+        self.sorted = true;
+        self.built_sorted = true;
+        self.write_meta()
+    }
+
+    /// Checks whether the leaves are known to be sorted
+    pub(crate) fn is_sorted(&self) -> bool {
+        self.sorted
+    }
+
+    /// Records that each leaf was hashed with its index
+    pub(crate) fn set_index_bound(&mut self) -> Result<(), S::Error> {
+        self.index_bound = true;
+        self.write_meta()
+    }
+
+    /// Restores the construction options of a tree loaded from a file
+    ///
+    /// The file's leaves are untrusted, so whether they are still sorted is
+    /// checked rather than taken from the file.
+    #[cfg(feature = "std")]
+    pub(crate) fn restore_params(&mut self, built_sorted: bool, index_bound: bool, still_sorted: bool) -> Result<(), S::Error> {
+        self.built_sorted = built_sorted;
+        self.index_bound = index_bound;
+        self.sorted = built_sorted && still_sorted;
+        self.write_meta()
+    }
+
+    /// Checks whether each leaf was hashed with its index, see [`MerkleTreeBuilder::bind_indices`]
+    ///
+    /// Trees that don't know their options report `false`, see [`MerkleTree::params`].
+    pub fn is_index_bound(&self) -> bool {
+        self.index_bound
+    }
+
+    /// Estimates the heap memory held by the tree, broken down by part
+    ///
+    /// Stores that keep nodes outside the heap, such as sled or the mapped
+    /// leaf file, report those levels as 0.
+    pub fn memory_breakdown(&self) -> MemoryBreakdown {
+        let mut nodes = (1..self.height).map(|level| self.store.approx_level_bytes(level)).sum::<usize>();
+        if let Interior::Lazy(cache) = &self.interior {
+            nodes += cache.approx_bytes(self.height);
+        }
+        let indexes = self.leaf_index.as_ref().map_or(0, |leaf_index| {
+            // A key buffer per distinct leaf and a slot per entry, plus a
+            // control byte per slot for hash maps
+            let keys = leaf_index.keys().map(Vec::capacity).sum::<usize>();
+            #[cfg(feature = "std")]
+            let slots = leaf_index.capacity() * (core::mem::size_of::<(Vec<u8>, usize)>() + 1);
+            #[cfg(not(feature = "std"))]
+            let slots = leaf_index.len() * core::mem::size_of::<(Vec<u8>, usize)>();
+            keys + slots
+        });
+
+        MemoryBreakdown {
+            leaves: self.store.approx_level_bytes(0),
+            nodes: nodes + self.root.capacity(),
+            indexes,
+        }
+    }
+
+    /// Estimates the total heap memory held by the tree, in bytes
+    pub fn approx_memory_bytes(&self) -> usize {
+        self.memory_breakdown().total()
+    }
+
+    /// Gets the number of leaves in the tree, including padding
+    pub fn leaf_count(&self) -> u64 {
+        self.leaf_count as u64
     }
     
+    /// Gets the number of leaves given on construction, without padding
+    ///
+    /// Leaves from this index up to [`MerkleTree::leaf_count`] are padding.
+    pub fn unpadded_leaf_count(&self) -> u64 {
+        self.unpadded_leaf_count as u64
+    }
+
+    /// Checks whether the leaf at `index` was added to pad the tree to a power of two
+    ///
+    /// Padding copies the last leaf, so a padding position holds a value
+    /// that is committed to only once. Proofs and lookups skip them: a proof
+    /// for one would make a duplicate appear committed.
+    ///
+    /// A future breaking release may pad with a domain-separated empty hash
+    /// instead, which couldn't be mistaken for a leaf at all.
+    pub fn is_padding(&self, index: LeafIndex) -> bool {
+        index >= self.unpadded_leaf_count() && index < self.leaf_count()
+    }
+
+    /// Fails for indices past the end of the tree and for padding leaves,
+    /// returning the leaf's position in the store
+    pub(crate) fn check_provable(&self, leaf_index: LeafIndex) -> Result<usize, MerkleTreeError> {
+        #[cfg(feature = "zeroize")]
+        if self.is_zeroized() {
+            return Err(MerkleTreeError::LeavesZeroized);
+        }
+        let Some(position) = crate::index::to_usize(leaf_index).filter(|&position| position < self.leaf_count) else {
+            return Err(MerkleTreeError::LeafIndexOutOfBounds { index: leaf_index, len: self.leaf_count() });
+        };
+        if self.is_padding(leaf_index) {
+            return Err(MerkleTreeError::PaddingLeaf { index: leaf_index });
+        }
+        Ok(position)
+    }
+
     /// Finds the leaf index for a given leaf value
-    pub fn find_leaf_index(&self, leaf_value: &[u8]) -> Option<usize> {
-        self.leaves.iter().position(|leaf| leaf == leaf_value)
+    ///
+    /// Returns the first matching index, never a padding leaf. Uses the leaf index if the tree was
+    /// built with one (see [`MerkleTreeBuilder::index_leaves`]), a binary
+    /// search if the leaves were sorted on construction, and a linear scan
+    /// for trees built in a given order.
+    pub fn find_leaf_index(&self, leaf_value: &[u8]) -> Option<LeafIndex> {
+        self.find_leaf_position(leaf_value).map(|position| position as LeafIndex)
+    }
+
+    /// Finds the store position of the first leaf with the given value
+    fn find_leaf_position(&self, leaf_value: &[u8]) -> Option<usize> {
+        if let Some(leaf_index) = &self.leaf_index {
+            return leaf_index.get(leaf_value).copied();
+        }
+        if self.sorted {
+            // Lower bound, so duplicates give their first index
+            let (mut low, mut high) = (0, self.unpadded_leaf_count);
+            while low < high {
+                let mid = low + (high - low) / 2;
+                match self.store.get(0, mid) {
+                    Some(leaf) if leaf.as_ref() < leaf_value => low = mid + 1,
+                    _ => high = mid,
+                }
+            }
+            let found = low < self.unpadded_leaf_count
+                && self.store.get(0, low).is_some_and(|leaf| leaf.as_ref() == leaf_value);
+            return found.then_some(low);
+        }
+        (0..self.unpadded_leaf_count).position(|index| {
+            self.store.get(0, index).is_some_and(|leaf| leaf.as_ref() == leaf_value)
+        })
     }
     
-    /// Generates a Merkle proof for the leaf at the given index
-    pub fn generate_proof(&self, leaf_index: usize) -> Result<MerkleProof<H>, &'static str> {
-        if leaf_index >= self.leaves.len() {
-            return Err("Leaf index out of bounds");
+    /// Looks up caller-held metadata for a leaf hash, such as a proof's leaf
+    ///
+    /// `metadata` is in leaf order: for trees built in the given order that
+    /// is the input order. Duplicate leaves resolve to the first, as in
+    /// [`MerkleTree::find_leaf_index`].
+    pub fn metadata_for_leaf<'a, M>(&self, leaf_hash: &[u8], metadata: &'a [M]) -> Option<&'a M> {
+        metadata.get(self.find_leaf_position(leaf_hash)?)
+    }
+
+    /// Checks whether the tree has a leaf with the given value
+    pub fn contains(&self, leaf_value: &[u8]) -> bool {
+        self.find_leaf_position(leaf_value).is_some()
+    }
+
+    /// Builds the index from leaf value to its first position
+    pub(crate) fn index_leaves(&mut self) {
+        let mut leaf_index = LeafLookup::new();
+        for index in 0..self.unpadded_leaf_count {
+            if let Some(leaf) = self.store.get(0, index) {
+                leaf_index.entry(leaf.as_ref().to_vec()).or_insert(index);
+            }
         }
+        self.leaf_index = Some(leaf_index);
+    }
+
+    /// Generates a Merkle proof for the leaf at the given index
+    ///
+    /// Padding leaves (see [`MerkleTree::is_padding`]) have no proof.
+    pub fn generate_proof(&self, leaf_index: LeafIndex) -> Result<MerkleProof<H>, MerkleTreeError> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("merkle_tree.generate_proof", index = leaf_index, depth = self.height - 1).entered();
+
+        let leaf_index = self.check_provable(leaf_index)?;
         
-        let mut proof_items = Vec::new();
+        let mut proof_items = Vec::with_capacity(self.height - 1);
         let mut current_index = leaf_index;
         
         for level in 0..self.height - 1 {
@@ -120,15 +1024,16 @@ impl<H: Hasher> MerkleTree<H> {
                 current_index + 1  // Sibling is on the right
             };
             
-            if let Some(sibling) = self.nodes.get(&(level, sibling_index)) {
+            if let Some(sibling) = self.with_node(level, sibling_index, ProofHash::from_slice)? {
                 proof_items.push(ProofItem {
-                    hash: sibling.clone(),
+                    hash: sibling,
                     is_left: is_right_child,  // If current is right, sibling is left
                 });
             } else {
                 // If the sibling doesn't exist (at the edge of an odd-length level),
                 // use the current node as its own sibling but with appropriate direction
-                let current_node = self.nodes.get(&(level, current_index)).unwrap().clone();
+                let current_node = self.with_node(level, current_index, ProofHash::from_slice)?
+                    .ok_or(MerkleTreeError::MissingNode { level, index: current_index as LeafIndex })?;
                 proof_items.push(ProofItem {
                     hash: current_node,
                     is_left: is_right_child,
@@ -138,31 +1043,145 @@ impl<H: Hasher> MerkleTree<H> {
             current_index /= 2;
         }
         
+        let leaf = self.store.try_get(0, leaf_index)?
+            .ok_or(MerkleTreeError::MissingNode { level: 0, index: leaf_index as LeafIndex })?
+            .as_ref()
+            .to_vec();
         Ok(MerkleProof::new(
-            self.leaves[leaf_index].clone(),
+            leaf,
             proof_items,
             self.hasher.clone(),
         ))
     }
     
+    /// Generates a Merkle proof for the leaf hashed from `data` at `index`
+    ///
+    /// The leaf is rebuilt as the tree hashed it, with the index prefixed for
+    /// index-bound trees, and must be the leaf at `index`.
+    pub fn generate_proof_for_data(&self, index: LeafIndex, data: &[u8]) -> Result<MerkleProof<H>, MerkleTreeError> {
+        let position = self.check_provable(index)?;
+        let leaf = if self.index_bound {
+            crate::utils::hash_indexed_leaf(&self.hasher, index, data)
+        } else {
+            self.hasher.hash_leaf(data)
+        };
+        if self.store.get(0, position).is_none_or(|stored| stored.as_ref() != leaf.as_slice()) {
+            return Err(MerkleTreeError::LeafNotFound);
+        }
+        self.generate_proof(index)
+    }
+
     /// Generates a Merkle proof for the given leaf value
-    pub fn generate_proof_by_value(&self, leaf_value: &[u8]) -> Result<MerkleProof<H>, &'static str> {
+    pub fn generate_proof_by_value(&self, leaf_value: &[u8]) -> Result<MerkleProof<H>, MerkleTreeError> {
         if let Some(index) = self.find_leaf_index(leaf_value) {
             self.generate_proof(index)
         } else {
-            Err("Leaf value not found in the tree")
+            Err(MerkleTreeError::LeafNotFound)
         }
     }
     
-    /// Verifies a Merkle proof
+    /// Generates proofs for the leaves at the given indices, in the same order
+    pub fn generate_proofs(&self, indices: &[LeafIndex]) -> Result<Vec<MerkleProof<H>>, MerkleTreeError> {
+        indices.iter().map(|&index| self.generate_proof(index)).collect()
+    }
+
+    /// Generates proofs for every leaf matching the predicate, with their leaf indices
+    ///
+    /// Padding leaves are skipped; any other leaf that can't be proven fails
+    /// the whole call rather than being left out.
+    pub fn generate_proofs_where<F: FnMut(&[u8]) -> bool>(
+        &self,
+        mut predicate: F
+    ) -> Result<Vec<(LeafIndex, MerkleProof<H>)>, MerkleTreeError> {
+        (0..self.leaf_count())
+            .filter(|&index| !self.is_padding(index))
+            .filter_map(|index| self.proof_if_matches(index, &mut predicate).transpose())
+            .collect()
+    }
+
+    /// Generates the proof for a leaf if it matches the predicate
+    fn proof_if_matches(
+        &self,
+        index: LeafIndex,
+        predicate: impl FnOnce(&[u8]) -> bool,
+    ) -> Result<Option<(LeafIndex, MerkleProof<H>)>, MerkleTreeError> {
+        let position = self.check_provable(index)?;
+        let leaf = self.store.try_get(0, position)?.ok_or(MerkleTreeError::MissingNode { level: 0, index })?;
+        if !predicate(leaf.as_ref()) {
+            return Ok(None);
+        }
+        Ok(Some((index, self.generate_proof(index)?)))
+    }
+
+    /// Verifies a Merkle proof, including that its leaf is one of this tree's leaves
+    ///
+    /// A proof can hash up to the root without proving a leaf: an interior
+    /// node with the rest of its path does too. This rejects such proofs by
+    /// looking the leaf up with [`MerkleTree::find_leaf_index`], which is a
+    /// linear scan for ordered trees built without a leaf index.
     pub fn verify_proof(&self, proof: &MerkleProof<H>) -> bool {
-        let calculated_root = proof.calculate_root();
-        self.root() == calculated_root
+        self.contains(&proof.leaf) && self.verify_proof_root_only(proof)
     }
 
-    
-    /// Hashes two nodes together to create a parent node
-    fn hash_pair(&self, left: &[u8], right: &[u8]) -> Vec<u8> {
-        self.hasher.hash_pair(left, right)
+    /// Verifies only that the proof hashes up to the root, as a verifier
+    /// holding just the root does
+    ///
+    /// This is the check [`MerkleProof::verify`] makes; it doesn't confirm
+    /// the proof's leaf is a leaf of this tree.
+    pub fn verify_proof_root_only(&self, proof: &MerkleProof<H>) -> bool {
+        proof.verify(self.root())
+    }
+
+    /// Gets the construction options [`MerkleTree::bound_root`] commits to
+    ///
+    /// These are kept through updates, saving and reopening.
+    pub fn params(&self) -> TreeParams {
+        let mut params = TreeParams::new(&self.hasher, self.built_sorted);
+        params.index_bound = self.index_bound;
+        params
+    }
+
+    /// Gets the root bound to the leaf count and construction options
+    ///
+    /// See [`crate::bound`]; verify proofs against it with
+    /// [`MerkleProof::verify_bound`].
+    pub fn bound_root(&self) -> Vec<u8> {
+        bound::bound_root(&self.hasher, self.root(), self.unpadded_leaf_count(), &self.params())
+    }
+}
+
+/// Parallel proof generation; the tree is only read, so workers share it without locking
+#[cfg(feature = "rayon")]
+impl<H: Hasher + Send + Sync, S: NodeStore + Sync> MerkleTree<H, S> {
+    /// Generates a proof for every leaf but the padding in parallel, in leaf order
+    ///
+    /// Fails if any leaf other than padding can't be proven.
+    pub fn generate_all_proofs_parallel(&self) -> Result<Vec<MerkleProof<H>>, MerkleTreeError> {
+        use rayon::prelude::*;
+
+        (0..self.leaf_count()).into_par_iter()
+            .filter(|&index| !self.is_padding(index))
+            .map(|index| self.generate_proof(index))
+            .collect()
+    }
+
+    /// The parallel counterpart of [`MerkleTree::generate_proofs`]
+    pub fn generate_proofs_parallel(&self, indices: &[LeafIndex]) -> Result<Vec<MerkleProof<H>>, MerkleTreeError> {
+        use rayon::prelude::*;
+
+        indices.par_iter().map(|&index| self.generate_proof(index)).collect()
+    }
+
+    /// The parallel counterpart of [`MerkleTree::generate_proofs_where`]
+    pub fn generate_proofs_where_parallel<F: Fn(&[u8]) -> bool + Sync>(
+        &self,
+        predicate: F
+    ) -> Result<Vec<(LeafIndex, MerkleProof<H>)>, MerkleTreeError> {
+        use rayon::prelude::*;
+
+        (0..self.leaf_count()).into_par_iter()
+            .filter(|&index| !self.is_padding(index))
+            .filter_map(|index| self.proof_if_matches(index, &predicate).transpose())
+            .collect()
     }
 }
@@ -0,0 +1,150 @@
+//! A Merkle tree that keeps the values its leaves were hashed from
+//!
+//! [`TypedMerkleTree`] hashes each value with `hash_leaf(value.as_ref())`
+//! and stores the values in leaf order, so a value and its proof can be
+//! looked up together without a parallel `Vec` on the caller's side.
+use alloc::vec::Vec;
+use crate::error::MerkleTreeError;
+use crate::hasher::Hasher;
+use crate::index::{self, LeafIndex};
+use crate::proof::MerkleProof;
+use crate::tree::MerkleTree;
+
+/// A Merkle tree over values of type `T`, keeping the values alongside their leaves
+pub struct TypedMerkleTree<T: AsRef<[u8]>, H: Hasher> {
+    /// The values, in the order of their leaves
+    values: Vec<T>,
+    /// The tree over the values' leaf hashes
+    tree: MerkleTree<H>,
+}
+
+impl<T: AsRef<[u8]>, H: Hasher> TypedMerkleTree<T, H> {
+    /// Creates a tree with the leaves sorted by hash, as in [`MerkleTree::new`]
+    ///
+    /// The values are reordered along with their leaves; values whose leaves
+    /// are equal keep their relative order.
+    pub fn new(values: Vec<T>, hasher: H) -> Result<Self, MerkleTreeError> {
+        let mut pairs: Vec<(Vec<u8>, T)> = values.into_iter()
+            .map(|value| (hasher.hash_leaf(value.as_ref()), value))
+            .collect();
+        pairs.sort_by(|(a, _), (b, _)| a.cmp(b));
+        let (leaves, values) = pairs.into_iter().unzip();
+        Self::build(leaves, values, hasher, true)
+    }
+
+    /// Creates a tree that keeps the values in the given order
+    pub fn new_ordered(values: Vec<T>, hasher: H) -> Result<Self, MerkleTreeError> {
+        let leaves = values.iter().map(|value| hasher.hash_leaf(value.as_ref())).collect();
+        Self::build(leaves, values, hasher, false)
+    }
+
+    fn build(leaves: Vec<Vec<u8>>, values: Vec<T>, hasher: H, sorted: bool) -> Result<Self, MerkleTreeError> {
+        if values.is_empty() {
+            return Err(MerkleTreeError::EmptyLeaves);
+        }
+        let mut tree = MerkleTree::new_ordered(leaves, hasher);
+        if sorted {
+            let Ok(()) = tree.set_sorted();
+        }
+        Ok(TypedMerkleTree { values, tree })
+    }
+
+    /// Builds an index from leaf hash to position, making reverse lookups O(1)
+    ///
+    /// Without it, lookups binary search sorted trees and scan ordered ones,
+    /// as [`MerkleTree::find_leaf_index`] does.
+    pub fn with_leaf_index(mut self) -> Self {
+        self.tree.index_leaves();
+        self
+    }
+
+    /// Gets the value at the given leaf index; padding leaves have none
+    pub fn value(&self, index: LeafIndex) -> Option<&T> {
+        self.values.get(index::to_usize(index)?)
+    }
+
+    /// Gets the values in leaf order
+    pub fn values(&self) -> &[T] {
+        &self.values
+    }
+
+    /// Gets the number of values, which is the leaf count without padding
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Always false, since a tree has at least one value
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Gets the tree over the values' leaf hashes
+    pub fn tree(&self) -> &MerkleTree<H> {
+        &self.tree
+    }
+
+    /// Gets the root hash
+    pub fn root(&self) -> &[u8] {
+        self.tree.root()
+    }
+
+    /// Finds the index of the first leaf holding a value with the same bytes
+    pub fn find_value(&self, value: &T) -> Option<LeafIndex> {
+        let leaf = self.tree.get_hasher().hash_leaf(value.as_ref());
+        self.tree.find_leaf_index(&leaf)
+    }
+
+    /// Recovers the value a leaf hash, such as a proof's leaf, was hashed from
+    ///
+    /// If several values hash to the same leaf this is the one with the
+    /// lowest index; [`TypedMerkleTree::originals_for_leaf`] returns them all.
+    pub fn original_for_leaf(&self, leaf_hash: &[u8]) -> Option<&T> {
+        self.value(self.tree.find_leaf_index(leaf_hash)?)
+    }
+
+    /// Gets every value hashed to `leaf_hash` with its index, in leaf order
+    pub fn originals_for_leaf<'a>(&'a self, leaf_hash: &'a [u8]) -> impl Iterator<Item = (LeafIndex, &'a T)> + 'a {
+        let first = self.tree.find_leaf_index(leaf_hash).unwrap_or(self.tree.unpadded_leaf_count());
+        (first..self.tree.unpadded_leaf_count())
+            .filter(move |&index| self.tree.get_leaf(index).is_some_and(|leaf| leaf.as_slice() == leaf_hash))
+            .filter_map(|index| Some((index, self.value(index)?)))
+    }
+
+    /// Gets the value at the given index together with its proof
+    pub fn prove(&self, index: LeafIndex) -> Result<(&T, MerkleProof<H>), MerkleTreeError> {
+        let proof = self.tree.generate_proof(index)?;
+        // Every provable index is below the number of values
+        let value = self.value(index).ok_or(MerkleTreeError::PaddingLeaf { index })?;
+        Ok((value, proof))
+    }
+
+    /// Generates a proof for the first leaf holding a value with the same bytes
+    pub fn prove_value(&self, value: &T) -> Result<MerkleProof<H>, MerkleTreeError> {
+        let index = self.find_value(value).ok_or(MerkleTreeError::LeafNotFound)?;
+        self.tree.generate_proof(index)
+    }
+
+    /// Iterates over the values with their leaf hashes and indices, in leaf order
+    pub fn iter(&self) -> impl Iterator<Item = (&T, &[u8], LeafIndex)> + '_ {
+        self.values.iter().zip(0..).filter_map(|(value, index)| {
+            Some((value, self.tree.get_leaf(index)?.as_slice(), index))
+        })
+    }
+
+    /// Consumes the tree, returning the values in leaf order and the tree
+    pub fn into_parts(self) -> (Vec<T>, MerkleTree<H>) {
+        (self.values, self.tree)
+    }
+}
+
+/// Overwrites the values, then the tree's leaves, keeping only the root
+///
+/// Afterwards there are no values, and proving fails as described for
+/// [`MerkleTree::zeroize_leaves`].
+#[cfg(feature = "zeroize")]
+impl<T: AsRef<[u8]> + zeroize::Zeroize, H: Hasher> zeroize::Zeroize for TypedMerkleTree<T, H> {
+    fn zeroize(&mut self) {
+        self.values.zeroize();
+        self.tree.zeroize_leaves();
+    }
+}
@@ -1,73 +1,971 @@
 /// Helper functions for working with Merkle trees
+#[cfg(feature = "std")]
 use std::collections::HashMap;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
 use super::tree::MerkleTree;
+use super::typed::TypedMerkleTree;
 use super::hasher::{Hasher, Sha256Hasher};
 use super::proof::MerkleProof;
+use super::error::MerkleTreeError;
+use super::index::LeafIndex;
+#[cfg(feature = "std")]
+use super::builder::BuildLimits;
+#[cfg(feature = "csv")]
+use super::error::LimitExceeded;
+#[cfg(feature = "std")]
+use super::error::ProofParseError;
+
+pub mod testing;
 
 /// Converts a string to bytes
 pub fn string_to_bytes(s: &str) -> Vec<u8> {
     s.as_bytes().to_vec()
 }
 
+/// Applies `f` to every item, keeping the input order
+///
+/// With the `rayon` feature the items are processed in parallel.
+fn map_in_order<T: Sync, U: Send>(items: &[T], f: impl Fn(&T) -> U + Sync + Send) -> Vec<U> {
+    #[cfg(feature = "rayon")]
+    {
+        use rayon::prelude::*;
+        items.par_iter().map(f).collect()
+    }
+    #[cfg(not(feature = "rayon"))]
+    {
+        items.iter().map(f).collect()
+    }
+}
+
+/// Hashes every item as a leaf, keeping the input order
+fn hash_leaves<T: AsRef<[u8]> + Sync, H: Hasher + Sync>(items: &[T], hasher: &H) -> Vec<Vec<u8>> {
+    map_in_order(items, |item| hasher.hash_leaf(item.as_ref()))
+}
+
 /// Creates a Merkle tree from a list of strings using SHA-256 hasher
 pub fn create_tree_from_strings(strings: Vec<&str>) -> MerkleTree<Sha256Hasher> {
-    let hasher = Sha256Hasher::new();
-    let leaves = strings.iter()
-        .map(|s| hasher.hash_leaf(string_to_bytes(s).as_slice()))
-        .collect();
-    
-    MerkleTree::new(leaves, hasher)
+    create_tree_from_strings_with_hasher(strings, Sha256Hasher::new())
 }
 
 /// Creates a Merkle tree from a list of strings with a custom hasher
-pub fn create_tree_from_strings_with_hasher<H: Hasher>(
+///
+/// The hasher must be `Sync` because the `rayon` feature hashes the leaves
+/// in parallel; the built-in hashers are stateless and qualify.
+pub fn create_tree_from_strings_with_hasher<H: Hasher + Sync>(
     strings: Vec<&str>, 
     hasher: H
 ) -> MerkleTree<H> {
-    let leaves = strings.iter()
-        .map(|s| hasher.hash_leaf(string_to_bytes(s).as_slice()))
-        .collect();
+    let leaves = hash_leaves(&strings, &hasher);
     
     MerkleTree::new(leaves, hasher)
 }
 
+/// Hashes `data` as the leaf at `index`: `hash_leaf(index LE || data)`
+///
+/// Used by trees built with [`crate::builder::MerkleTreeBuilder::bind_indices`], whose
+/// leaves are only valid at their own position.
+pub fn hash_indexed_leaf<H: Hasher>(hasher: &H, index: LeafIndex, data: &[u8]) -> Vec<u8> {
+    let mut prefixed = Vec::with_capacity(8 + data.len());
+    prefixed.extend_from_slice(&index.to_le_bytes());
+    prefixed.extend_from_slice(data);
+    hasher.hash_leaf(&prefixed)
+}
+
+/// Verifies that a proof is for `data` at `index` of an index-bound tree with the given root
+pub fn verify_indexed_leaf<H: Hasher>(root: &[u8], index: LeafIndex, data: &[u8], proof: &MerkleProof<H>) -> bool {
+    proof.leaf == hash_indexed_leaf(&proof.hasher, index, data) && proof.verify(root)
+}
+
+/// Creates a Merkle tree from a list of strings that keeps the strings,
+/// so a proof's leaf hash can be traced back to its string
+///
+/// The leaves are sorted as in [`create_tree_from_strings`], which gives the
+/// same root.
+pub fn create_typed_tree_from_strings(strings: Vec<&str>) -> Result<TypedMerkleTree<String, Sha256Hasher>, MerkleTreeError> {
+    create_typed_tree_from_strings_with_hasher(strings, Sha256Hasher::new())
+}
+
+/// Creates a Merkle tree from a list of strings with a custom hasher, keeping
+/// the strings
+pub fn create_typed_tree_from_strings_with_hasher<H: Hasher>(
+    strings: Vec<&str>,
+    hasher: H
+) -> Result<TypedMerkleTree<String, H>, MerkleTreeError> {
+    TypedMerkleTree::new(strings.into_iter().map(String::from).collect(), hasher)
+}
+
+/// Creates a Merkle tree from a list of byte strings, hashing each as a leaf
+///
+/// Like [`create_tree_from_strings_with_hasher`], the leaves are hashed in
+/// parallel with the `rayon` feature.
+pub fn create_tree_from_bytes<H: Hasher + Sync>(
+    items: Vec<&[u8]>,
+    hasher: H
+) -> MerkleTree<H> {
+    let leaves = hash_leaves(&items, &hasher);
+
+    MerkleTree::new(leaves, hasher)
+}
+
+/// SplitMix64, a fixed generator so seeded output is the same on every platform and release
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+/// Generates `count` pseudo-random leaves of `size` bytes each from `seed`
+///
+/// The bytes come from SplitMix64 seeded with `seed`: each leaf is filled
+/// with successive outputs in little-endian order, and whatever is left of
+/// the last output when a leaf is full is dropped. The same arguments give
+/// the same leaves everywhere, for benchmarks and fuzz corpora.
+pub fn random_leaves(seed: u64, count: usize, size: usize) -> Vec<Vec<u8>> {
+    let mut rng = SplitMix64(seed);
+    (0..count)
+        .map(|_| {
+            let mut leaf = Vec::with_capacity(size);
+            while leaf.len() < size {
+                let bytes = rng.next_u64().to_le_bytes();
+                let take = (size - leaf.len()).min(bytes.len());
+                leaf.extend_from_slice(&bytes[..take]);
+            }
+            leaf
+        })
+        .collect()
+}
+
+/// Builds a tree over [`random_leaves`], each hashed with `hash_leaf`
+///
+/// The leaves are sorted as in [`MerkleTree::new`], so the root depends only
+/// on the arguments. Fails if `leaf_count` is zero.
+pub fn random_tree<H: Hasher>(
+    seed: u64,
+    leaf_count: usize,
+    leaf_size: usize,
+    hasher: H,
+) -> Result<MerkleTree<H>, MerkleTreeError> {
+    let leaves = random_leaves(seed, leaf_count, leaf_size)
+        .iter()
+        .map(|data| hasher.hash_leaf(data))
+        .collect();
+    MerkleTree::builder(hasher).build(leaves)
+}
+
+/// Computes the root the tree over `leaves` would have, without building the tree
+///
+/// Leaves are folded into a stack of pending subtree roots, one per set bit of
+/// the count so far, so memory stays O(log n) however long the input is. The
+/// padding matches `MerkleTree`: the last leaf is repeated up to the next power
+/// of two, using precomputed roots of all-padding subtrees.
+///
+/// Leaves are taken in the given order and not sorted. The result equals
+/// `MerkleTree::new_ordered(leaves).root()`, and equals `MerkleTree::new` only
+/// if the input is already sorted.
+pub fn compute_root_streaming<I: IntoIterator<Item = Vec<u8>>, H: Hasher>(
+    leaves: I,
+    hasher: &H
+) -> Result<Vec<u8>, MerkleTreeError> {
+    streaming_root(leaves, hasher).ok_or(MerkleTreeError::EmptyLeaves)
+}
+
+/// Items [`compute_roots_multi`] reads before hashing them with every hasher
+const MULTI_ROOT_BATCH: usize = 1024;
+
+/// Computes the root under each of several hashers in one pass over the data
+///
+/// Each item is read once, hashed as a leaf with every hasher, and folded
+/// into that hasher's own streaming root as in [`compute_root_streaming`],
+/// so the roots are in hasher order and each equals
+/// `MerkleTree::new_ordered(leaves, hasher).root()`. Items are taken in
+/// batches; with the `rayon` feature the hashers work through a batch in
+/// parallel. Memory is one batch of items plus O(log n) per hasher.
+pub fn compute_roots_multi(
+    leaves_data: impl Iterator<Item = Vec<u8>>,
+    hashers: &[&dyn crate::hasher::DynHasher],
+) -> Result<Vec<Vec<u8>>, MerkleTreeError> {
+    // Each hasher's subtrees so far, and the last leaf, held back to pad with
+    let mut streams: Vec<(RootFrontier, Option<Vec<u8>>)> = vec![Default::default(); hashers.len()];
+    let fold = |(frontier, last): &mut (RootFrontier, Option<Vec<u8>>), hasher: &&dyn crate::hasher::DynHasher, batch: &[Vec<u8>]| {
+        for item in batch {
+            if let Some(previous) = last.replace(hasher.hash_leaf(item)) {
+                frontier.push_leaf(previous, hasher);
+            }
+        }
+    };
+
+    let mut leaves_data = leaves_data.peekable();
+    let mut batch = Vec::with_capacity(MULTI_ROOT_BATCH);
+    while leaves_data.peek().is_some() {
+        batch.clear();
+        batch.extend(leaves_data.by_ref().take(MULTI_ROOT_BATCH));
+        #[cfg(feature = "rayon")]
+        {
+            use rayon::prelude::*;
+            streams.par_iter_mut().zip(hashers).for_each(|(stream, hasher)| fold(stream, hasher, &batch));
+        }
+        #[cfg(not(feature = "rayon"))]
+        streams.iter_mut().zip(hashers).for_each(|(stream, hasher)| fold(stream, hasher, &batch));
+    }
+
+    streams.into_iter().zip(hashers)
+        .map(|((mut frontier, last), hasher)| {
+            let last = last.ok_or(MerkleTreeError::EmptyLeaves)?;
+            frontier.push_leaf(last.clone(), hasher);
+            frontier.into_root(last, hasher).ok_or(MerkleTreeError::EmptyLeaves)
+        })
+        .collect()
+}
+
+/// [`compute_root_streaming`] over borrowed or owned leaves, `None` if there are none
+pub(crate) fn streaming_root<T: AsRef<[u8]>, H: Hasher>(
+    leaves: impl IntoIterator<Item = T>,
+    hasher: &H
+) -> Option<Vec<u8>> {
+    // Each leaf is pushed once the next arrives, so only the last one is
+    // copied for the padding
+    let mut frontier = RootFrontier::default();
+    let mut leaves = leaves.into_iter();
+    let mut last_leaf = leaves.next()?;
+    for leaf in leaves {
+        frontier.push_leaf(last_leaf.as_ref().to_vec(), hasher);
+        last_leaf = leaf;
+    }
+    let last_leaf = last_leaf.as_ref().to_vec();
+    frontier.push_leaf(last_leaf.clone(), hasher);
+    frontier.into_root(last_leaf, hasher)
+}
+
+/// The roots of the complete subtrees over the leaves pushed so far, one per
+/// set bit of the leaf count, from which the padded root can be finished
+#[derive(Clone, Default)]
+pub(crate) struct RootFrontier {
+    /// (height, root) of complete subtrees, heights strictly decreasing
+    pending: Vec<(usize, Vec<u8>)>,
+}
+
+impl RootFrontier {
+    /// Pushes a subtree root, merging it with equal-height subtrees to its left
+    fn push<H: Hasher>(&mut self, mut height: usize, mut node: Vec<u8>, hasher: &H) {
+        while let Some((_, left)) = self.pending.pop_if(|(left_height, _)| *left_height == height) {
+            node = hasher.hash_pair(&left, &node);
+            height += 1;
+        }
+        self.pending.push((height, node));
+    }
+
+    /// Pushes the next leaf
+    pub(crate) fn push_leaf<H: Hasher>(&mut self, leaf: Vec<u8>, hasher: &H) {
+        self.push(0, leaf, hasher);
+    }
+
+    /// Finishes the root, padding with copies of `last_leaf`, the leaf pushed last
+    pub(crate) fn into_root<H: Hasher>(mut self, last_leaf: Vec<u8>, hasher: &H) -> Option<Vec<u8>> {
+        // The smallest pending subtree always ends where padding begins, so its
+        // sibling is made only of copies of the last leaf
+        let mut padding = vec![last_leaf];
+        loop {
+            let (height, left) = self.pending.pop()?;
+            if self.pending.is_empty() {
+                return Some(left);
+            }
+            while padding.len() <= height {
+                let top = &padding[padding.len() - 1];
+                padding.push(hasher.hash_pair(top, top));
+            }
+            self.push(height + 1, hasher.hash_pair(&left, &padding[height]), hasher);
+        }
+    }
+}
+
+/// Generates the proof for a raw element, hashing it with `hash_leaf`
+///
+/// An absent element is [`MerkleTreeError::LeafNotFound`], so callers can
+/// tell it apart from a tree that failed to produce a proof.
+pub fn prove_element<H: Hasher, T: AsRef<[u8]>>(
+    tree: &MerkleTree<H>,
+    element: T
+) -> Result<MerkleProof<H>, MerkleTreeError> {
+    let leaf_value = tree.get_hasher().hash_leaf(element.as_ref());
+    tree.generate_proof_by_value(&leaf_value)
+}
+
 /// Verifies an element in the tree exists
-pub fn verify_element_in_tree<H: Hasher>(
+///
+/// The element is hashed with `hash_leaf`, so a `&str` and its UTF-8 bytes
+/// give the same answer. `false` means the element is absent; a present
+/// element whose proof can't be generated, such as from a store missing
+/// nodes, doesn't verify either. Use [`prove_element`] to tell these apart.
+pub fn verify_element_in_tree<H: Hasher, T: AsRef<[u8]>>(
     tree: &MerkleTree<H>,
-    element: &str
+    element: T
 ) -> bool {
+    prove_element(tree, element).is_ok_and(|proof| proof.verify(tree.root()))
+}
+
+/// The answer to one query of [`check_membership_batch`]
+pub enum MembershipResult<H: Hasher> {
+    /// The element is the leaf at `index`, first among duplicates
+    Present { index: LeafIndex, proof: MerkleProof<H> },
+    /// No leaf is the element's hash
+    Absent,
+}
+
+/// Checks which elements are in the tree, proving the ones that are
+///
+/// Each element is hashed with `hash_leaf` and looked up as in
+/// [`MerkleTree::find_leaf_index`], so trees built with
+/// [`crate::MerkleTreeBuilder::index_leaves`] or sorted answer each query
+/// without a scan. There is one result per element, in input order,
+/// duplicates included; with the `rayon` feature the elements are hashed,
+/// looked up and proven in parallel. A present element whose proof can't be
+/// generated, such as from a store missing nodes, fails the whole batch with
+/// the first such error rather than being reported absent.
+pub fn check_membership_batch<H: Hasher + Send + Sync, T: AsRef<[u8]> + Sync>(
+    tree: &MerkleTree<H>,
+    items: &[T]
+) -> Result<Vec<MembershipResult<H>>, MerkleTreeError> {
     let hasher = tree.get_hasher();
-    let leaf_value = hasher.hash_leaf(string_to_bytes(element).as_slice());
-    
-    match tree.generate_proof_by_value(&leaf_value) {
-        Ok(proof) => proof.verify(&tree.root()),
-        Err(_) => false
-    }
+    map_in_order(items, |item| {
+        let leaf_value = hasher.hash_leaf(item.as_ref());
+        match tree.find_leaf_index(&leaf_value) {
+            Some(index) => Ok(MembershipResult::Present { index, proof: tree.generate_proof(index)? }),
+            None => Ok(MembershipResult::Absent),
+        }
+    })
+    .into_iter()
+    .collect()
 }
 
-/// Example of using a proof in the format provided
+/// Verifies a proof given in the format of [`MerkleProof::to_debug_format`]
+///
+/// Malformed proof data is an error naming the bad item and field, never a
+/// panic; a well-formed proof for the wrong root is `Ok(false)`.
+#[cfg(feature = "std")]
 pub fn verify_with_formatted_proof<H: Hasher>(
     root: &[u8],
     leaf: Vec<u8>,
     proof_data: Vec<HashMap<String, String>>,
     hasher: H
+) -> Result<bool, ProofParseError> {
+    let proof = MerkleProof::from_debug_format(leaf, &proof_data, hasher)?;
+    Ok(proof.verify(root))
+}
+
+/// Verifies a proof given as untyped JSON, in any shape clients send
+///
+/// The JSON is an object with a hex `leaf` and the path in one of these shapes:
+///
+/// - `proof`: items with a hex `hash` and a `direction` of `left` or
+///   `right`, as in the JSON proof format and [`MerkleProof::to_debug_format`];
+///   an item may give `is_left` as a bool instead of `direction`, and other
+///   keys are ignored
+/// - `index` and `siblings`: the leaf's position and the sibling hashes from
+///   the leaf up, each sibling's side taken from a bit of the index
+///
+/// Every shape is normalized to the debug format and parsed by
+/// [`MerkleProof::from_debug_format`]. Any JSON that isn't a proof is an
+/// error, never a panic; a well-formed proof for the wrong root is `Ok(false)`.
+#[cfg(all(feature = "json", feature = "std"))]
+pub fn verify_json_proof<H: Hasher>(
+    root: &[u8],
+    value: &serde_json::Value,
+    hasher: &H
+) -> Result<bool, ProofParseError> {
+    let object = value.as_object().ok_or(ProofParseError::UnrecognizedShape)?;
+    let leaf = object.get("leaf").ok_or(ProofParseError::MissingKey("leaf"))?;
+    let leaf = leaf.as_str()
+        .and_then(|leaf| hex::decode(leaf).ok())
+        .ok_or_else(|| wrong_type("leaf".into(), "a hex string"))?;
+    let items = match (object.get("proof"), object.get("siblings")) {
+        (Some(proof), _) => json_proof_items(proof)?,
+        (None, Some(siblings)) => json_indexed_items(object.get("index"), siblings)?,
+        (None, None) => return Err(ProofParseError::UnrecognizedShape),
+    };
+    let proof = MerkleProof::from_debug_format(leaf, &items, hasher.clone())?;
+    Ok(proof.verify(root))
+}
+
+#[cfg(all(feature = "json", feature = "std"))]
+fn wrong_type(field: String, expected: &'static str) -> ProofParseError {
+    ProofParseError::WrongType { field, expected }
+}
+
+/// Converts `proof` items to the debug format, leaving missing and bad values to its parser
+#[cfg(all(feature = "json", feature = "std"))]
+fn json_proof_items(proof: &serde_json::Value) -> Result<Vec<HashMap<String, String>>, ProofParseError> {
+    let items = proof.as_array().ok_or_else(|| wrong_type("proof".into(), "an array of proof items"))?;
+    items.iter().enumerate().map(|(index, item)| {
+        let fields = item.as_object().ok_or_else(|| wrong_type(format!("proof[{}]", index), "an object"))?;
+        let mut normalized = HashMap::new();
+        if let Some(hash) = fields.get("hash") {
+            let hash = hash.as_str().ok_or_else(|| wrong_type(format!("proof[{}].hash", index), "a hex string"))?;
+            normalized.insert("hash".to_string(), hash.to_string());
+        }
+        let direction = match (fields.get("direction"), fields.get("is_left")) {
+            (Some(direction), _) => Some(direction.as_str()
+                .ok_or_else(|| wrong_type(format!("proof[{}].direction", index), "\"left\" or \"right\""))?),
+            (None, Some(is_left)) => match is_left.as_bool() {
+                Some(true) => Some("left"),
+                Some(false) => Some("right"),
+                None => return Err(wrong_type(format!("proof[{}].is_left", index), "a bool")),
+            },
+            (None, None) => None,
+        };
+        if let Some(direction) = direction {
+            normalized.insert("direction".to_string(), direction.to_string());
+        }
+        Ok(normalized)
+    }).collect()
+}
+
+/// Converts `index` and `siblings` to the debug format: a set bit `k` of the
+/// index puts the leaf's ancestor on level `k` on the right, so its sibling is on the left
+#[cfg(all(feature = "json", feature = "std"))]
+fn json_indexed_items(
+    index: Option<&serde_json::Value>,
+    siblings: &serde_json::Value,
+) -> Result<Vec<HashMap<String, String>>, ProofParseError> {
+    let index = index.ok_or(ProofParseError::MissingKey("index"))?
+        .as_u64()
+        .ok_or_else(|| wrong_type("index".into(), "a non-negative integer"))?;
+    let siblings = siblings.as_array().ok_or_else(|| wrong_type("siblings".into(), "an array of hex strings"))?;
+    if siblings.len() < 64 && index >> siblings.len() != 0 {
+        return Err(wrong_type("index".into(), "a position within the proof's depth"));
+    }
+    siblings.iter().enumerate().map(|(level, sibling)| {
+        let hash = sibling.as_str().ok_or_else(|| wrong_type(format!("siblings[{}]", level), "a hex string"))?;
+        let is_left = level < 64 && (index >> level) & 1 == 1;
+        Ok(HashMap::from([
+            ("hash".to_string(), hash.to_string()),
+            ("direction".to_string(), if is_left { "left" } else { "right" }.to_string()),
+        ]))
+    }).collect()
+}
+/// How repeated values in a CSV column are turned into leaves
+#[cfg(feature = "csv")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    /// Every occurrence becomes its own leaf
+    Keep,
+    /// Repeated values share a single leaf
+    Merge,
+    /// A repeated value is an error
+    Reject,
+}
+
+/// How empty cells in a CSV column are handled
+#[cfg(feature = "csv")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EmptyCellPolicy {
+    /// Empty cells are left out of the tree
+    Skip,
+    /// Empty cells are hashed as the empty string
+    Keep,
+    /// An empty cell is an error
+    Reject,
+}
+
+/// Options for building a tree from a CSV column
+#[cfg(feature = "csv")]
+#[derive(Clone, Copy, Debug)]
+pub struct CsvOptions {
+    /// Lowercase values after trimming (useful for hex addresses)
+    pub lowercase: bool,
+    /// What to do with repeated values
+    pub duplicates: DuplicatePolicy,
+    /// What to do with empty cells
+    pub empty_cells: EmptyCellPolicy,
+    /// Caps on the values read; merged duplicates count towards the bytes but not the leaves
+    pub limits: BuildLimits,
+}
+
+#[cfg(feature = "csv")]
+impl Default for CsvOptions {
+    fn default() -> Self {
+        CsvOptions {
+            lowercase: false,
+            duplicates: DuplicatePolicy::Keep,
+            empty_cells: EmptyCellPolicy::Skip,
+            limits: BuildLimits::default(),
+        }
+    }
+}
+
+/// Errors returned when building a tree from CSV data
+#[cfg(feature = "csv")]
+#[derive(Debug)]
+pub enum CsvTreeError {
+    /// The CSV data could not be read or parsed
+    Csv(csv::Error),
+    /// The header row has no column with the requested name
+    MissingColumn(String),
+    /// A row has an empty cell and empty cells are rejected
+    EmptyCell { row: usize },
+    /// A row repeats an earlier value and duplicates are rejected
+    DuplicateValue { row: usize },
+    /// No row produced a leaf
+    NoLeaves,
+    /// The column is larger than the [`CsvOptions::limits`] allow
+    LimitExceeded(LimitExceeded),
+}
+
+#[cfg(feature = "csv")]
+impl std::fmt::Display for CsvTreeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CsvTreeError::Csv(err) => write!(f, "CSV error: {}", err),
+            CsvTreeError::MissingColumn(column) => write!(f, "Column '{}' not found", column),
+            CsvTreeError::EmptyCell { row } => write!(f, "Empty cell in row {}", row),
+            CsvTreeError::DuplicateValue { row } => write!(f, "Duplicate value in row {}", row),
+            CsvTreeError::NoLeaves => write!(f, "CSV column contains no leaves"),
+            CsvTreeError::LimitExceeded(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+#[cfg(feature = "csv")]
+impl std::error::Error for CsvTreeError {}
+
+#[cfg(feature = "csv")]
+impl From<csv::Error> for CsvTreeError {
+    fn from(err: csv::Error) -> Self {
+        CsvTreeError::Csv(err)
+    }
+}
+
+#[cfg(feature = "csv")]
+impl From<LimitExceeded> for CsvTreeError {
+    fn from(err: LimitExceeded) -> Self {
+        CsvTreeError::LimitExceeded(err)
+    }
+}
+
+/// Creates a Merkle tree from a named CSV column using the default options
+///
+/// Returns the tree together with a mapping from data row (0-based, header
+/// excluded) to the index of that row's leaf in the tree. Rows that did not
+/// produce a leaf map to `None`.
+#[cfg(feature = "csv")]
+pub fn create_tree_from_csv<R: std::io::Read, H: Hasher>(
+    reader: R,
+    column: &str,
+    hasher: H
+) -> Result<(MerkleTree<H>, Vec<Option<LeafIndex>>), CsvTreeError> {
+    create_tree_from_csv_with_options(reader, column, hasher, CsvOptions::default())
+}
+
+/// Creates a Merkle tree from a named CSV column
+///
+/// Values are trimmed and, if requested, lowercased before hashing.
+/// See [`create_tree_from_csv`] for the meaning of the returned row mapping.
+#[cfg(feature = "csv")]
+pub fn create_tree_from_csv_with_options<R: std::io::Read, H: Hasher>(
+    reader: R,
+    column: &str,
+    hasher: H,
+    options: CsvOptions
+) -> Result<(MerkleTree<H>, Vec<Option<LeafIndex>>), CsvTreeError> {
+    let mut csv_reader = csv::Reader::from_reader(reader);
+    let column_index = csv_reader.headers()?
+        .iter()
+        .position(|header| header.trim() == column)
+        .ok_or_else(|| CsvTreeError::MissingColumn(column.to_string()))?;
+
+    // Leaf hash for every row, or None if the row was skipped
+    let mut row_leaves: Vec<Option<Vec<u8>>> = Vec::new();
+    let mut seen = std::collections::HashSet::new();
+    let mut leaves = Vec::new();
+    let mut counter = options.limits.counter();
+
+    for (row, record) in csv_reader.records().enumerate() {
+        let record = record?;
+        let value = record.get(column_index).unwrap_or("").trim();
+
+        if value.is_empty() {
+            match options.empty_cells {
+                EmptyCellPolicy::Skip => {
+                    row_leaves.push(None);
+                    continue;
+                }
+                EmptyCellPolicy::Reject => return Err(CsvTreeError::EmptyCell { row }),
+                EmptyCellPolicy::Keep => {}
+            }
+        }
+
+        let value = if options.lowercase {
+            value.to_lowercase()
+        } else {
+            value.to_string()
+        };
+        counter.add_bytes(leaves.len(), value.len())?;
+        let leaf = hasher.hash_leaf(string_to_bytes(&value).as_slice());
+
+        if !seen.insert(leaf.clone()) {
+            match options.duplicates {
+                DuplicatePolicy::Reject => return Err(CsvTreeError::DuplicateValue { row }),
+                DuplicatePolicy::Merge => {
+                    row_leaves.push(Some(leaf));
+                    continue;
+                }
+                DuplicatePolicy::Keep => {}
+            }
+        }
+
+        counter.add_leaf()?;
+        leaves.push(leaf.clone());
+        row_leaves.push(Some(leaf));
+    }
+
+    if leaves.is_empty() {
+        return Err(CsvTreeError::NoLeaves);
+    }
+
+    // The tree sorts its leaves, so recover each row's position the same way
+    let mut sorted = leaves.clone();
+    sorted.sort();
+    let mut occurrences: HashMap<Vec<u8>, usize> = HashMap::new();
+    let row_to_leaf = row_leaves.into_iter()
+        .map(|leaf| leaf.map(|leaf| {
+            let first = sorted.partition_point(|l| l < &leaf);
+            let offset = occurrences.entry(leaf).or_insert(0);
+            let index = first + *offset;
+            if options.duplicates == DuplicatePolicy::Keep {
+                *offset += 1;
+            }
+            index as LeafIndex
+        }))
+        .collect();
+
+    Ok((MerkleTree::new(leaves, hasher), row_to_leaf))
+}
+
+/// How much raw input the line and file builders gather before hashing it as one batch
+#[cfg(feature = "std")]
+pub const HASH_BATCH_BYTES: usize = 1 << 20;
+
+/// Options for building a tree from line-delimited input
+#[cfg(feature = "std")]
+#[derive(Clone, Copy, Debug)]
+pub struct LineOptions {
+    /// Skip lines that are empty after removing the line terminator
+    pub skip_blank: bool,
+    /// Maximum accepted line length in bytes, excluding the line terminator
+    pub max_line_len: usize,
+    /// Caps on the lines read, counting neither terminators nor skipped blank lines
+    pub limits: BuildLimits,
+}
+
+#[cfg(feature = "std")]
+impl Default for LineOptions {
+    fn default() -> Self {
+        LineOptions {
+            skip_blank: true,
+            max_line_len: 64 * 1024,
+            limits: BuildLimits::default(),
+        }
+    }
+}
+
+/// Creates a Merkle tree from a reader with one entry per line using the default options
+#[cfg(feature = "std")]
+pub fn create_tree_from_lines<R: std::io::BufRead, H: Hasher + Sync>(
+    reader: R,
+    hasher: H
+) -> Result<MerkleTree<H>, MerkleTreeError> {
+    create_tree_from_lines_with_options(reader, hasher, LineOptions::default())
+}
+
+/// Creates a Merkle tree from a reader with one entry per line
+///
+/// Lines are hashed in batches as they are read, with `\n` or `\r\n`
+/// stripped, so beyond the leaf hashes only about [`HASH_BATCH_BYTES`] of raw
+/// input is held in memory. A line longer than `max_line_len` fails with
+/// [`MerkleTreeError::LineTooLong`] without being buffered in full, and input
+/// over the [`LineOptions::limits`] fails with
+/// [`MerkleTreeError::LimitExceeded`] as soon as the line going over is read.
+#[cfg(feature = "std")]
+pub fn create_tree_from_lines_with_options<R: std::io::BufRead, H: Hasher + Sync>(
+    mut reader: R,
+    hasher: H,
+    options: LineOptions
+) -> Result<MerkleTree<H>, MerkleTreeError> {
+    use std::io::{BufRead, Read};
+
+    let mut leaves = Vec::new();
+    let mut batch = Vec::new();
+    let mut batch_bytes = 0;
+    let mut counter = options.limits.counter();
+
+    for line_number in 1.. {
+        if batch_bytes >= HASH_BATCH_BYTES {
+            leaves.extend(hash_leaves(&batch, &hasher));
+            batch.clear();
+            batch_bytes = 0;
+        }
+
+        // Room for the longest accepted line plus a "\r\n" terminator
+        let limit = options.max_line_len.min(counter.max_item_len()) as u64 + 2;
+        let mut line = Vec::new();
+        if (&mut reader).take(limit).read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+
+        if line.last() == Some(&b'\n') {
+            line.pop();
+            if line.last() == Some(&b'\r') {
+                line.pop();
+            }
+        }
+
+        if line.len() > options.max_line_len {
+            return Err(MerkleTreeError::LineTooLong { line: line_number, max: options.max_line_len });
+        }
+
+        if line.is_empty() && options.skip_blank {
+            continue;
+        }
+        counter.add(line.len())?;
+
+        // Count a fixed overhead per line so runs of short lines still flush
+        batch_bytes += line.len() + 64;
+        batch.push(line);
+    }
+    leaves.extend(hash_leaves(&batch, &hasher));
+
+    if leaves.is_empty() {
+        return Err(MerkleTreeError::EmptyLeaves);
+    }
+
+    Ok(MerkleTree::new(leaves, hasher))
+}
+
+/// Creates a Merkle tree over fixed-size chunks of a file
+///
+/// Each chunk of `chunk_size` bytes is hashed as a leaf in file order; the
+/// final chunk is hashed as-is even if it is shorter. Leaves are not sorted,
+/// so leaf index `i` is chunk `i`. Returns the tree and the number of chunks.
+/// Chunks are read and hashed in batches of about [`HASH_BATCH_BYTES`].
+#[cfg(feature = "std")]
+pub fn create_tree_from_file<P: AsRef<std::path::Path>, H: Hasher + Sync>(
+    path: P,
+    chunk_size: usize,
+    hasher: H
+) -> Result<(MerkleTree<H>, usize), MerkleTreeError> {
+    use std::io::Read;
+
+    if chunk_size == 0 {
+        return Err(MerkleTreeError::InvalidChunkSize);
+    }
+
+    let mut file = std::fs::File::open(path)?;
+    let mut leaves = Vec::new();
+    let batch_len = (HASH_BATCH_BYTES / chunk_size).max(1);
+    let mut batch = Vec::with_capacity(batch_len);
+
+    loop {
+        let mut chunk = Vec::with_capacity(chunk_size);
+        let at_end = (&mut file).take(chunk_size as u64).read_to_end(&mut chunk)? == 0;
+        if !at_end {
+            batch.push(chunk);
+        }
+        if at_end || batch.len() == batch_len {
+            leaves.extend(hash_leaves(&batch, &hasher));
+            batch.clear();
+        }
+        if at_end {
+            break;
+        }
+    }
+
+    if leaves.is_empty() {
+        return Err(MerkleTreeError::EmptyLeaves);
+    }
+
+    let chunk_count = leaves.len();
+    Ok((MerkleTree::new_ordered(leaves, hasher), chunk_count))
+}
+
+/// Verifies a single file chunk against the root of a chunked file tree
+///
+/// Checks that `chunk_index` is one of the file's `chunk_count` chunks, as
+/// returned by [`create_tree_from_file`], that the proof is for these chunk
+/// bytes, that its path leads to `chunk_index`, and that it hashes up to
+/// `root`. The chunk count matters because the tree pads with copies of the
+/// last chunk, and a proof of a padding position would otherwise pass for a
+/// chunk past the end of the file. Both the chunk and the proof are hashed
+/// with `hasher`; the proof's own hasher isn't used.
+pub fn verify_file_chunk<H: Hasher>(
+    root: &[u8],
+    chunk_index: usize,
+    chunk_count: usize,
+    chunk_bytes: &[u8],
+    proof: &MerkleProof<H>,
+    hasher: &H
 ) -> bool {
-    // Convert the formatted proof data to ProofItem
-    let mut proof_items = Vec::new();
-    
-    for item in proof_data {
-        let hash = hex::decode(item.get("hash").unwrap()).unwrap();
-        let is_left = item.get("direction").unwrap() == "left";
-        
-        proof_items.push(crate::proof::ProofItem {
-            hash,
-            is_left,
-        });
+    if chunk_index >= chunk_count || proof.leaf != hasher.hash_leaf(chunk_bytes) {
+        return false;
     }
-    
-    // Create the proof
-    let proof = MerkleProof::new(leaf, proof_items, hasher);
-    
-    // Verify
-    proof.verify(root)
-}
\ No newline at end of file
+
+    // Every level's direction must match the corresponding bit of the index
+    let depth = proof.proof_items.len() as u32;
+    if chunk_index.checked_shr(depth).unwrap_or(0) != 0 {
+        return false;
+    }
+    let path_matches = proof.proof_items.iter()
+        .enumerate()
+        .all(|(level, item)| item.is_left == ((chunk_index >> level) & 1 == 1));
+
+    path_matches && proof.calculate_root_with(hasher) == root
+}
+
+/// Options for building a tree from a directory
+#[cfg(feature = "std")]
+#[derive(Clone, Debug, Default)]
+pub struct DirOptions {
+    /// Wildcard patterns (`*` and `?`) for paths to leave out
+    ///
+    /// A pattern excludes an entry if it matches the entry's relative path
+    /// or any single component of it, so `target` skips a whole directory
+    /// and `*.log` skips log files anywhere.
+    pub exclude: Vec<String>,
+    /// Follow symbolic links instead of ignoring them
+    pub follow_symlinks: bool,
+}
+
+/// Creates a Merkle tree committing to every file under a directory using the default options
+#[cfg(feature = "std")]
+pub fn create_tree_from_dir<P: AsRef<std::path::Path>, H: Hasher + Sync>(
+    path: P,
+    hasher: H
+) -> Result<(MerkleTree<H>, Vec<String>), MerkleTreeError> {
+    create_tree_from_dir_with_options(path, hasher, &DirOptions::default())
+}
+
+/// Creates a Merkle tree committing to every file under a directory
+///
+/// Each file becomes the leaf `hash_leaf(relative_path || content_hash)`,
+/// where `content_hash` is `hash_leaf` over the file contents (streamed, not
+/// buffered) and `relative_path` is the UTF-8 path relative to `path`.
+///
+/// Path normalization affects the root, so it is fixed:
+/// - components are joined with `/` on every platform, with no leading `./`
+/// - names are used byte-for-byte with no Unicode normalization, so NFC and
+///   NFD spellings of the same name give different roots
+/// - a name that is not valid UTF-8 is an `InvalidData` I/O error
+///
+/// Leaves are ordered by relative path (byte-wise) and not re-sorted, so the
+/// returned paths give the file for each leaf index. With the `rayon`
+/// feature the files are hashed in parallel.
+#[cfg(feature = "std")]
+pub fn create_tree_from_dir_with_options<P: AsRef<std::path::Path>, H: Hasher + Sync>(
+    path: P,
+    hasher: H,
+    options: &DirOptions
+) -> Result<(MerkleTree<H>, Vec<String>), MerkleTreeError> {
+    let root = path.as_ref();
+    let mut files = Vec::new();
+    let mut visited = std::collections::HashSet::new();
+    visited.insert(root.canonicalize()?);
+    collect_dir_files(root, "", options, &mut visited, &mut files)?;
+
+    if files.is_empty() {
+        return Err(MerkleTreeError::EmptyLeaves);
+    }
+
+    files.sort();
+
+    let leaves = map_in_order(&files, |relative| {
+        let file = std::fs::File::open(root.join(relative))?;
+        let mut leaf_data = string_to_bytes(relative);
+        leaf_data.extend(hasher.hash_leaf_reader(file)?);
+        Ok(hasher.hash_leaf(&leaf_data))
+    }).into_iter().collect::<std::io::Result<Vec<_>>>()?;
+
+    Ok((MerkleTree::new_ordered(leaves, hasher), files))
+}
+
+/// Recursively collects the normalized relative paths of files under `dir`
+#[cfg(feature = "std")]
+fn collect_dir_files(
+    dir: &std::path::Path,
+    prefix: &str,
+    options: &DirOptions,
+    visited: &mut std::collections::HashSet<std::path::PathBuf>,
+    files: &mut Vec<String>
+) -> std::io::Result<()> {
+    use std::io::{Error, ErrorKind};
+
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().into_string().map_err(|name| {
+            Error::new(ErrorKind::InvalidData, format!("Non UTF-8 file name: {:?}", name))
+        })?;
+        let relative = if prefix.is_empty() {
+            name.clone()
+        } else {
+            format!("{}/{}", prefix, name)
+        };
+
+        let excluded = options.exclude.iter()
+            .any(|pattern| wildcard_match(pattern, &relative) || wildcard_match(pattern, &name));
+        if excluded {
+            continue;
+        }
+
+        let mut file_type = entry.file_type()?;
+        if file_type.is_symlink() {
+            if !options.follow_symlinks {
+                continue;
+            }
+            file_type = std::fs::metadata(entry.path())?.file_type();
+        }
+
+        if file_type.is_dir() {
+            // Guard against symlink cycles
+            if visited.insert(entry.path().canonicalize()?) {
+                collect_dir_files(&entry.path(), &relative, options, visited, files)?;
+            }
+        } else if file_type.is_file() {
+            files.push(relative);
+        }
+    }
+
+    Ok(())
+}
+
+/// Matches `text` against a pattern where `*` is any run of characters and `?` is one character
+#[cfg(feature = "std")]
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` seen and the text position it was tried at
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star, star_t)) = backtrack {
+            p = star + 1;
+            t = star_t + 1;
+            backtrack = Some((star, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
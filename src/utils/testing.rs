@@ -0,0 +1,96 @@
+//! Assertions for tests comparing trees built different ways
+//!
+//! When two construction paths disagree, [`assert_same_root`] reports the
+//! first node they differ on instead of two opaque roots.
+use core::fmt;
+use core::ops::Range;
+use alloc::vec::Vec;
+use crate::hasher::Hasher;
+use crate::index::LeafIndex;
+use crate::store::NodeStore;
+use crate::tree::MerkleTree;
+
+/// Where two trees first differ, from [`explain_root_mismatch`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MismatchReport {
+    /// The level of the differing node, with the leaves on level 0
+    pub level: usize,
+    /// The node's index within its level
+    pub index: LeafIndex,
+    /// The node in the first tree, `None` if that tree has no such node
+    pub left: Option<Vec<u8>>,
+    /// The node in the second tree, `None` if that tree has no such node
+    pub right: Option<Vec<u8>>,
+    /// The leaf positions under the node, padding included
+    pub leaves: Range<LeafIndex>,
+}
+
+impl fmt::Display for MismatchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let hex = |node: &Option<Vec<u8>>| node.as_ref().map_or_else(|| "<none>".into(), hex::encode);
+        write!(
+            f,
+            "trees first differ at level {}, index {} (leaves {}..{}): {} != {}",
+            self.level,
+            self.index,
+            self.leaves.start,
+            self.leaves.end,
+            hex(&self.left),
+            hex(&self.right),
+        )
+    }
+}
+
+/// Finds the first node two trees differ on, or `None` if their roots are equal
+///
+/// Levels are compared from the leaves up and each level left to right, so
+/// a changed leaf is reported at level 0 rather than at every node above
+/// it. Trees of different sizes differ at the first node only one of them
+/// has. If every node that can be read matches, as with stores missing
+/// nodes, the roots are reported.
+pub fn explain_root_mismatch<H1, S1, H2, S2>(a: &MerkleTree<H1, S1>, b: &MerkleTree<H2, S2>) -> Option<MismatchReport>
+where
+    H1: Hasher,
+    S1: NodeStore,
+    H2: Hasher,
+    S2: NodeStore,
+{
+    if a.root() == b.root() {
+        return None;
+    }
+
+    let levels = a.leaf_count().max(b.leaf_count()).trailing_zeros() as usize + 1;
+    let report = |level: usize, index: LeafIndex, left, right| MismatchReport {
+        level,
+        index,
+        left,
+        right,
+        leaves: crate::index::leaf_range(level as u32, index)
+            .expect("a node of a tree covers leaves the tree holds"),
+    };
+    for level in 0..levels {
+        let width = (a.leaf_count().max(b.leaf_count())) >> level;
+        for index in 0..width {
+            let left = a.get_node(level, index);
+            let right = b.get_node(level, index);
+            if left != right {
+                return Some(report(level, index, left, right));
+            }
+        }
+    }
+    Some(report(levels - 1, 0, Some(a.root().to_vec()), Some(b.root().to_vec())))
+}
+
+/// Asserts that two trees have the same root, panicking with a [`MismatchReport`] if not
+#[track_caller]
+pub fn assert_same_root<H1, S1, H2, S2>(a: &MerkleTree<H1, S1>, b: &MerkleTree<H2, S2>)
+where
+    H1: Hasher,
+    S1: NodeStore,
+    H2: Hasher,
+    S2: NodeStore,
+{
+    if let Some(report) = explain_root_mismatch(a, b) {
+        panic!("roots differ: {}", report);
+    }
+}
@@ -0,0 +1,218 @@
+//! Test vectors for keeping other implementations in step with this crate
+//!
+//! [`generate_vectors`] builds trees over fixed leaves and records each
+//! tree's root and the proof of every leaf; [`write_vectors`] saves them as
+//! a JSON fixture and [`verify_vectors`] rebuilds every vector in a fixture
+//! and checks it still matches. The default set, 1 to 9 leaves for SHA-256
+//! and Keccak-256, sorted and in order, ships as `tests/vectors/default.json`.
+//!
+//! A fixture is `{"version": 1, "vectors": [...]}`, each vector holding:
+//!
+//! - `hasher`: the [`BuiltinHasher`] name
+//! - `options`: `sorted` and the `padding` strategy, always `duplicate-last`
+//! - `leaves`: the leaves in hex, as given to the tree, before any sorting
+//! - `root`: the root in hex
+//! - `proofs`: in the JSON proof format, the proof of each leaf of the built
+//!   tree in tree order, so for sorted vectors in sorted order; padding
+//!   leaves have none
+use std::fmt;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::hasher::{BuiltinHasher, Hasher, Keccak256Hasher, Sha256Hasher};
+use crate::tree::MerkleTree;
+
+/// The version of the fixture format
+pub const VECTORS_VERSION: u32 = 1;
+
+/// Which vectors [`generate_vectors`] produces: one per hasher, leaf count and sort mode
+#[derive(Clone, Debug)]
+pub struct VectorConfig {
+    pub hashers: Vec<BuiltinHasher>,
+    pub leaf_counts: Vec<usize>,
+    pub sort_modes: Vec<bool>,
+}
+
+impl Default for VectorConfig {
+    /// The shipped set: 1 to 9 leaves, SHA-256 and Keccak-256, ordered and sorted
+    fn default() -> Self {
+        VectorConfig {
+            hashers: vec![BuiltinHasher::Sha256(Sha256Hasher::new()), BuiltinHasher::Keccak256(Keccak256Hasher::new())],
+            leaf_counts: (1..=9).collect(),
+            sort_modes: vec![false, true],
+        }
+    }
+}
+
+/// How a vector's tree is built
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VectorOptions {
+    pub sorted: bool,
+    pub padding: String,
+}
+
+/// One tree with its expected root and proofs
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TestVector {
+    pub hasher: String,
+    pub options: VectorOptions,
+    pub leaves: Vec<String>,
+    pub root: String,
+    pub proofs: Vec<serde_json::Value>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct VectorFile {
+    version: u32,
+    vectors: Vec<TestVector>,
+}
+
+/// Errors from reading or checking a fixture
+#[derive(Debug)]
+pub enum VectorError {
+    /// Reading or writing the fixture failed
+    Io(std::io::Error),
+    /// The fixture is not valid JSON or doesn't have the fixture's shape
+    Json(serde_json::Error),
+    /// The fixture was written in a format version this crate doesn't read
+    UnsupportedVersion(u32),
+    /// A vector no longer matches what the crate computes
+    Mismatch { vector: usize, reason: &'static str },
+}
+
+impl fmt::Display for VectorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VectorError::Io(err) => write!(f, "I/O error: {}", err),
+            VectorError::Json(err) => write!(f, "JSON error: {}", err),
+            VectorError::UnsupportedVersion(version) => write!(f, "Unsupported vector format version {}", version),
+            VectorError::Mismatch { vector, reason } => write!(f, "Vector {}: {}", vector, reason),
+        }
+    }
+}
+
+impl std::error::Error for VectorError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            VectorError::Io(err) => Some(err),
+            VectorError::Json(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for VectorError {
+    fn from(err: std::io::Error) -> Self {
+        VectorError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for VectorError {
+    fn from(err: serde_json::Error) -> Self {
+        VectorError::Json(err)
+    }
+}
+
+/// Builds a vector's tree and records its root and proofs
+fn build_vector(hasher: &BuiltinHasher, leaves: Vec<Vec<u8>>, sorted: bool) -> TestVector {
+    let hex_leaves = leaves.iter().map(hex::encode).collect();
+    let tree = MerkleTree::builder(hasher.clone())
+        .sort_leaves(sorted)
+        .build(leaves)
+        .expect("vectors have at least one leaf of the hasher's size");
+    let proofs = (0..tree.unpadded_leaf_count())
+        .map(|index| {
+            let proof = tree.generate_proof(index).expect("every real leaf has a proof");
+            serde_json::to_value(&proof).expect("proof serialization cannot fail")
+        })
+        .collect();
+
+    TestVector {
+        hasher: hasher.name(),
+        options: VectorOptions { sorted, padding: "duplicate-last".into() },
+        leaves: hex_leaves,
+        root: hex::encode(tree.root()),
+        proofs,
+    }
+}
+
+/// Generates the vectors `config` asks for
+///
+/// Leaf `i` of every vector is `hash_leaf("leaf {i}")`, so vectors with
+/// more leaves extend the ones with fewer.
+pub fn generate_vectors(config: &VectorConfig) -> Vec<TestVector> {
+    let mut vectors = Vec::new();
+    for hasher in &config.hashers {
+        for &count in &config.leaf_counts {
+            let leaves: Vec<Vec<u8>> = (0..count).map(|i| hasher.hash_leaf(format!("leaf {}", i).as_bytes())).collect();
+            for &sorted in &config.sort_modes {
+                vectors.push(build_vector(hasher, leaves.clone(), sorted));
+            }
+        }
+    }
+    vectors
+}
+
+/// Encodes vectors as a fixture: pretty-printed JSON with a trailing newline
+pub fn vectors_to_json(vectors: &[TestVector]) -> String {
+    let file = VectorFile { version: VECTORS_VERSION, vectors: vectors.to_vec() };
+    let mut json = serde_json::to_string_pretty(&file).expect("vector serialization cannot fail");
+    json.push('\n');
+    json
+}
+
+/// Parses a fixture written by [`vectors_to_json`]
+pub fn vectors_from_json(json: &str) -> Result<Vec<TestVector>, VectorError> {
+    let file: VectorFile = serde_json::from_str(json)?;
+    if file.version != VECTORS_VERSION {
+        return Err(VectorError::UnsupportedVersion(file.version));
+    }
+    Ok(file.vectors)
+}
+
+/// Writes vectors to a fixture file
+pub fn write_vectors<P: AsRef<Path>>(path: P, vectors: &[TestVector]) -> Result<(), VectorError> {
+    std::fs::write(path, vectors_to_json(vectors))?;
+    Ok(())
+}
+
+/// Rebuilds every vector in a fixture file and checks it, returning how many there were
+///
+/// Each root must match, and each recorded proof must equal the proof the
+/// crate generates now, field for field, and verify against the root.
+pub fn verify_vectors<P: AsRef<Path>>(path: P) -> Result<usize, VectorError> {
+    let vectors = vectors_from_json(&std::fs::read_to_string(path)?)?;
+    for (index, vector) in vectors.iter().enumerate() {
+        let mismatch = |reason| VectorError::Mismatch { vector: index, reason };
+
+        let hasher = BuiltinHasher::from_name(&vector.hasher).ok_or(mismatch("unknown hasher"))?;
+        if vector.options.padding != "duplicate-last" {
+            return Err(mismatch("unknown padding strategy"));
+        }
+        let leaves = vector.leaves.iter()
+            .map(hex::decode)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| mismatch("invalid hex in leaves"))?;
+        if leaves.is_empty() || leaves.iter().any(|leaf| leaf.len() != hasher.output_size()) {
+            return Err(mismatch("leaves are not hashes"));
+        }
+
+        let expected = build_vector(&hasher, leaves, vector.options.sorted);
+        if vector.root != expected.root {
+            return Err(mismatch("root differs"));
+        }
+        if vector.proofs != expected.proofs {
+            return Err(mismatch("proofs differ"));
+        }
+        let root = hex::decode(&vector.root).map_err(|_| mismatch("invalid hex in root"))?;
+        for proof in &vector.proofs {
+            let proof = crate::proof::MerkleProof::from_json(&proof.to_string(), hasher.clone())?;
+            if !proof.verify(&root) {
+                return Err(mismatch("proof does not verify"));
+            }
+        }
+    }
+    Ok(vectors.len())
+}
@@ -0,0 +1,108 @@
+//! wasm-bindgen bindings for building trees and verifying proofs in the browser
+//!
+//! Hashers are chosen by name (see [`BuiltinHasher::from_name`]) and proofs
+//! use the JSON proof format. Errors are thrown as JS exceptions.
+use js_sys::{Uint8Array, JSON};
+use wasm_bindgen::prelude::*;
+use crate::hasher::{BuiltinHasher, Hasher};
+use crate::index::LeafIndex;
+use crate::proof::MerkleProof;
+use crate::tree::MerkleTree;
+
+/// A Merkle tree over raw leaf data, hashed with a built-in hasher
+#[wasm_bindgen]
+pub struct WasmMerkleTree {
+    tree: MerkleTree<BuiltinHasher>,
+}
+
+#[wasm_bindgen]
+impl WasmMerkleTree {
+    /// Builds a tree by hashing each leaf with the named hasher
+    #[wasm_bindgen(constructor)]
+    pub fn new(leaves: Vec<Uint8Array>, hasher_name: &str) -> Result<WasmMerkleTree, JsError> {
+        let leaves = leaves.iter().map(|leaf| leaf.to_vec()).collect();
+        let tree = build_tree(leaves, hasher_name).map_err(|err| JsError::new(&err))?;
+        Ok(WasmMerkleTree { tree })
+    }
+
+    /// Gets the root as a hex string
+    pub fn root_hex(&self) -> String {
+        hex::encode(self.tree.root())
+    }
+
+    /// Generates the proof for a leaf index as a JSON proof object
+    pub fn generate_proof(&self, index: usize) -> Result<JsValue, JsError> {
+        let json = proof_json(&self.tree, index as LeafIndex).map_err(|err| JsError::new(&err))?;
+        JSON::parse(&json).map_err(|_| JsError::new("Failed to build the proof object"))
+    }
+}
+
+/// Verifies a proof (a JSON proof object or its string form) against a hex root
+#[wasm_bindgen]
+pub fn verify_proof(root_hex: &str, proof_json: JsValue, hasher_name: &str) -> Result<bool, JsError> {
+    let json = match proof_json.as_string() {
+        Some(json) => json,
+        None => JSON::stringify(&proof_json)
+            .map_err(|_| JsError::new("Proof is not JSON-serializable"))?
+            .into(),
+    };
+    verify_json(root_hex, &json, hasher_name).map_err(|err| JsError::new(&err))
+}
+
+fn hasher_by_name(name: &str) -> Result<BuiltinHasher, String> {
+    BuiltinHasher::from_name(name).ok_or_else(|| format!("Unknown hasher '{}'", name))
+}
+
+/// Builds the tree behind [`WasmMerkleTree::new`], reporting errors instead of panicking
+fn build_tree(leaves: Vec<Vec<u8>>, hasher_name: &str) -> Result<MerkleTree<BuiltinHasher>, String> {
+    let hasher = hasher_by_name(hasher_name)?;
+    if leaves.is_empty() {
+        return Err("Cannot create a Merkle tree with no leaves".to_string());
+    }
+    let leaves = leaves.iter().map(|leaf| hasher.hash_leaf(leaf)).collect();
+    Ok(MerkleTree::new(leaves, hasher))
+}
+
+fn proof_json(tree: &MerkleTree<BuiltinHasher>, index: LeafIndex) -> Result<String, String> {
+    Ok(tree.generate_proof(index).map_err(|err| err.to_string())?.to_json())
+}
+
+fn verify_json(root_hex: &str, json: &str, hasher_name: &str) -> Result<bool, String> {
+    let hasher = hasher_by_name(hasher_name)?;
+    let root = hex::decode(root_hex).map_err(|err| format!("Invalid root: {}", err))?;
+    let proof = MerkleProof::from_json(json, hasher).map_err(|err| format!("Invalid proof: {}", err))?;
+    Ok(proof.verify(&root))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_prove_verify() {
+        let leaves = vec![b"alice".to_vec(), b"bob".to_vec(), b"carol".to_vec()];
+        for name in ["sha256", "blake2b256", "blake2b512"] {
+            let tree = build_tree(leaves.clone(), name).unwrap();
+            let root_hex = hex::encode(tree.root());
+            for index in 0..tree.unpadded_leaf_count() {
+                let json = proof_json(&tree, index).unwrap();
+                assert_eq!(verify_json(&root_hex, &json, name), Ok(true));
+            }
+        }
+    }
+
+    #[test]
+    fn test_errors_instead_of_panics() {
+        assert!(build_tree(Vec::new(), "sha256").is_err());
+        assert!(build_tree(vec![b"leaf".to_vec()], "md5").is_err());
+
+        let tree = build_tree(vec![b"leaf1".to_vec(), b"leaf2".to_vec()], "sha256").unwrap();
+        assert!(proof_json(&tree, 5).is_err());
+
+        let json = proof_json(&tree, 0).unwrap();
+        let root_hex = hex::encode(tree.root());
+        assert!(verify_json("not hex", &json, "sha256").is_err());
+        assert!(verify_json(&root_hex, "{\"leaf\": 1}", "sha256").is_err());
+        assert_eq!(verify_json(&root_hex, &json, "blake2b256"), Ok(false));
+    }
+}
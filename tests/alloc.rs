@@ -0,0 +1,220 @@
+//! Heap accounting for tree construction and verification, in its own binary
+//! so the counting allocator sees only these tests
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use merkle_tree::hasher::{Blake2bHasher, Hasher, Sha256Hasher};
+use merkle_tree::proof::{MerkleProof, ProofItem};
+use merkle_tree::tree::MerkleTree;
+
+struct CountingAllocator;
+
+thread_local! {
+    // Counted per thread, so tests running in parallel don't see each other
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    static ALLOCATED: Cell<usize> = const { Cell::new(0) };
+}
+
+fn record(bytes: usize) {
+    // `try_with` because the allocator also runs while thread-locals are torn down
+    let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+    let _ = ALLOCATED.try_with(|total| total.set(total.get() + bytes));
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        record(layout.size());
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        record(new_size.saturating_sub(layout.size()));
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Runs `f`, returning its result with the number of allocations and bytes it made
+fn measure<T>(f: impl FnOnce() -> T) -> (T, usize, usize) {
+    let (count_before, bytes_before) = (ALLOCATIONS.get(), ALLOCATED.get());
+    let result = f();
+    (result, ALLOCATIONS.get() - count_before, ALLOCATED.get() - bytes_before)
+}
+
+#[test]
+fn test_construction_does_not_copy_leaves() {
+    const LEAVES: usize = 1 << 16;
+    let hasher = Sha256Hasher::new();
+    let leaves: Vec<Vec<u8>> = (0..LEAVES as u32)
+        .map(|i| hasher.hash_leaf(&i.to_le_bytes()))
+        .collect();
+    // One copy of the leaves: a 32-byte hash plus a `Vec` header each
+    let leaf_bytes = LEAVES * (32 + std::mem::size_of::<Vec<u8>>());
+
+    let (tree, _, allocated) = measure(|| MerkleTree::new(leaves, hasher));
+
+    // The LEAVES - 1 interior nodes cost about one leaf layer; a copy of the
+    // leaves on top of that would double it
+    assert!(allocated < leaf_bytes * 5 / 4, "allocated {} bytes for {} bytes of leaves", allocated, leaf_bytes);
+    assert_eq!(tree.leaf_count(), LEAVES as u64);
+}
+
+#[test]
+fn test_interior_nodes_allocate_once() {
+    const LEAVES: usize = 1 << 12;
+    // Blake2b truncates its output, which used to cost a second allocation per node
+    let hasher = Blake2bHasher::new(32);
+    let leaves: Vec<Vec<u8>> = (0..LEAVES as u32)
+        .map(|i| hasher.hash_leaf(&i.to_le_bytes()))
+        .collect();
+
+    let (_, allocations, _) = measure(|| MerkleTree::new(leaves, hasher));
+
+    // One buffer per interior node, plus a few vectors per level
+    assert!(allocations < LEAVES + 64, "{} allocations for {} interior nodes", allocations, LEAVES - 1);
+}
+
+#[test]
+fn test_verification_reuses_buffers() {
+    let hasher = Sha256Hasher::new();
+    // A proof as deep as a million-leaf tree's
+    let proof_items = (0..20u32)
+        .map(|level| ProofItem { hash: hasher.hash_leaf(&level.to_le_bytes()).into(), is_left: level % 3 == 0 })
+        .collect();
+    let proof = MerkleProof::new(hasher.hash_leaf(b"leaf"), proof_items, hasher);
+    let root = proof.calculate_root();
+
+    // Two buffers for the whole path instead of one per level
+    let (valid, allocations, _) = measure(|| proof.verify(&root));
+    assert!(valid);
+    assert_eq!(allocations, 2);
+}
+
+/// A cheap stand-in hasher, so a million-leaf tree builds quickly in debug builds
+#[derive(Clone)]
+struct XorHasher;
+
+impl Hasher for XorHasher {
+    fn hash_leaf(&self, data: &[u8]) -> Vec<u8> {
+        let mut hash = vec![0; 32];
+        for (i, byte) in data.iter().enumerate() {
+            hash[i % 32] ^= byte;
+        }
+        hash
+    }
+
+    fn hash_pair(&self, left: &[u8], right: &[u8]) -> Vec<u8> {
+        left.iter().zip(right).map(|(l, r)| l.rotate_left(1) ^ r).collect()
+    }
+
+    fn hash_pair_into(&self, left: &[u8], right: &[u8], out: &mut Vec<u8>) {
+        out.clear();
+        out.extend(left.iter().zip(right).map(|(l, r)| l.rotate_left(1) ^ r));
+    }
+}
+
+#[test]
+fn test_proof_generation_allocations() {
+    let hasher = XorHasher;
+    let leaves: Vec<Vec<u8>> = (0..1u32 << 20)
+        .map(|i| hasher.hash_leaf(&i.to_le_bytes()))
+        .collect();
+    let tree = MerkleTree::new_ordered(leaves, hasher);
+
+    // The leaf and one preallocated item vector with the hashes inline,
+    // instead of a growing vector and a heap buffer per level
+    let (proof, allocations, _) = measure(|| tree.generate_proof(777).unwrap());
+    assert_eq!(proof.proof_items.len(), 20);
+    assert_eq!(allocations, 2);
+    assert!(tree.verify_proof(&proof));
+
+    // The binary format is unchanged by the inline storage
+    let bytes = proof.to_bytes();
+    assert_eq!(bytes.len(), 4 + 32 + 4 + 20 * (1 + 4 + 32));
+    assert_eq!(MerkleProof::from_bytes(&bytes, XorHasher).unwrap().to_bytes(), bytes);
+}
+
+#[test]
+fn test_fixed_size_tree_allocations() {
+    use merkle_tree::fixed::{Hash32, MerkleTree32};
+
+    let hasher = XorHasher;
+    let leaves: Vec<Hash32> = (0..1u32 << 20)
+        .map(|i| hasher.hash_leaf(&i.to_le_bytes()).try_into().unwrap())
+        .collect();
+
+    // A million-leaf build grows one vector and reuses one hash buffer
+    let (tree, allocations, _) = measure(|| MerkleTree32::new(leaves, hasher).unwrap());
+    assert!(allocations <= 4, "{} allocations", allocations);
+
+    let proof = tree.generate_proof(4242).unwrap();
+    let (valid, allocations, _) = measure(|| tree.verify_proof(&proof));
+    assert!(valid);
+    assert_eq!(allocations, 1);
+}
+
+#[test]
+fn test_build_limits_stop_before_the_leaves_are_made() {
+    use merkle_tree::builder::BuildLimits;
+    use merkle_tree::error::{LimitExceeded, MerkleTreeError};
+    use merkle_tree::utils::{create_tree_from_lines_with_options, LineOptions};
+
+    const ITEMS: usize = 1 << 20;
+    let limits = BuildLimits { max_leaves: Some(1000), ..BuildLimits::default() };
+    // Every leaf would take a 32-byte hash and a `Vec` header
+    let all_leaves = ITEMS * (32 + std::mem::size_of::<Vec<u8>>());
+
+    let data: Vec<[u8; 4]> = (0..ITEMS as u32).map(u32::to_le_bytes).collect();
+    let (result, _, allocated) = measure(|| MerkleTree::builder(XorHasher).limits(limits).build_from_data(&data));
+    assert!(matches!(result, Err(MerkleTreeError::LimitExceeded(LimitExceeded::Leaves { max: 1000 }))));
+    assert!(allocated < all_leaves / 100, "allocated {} bytes", allocated);
+
+    // The line builder stops reading at the line going over
+    let input = b"line\n".repeat(ITEMS);
+    let mut reader = std::io::Cursor::new(&input);
+    let options = LineOptions { limits, ..LineOptions::default() };
+    let (result, _, allocated) = measure(|| create_tree_from_lines_with_options(&mut reader, XorHasher, options));
+    assert!(matches!(result, Err(MerkleTreeError::LimitExceeded(LimitExceeded::Leaves { max: 1000 }))));
+    assert!(allocated < all_leaves / 100, "allocated {} bytes", allocated);
+    assert_eq!(reader.position(), 1001 * 5);
+
+    // A stream of leaves, such as a cursor, is cut off at the leaf going over,
+    // and its size hint doesn't reserve past the limit
+    let mut pulled = 0;
+    let stream = (0..ITEMS as u32).map(|i| {
+        pulled += 1;
+        XorHasher.hash_leaf(&i.to_le_bytes())
+    });
+    let (result, _, allocated) = measure(|| MerkleTree::builder(XorHasher).limits(limits).build_from_iter(stream));
+    assert!(matches!(result, Err(MerkleTreeError::LimitExceeded(LimitExceeded::Leaves { max: 1000 }))));
+    assert!(allocated < all_leaves / 100, "allocated {} bytes", allocated);
+    assert_eq!(pulled, 1001);
+}
+
+#[cfg(feature = "msgpack")]
+#[test]
+fn test_msgpack_array_lengths_are_not_preallocated() {
+    // A proof frame whose leaf is an array claiming 2^32 - 1 bytes, two of them present
+    let mut frame = vec![0x83];
+    for (key, value) in [("version", &[0x01][..]), ("hasher", &[0xa6][..])] {
+        frame.push(0xa0 | key.len() as u8);
+        frame.extend_from_slice(key.as_bytes());
+        frame.extend_from_slice(value);
+    }
+    frame.extend_from_slice(b"sha256");
+    frame.push(0xa5);
+    frame.extend_from_slice(b"proof");
+    frame.extend_from_slice(&[0x82, 0xa4]);
+    frame.extend_from_slice(b"leaf");
+    frame.extend_from_slice(&[0xdd, 0xff, 0xff, 0xff, 0xff, 0x01, 0x02]);
+
+    let (result, _, allocated) = measure(|| MerkleProof::from_msgpack(&frame, Sha256Hasher::new()));
+    assert!(result.is_err());
+    assert!(allocated < 4096, "allocated {} bytes", allocated);
+}
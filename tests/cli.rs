@@ -0,0 +1,108 @@
+//! Runs the `merkle` binary over temporary files
+#![cfg(feature = "cli")]
+
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Output, Stdio};
+use merkle_tree::hasher::{BuiltinHasher, Hasher};
+use merkle_tree::proof::MerkleProof;
+use merkle_tree::tree::MerkleTree;
+
+const LEAVES: &str = "alpha\nbravo\ncharlie\ndelta\necho\n";
+
+fn merkle(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_merkle")).args(args).output().unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+fn expected_tree(name: &str) -> MerkleTree<BuiltinHasher> {
+    let hasher = BuiltinHasher::from_name(name).unwrap();
+    let leaves = LEAVES.lines().map(|line| hasher.hash_leaf(line.as_bytes())).collect();
+    MerkleTree::new(leaves, hasher)
+}
+
+fn write_leaves(dir: &Path) -> String {
+    let path = dir.join("leaves.txt");
+    std::fs::write(&path, LEAVES).unwrap();
+    path.to_str().unwrap().to_string()
+}
+
+#[test]
+fn root_matches_the_library() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = write_leaves(dir.path());
+    for hasher in ["sha256", "keccak256"] {
+        let output = merkle(&["root", "--input", &input, "--hasher", hasher]);
+        assert!(output.status.success());
+        assert_eq!(stdout(&output).trim(), hex::encode(expected_tree(hasher).root()));
+    }
+
+    // Leaves can come from stdin
+    let mut child = Command::new(env!("CARGO_BIN_EXE_merkle"))
+        .arg("root")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(LEAVES.as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(stdout(&output).trim(), hex::encode(expected_tree("sha256").root()));
+}
+
+#[test]
+fn prove_and_verify() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = write_leaves(dir.path());
+    let tree = expected_tree("sha256");
+    let root = hex::encode(tree.root());
+
+    let proof_path = dir.path().join("proof.json");
+    let output = merkle(&["prove", "--input", &input, "--index", "3", "--out", proof_path.to_str().unwrap()]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let json = std::fs::read_to_string(&proof_path).unwrap();
+    assert_eq!(json.trim(), tree.generate_proof(3).unwrap().to_json());
+
+    let output = merkle(&["verify", "--root", &root, "--proof", proof_path.to_str().unwrap(), "--hasher", "sha256"]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output).trim(), "valid");
+
+    // Proving by value writes the same proof to stdout
+    let output = merkle(&["prove", "--input", &input, "--value", "charlie"]);
+    assert!(output.status.success());
+    let by_value = MerkleProof::from_json(stdout(&output).trim(), BuiltinHasher::from_name("sha256").unwrap()).unwrap();
+    assert_eq!(by_value.leaf, BuiltinHasher::from_name("sha256").unwrap().hash_leaf(b"charlie"));
+    assert!(by_value.verify(tree.root()));
+}
+
+#[test]
+fn failing_verify_exits_non_zero() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = write_leaves(dir.path());
+    let proof_path = dir.path().join("proof.json");
+    assert!(merkle(&["prove", "--input", &input, "--index", "0", "--out", proof_path.to_str().unwrap()]).status.success());
+
+    let wrong_root = hex::encode([0u8; 32]);
+    let output = merkle(&["verify", "--root", &wrong_root, "--proof", proof_path.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stdout(&output).trim(), "invalid");
+
+    // Errors are messages with status 2, not panics
+    let missing = dir.path().join("missing.txt");
+    for (args, message) in [
+        (vec!["root", "--input", missing.to_str().unwrap()], "error: cannot open"),
+        (vec!["root", "--input", &input, "--hasher", "md5"], "error: unknown hasher 'md5'"),
+        (vec!["prove", "--input", &input, "--index", "9"], "error: cannot generate the proof"),
+        (vec!["prove", "--input", &input, "--value", "zulu"], "error: cannot generate the proof"),
+        (vec!["verify", "--root", "xyz", "--proof", proof_path.to_str().unwrap()], "error: invalid root"),
+        (vec!["verify", "--root", &wrong_root, "--proof", &input], "error: invalid proof"),
+    ] {
+        let output = merkle(&args);
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert_eq!(output.status.code(), Some(2), "{:?}: {}", args, stderr);
+        assert!(stderr.starts_with(message), "{:?}: {}", args, stderr);
+        assert!(!stderr.contains("panicked"));
+    }
+}
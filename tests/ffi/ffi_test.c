@@ -0,0 +1,60 @@
+/* Exercises the C ABI end to end; compiled and run by tests/ffi.rs */
+#include <stdio.h>
+#include <string.h>
+#include "merkle_tree.h"
+
+#define CHECK(cond)                                                   \
+    do {                                                              \
+        if (!(cond)) {                                                \
+            fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__,    \
+                    __LINE__, #cond);                                 \
+            return 1;                                                 \
+        }                                                             \
+    } while (0)
+
+int main(void) {
+    const char *data[] = {"alice", "bob", "carol"};
+    const uint8_t *leaves[3];
+    uintptr_t lens[3];
+    for (int i = 0; i < 3; i++) {
+        leaves[i] = (const uint8_t *)data[i];
+        lens[i] = strlen(data[i]);
+    }
+
+    MerkleTreeHandle *tree = NULL;
+    CHECK(merkle_tree_new(leaves, lens, 3, "md5", &tree) == MerkleStatus_UnknownHasher);
+    CHECK(merkle_tree_new(leaves, lens, 0, "sha256", &tree) == MerkleStatus_InvalidArgument);
+    CHECK(merkle_tree_new(leaves, lens, 3, "sha256", &tree) == MerkleStatus_Ok);
+    CHECK(tree != NULL);
+
+    /* Query the root size first, then fetch it */
+    uintptr_t root_len = 0;
+    CHECK(merkle_tree_root(tree, NULL, 0, &root_len) == MerkleStatus_BufferTooSmall);
+    CHECK(root_len == 32);
+    uint8_t root[32];
+    CHECK(merkle_tree_root(tree, root, sizeof root, &root_len) == MerkleStatus_Ok);
+
+    MerkleProofHandle *proof = NULL;
+    CHECK(merkle_tree_generate_proof(tree, 99, &proof) == MerkleStatus_IndexOutOfBounds);
+    CHECK(merkle_tree_generate_proof(tree, 1, &proof) == MerkleStatus_Ok);
+
+    uint8_t bytes[256];
+    uintptr_t bytes_len = 0;
+    CHECK(merkle_proof_serialize(proof, bytes, sizeof bytes, &bytes_len) == MerkleStatus_Ok);
+    merkle_proof_free(proof);
+
+    bool valid = false;
+    CHECK(merkle_proof_verify(bytes, bytes_len, root, root_len, "sha256", &valid) == MerkleStatus_Ok);
+    CHECK(valid);
+
+    root[0] ^= 1;
+    CHECK(merkle_proof_verify(bytes, bytes_len, root, root_len, "sha256", &valid) == MerkleStatus_Ok);
+    CHECK(!valid);
+
+    CHECK(merkle_proof_verify(bytes, bytes_len - 1, root, root_len, "sha256", &valid) == MerkleStatus_InvalidProof);
+    CHECK(merkle_proof_verify(bytes, bytes_len, root, root_len, "sha256", NULL) == MerkleStatus_NullPointer);
+
+    merkle_tree_free(tree);
+    printf("ffi ok\n");
+    return 0;
+}
@@ -0,0 +1,114 @@
+//! Sends each route of the proof service's router a request with `oneshot`
+#![cfg(feature = "server")]
+
+use std::sync::Arc;
+use axum::body::Body;
+use axum::http::{header, Method, Request, StatusCode};
+use axum::Router;
+use merkle_tree::hasher::{Hasher, Sha256Hasher};
+use merkle_tree::server::{router, MAX_BODY_BYTES};
+use merkle_tree::tree::MerkleTree;
+use merkle_tree::utils::create_tree_from_strings;
+use serde_json::json;
+use tower::ServiceExt;
+
+fn app() -> (Router, Arc<MerkleTree<Sha256Hasher>>) {
+    let tree = Arc::new(create_tree_from_strings(vec!["alpha", "bravo", "charlie"]));
+    (router(Arc::clone(&tree)), tree)
+}
+
+/// Sends a request, returning the status and the JSON body
+async fn send(app: &Router, method: Method, uri: &str, content_type: Option<&str>, body: impl Into<Body>) -> (StatusCode, serde_json::Value) {
+    let mut request = Request::builder().method(method).uri(uri);
+    if let Some(content_type) = content_type {
+        request = request.header(header::CONTENT_TYPE, content_type);
+    }
+    let response = app.clone().oneshot(request.body(body.into()).unwrap()).await.unwrap();
+    assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json", "{}", uri);
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+#[tokio::test]
+async fn root_route() {
+    let (app, tree) = app();
+    let response = send(&app, Method::GET, "/root", None, Body::empty()).await;
+    assert_eq!(response, (StatusCode::OK, json!({ "root": hex::encode(tree.root()), "leaf_count": 3 })));
+    // A query string doesn't change the route
+    assert_eq!(send(&app, Method::GET, "/root?format=json", None, Body::empty()).await.0, StatusCode::OK);
+    assert_eq!(send(&app, Method::POST, "/root", None, Body::empty()).await.0, StatusCode::METHOD_NOT_ALLOWED);
+}
+
+#[tokio::test]
+async fn proof_route() {
+    let (app, tree) = app();
+    let (status, proof) = send(&app, Method::GET, "/proof/1", None, Body::empty()).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(proof, serde_json::from_str::<serde_json::Value>(&tree.generate_proof(1).unwrap().to_json()).unwrap());
+
+    // Past the end, the padding leaf, past u64 and not a number
+    for (uri, status) in [
+        ("/proof/9", StatusCode::NOT_FOUND),
+        ("/proof/3", StatusCode::NOT_FOUND),
+        ("/proof/18446744073709551616", StatusCode::BAD_REQUEST),
+        ("/proof/one", StatusCode::BAD_REQUEST),
+    ] {
+        let (actual, error) = send(&app, Method::GET, uri, None, Body::empty()).await;
+        assert_eq!(actual, status, "{}", uri);
+        assert!(error["error"].is_string());
+    }
+    assert_eq!(send(&app, Method::DELETE, "/proof/0", None, Body::empty()).await.0, StatusCode::METHOD_NOT_ALLOWED);
+}
+
+#[tokio::test]
+async fn proof_by_value_route() {
+    let (app, tree) = app();
+    // As hex or as raw bytes
+    let expected = Sha256Hasher::new().hash_leaf(b"charlie");
+    for (content_type, body) in [(None, hex::encode("charlie").into_bytes()), (Some("application/octet-stream"), b"charlie".to_vec())] {
+        let (status, proof) = send(&app, Method::POST, "/proof/by-value", content_type, body).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(proof["leaf"], hex::encode(&expected));
+        let index = tree.find_leaf_index(&expected).unwrap();
+        assert_eq!(proof, serde_json::from_str::<serde_json::Value>(&tree.generate_proof(index).unwrap().to_json()).unwrap());
+    }
+
+    let response = send(&app, Method::POST, "/proof/by-value", None, "7a756c75").await;
+    assert_eq!(response.0, StatusCode::NOT_FOUND);
+    let response = send(&app, Method::POST, "/proof/by-value", None, "not hex").await;
+    assert_eq!(response, (StatusCode::BAD_REQUEST, json!({ "error": "body is not hex" })));
+    let response = send(&app, Method::POST, "/proof/by-value", None, vec![b'0'; MAX_BODY_BYTES + 2]).await;
+    assert_eq!(response, (StatusCode::PAYLOAD_TOO_LARGE, json!({ "error": "body too large" })));
+    assert_eq!(send(&app, Method::GET, "/proof/by-value", None, Body::empty()).await.0, StatusCode::METHOD_NOT_ALLOWED);
+}
+
+#[tokio::test]
+async fn verify_route() {
+    let (app, tree) = app();
+    let proof = tree.generate_proof(0).unwrap().to_json();
+    let response = send(&app, Method::POST, "/verify", Some("application/json"), proof).await;
+    assert_eq!(response, (StatusCode::OK, json!({ "valid": true })));
+    let other = create_tree_from_strings(vec!["x", "y"]).generate_proof(0).unwrap().to_json();
+    let response = send(&app, Method::POST, "/verify", None, other).await;
+    assert_eq!(response, (StatusCode::OK, json!({ "valid": false })));
+
+    for body in ["{\"leaf\": 1}", "[1, 2]"] {
+        let (status, error) = send(&app, Method::POST, "/verify", None, body).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{}", body);
+        assert!(error["error"].is_string());
+    }
+    assert_eq!(send(&app, Method::GET, "/verify", None, Body::empty()).await.0, StatusCode::METHOD_NOT_ALLOWED);
+}
+
+#[tokio::test]
+async fn unknown_routes_and_nesting() {
+    let (app, tree) = app();
+    let response = send(&app, Method::GET, "/leaves", None, Body::empty()).await;
+    assert_eq!(response, (StatusCode::NOT_FOUND, json!({ "error": "not found" })));
+
+    // Nested under a prefix, as an application would mount it
+    let nested = Router::new().nest("/merkle", app);
+    let (status, root) = send(&nested, Method::GET, "/merkle/root", None, Body::empty()).await;
+    assert_eq!((status, root["root"].as_str()), (StatusCode::OK, Some(hex::encode(tree.root()).as_str())));
+}
@@ -0,0 +1,36 @@
+//! Node tests for the wasm bindings; run with `wasm-pack test --node -- --features wasm`
+#![cfg(all(target_arch = "wasm32", feature = "wasm"))]
+
+use js_sys::Uint8Array;
+use merkle_tree::wasm::{verify_proof, WasmMerkleTree};
+use wasm_bindgen_test::wasm_bindgen_test;
+
+fn leaves() -> Vec<Uint8Array> {
+    ["alice", "bob", "carol"].iter()
+        .map(|leaf| Uint8Array::from(leaf.as_bytes()))
+        .collect()
+}
+
+#[wasm_bindgen_test]
+fn build_prove_verify() {
+    let tree = WasmMerkleTree::new(leaves(), "sha256").unwrap();
+    let root = tree.root_hex();
+
+    for index in 0..3 {
+        let proof = tree.generate_proof(index).unwrap();
+        assert!(verify_proof(&root, proof, "sha256").unwrap());
+    }
+
+    let proof = tree.generate_proof(0).unwrap();
+    assert!(!verify_proof(&root, proof, "blake2b256").unwrap());
+}
+
+#[wasm_bindgen_test]
+fn errors_become_exceptions() {
+    assert!(WasmMerkleTree::new(Vec::new(), "sha256").is_err());
+    assert!(WasmMerkleTree::new(leaves(), "md5").is_err());
+
+    let tree = WasmMerkleTree::new(leaves(), "sha256").unwrap();
+    assert!(tree.generate_proof(99).is_err());
+    assert!(verify_proof(&tree.root_hex(), "not json".into(), "sha256").is_err());
+}